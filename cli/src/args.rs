@@ -0,0 +1,75 @@
+//! Minimal `--name value` argument parsing.
+//!
+//! Every option takes exactly one value and may be repeated; bare words are
+//! positional arguments. Each subcommand declares the options it accepts, so
+//! typos are reported instead of silently ignored.
+
+use crate::error::CliError;
+
+/// Parsed arguments of one subcommand.
+#[derive(Debug, Default)]
+pub struct Args {
+    options: Vec<(String, String)>,
+    positionals: Vec<String>,
+}
+
+impl Args {
+    /// Parses `raw`, accepting only the options listed in `allowed`.
+    pub fn parse<I>(raw: I, allowed: &[&str]) -> Result<Self, CliError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut args = Args::default();
+        let mut raw = raw.into_iter();
+        while let Some(arg) = raw.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                args.positionals.push(arg);
+                continue;
+            };
+            // Accept both `--name value` and `--name=value`.
+            let (name, value) = match name.split_once('=') {
+                Some((name, value)) => (name.to_string(), value.to_string()),
+                None => {
+                    let value = raw.next().ok_or_else(|| {
+                        CliError::Usage(format!("option --{} requires a value", name))
+                    })?;
+                    (name.to_string(), value)
+                }
+            };
+            if !allowed.contains(&name.as_str()) {
+                return Err(CliError::Usage(format!("unknown option --{}", name)));
+            }
+            args.options.push((name, value));
+        }
+        Ok(args)
+    }
+
+    /// Last value given for `name`.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(option, _)| option == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Every value given for `name`, in order.
+    pub fn values(&self, name: &str) -> Vec<&str> {
+        self.options
+            .iter()
+            .filter(|(option, _)| option == name)
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    /// Value of a mandatory option.
+    pub fn required(&self, name: &str) -> Result<&str, CliError> {
+        self.value(name)
+            .ok_or_else(|| CliError::Usage(format!("missing required option --{}", name)))
+    }
+
+    /// Positional arguments.
+    pub fn positionals(&self) -> &[String] {
+        &self.positionals
+    }
+}
@@ -0,0 +1,312 @@
+//! Subcommand implementations.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use wavemark::api::builder::{DetectionReport, WatermarkBuilder};
+use wavemark::api::wav;
+use wavemark::detect::confidence::{Decision, DetectionConfidence};
+use wavemark::embed::masking::MaskingConfig;
+use wavemark::embed::params::{EmbedParams, Preset};
+use wavemark::format::codec::{CodecOptions, FrameCodec, FrameEnvelope, FrameHeader};
+use wavemark::format::encryption::EncryptionContext;
+use wavemark::format::payload::{
+    AccountId, MetadataField, MetadataKey, MetadataTimestamp, MetadataValue, PayloadFrame,
+    WellKnownField,
+};
+use wavemark::format::FormatBuilder;
+use wavemark::key::derivation::KeyContext;
+
+use crate::args::Args;
+use crate::error::CliError;
+use crate::json::Json;
+
+/// Environment variable consulted when `--key-file` is not given.
+pub const KEY_ENV: &str = "WAVEMARK_MASTER_KEY";
+/// Default size of generated master secrets in bytes.
+const DEFAULT_KEY_BYTES: usize = 32;
+
+/// Options shared by `embed` and `detect`.
+pub const KEY_OPTIONS: &[&str] = &[
+    "key-file",
+    "account",
+    "session",
+    "preset",
+    "strength",
+    "internal-rate",
+];
+
+/// `wavemark embed`: watermarks a WAV file.
+pub fn embed(args: &Args) -> Result<Json, CliError> {
+    let input = args.required("input")?;
+    let output = args.required("output")?;
+    let masking = match args.value("masking") {
+        None | Some("off") => None,
+        Some("on") => Some(MaskingConfig::default()),
+        Some(other) => {
+            return Err(CliError::Usage(format!(
+                "--masking expects on or off, got {:?}",
+                other
+            )))
+        }
+    };
+    let session = session_key(args)?;
+
+    let mut payload = FormatBuilder::new();
+    let mut has_account = false;
+    for field in args.values("field") {
+        let field = parse_field(field)?;
+        has_account |= field.key == MetadataKey::well_known(WellKnownField::AccountId);
+        payload = payload.field(field)?;
+    }
+    if !has_account {
+        payload
+            .payload_builder()
+            .account_id(args.required("account")?)?;
+    }
+
+    let mut builder = watermark_builder(args, session)?.payload(payload.clone());
+    if let Some(config) = masking {
+        builder = builder.masking(config);
+    }
+    let bytes = payload.build()?.bytes;
+    let report = wav::embed_wav_file(input, output, builder)?;
+
+    Ok(Json::object()
+        .with("output", output)
+        .with("sample_rate", report.spec.sample_rate as i64)
+        .with("channels", report.spec.channels as i64)
+        .with("bits_per_sample", report.spec.bits_per_sample as i64)
+        .with("frames", report.frames)
+        .with("clipped_samples", report.clipped_samples)
+        .with("payload_bytes", bytes.len())
+        .with("payload_hex", to_hex(&bytes)))
+}
+
+/// `wavemark detect`: searches a WAV file for a watermark.
+///
+/// Returns the report and whether a payload was found.
+pub fn detect(args: &Args) -> Result<(Json, bool), CliError> {
+    let input = args.required("input")?;
+    let session = session_key(args)?;
+    let report = wav::detect_wav_file(input, watermark_builder(args, session)?)?;
+    let present = report.is_present();
+    Ok((detection_json(&report), present))
+}
+
+/// `wavemark inspect-payload`: decodes hex-encoded codec bytes.
+///
+/// The header is reported for every well-formed frame. Encrypted frames
+/// cannot be opened without their key, so their frame is reported as null
+/// together with the reason.
+pub fn inspect_payload(args: &Args) -> Result<Json, CliError> {
+    let hex = match args.positionals() {
+        [hex] => hex,
+        _ => {
+            return Err(CliError::Usage(
+                "inspect-payload expects exactly one hex string".into(),
+            ))
+        }
+    };
+    let bytes = from_hex(hex)?;
+    let header = FrameHeader::parse(&bytes)?;
+    let report = Json::object().with("length", bytes.len()).with(
+        "version",
+        format!("{}.{}", header.version.major, header.version.minor),
+    );
+
+    let codec = FrameCodec::new(CodecOptions::default());
+    match header.envelope {
+        FrameEnvelope::Plain => {
+            let frame = codec.decode(&bytes, &EncryptionContext::default())?;
+            Ok(report
+                .with("envelope", "plain")
+                .with("frame", frame_json(&frame)))
+        }
+        FrameEnvelope::EncryptedHash => Ok(report
+            .with("envelope", "encrypted")
+            .with("frame", Json::Null)
+            .with("frame_error", "encrypted payloads need their key to decode")),
+    }
+}
+
+/// `wavemark keygen`: creates a random master secret.
+///
+/// The hex secret is written to `--output` (never overwriting an existing
+/// file) or returned for printing.
+pub fn keygen(args: &Args) -> Result<Option<String>, CliError> {
+    let len = match args.value("bytes") {
+        Some(value) => value
+            .parse::<usize>()
+            .map_err(|_| CliError::Usage(format!("invalid --bytes value {:?}", value)))?,
+        None => DEFAULT_KEY_BYTES,
+    };
+    if len < KeyContext::MIN_SECRET_LEN {
+        return Err(CliError::Usage(format!(
+            "--bytes must be at least {}",
+            KeyContext::MIN_SECRET_LEN
+        )));
+    }
+
+    let mut secret = vec![0u8; len];
+    getrandom::getrandom(&mut secret).map_err(|err| CliError::Key(err.to_string()))?;
+    let hex = to_hex(&secret);
+    secret.fill(0);
+
+    match args.value("output") {
+        Some(path) => {
+            let mut options = OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.mode(0o600);
+            }
+            let mut file = options.open(path)?;
+            writeln!(file, "{}", hex)?;
+            Ok(None)
+        }
+        None => Ok(Some(hex)),
+    }
+}
+
+/// Loads the master secret and narrows it to the requested session.
+fn session_key(args: &Args) -> Result<KeyContext, CliError> {
+    let hex = match args.value("key-file") {
+        Some(path) => read_key_file(Path::new(path))?,
+        None => std::env::var(KEY_ENV)
+            .map_err(|_| CliError::Usage(format!("pass --key-file or set {}", KEY_ENV)))?,
+    };
+    let secret = from_hex(hex.trim())
+        .map_err(|_| CliError::Key("master secret must be hex-encoded".into()))?;
+    Ok(KeyContext::from_master_secret(&secret)?
+        .account(args.required("account")?)?
+        .session(args.required("session")?)?)
+}
+
+fn read_key_file(path: &Path) -> Result<String, CliError> {
+    fs::read_to_string(path)
+        .map_err(|err| CliError::Key(format!("cannot read {}: {}", path.display(), err)))
+}
+
+fn watermark_builder(args: &Args, session: KeyContext) -> Result<WatermarkBuilder, CliError> {
+    let preset = match args.value("preset").unwrap_or("balanced") {
+        "transparent" => Preset::Transparent,
+        "balanced" => Preset::Balanced,
+        "robust" => Preset::Robust,
+        other => return Err(CliError::Usage(format!("unknown preset {:?}", other))),
+    };
+    let mut params = EmbedParams::builder().preset(preset);
+    if let Some(strength) = args.value("strength") {
+        params =
+            params.strength_db(strength.parse().map_err(|_| {
+                CliError::Usage(format!("invalid --strength value {:?}", strength))
+            })?);
+    }
+    // The WAV layer sets each file's own rate; the preset is resolved at the
+    // internal rate, or at the file's rate with `--internal-rate native`.
+    let builder = WatermarkBuilder::new(44_100)
+        .key(session)
+        .params_builder(params);
+    match args.value("internal-rate") {
+        None => Ok(builder.internal_rate(WatermarkBuilder::CANONICAL_RATE)),
+        Some("native") => Ok(builder.native_rate()),
+        Some(rate) => match rate.parse::<u32>() {
+            Ok(rate) if rate > 0 => Ok(builder.internal_rate(rate)),
+            _ => Err(CliError::Usage(format!(
+                "--internal-rate expects a rate in hertz or native, got {:?}",
+                rate
+            ))),
+        },
+    }
+}
+
+/// Parses `key=value`, typing well-known fields.
+fn parse_field(field: &str) -> Result<MetadataField, CliError> {
+    let (key, value) = field
+        .split_once('=')
+        .ok_or_else(|| CliError::Usage(format!("field {:?} is not key=value", field)))?;
+    let key = MetadataKey::try_from(key)?;
+    let value = match &key {
+        MetadataKey::WellKnown(WellKnownField::AccountId) => {
+            MetadataValue::Account(AccountId::new(value)?)
+        }
+        MetadataKey::WellKnown(WellKnownField::IssuedAt | WellKnownField::ExpiresAt) => {
+            let seconds = value.parse::<i64>().map_err(|_| {
+                CliError::Usage(format!("{} expects Unix seconds, got {:?}", key, value))
+            })?;
+            MetadataValue::Timestamp(MetadataTimestamp::from_unix_seconds(seconds)?)
+        }
+        _ => MetadataValue::Text(value.to_string()),
+    };
+    Ok(MetadataField::new(key, value))
+}
+
+fn detection_json(report: &DetectionReport) -> Json {
+    Json::object()
+        .with("detected", report.is_present())
+        .with(
+            "payload_start",
+            report.payload_start.map(|start| start as i64),
+        )
+        .with(
+            "frame",
+            report.frame.as_ref().map_or(Json::Null, frame_json),
+        )
+        .with("payload_hex", report.bytes.as_deref().map(to_hex))
+        .with("confidence", confidence_json(&report.confidence))
+}
+
+fn confidence_json(confidence: &DetectionConfidence) -> Json {
+    let decision = match confidence.decision() {
+        Decision::Present => "present",
+        Decision::Absent => "absent",
+        Decision::Inconclusive => "inconclusive",
+    };
+    Json::object()
+        .with("decision", decision)
+        .with(
+            "false_positive_probability",
+            confidence.false_positive_probability(),
+        )
+        .with("z_score", confidence.z_score())
+        .with("known_bits", confidence.known_bits())
+        .with("hypotheses", confidence.hypotheses())
+}
+
+fn frame_json(frame: &PayloadFrame) -> Json {
+    let mut object = Json::object();
+    for (key, value) in frame.iter() {
+        let value = match value {
+            MetadataValue::Account(account) => Json::from(account.as_str()),
+            MetadataValue::Timestamp(timestamp) => {
+                timestamp.to_unix_seconds().map_or(Json::Null, Json::from)
+            }
+            MetadataValue::Text(text) => Json::from(text.as_str()),
+            MetadataValue::Integer(value) => Json::from(*value),
+            MetadataValue::Bool(value) => Json::from(*value),
+            MetadataValue::Blob(bytes) => Json::from(to_hex(bytes)),
+        };
+        object = object.with(&key.as_str(), value);
+    }
+    object
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Result<Vec<u8>, CliError> {
+    let hex = hex.trim();
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(CliError::Usage(format!("{:?} is not a hex string", hex)));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| {
+            u8::from_str_radix(&hex[index..index + 2], 16)
+                .map_err(|_| CliError::Usage(format!("{:?} is not a hex string", hex)))
+        })
+        .collect()
+}
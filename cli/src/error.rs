@@ -0,0 +1,97 @@
+//! Errors reported by the command-line tool.
+
+use std::fmt;
+
+use wavemark::api::builder::WatermarkError;
+use wavemark::api::wav::WavError;
+use wavemark::embed::spread_spectrum::EmbedError;
+use wavemark::format::codec::CodecError;
+use wavemark::format::payload::PayloadError;
+use wavemark::key::derivation::KeyError;
+
+/// Failure of a subcommand.
+#[derive(Debug)]
+pub enum CliError {
+    /// Arguments are missing or malformed.
+    Usage(String),
+    /// Key material could not be read or generated.
+    Key(String),
+    /// Reading or writing a file failed.
+    Io(std::io::Error),
+    /// Payload fields are invalid.
+    Payload(PayloadError),
+    /// Payload bytes could not be decoded.
+    Codec(CodecError),
+    /// Embedding parameters are invalid.
+    Params(EmbedError),
+    /// WAV processing failed.
+    Wav(WavError),
+}
+
+impl CliError {
+    /// Process exit code for the error.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CliError::Usage(_) => 64,
+            _ => 2,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "{}", message),
+            CliError::Key(message) => write!(f, "key error: {}", message),
+            CliError::Io(err) => write!(f, "i/o error: {}", err),
+            CliError::Payload(err) => write!(f, "payload error: {}", err),
+            CliError::Codec(err) => write!(f, "codec error: {}", err),
+            CliError::Params(err) => write!(f, "parameter error: {}", err),
+            CliError::Wav(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<std::io::Error> for CliError {
+    fn from(err: std::io::Error) -> Self {
+        CliError::Io(err)
+    }
+}
+
+impl From<KeyError> for CliError {
+    fn from(err: KeyError) -> Self {
+        CliError::Key(err.to_string())
+    }
+}
+
+impl From<PayloadError> for CliError {
+    fn from(err: PayloadError) -> Self {
+        CliError::Payload(err)
+    }
+}
+
+impl From<CodecError> for CliError {
+    fn from(err: CodecError) -> Self {
+        CliError::Codec(err)
+    }
+}
+
+impl From<EmbedError> for CliError {
+    fn from(err: EmbedError) -> Self {
+        CliError::Params(err)
+    }
+}
+
+impl From<WavError> for CliError {
+    fn from(err: WavError) -> Self {
+        CliError::Wav(err)
+    }
+}
+
+impl From<WatermarkError> for CliError {
+    fn from(err: WatermarkError) -> Self {
+        CliError::Wav(WavError::Watermark(err))
+    }
+}
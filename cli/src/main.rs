@@ -0,0 +1,131 @@
+//! `wavemark` command-line tool.
+//!
+//! ```text
+//! wavemark keygen [--bytes N] [--output PATH]
+//! wavemark embed --input IN.wav --output OUT.wav --account ID --session ID
+//!                [--key-file PATH] [--field key=value]... [--preset NAME]
+//!                [--strength DB] [--internal-rate HZ|native] [--masking on|off]
+//! wavemark detect --input IN.wav --account ID --session ID [--key-file PATH]
+//!                 [--preset NAME] [--strength DB] [--internal-rate HZ|native]
+//! wavemark inspect-payload HEX
+//! ```
+//!
+//! The master secret is read as hex from `--key-file` or, failing that, from
+//! the `WAVEMARK_MASTER_KEY` environment variable. Results are printed as JSON
+//! on stdout. `wavemark <command> --help` prints the usage of one command.
+//! `detect` exits with status 1 when no watermark is found; errors
+//! exit with 2, or 64 for usage errors.
+
+mod args;
+mod commands;
+mod error;
+mod json;
+
+use std::process::ExitCode;
+
+use args::Args;
+use error::CliError;
+
+const USAGE: &str = "\
+usage: wavemark <command> [options]
+
+commands:
+  keygen           generate a random master secret
+  embed            watermark a WAV file
+  detect           search a WAV file for a watermark
+  inspect-payload  decode hex-encoded payload bytes
+
+embed and detect require --account and --session, and read the hex master
+secret from --key-file or WAVEMARK_MASTER_KEY. Run wavemark <command> --help
+for the options of a command.";
+
+/// Usage of `command`, printed for `wavemark <command> --help`.
+fn command_usage(command: &str) -> Option<&'static str> {
+    let usage = match command {
+        "keygen" => "usage: wavemark keygen [--bytes N] [--output PATH]",
+        "embed" => {
+            "\
+usage: wavemark embed --input IN.wav --output OUT.wav --account ID --session ID
+                      [--key-file PATH] [--field key=value]... [--preset NAME]
+                      [--strength DB] [--internal-rate HZ|native] [--masking on|off]
+
+presets: transparent, balanced (default), robust
+--internal-rate defaults to 16000, so copies at other rates stay detectable."
+        }
+        "detect" => {
+            "\
+usage: wavemark detect --input IN.wav --account ID --session ID [--key-file PATH]
+                       [--preset NAME] [--strength DB] [--internal-rate HZ|native]
+
+--preset, --strength and --internal-rate must match the values used to embed."
+        }
+        "inspect-payload" => "usage: wavemark inspect-payload HEX",
+        _ => return None,
+    };
+    Some(usage)
+}
+
+fn main() -> ExitCode {
+    let mut raw = std::env::args().skip(1);
+    let Some(command) = raw.next() else {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(64);
+    };
+
+    match run(&command, raw) {
+        Ok(code) => ExitCode::from(code),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            if matches!(err, CliError::Usage(_)) {
+                eprintln!("\n{}", USAGE);
+            }
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
+
+fn run(command: &str, raw: impl Iterator<Item = String>) -> Result<u8, CliError> {
+    let raw: Vec<String> = raw.collect();
+    if let Some(usage) = command_usage(command) {
+        if raw.iter().any(|arg| arg == "--help" || arg == "-h") {
+            println!("{}", usage);
+            return Ok(0);
+        }
+    }
+
+    match command {
+        "keygen" => {
+            let args = Args::parse(raw, &["bytes", "output"])?;
+            if let Some(hex) = commands::keygen(&args)? {
+                println!("{}", hex);
+            }
+            Ok(0)
+        }
+        "embed" => {
+            let allowed = [
+                commands::KEY_OPTIONS,
+                &["input", "output", "field", "masking"],
+            ];
+            let args = Args::parse(raw, &allowed.concat())?;
+            println!("{}", commands::embed(&args)?.to_pretty_string());
+            Ok(0)
+        }
+        "detect" => {
+            let allowed = [commands::KEY_OPTIONS, &["input"]];
+            let args = Args::parse(raw, &allowed.concat())?;
+            let (report, present) = commands::detect(&args)?;
+            println!("{}", report.to_pretty_string());
+            Ok(if present { 0 } else { 1 })
+        }
+        "inspect-payload" => {
+            let args = Args::parse(raw, &[])?;
+            println!("{}", commands::inspect_payload(&args)?.to_pretty_string());
+            Ok(0)
+        }
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(0)
+        }
+        other => Err(CliError::Usage(format!("unknown command {:?}", other))),
+    }
+}
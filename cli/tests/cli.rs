@@ -0,0 +1,249 @@
+//! End-to-end tests for the `wavemark` binary.
+
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::Arc;
+
+use hound::{SampleFormat, WavSpec, WavWriter};
+use wavemark::format::encryption::{ChaCha20Poly1305Strategy, EncryptedHashConfig, EncryptionMode};
+
+const BIN: &str = env!("CARGO_BIN_EXE_wavemark");
+
+fn wavemark(args: &[&str]) -> Output {
+    Command::new(BIN)
+        .args(args)
+        .env_remove("WAVEMARK_MASTER_KEY")
+        .output()
+        .expect("binary runs")
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wavemark-cli-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Writes `seconds` of mono 16-bit noise and tone.
+fn write_host(path: &Path, sample_rate: u32, seconds: u32) -> Result<(), Box<dyn Error>> {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut writer = WavWriter::create(path, spec)?;
+    let mut state = 0x0f1e_2d3cu32;
+    for index in 0..sample_rate * seconds {
+        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        let noise = (state >> 8) as f32 / (1u32 << 24) as f32 - 0.5;
+        let t = index as f32 / sample_rate as f32;
+        let sample = 0.25 * (2.0 * std::f32::consts::PI * 220.0 * t).sin() + 0.4 * noise;
+        writer.write_sample((sample * 32_768.0).round() as i16)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+#[test]
+fn keygen_prints_hex_and_refuses_to_overwrite() -> Result<(), Box<dyn Error>> {
+    let output = wavemark(&["keygen", "--bytes", "24"]);
+    assert!(output.status.success());
+    let hex = stdout(&output);
+    assert_eq!(hex.trim().len(), 48);
+    assert!(hex.trim().bytes().all(|b| b.is_ascii_hexdigit()));
+
+    let dir = temp_dir("keygen");
+    let key = dir.join("master.key");
+    let key_path = key.to_str().unwrap();
+    assert!(wavemark(&["keygen", "--output", key_path]).status.success());
+    assert_eq!(std::fs::read_to_string(&key)?.trim().len(), 64);
+    let again = wavemark(&["keygen", "--output", key_path]);
+    std::fs::remove_dir_all(&dir)?;
+    assert_eq!(again.status.code(), Some(2));
+
+    let short = wavemark(&["keygen", "--bytes", "8"]);
+    assert_eq!(short.status.code(), Some(64));
+    Ok(())
+}
+
+#[test]
+fn embed_then_detect_reports_fields() -> Result<(), Box<dyn Error>> {
+    let dir = temp_dir("round-trip");
+    let key = dir.join("master.key");
+    let input = dir.join("input.wav");
+    let marked = dir.join("marked.wav");
+    std::fs::write(&key, "00112233445566778899aabbccddeeff\n")?;
+    write_host(&input, 44_100, 10)?;
+
+    let key_args = [
+        "--key-file",
+        key.to_str().unwrap(),
+        "--account",
+        "acct_cli",
+        "--session",
+        "s1",
+        "--strength",
+        "3",
+    ];
+    let mut embed = vec![
+        "embed",
+        "--input",
+        input.to_str().unwrap(),
+        "--output",
+        marked.to_str().unwrap(),
+        "--field",
+        "content_id=ep-42",
+    ];
+    embed.extend(key_args);
+    let embedded = wavemark(&embed);
+    assert!(embedded.status.success(), "{:?}", embedded);
+    assert!(stdout(&embedded).contains("\"frames\": 441000"));
+
+    let mut detect = vec!["detect", "--input", marked.to_str().unwrap()];
+    detect.extend(key_args);
+    let detected = wavemark(&detect);
+    assert!(detected.status.success(), "{:?}", detected);
+    let report = stdout(&detected);
+    assert!(report.contains("\"detected\": true"));
+    assert!(report.contains("\"account_id\": \"acct_cli\""));
+    assert!(report.contains("\"content_id\": \"ep-42\""));
+    assert!(report.contains("\"decision\": \"present\""));
+
+    let mut unmarked = vec!["detect", "--input", input.to_str().unwrap()];
+    unmarked.extend(key_args);
+    let clean = wavemark(&unmarked);
+    std::fs::remove_dir_all(&dir)?;
+    assert_eq!(clean.status.code(), Some(1));
+    assert!(stdout(&clean).contains("\"detected\": false"));
+    Ok(())
+}
+
+#[test]
+fn inspect_payload_decodes_codec_bytes() {
+    // Plain v1.0 frame holding `account_id = acct` built by `FormatBuilder`.
+    let mut payload = wavemark::format::FormatBuilder::new();
+    payload.payload_builder().account_id("acct").unwrap();
+    let bytes = payload.build().unwrap().bytes;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+    let output = wavemark(&["inspect-payload", &hex]);
+    assert!(output.status.success(), "{:?}", output);
+    let report = stdout(&output);
+    assert!(report.contains("\"version\": \"1.0\""));
+    assert!(report.contains("\"envelope\": \"plain\""));
+    assert!(report.contains("\"account_id\": \"acct\""));
+
+    let garbage = wavemark(&["inspect-payload", "5741"]);
+    assert_eq!(garbage.status.code(), Some(2));
+
+    // Encrypted frames still show their header.
+    let strategy = ChaCha20Poly1305Strategy::new([0x42; 32]);
+    let mut payload = wavemark::format::FormatBuilder::new().encryption_mode(
+        EncryptionMode::EncryptedHash(EncryptedHashConfig {
+            strategy: Arc::new(strategy),
+            key_id: None,
+            nonce: None,
+        }),
+    );
+    payload.payload_builder().account_id("acct").unwrap();
+    let bytes = payload.build().unwrap().bytes;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+    let output = wavemark(&["inspect-payload", &hex]);
+    assert!(output.status.success(), "{:?}", output);
+    let report = stdout(&output);
+    assert!(report.contains("\"version\": \"1.0\""));
+    assert!(report.contains("\"envelope\": \"encrypted\""));
+    assert!(report.contains("\"frame\": null"));
+}
+
+#[test]
+fn presets_are_resolved_at_the_processing_rate() -> Result<(), Box<dyn Error>> {
+    let dir = temp_dir("rates");
+    let key = dir.join("master.key");
+    std::fs::write(&key, "00112233445566778899aabbccddeeff\n")?;
+
+    // Preset bands are capped below the Nyquist frequency of the rate they
+    // are resolved at, whether that is the internal rate or the file's.
+    for (sample_rate, preset, internal_rate) in [
+        (8_000, "balanced", "native"),
+        (16_000, "robust", "16000"),
+        (44_100, "robust", "native"),
+    ] {
+        let input = dir.join(format!("{}.wav", sample_rate));
+        let marked = dir.join(format!("{}-marked.wav", sample_rate));
+        write_host(&input, sample_rate, 2)?;
+        let embed = |internal_rate: &str| {
+            wavemark(&[
+                "embed",
+                "--input",
+                input.to_str().unwrap(),
+                "--output",
+                marked.to_str().unwrap(),
+                "--key-file",
+                key.to_str().unwrap(),
+                "--account",
+                "acct_cli",
+                "--session",
+                "s1",
+                "--preset",
+                preset,
+                "--internal-rate",
+                internal_rate,
+            ])
+        };
+        let embedded = embed(internal_rate);
+        assert!(embedded.status.success(), "{:?}", embedded);
+        assert!(stdout(&embedded).contains(&format!("\"sample_rate\": {}", sample_rate)));
+
+        let invalid = embed("fast");
+        assert_eq!(invalid.status.code(), Some(64));
+        assert!(String::from_utf8_lossy(&invalid.stderr).contains("--internal-rate"));
+    }
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn usage_errors_exit_with_64() {
+    assert_eq!(wavemark(&[]).status.code(), Some(64));
+    assert_eq!(wavemark(&["frobnicate"]).status.code(), Some(64));
+    assert_eq!(
+        wavemark(&["keygen", "--colour", "blue"]).status.code(),
+        Some(64)
+    );
+    let missing_key = wavemark(&[
+        "detect",
+        "--input",
+        "x.wav",
+        "--account",
+        "a",
+        "--session",
+        "s",
+    ]);
+    assert_eq!(missing_key.status.code(), Some(64));
+    assert!(String::from_utf8_lossy(&missing_key.stderr).contains("WAVEMARK_MASTER_KEY"));
+
+    let masking = wavemark(&[
+        "embed",
+        "--input",
+        "x.wav",
+        "--output",
+        "y.wav",
+        "--masking",
+        "yes",
+    ]);
+    assert_eq!(masking.status.code(), Some(64));
+    assert!(String::from_utf8_lossy(&masking.stderr).contains("--masking"));
+
+    // Help is available after the subcommand.
+    let help = wavemark(&["embed", "--help"]);
+    assert!(help.status.success());
+    assert!(stdout(&help).contains("--masking on|off"));
+    assert!(wavemark(&["detect", "-h"]).status.success());
+}
@@ -1,28 +1,69 @@
 //! Basic usage example for Wavemark
 //!
-//! This example demonstrates how to use the wavemark library.
+//! This example builds a payload, embeds it into a synthetic signal and
+//! detects it again with the high-level `WatermarkBuilder` API.
 
-use wavemark::{api, decoder, encoder, fourier};
+use std::error::Error;
 
-fn main() {
+use wavemark::api::builder::WatermarkBuilder;
+use wavemark::embed::params::{EmbedParams, Preset};
+use wavemark::format::FormatBuilder;
+use wavemark::key::derivation::KeyContext;
+
+fn main() -> Result<(), Box<dyn Error>> {
     println!("Wavemark Basic Usage Example");
     println!("============================");
 
-    // Test encoder
-    encoder::encode();
-    println!("✅ Encoder function called");
+    let sample_rate = 44_100;
+    let session = KeyContext::from_master_secret(b"example-master-secret")?
+        .account("acct_example")?
+        .session("session-1")?;
+
+    let mut payload = FormatBuilder::new();
+    payload
+        .payload_builder()
+        .account_id("acct_example")?
+        .text_field("content.title", "Example")?;
+
+    let params = EmbedParams::builder()
+        .preset(Preset::Balanced)
+        .sample_rate(sample_rate)
+        .strength_db(3.0)
+        .build()?;
+    let (mut embedder, mut detector) = WatermarkBuilder::new(sample_rate)
+        .payload(payload)
+        .key(session)
+        .params(params)
+        .build()?;
+    println!(
+        "Payload: {} bytes at {:.1} bits/s",
+        embedder.payload_bytes().len(),
+        embedder.params().bit_rate()
+    );
 
-    // Test decoder
-    decoder::decode();
-    println!("✅ Decoder function called");
+    // Eight seconds of a tone over noise stands in for real audio.
+    let mut state = 0x1234_5678u32;
+    let mut samples: Vec<f32> = (0..sample_rate as usize * 8)
+        .map(|n| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let noise = (state >> 8) as f32 / (1u32 << 24) as f32 - 0.5;
+            let t = n as f32 / sample_rate as f32;
+            0.25 * (2.0 * std::f32::consts::PI * 220.0 * t).sin() + 0.2 * noise
+        })
+        .collect();
 
-    // Test fourier
-    fourier::transform();
-    println!("✅ Fourier transform function called");
+    embedder.embed(&mut samples)?;
+    println!("Embedded watermark into {} samples", samples.len());
 
-    // Test api
-    api::process();
-    println!("✅ API process function called");
+    let report = detector.detect(&samples)?;
+    match &report.frame {
+        Some(frame) => println!(
+            "Detected account {:?} (false-positive probability {:.2e})",
+            frame.account_id().map(|id| id.as_str()),
+            report.confidence.false_positive_probability()
+        ),
+        None => println!("No watermark detected ({:?})", report.decision()),
+    }
 
-    println!("\n🎉 All wavemark functions working!");
+    Ok(())
 }
@@ -1,6 +1,606 @@
-#![allow(dead_code)]
+//! One-stop builder that wires payload formatting, key derivation, embedding
+//! and detection together.
+//!
+//! [`WatermarkBuilder`] collects a [`FormatBuilder`] payload, a session-level
+//! [`KeyContext`], optional [`EmbedParams`] and the sample rate of the audio.
+//! It yields a matching pair of handles:
+//!
+//! - [`Embedder`] serializes the payload once and marks buffers in place.
+//! - [`Detector`] regenerates the same spreading code, searches audio for a
+//!   payload and summarises the outcome in a [`DetectionReport`].
+//!
+//! Multichannel audio is marked through [`Embedder::embed_chunk`] according
+//! to the builder's [`ChannelMode`], [`ChannelMode::Mid`] by default, and
+//! [`Detector::detect_chunk`] searches the mean downmix. Payloads embedded in
+//! [`ChannelMode::Independent`] are found by
+//! [`WatermarkBuilder::build_channel_detector`].
+//!
+//! With [`WatermarkBuilder::internal_rate`], embedding and detection run at a
+//! fixed internal rate instead of the rate of the audio. The embedder
+//! resamples its input to that rate, marks it and adds the resampled change
+//! back; the detector resamples its input before searching. A watermark
+//! embedded in a 24 kHz clip is then found in a 44.1 kHz or 16 kHz copy by a
+//! detector built for the copy's rate, without knowing the original one.
+//! Content above the internal Nyquist frequency passes through unmarked, so
+//! [`WatermarkBuilder::CANONICAL_RATE`] is the lowest rate a clip is expected
+//! to be resampled to. Buffers are processed at their own rate unless an
+//! internal rate is set; the [`wav`](crate::api::wav) functions default to
+//! the canonical rate instead, unless [`WatermarkBuilder::native_rate`] is
+//! called.
+//!
+//! Without explicit parameters the [`Preset::Balanced`] preset is used.
+//! Parameters are resolved at the builder's sample rate, or at the internal
+//! rate when one is set. A preset given through [`WatermarkBuilder::preset`]
+//! or [`WatermarkBuilder::params_builder`] is resolved there too, so its band
+//! fits below that rate's Nyquist frequency. Explicit [`EmbedParams`] are
+//! adopted with only their sample rate replaced, then validated.
+//!
+//! ```ignore
+//! use wavemark::api::builder::WatermarkBuilder;
+//! use wavemark::format::FormatBuilder;
+//!
+//! let mut payload = FormatBuilder::new();
+//! payload.payload_builder().account_id("acct_demo")?;
+//!
+//! let (mut embedder, mut detector) = WatermarkBuilder::new(48_000)
+//!     .payload(payload)
+//!     .key(session)
+//!     .build()?;
+//!
+//! embedder.embed(&mut samples)?;
+//! let report = detector.detect(&samples)?;
+//! assert!(report.is_present());
+//! ```
 
-//! Placeholder builder for configuring watermark sessions.
+use std::fmt;
 
-/// Placeholder builder struct for constructing watermark pipelines.
-pub struct WatermarkBuilder;
+use crate::core::sample::Sample;
+use crate::core::types::{AudioChunk, AudioChunkError, ChannelLayout};
+use crate::detect::confidence::{ConfidenceConfig, Decision, DetectionConfidence};
+use crate::detect::correlator::{Correlator, DetectError};
+use crate::detect::stretch::StretchSearch;
+use crate::detect::sync::SyncMatch;
+use crate::embed::masking::MaskingConfig;
+use crate::embed::multichannel::{channel_context, ChannelMode, MultichannelEmbedder};
+use crate::embed::params::{EmbedParams, EmbedParamsBuilder, Preset};
+use crate::embed::spread_spectrum::EmbedError;
+use crate::format::codec::{CodecError, FrameCodec};
+use crate::format::encryption::EncryptionContext;
+use crate::format::fec::FecReport;
+use crate::format::payload::PayloadFrame;
+use crate::format::FormatBuilder;
+use crate::key::derivation::KeyContext;
+use crate::transforms::resample::{ResampleError, Resampler};
+
+/// Collects everything needed to embed and detect one watermark.
+#[derive(Debug, Clone)]
+pub struct WatermarkBuilder {
+    sample_rate: u32,
+    payload: Option<FormatBuilder>,
+    key: Option<KeyContext>,
+    params: Option<EmbedParamsBuilder>,
+    masking: Option<MaskingConfig>,
+    confidence: ConfidenceConfig,
+    stretch: Option<StretchSearch>,
+    channel_mode: ChannelMode,
+    rate: RatePolicy,
+}
+
+impl WatermarkBuilder {
+    /// Internal rate for clips that may be resampled as low as 16 kHz.
+    pub const CANONICAL_RATE: u32 = 16_000;
+
+    /// Starts a builder for audio sampled at `sample_rate` hertz.
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            payload: None,
+            key: None,
+            params: None,
+            masking: None,
+            confidence: ConfidenceConfig::default(),
+            stretch: None,
+            channel_mode: ChannelMode::default(),
+            rate: RatePolicy::Unset,
+        }
+    }
+
+    /// Replaces the sample rate given to [`WatermarkBuilder::new`].
+    pub fn sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    /// Sets the payload to embed; its codec options also configure detection.
+    pub fn payload(mut self, payload: FormatBuilder) -> Self {
+        self.payload = Some(payload);
+        self
+    }
+
+    /// Sets the session-level key the spreading code is derived from.
+    pub fn key(mut self, key: KeyContext) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Overrides the embedding parameters.
+    pub fn params(mut self, params: EmbedParams) -> Self {
+        self.params = Some(params.to_builder());
+        self
+    }
+
+    /// Overrides the embedding parameters with a builder that is completed
+    /// at the processing rate.
+    pub fn params_builder(mut self, params: EmbedParamsBuilder) -> Self {
+        self.params = Some(params);
+        self
+    }
+
+    /// Embeds with `preset`, resolved at the processing rate.
+    pub fn preset(self, preset: Preset) -> Self {
+        self.params_builder(EmbedParams::builder().preset(preset))
+    }
+
+    /// Shapes the watermark with the psychoacoustic masking model.
+    pub fn masking(mut self, config: MaskingConfig) -> Self {
+        self.masking = Some(config);
+        self
+    }
+
+    /// Sets the thresholds used to classify detections.
+    pub fn confidence(mut self, config: ConfidenceConfig) -> Self {
+        self.confidence = config;
+        self
+    }
+
+    /// Lets the detector search for speed, tempo or pitch changes.
+    pub fn stretch_search(mut self, search: StretchSearch) -> Self {
+        self.stretch = Some(search);
+        self
+    }
+
+    /// Sets how [`Embedder::embed_chunk`] distributes the payload over channels.
+    pub fn channel_mode(mut self, mode: ChannelMode) -> Self {
+        self.channel_mode = mode;
+        self
+    }
+
+    /// Embeds and detects at `rate` hertz, resampling audio of other rates.
+    ///
+    /// Embedder and detector must use the same internal rate; the sample rate
+    /// of the audio may then differ between them.
+    pub fn internal_rate(mut self, rate: u32) -> Self {
+        self.rate = RatePolicy::Internal(rate);
+        self
+    }
+
+    /// Embeds and detects at the sample rate of the audio, without resampling.
+    ///
+    /// This is the default, except in the [`wav`](crate::api::wav) functions,
+    /// which otherwise use [`WatermarkBuilder::CANONICAL_RATE`].
+    pub fn native_rate(mut self) -> Self {
+        self.rate = RatePolicy::Native;
+        self
+    }
+
+    /// Uses `rate` as the internal rate unless one was set or the native
+    /// rate was requested.
+    pub(crate) fn default_internal_rate(mut self, rate: u32) -> Self {
+        if self.rate == RatePolicy::Unset {
+            self.rate = RatePolicy::Internal(rate);
+        }
+        self
+    }
+
+    /// Builds the embedder and the matching detector.
+    pub fn build(self) -> Result<(Embedder, Detector), WatermarkError> {
+        let detector = self.build_detector()?;
+        let embedder = self.build_embedder()?;
+        Ok((embedder, detector))
+    }
+
+    /// Builds an embedder; requires a payload and a key.
+    pub fn build_embedder(self) -> Result<Embedder, WatermarkError> {
+        let params = self.resolve_params()?;
+        let conversion = self.conversion()?;
+        let key = self.key.ok_or(WatermarkError::MissingKey)?;
+        let payload = self.payload.ok_or(WatermarkError::MissingPayload)?;
+        let (codec, context) = (payload.codec().clone(), payload.context().clone());
+        let bytes = payload.build()?.bytes;
+        // The frame as serialized, e.g. with timestamps truncated to seconds.
+        let frame = codec.decode(&bytes, &context)?;
+
+        let mut inner = MultichannelEmbedder::from_key_context(&key, params, self.channel_mode)?;
+        if let Some(config) = self.masking {
+            inner = inner.with_masking(config)?;
+        }
+        Ok(Embedder {
+            inner,
+            conversion,
+            frame,
+            bytes,
+        })
+    }
+
+    /// Builds a detector; requires a key.
+    ///
+    /// When no payload was supplied, frames are decoded with default codec
+    /// options and no encryption context.
+    pub fn build_detector(&self) -> Result<Detector, WatermarkError> {
+        let key = self.key.as_ref().ok_or(WatermarkError::MissingKey)?;
+        self.detector_for(key)
+    }
+
+    /// Builds a detector for the payload of channel `channel` embedded in
+    /// [`ChannelMode::Independent`]; requires a key.
+    ///
+    /// Channel 0 shares the session key, so its detector equals
+    /// [`WatermarkBuilder::build_detector`].
+    pub fn build_channel_detector(&self, channel: usize) -> Result<Detector, WatermarkError> {
+        let key = self.key.as_ref().ok_or(WatermarkError::MissingKey)?;
+        self.detector_for(&channel_context(key, channel))
+    }
+
+    fn detector_for(&self, key: &KeyContext) -> Result<Detector, WatermarkError> {
+        let params = self.resolve_params()?;
+        let (codec, context) = match &self.payload {
+            Some(payload) => (payload.codec().clone(), payload.context().clone()),
+            None => (
+                FrameCodec::new(Default::default()),
+                EncryptionContext::default(),
+            ),
+        };
+
+        let mut correlator =
+            Correlator::from_key_context(key, params)?.with_confidence(self.confidence);
+        if let Some(search) = &self.stretch {
+            correlator = correlator.with_stretch_search(search.clone())?;
+        }
+        Ok(Detector {
+            correlator,
+            conversion: self.conversion()?,
+            codec,
+            context,
+        })
+    }
+
+    fn conversion(&self) -> Result<Option<RateConversion>, WatermarkError> {
+        match self.rate {
+            RatePolicy::Internal(rate) if rate != self.sample_rate => Ok(Some(RateConversion {
+                to_internal: Resampler::new(self.sample_rate, rate)?,
+                from_internal: Resampler::new(rate, self.sample_rate)?,
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    fn resolve_params(&self) -> Result<EmbedParams, WatermarkError> {
+        let params = match &self.params {
+            Some(params) => params.clone(),
+            None => EmbedParams::builder().preset(Preset::Balanced),
+        };
+        Ok(params.sample_rate(self.processing_rate()).build()?)
+    }
+
+    /// Rate the watermark is embedded and detected at.
+    fn processing_rate(&self) -> u32 {
+        match self.rate {
+            RatePolicy::Internal(rate) => rate,
+            RatePolicy::Unset | RatePolicy::Native => self.sample_rate,
+        }
+    }
+}
+
+/// Rate at which a [`WatermarkBuilder`] embeds and detects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RatePolicy {
+    /// Neither an internal nor the native rate was requested.
+    Unset,
+    /// The sample rate of the audio.
+    Native,
+    /// A fixed internal rate.
+    Internal(u32),
+}
+
+/// Resamplers between the audio's rate and the internal rate.
+#[derive(Debug, Clone)]
+struct RateConversion {
+    to_internal: Resampler,
+    from_internal: Resampler,
+}
+
+impl RateConversion {
+    /// Input samples per internal sample.
+    fn ratio(&self) -> f64 {
+        self.to_internal.from_rate() as f64 / self.to_internal.to_rate() as f64
+    }
+}
+
+/// Embeds one serialized payload into audio buffers.
+#[derive(Debug)]
+pub struct Embedder {
+    inner: MultichannelEmbedder,
+    conversion: Option<RateConversion>,
+    frame: PayloadFrame,
+    bytes: Vec<u8>,
+}
+
+impl Embedder {
+    /// Watermarks mono `samples` in place.
+    pub fn embed(&mut self, samples: &mut [f32]) -> Result<(), WatermarkError> {
+        let mut chunk = AudioChunk::interleaved_mut(samples, ChannelLayout::Mono)
+            .expect("mono layout accepts any length");
+        self.embed_chunk(&mut chunk)
+    }
+
+    /// Watermarks every channel of `chunk` in place, as set by the channel mode.
+    ///
+    /// Integer chunks are written back in their own format with the chunk's
+    /// dither. In [`ChannelMode::Independent`] every channel carries this
+    /// embedder's payload under its own channel key; embed different payloads
+    /// per channel with a [`MultichannelEmbedder`].
+    pub fn embed_chunk<S: Sample>(
+        &mut self,
+        chunk: &mut AudioChunk<'_, S>,
+    ) -> Result<(), WatermarkError> {
+        if !chunk.is_writable() {
+            return Err(EmbedError::from(AudioChunkError::ReadOnly).into());
+        }
+        let payloads = vec![self.bytes.as_slice(); self.inner.payload_count(chunk.channels())];
+        let Some(conversion) = &self.conversion else {
+            self.inner.embed(chunk, &payloads)?;
+            return Ok(());
+        };
+
+        // Mark an internal-rate copy and add back only what marking changed.
+        let original = (0..chunk.channels())
+            .map(|channel| Ok(conversion.to_internal.process(&chunk.channel(channel)?)))
+            .collect::<Result<Vec<_>, AudioChunkError>>()
+            .map_err(EmbedError::from)?;
+        let mut marked = original.clone();
+        let planes = marked.iter_mut().map(Vec::as_mut_slice).collect();
+        let mut internal = AudioChunk::planar_mut(planes).map_err(EmbedError::from)?;
+        self.inner.embed(&mut internal, &payloads)?;
+
+        for (channel, (before, after)) in original.iter().zip(&marked).enumerate() {
+            let change: Vec<f32> = after.iter().zip(before).map(|(a, b)| a - b).collect();
+            let change = conversion.from_internal.process(&change);
+            let mut samples = chunk.channel(channel).map_err(EmbedError::from)?;
+            for (sample, delta) in samples.iter_mut().zip(change) {
+                *sample += delta;
+            }
+            chunk
+                .set_channel(channel, &samples)
+                .map_err(EmbedError::from)?;
+        }
+        Ok(())
+    }
+
+    /// Payload frame being embedded, as a detector decodes it.
+    pub fn frame(&self) -> &PayloadFrame {
+        &self.frame
+    }
+
+    /// Serialized payload bytes being embedded.
+    pub fn payload_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Embedding parameters in use, at the internal rate when one is set.
+    pub fn params(&self) -> &EmbedParams {
+        self.inner.params()
+    }
+
+    /// Sample rate of the audio this embedder marks.
+    pub fn sample_rate(&self) -> u32 {
+        match &self.conversion {
+            Some(conversion) => conversion.to_internal.from_rate(),
+            None => self.inner.params().sample_rate(),
+        }
+    }
+
+    /// Channel mode used by [`Embedder::embed_chunk`].
+    pub fn channel_mode(&self) -> ChannelMode {
+        self.inner.mode()
+    }
+}
+
+/// Searches audio for a payload embedded with the same key and parameters.
+#[derive(Debug)]
+pub struct Detector {
+    correlator: Correlator,
+    conversion: Option<RateConversion>,
+    codec: FrameCodec,
+    context: EncryptionContext,
+}
+
+impl Detector {
+    /// Detects and decodes a payload in `samples`.
+    ///
+    /// Audio without a decodable payload yields a report without a frame;
+    /// only configuration problems and too-short input are errors.
+    /// With an internal rate, sample positions in the report refer to
+    /// `samples`, not to the resampled copy that was searched.
+    pub fn detect(&mut self, samples: &[f32]) -> Result<DetectionReport, WatermarkError> {
+        let resampled;
+        let (samples, ratio) = match &self.conversion {
+            Some(conversion) => {
+                resampled = conversion.to_internal.process(samples);
+                (resampled.as_slice(), conversion.ratio())
+            }
+            None => (samples, 1.0),
+        };
+        let rescale = |position: isize| (position as f64 * ratio).round() as isize;
+
+        match self.correlator.detect(samples, &self.codec, &self.context) {
+            Ok(detection) => Ok(DetectionReport {
+                frame: Some(detection.frame),
+                bytes: Some(detection.bytes),
+                payload_start: Some(rescale(detection.payload_start)),
+                sync: detection.sync.map(|sync| SyncMatch {
+                    offset: rescale(sync.offset),
+                    symbol_phase: (sync.symbol_phase as f64 * ratio).round() as usize,
+                    ..sync
+                }),
+                stretch: detection.stretch,
+                confidence: detection.confidence,
+                fec: detection.fec,
+            }),
+            Err(DetectError::NotDetected(confidence)) => Ok(DetectionReport {
+                frame: None,
+                bytes: None,
+                payload_start: None,
+                sync: None,
+                stretch: 1.0,
+                confidence,
+                fec: None,
+            }),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Channel soft bits of a payload whose embedding started at sample
+    /// `origin` of `samples`, read without searching; see
+    /// [`Correlator::soft_bits_at`].
+    pub fn soft_bits_at(
+        &mut self,
+        samples: &[f32],
+        origin: isize,
+        bit_count: usize,
+    ) -> Result<Vec<f32>, WatermarkError> {
+        let Some(conversion) = &self.conversion else {
+            return Ok(self.correlator.soft_bits_at(samples, origin, bit_count)?);
+        };
+        let resampled = conversion.to_internal.process(samples);
+        let origin = (origin as f64 / conversion.ratio()).round() as isize;
+        Ok(self
+            .correlator
+            .soft_bits_at(&resampled, origin, bit_count)?)
+    }
+
+    /// Detects and decodes a payload in the mean downmix of `chunk`.
+    pub fn detect_chunk<S: Sample>(
+        &mut self,
+        chunk: &AudioChunk<'_, S>,
+    ) -> Result<DetectionReport, WatermarkError> {
+        self.detect(&chunk.downmix())
+    }
+
+    /// Number of samples needed before detection can run.
+    pub fn min_samples(&self) -> usize {
+        match &self.conversion {
+            Some(conversion) => conversion
+                .from_internal
+                .output_len(self.correlator.min_samples()),
+            None => self.correlator.min_samples(),
+        }
+    }
+
+    /// Embedding parameters the detector expects, at the internal rate when
+    /// one is set.
+    pub fn params(&self) -> &EmbedParams {
+        self.correlator.params()
+    }
+
+    /// Thresholds behind the confidence decision.
+    pub fn confidence_config(&self) -> &ConfidenceConfig {
+        self.correlator.confidence_config()
+    }
+
+    /// Sample rate of the audio this detector searches.
+    pub fn sample_rate(&self) -> u32 {
+        match &self.conversion {
+            Some(conversion) => conversion.to_internal.from_rate(),
+            None => self.correlator.params().sample_rate(),
+        }
+    }
+}
+
+/// Outcome of [`Detector::detect`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectionReport {
+    /// Decoded payload frame, if one was found.
+    pub frame: Option<PayloadFrame>,
+    /// Codec bytes of the decoded frame.
+    pub bytes: Option<Vec<u8>>,
+    /// Sample index of the first bit of the located payload copy.
+    pub payload_start: Option<isize>,
+    /// Recovered sync position and symbol phase, when the parameters enable sync.
+    pub sync: Option<SyncMatch>,
+    /// Estimated speed, tempo or pitch factor of the input; `1.0` when no
+    /// payload was found or no stretch search is configured.
+    pub stretch: f32,
+    /// Statistical confidence of the best match.
+    pub confidence: DetectionConfidence,
+    /// Shards repaired by forward error correction, when a frame decoded
+    /// and the mapper uses FEC.
+    pub fec: Option<FecReport>,
+}
+
+impl DetectionReport {
+    /// Returns `true` when a frame decoded and the confidence decision is `Present`.
+    pub fn is_present(&self) -> bool {
+        self.frame.is_some() && self.confidence.decision() == Decision::Present
+    }
+
+    /// Confidence decision for the best match.
+    pub fn decision(&self) -> Decision {
+        self.confidence.decision()
+    }
+}
+
+/// Errors raised by the high-level API.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatermarkError {
+    /// [`WatermarkBuilder::payload`] was not called.
+    MissingPayload,
+    /// [`WatermarkBuilder::key`] was not called.
+    MissingKey,
+    /// Payload serialization failed.
+    Format(CodecError),
+    /// Embedding parameters or embedding failed.
+    Embed(EmbedError),
+    /// Detection failed.
+    Detect(DetectError),
+    /// The audio cannot be converted to the internal rate.
+    Resample(ResampleError),
+}
+
+impl fmt::Display for WatermarkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatermarkError::MissingPayload => write!(f, "no payload was provided"),
+            WatermarkError::MissingKey => write!(f, "no key was provided"),
+            WatermarkError::Format(err) => write!(f, "format error: {}", err),
+            WatermarkError::Embed(err) => write!(f, "embed error: {}", err),
+            WatermarkError::Detect(err) => write!(f, "detect error: {}", err),
+            WatermarkError::Resample(err) => write!(f, "resample error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for WatermarkError {}
+
+impl From<CodecError> for WatermarkError {
+    fn from(err: CodecError) -> Self {
+        WatermarkError::Format(err)
+    }
+}
+
+impl From<EmbedError> for WatermarkError {
+    fn from(err: EmbedError) -> Self {
+        WatermarkError::Embed(err)
+    }
+}
+
+impl From<DetectError> for WatermarkError {
+    fn from(err: DetectError) -> Self {
+        WatermarkError::Detect(err)
+    }
+}
+
+impl From<ResampleError> for WatermarkError {
+    fn from(err: ResampleError) -> Self {
+        WatermarkError::Resample(err)
+    }
+}
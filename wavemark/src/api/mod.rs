@@ -1,3 +1,25 @@
-//! High-level API placeholders.
+//! High-level watermarking API.
+//!
+//! [`builder::WatermarkBuilder`] configures embedding and detection;
+//! [`process`] marks a buffer and verifies the mark in one call, and
+//! [`wav`] does the same for WAV files.
 
 pub mod builder;
+pub mod wav;
+
+use crate::api::builder::{DetectionReport, WatermarkBuilder, WatermarkError};
+
+/// Marks `samples` in place with the payload configured on `builder`, then
+/// searches the marked samples with a matching detector.
+///
+/// The returned report shows whether the mark is recoverable from the buffer
+/// as written. Buffers shorter than one payload repetition are marked but
+/// fail detection with [`WatermarkError::Detect`].
+pub fn process(
+    builder: WatermarkBuilder,
+    samples: &mut [f32],
+) -> Result<DetectionReport, WatermarkError> {
+    let (mut embedder, mut detector) = builder.build()?;
+    embedder.embed(samples)?;
+    detector.detect(samples)
+}
@@ -0,0 +1,331 @@
+//! WAV file watermarking built on `hound`.
+//!
+//! These functions wrap [`WatermarkBuilder`] for audio stored as RIFF WAVE.
+//! The builder's sample rate is replaced by the file's, so one builder can be
+//! reused across files of different rates. Unless the builder sets an
+//! [internal rate](WatermarkBuilder::internal_rate) or asks for the
+//! [native rate](WatermarkBuilder::native_rate), files are marked and
+//! searched at [`WatermarkBuilder::CANONICAL_RATE`], so a file's watermark is
+//! found in copies converted to another rate.
+//!
+//! Integer PCM of 8, 16, 24 and 32 bits and 32-bit float PCM are supported.
+//! Samples are kept in their native [`Sample`] format, the channels are
+//! watermarked as set by the builder's [`ChannelMode`], and the result is
+//! written back with the input's exact [`WavSpec`]. Integer output is
+//! quantized with the chunk's triangular [`Dither`], so a watermark smaller
+//! than one LSB is not rounded away. Values beyond the format's range are
+//! clamped; [`WavEmbedReport::clipped_samples`] counts them so callers can
+//! reduce the level of hot masters.
+//!
+//! Detection runs on the mono downmix, then on every channel, and reports the
+//! first `Present` result, with its confidence corrected for every signal
+//! searched. When none is present, the closest match is reported.
+//!
+//! ```ignore
+//! use wavemark::api::wav;
+//!
+//! let builder = WatermarkBuilder::new(44_100).payload(payload).key(session);
+//! let report = wav::embed_wav_file("master.wav", "marked.wav", builder.clone())?;
+//! println!("{} channels, {} clipped samples", report.spec.channels, report.clipped_samples);
+//!
+//! let detection = wav::detect_wav_file("marked.wav", builder)?;
+//! assert!(detection.is_present());
+//! ```
+//!
+//! [`ChannelMode`]: crate::embed::multichannel::ChannelMode
+//! [`Dither`]: crate::core::sample::Dither
+
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, Write};
+use std::path::Path;
+
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+
+use crate::api::builder::{DetectionReport, Embedder, WatermarkBuilder, WatermarkError};
+use crate::core::sample::{Sample, I24};
+use crate::core::types::{AudioChunk, ChannelLayout};
+
+/// Summary of a watermarked WAV stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WavEmbedReport {
+    /// Format of both the input and the output.
+    pub spec: WavSpec,
+    /// Number of sample frames (samples per channel).
+    pub frames: usize,
+    /// Integer samples clamped to the format's range after embedding.
+    pub clipped_samples: usize,
+}
+
+/// Watermarks the WAV file at `input` and writes the result to `output`.
+pub fn embed_wav_file<P, Q>(
+    input: P,
+    output: Q,
+    builder: WatermarkBuilder,
+) -> Result<WavEmbedReport, WavError>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let reader = BufReader::new(File::open(input)?);
+    let writer = BufWriter::new(File::create(output)?);
+    embed_wav(reader, writer, builder)
+}
+
+/// Watermarks a WAV stream read from `reader` and writes it to `writer`.
+pub fn embed_wav<R, W>(
+    reader: R,
+    writer: W,
+    builder: WatermarkBuilder,
+) -> Result<WavEmbedReport, WavError>
+where
+    R: Read,
+    W: Write + Seek,
+{
+    let mut reader = WavReader::new(reader)?;
+    let spec = reader.spec();
+    check_spec(spec)?;
+    let mut embedder = file_builder(builder, spec).build_embedder()?;
+    let mut writer = WavWriter::new(writer, spec)?;
+
+    let (frames, clipped_samples) = match (spec.sample_format, spec.bits_per_sample) {
+        (SampleFormat::Float, _) => {
+            let samples = reader.samples::<f32>().collect::<Result<_, _>>()?;
+            embed_samples(&mut embedder, samples, spec, |sample: f32| {
+                writer.write_sample(sample)
+            })?
+        }
+        (SampleFormat::Int, 8) => {
+            let samples = reader.samples::<i8>().collect::<Result<_, _>>()?;
+            embed_samples(&mut embedder, samples, spec, |sample: i8| {
+                writer.write_sample(sample)
+            })?
+        }
+        (SampleFormat::Int, 16) => {
+            let samples = reader.samples::<i16>().collect::<Result<_, _>>()?;
+            embed_samples(&mut embedder, samples, spec, |sample: i16| {
+                writer.write_sample(sample)
+            })?
+        }
+        (SampleFormat::Int, 24) => {
+            let samples = reader
+                .samples::<i32>()
+                .map(|sample| sample.map(I24::saturating))
+                .collect::<Result<_, _>>()?;
+            embed_samples(&mut embedder, samples, spec, |sample: I24| {
+                writer.write_sample(sample.get())
+            })?
+        }
+        (SampleFormat::Int, _) => {
+            let samples = reader.samples::<i32>().collect::<Result<_, _>>()?;
+            embed_samples(&mut embedder, samples, spec, |sample: i32| {
+                writer.write_sample(sample)
+            })?
+        }
+    };
+    writer.finalize()?;
+
+    Ok(WavEmbedReport {
+        spec,
+        frames,
+        clipped_samples,
+    })
+}
+
+/// Watermarks interleaved `samples` in their native format and passes them
+/// to `write`, returning the number of frames and of clamped samples.
+///
+/// A truncated final frame is dropped so all channels stay aligned.
+fn embed_samples<S, F>(
+    embedder: &mut Embedder,
+    mut samples: Vec<S>,
+    spec: WavSpec,
+    mut write: F,
+) -> Result<(usize, usize), WavError>
+where
+    S: Sample,
+    F: FnMut(S) -> Result<(), hound::Error>,
+{
+    let channels = spec.channels as usize;
+    samples.truncate(samples.len() / channels * channels);
+    let layout = ChannelLayout::from_channels(channels);
+    let mut chunk = AudioChunk::interleaved_mut(&mut samples, layout)
+        .expect("whole frames of at least one channel");
+    embedder.embed_chunk(&mut chunk)?;
+    let (frames, clipped) = (chunk.frames(), chunk.clipped_samples());
+
+    for sample in samples {
+        write(sample)?;
+    }
+    Ok((frames, clipped))
+}
+
+/// Detects a watermark in the WAV file at `path`.
+pub fn detect_wav_file<P: AsRef<Path>>(
+    path: P,
+    builder: WatermarkBuilder,
+) -> Result<DetectionReport, WavError> {
+    detect_wav(BufReader::new(File::open(path)?), builder)
+}
+
+/// Detects a watermark in a WAV stream read from `reader`.
+///
+/// The mean downmix is searched first, then each channel separately. Every
+/// report's confidence is corrected for the number of signals searched, so a
+/// multichannel file keeps the configured false-alarm rate. The first signal
+/// that decodes a frame with a `Present` decision wins; otherwise the report
+/// with the lowest false-positive probability is returned.
+pub fn detect_wav<R: Read>(
+    reader: R,
+    builder: WatermarkBuilder,
+) -> Result<DetectionReport, WavError> {
+    let (spec, channels) = read_channels(WavReader::new(reader)?)?;
+    let mut detector = file_builder(builder, spec).build_detector()?;
+
+    let downmix = match channels.len() {
+        1 => None,
+        _ => Some(
+            AudioChunk::planar(channels.iter().map(Vec::as_slice).collect())
+                .expect("channels have equal length")
+                .downmix(),
+        ),
+    };
+
+    let searched = (downmix.iter().count() + channels.len()) as u64;
+    let config = *detector.confidence_config();
+    let mut best: Option<DetectionReport> = None;
+    for signal in downmix.iter().chain(&channels) {
+        let mut report = detector.detect(signal)?;
+        report.confidence = report.confidence.widened(searched, &config);
+        if report.is_present() {
+            return Ok(report);
+        }
+        let better = best.as_ref().is_none_or(|best| {
+            report.confidence.false_positive_probability()
+                < best.confidence.false_positive_probability()
+        });
+        if better {
+            best = Some(report);
+        }
+    }
+    best.ok_or(WavError::Empty)
+}
+
+/// `builder` set up for a file of format `spec`.
+fn file_builder(builder: WatermarkBuilder, spec: WavSpec) -> WatermarkBuilder {
+    builder
+        .sample_rate(spec.sample_rate)
+        .default_internal_rate(WatermarkBuilder::CANONICAL_RATE)
+}
+
+/// Reads all samples, de-interleaved and scaled to `[-1, 1)`.
+fn read_channels<R: Read>(mut reader: WavReader<R>) -> Result<(WavSpec, Vec<Vec<f32>>), WavError> {
+    let spec = reader.spec();
+    check_spec(spec)?;
+    let channel_count = spec.channels as usize;
+    let frames = reader.duration() as usize;
+    let mut channels = vec![Vec::with_capacity(frames); channel_count];
+
+    match spec.sample_format {
+        SampleFormat::Float => {
+            for (index, sample) in reader.samples::<f32>().enumerate() {
+                channels[index % channel_count].push(sample?);
+            }
+        }
+        SampleFormat::Int => {
+            let scale = full_scale(spec.bits_per_sample).recip();
+            for (index, sample) in reader.samples::<i32>().enumerate() {
+                channels[index % channel_count].push((sample? as f64 * scale) as f32);
+            }
+        }
+    }
+
+    // A truncated final frame is dropped so all channels stay aligned.
+    let frames = channels.iter().map(Vec::len).min().unwrap_or(0);
+    for channel in channels.iter_mut() {
+        channel.truncate(frames);
+    }
+    Ok((spec, channels))
+}
+
+fn check_spec(spec: WavSpec) -> Result<(), WavError> {
+    let supported = match spec.sample_format {
+        SampleFormat::Int => matches!(spec.bits_per_sample, 8 | 16 | 24 | 32),
+        SampleFormat::Float => spec.bits_per_sample == 32,
+    };
+    if !supported {
+        return Err(WavError::UnsupportedFormat {
+            bits_per_sample: spec.bits_per_sample,
+            sample_format: spec.sample_format,
+        });
+    }
+    if spec.channels == 0 {
+        return Err(WavError::Empty);
+    }
+    Ok(())
+}
+
+/// Magnitude of the most negative integer sample, `2^(bits - 1)`.
+fn full_scale(bits_per_sample: u16) -> f64 {
+    (1u64 << (bits_per_sample - 1)) as f64
+}
+
+/// Errors raised while watermarking WAV audio.
+#[derive(Debug)]
+pub enum WavError {
+    /// Reading or writing the file failed.
+    Io(std::io::Error),
+    /// The WAV container is malformed or cannot be written.
+    Wav(hound::Error),
+    /// Sample format is not one of the supported PCM variants.
+    UnsupportedFormat {
+        bits_per_sample: u16,
+        sample_format: SampleFormat,
+    },
+    /// The stream contains no channels.
+    Empty,
+    /// Building, embedding or detecting the watermark failed.
+    Watermark(WatermarkError),
+}
+
+impl fmt::Display for WavError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WavError::Io(err) => write!(f, "i/o error: {}", err),
+            WavError::Wav(err) => write!(f, "wav error: {}", err),
+            WavError::UnsupportedFormat {
+                bits_per_sample,
+                sample_format,
+            } => write!(
+                f,
+                "unsupported sample format: {}-bit {:?}",
+                bits_per_sample, sample_format
+            ),
+            WavError::Empty => write!(f, "wav stream contains no channels"),
+            WavError::Watermark(err) => write!(f, "watermark error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for WavError {}
+
+impl From<std::io::Error> for WavError {
+    fn from(err: std::io::Error) -> Self {
+        WavError::Io(err)
+    }
+}
+
+impl From<hound::Error> for WavError {
+    fn from(err: hound::Error) -> Self {
+        match err {
+            hound::Error::IoError(err) => WavError::Io(err),
+            err => WavError::Wav(err),
+        }
+    }
+}
+
+impl From<WatermarkError> for WavError {
+    fn from(err: WatermarkError) -> Self {
+        WavError::Watermark(err)
+    }
+}
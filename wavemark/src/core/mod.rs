@@ -1,4 +1,5 @@
-//! Core module placeholders for the watermarking library.
+//! Core types and state shared across the library.
 
+pub mod sample;
 pub mod state;
 pub mod types;
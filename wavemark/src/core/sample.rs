@@ -0,0 +1,218 @@
+//! Native PCM sample formats and their conversion to the float domain.
+//!
+//! Embedding and detection work on `f32` samples in `[-1, 1)`. [`Sample`]
+//! converts each supported storage format to and from that domain:
+//!
+//! | Format  | Range                  | Full scale |
+//! |---------|------------------------|------------|
+//! | `i8`    | `-128..=127`           | `2^7`      |
+//! | `i16`   | `-32768..=32767`       | `2^15`     |
+//! | [`I24`] | `-8388608..=8388607`   | `2^23`     |
+//! | `i32`   | `-2^31..=2^31 - 1`     | `2^31`     |
+//! | `f32`   | unbounded              | `1.0`      |
+//! | `f64`   | unbounded              | `1.0`      |
+//!
+//! Writing a float back to an integer format quantizes it. Quantizing a small
+//! change such as a watermark by plain rounding turns it into distortion
+//! correlated with the signal; [`Dither::Triangular`] adds triangular noise of
+//! ±1 LSB before rounding so the error becomes benign white noise instead.
+//! Values outside the format's range are clamped and reported as clipped.
+//!
+//! ```ignore
+//! use wavemark::core::sample::{Dither, Sample};
+//!
+//! let mut dither = Dither::Triangular.generator();
+//! let (value, clipped) = i16::from_f32(0.25, dither.next_offset());
+//! assert!((value.to_f32() - 0.25).abs() <= 1.0 / 32_768.0);
+//! assert!(!clipped);
+//! ```
+
+/// A PCM sample format that audio can be watermarked in.
+pub trait Sample: Copy + PartialEq + Send + Sync + 'static {
+    /// Whether the format quantizes, and therefore benefits from dither.
+    const QUANTIZED: bool;
+
+    /// Converts to the float domain, full scale mapping to `1.0`.
+    fn to_f32(self) -> f32;
+
+    /// Converts from the float domain after adding `dither` LSBs.
+    ///
+    /// Returns the sample and whether it had to be clamped to the format's
+    /// range. Float formats ignore `dither` and never clamp.
+    fn from_f32(value: f32, dither: f32) -> (Self, bool);
+}
+
+/// Quantizes `value` to an integer in `-scale..scale` after adding `dither`.
+fn quantize(value: f32, dither: f32, scale: f64) -> (i32, bool) {
+    let level = (value as f64 * scale + dither as f64).round();
+    let clamped = level.clamp(-scale, scale - 1.0);
+    (clamped as i32, clamped != level)
+}
+
+impl Sample for i8 {
+    const QUANTIZED: bool = true;
+
+    fn to_f32(self) -> f32 {
+        self as f32 / 128.0
+    }
+
+    fn from_f32(value: f32, dither: f32) -> (Self, bool) {
+        let (level, clipped) = quantize(value, dither, 128.0);
+        (level as i8, clipped)
+    }
+}
+
+impl Sample for i16 {
+    const QUANTIZED: bool = true;
+
+    fn to_f32(self) -> f32 {
+        self as f32 / 32_768.0
+    }
+
+    fn from_f32(value: f32, dither: f32) -> (Self, bool) {
+        let (level, clipped) = quantize(value, dither, 32_768.0);
+        (level as i16, clipped)
+    }
+}
+
+impl Sample for I24 {
+    const QUANTIZED: bool = true;
+
+    fn to_f32(self) -> f32 {
+        self.0 as f32 / I24::FULL_SCALE as f32
+    }
+
+    fn from_f32(value: f32, dither: f32) -> (Self, bool) {
+        let (level, clipped) = quantize(value, dither, I24::FULL_SCALE as f64);
+        (I24(level), clipped)
+    }
+}
+
+impl Sample for i32 {
+    const QUANTIZED: bool = true;
+
+    fn to_f32(self) -> f32 {
+        (self as f64 / 2_147_483_648.0) as f32
+    }
+
+    fn from_f32(value: f32, dither: f32) -> (Self, bool) {
+        quantize(value, dither, 2_147_483_648.0)
+    }
+}
+
+impl Sample for f32 {
+    const QUANTIZED: bool = false;
+
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    fn from_f32(value: f32, _dither: f32) -> (Self, bool) {
+        (value, false)
+    }
+}
+
+impl Sample for f64 {
+    const QUANTIZED: bool = false;
+
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+
+    fn from_f32(value: f32, _dither: f32) -> (Self, bool) {
+        (value as f64, false)
+    }
+}
+
+/// A 24-bit integer sample held in the low bits of an `i32`, as WAV readers
+/// and audio interfaces usually deliver it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct I24(i32);
+
+impl I24 {
+    /// Smallest 24-bit value.
+    pub const MIN: Self = Self(-(1 << 23));
+    /// Largest 24-bit value.
+    pub const MAX: Self = Self((1 << 23) - 1);
+    /// Magnitude of [`I24::MIN`].
+    const FULL_SCALE: i32 = 1 << 23;
+
+    /// Wraps `value`, or returns `None` outside the 24-bit range.
+    pub fn new(value: i32) -> Option<Self> {
+        (Self::MIN.0..=Self::MAX.0)
+            .contains(&value)
+            .then_some(Self(value))
+    }
+
+    /// Wraps `value`, clamped to the 24-bit range.
+    pub fn saturating(value: i32) -> Self {
+        Self(value.clamp(Self::MIN.0, Self::MAX.0))
+    }
+
+    /// The sample as an `i32`.
+    pub fn get(self) -> i32 {
+        self.0
+    }
+}
+
+impl From<I24> for i32 {
+    fn from(sample: I24) -> Self {
+        sample.0
+    }
+}
+
+/// Noise added before quantizing floats back to integer samples.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Dither {
+    /// Plain rounding.
+    None,
+    /// Triangular (TPDF) noise spanning ±1 LSB.
+    #[default]
+    Triangular,
+}
+
+impl Dither {
+    /// Seed of the generator returned by [`Dither::generator`].
+    pub const DEFAULT_SEED: u32 = 0x2545_f491;
+
+    /// Deterministic noise source for this dither.
+    pub fn generator(self) -> DitherGenerator {
+        DitherGenerator {
+            dither: self,
+            state: Self::DEFAULT_SEED,
+        }
+    }
+}
+
+/// Stream of dither offsets in LSBs.
+#[derive(Debug, Clone)]
+pub struct DitherGenerator {
+    dither: Dither,
+    state: u32,
+}
+
+impl DitherGenerator {
+    /// Restarts the noise sequence from `seed`.
+    pub fn with_seed(mut self, seed: u32) -> Self {
+        // Xorshift has a fixed point at zero.
+        self.state = seed.max(1);
+        self
+    }
+
+    /// Next offset to add before rounding.
+    pub fn next_offset(&mut self) -> f32 {
+        match self.dither {
+            Dither::None => 0.0,
+            Dither::Triangular => self.uniform() + self.uniform() - 1.0,
+        }
+    }
+
+    /// Uniform value in `[0, 1)` from a 32-bit xorshift generator.
+    fn uniform(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        (self.state >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
@@ -1,6 +1,27 @@
-#![allow(dead_code)]
+//! Rolling state shared across chunks of one stream.
 
-//! Placeholder types describing shared pipeline state.
+/// Position of a chunked stream within the payload schedule.
+///
+/// The schedule is a function of the absolute frame index only, so a stream
+/// split into chunks of any size, or handed between controllers by copying
+/// this state, is marked exactly as if it had been processed in one piece.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChunkState {
+    /// Frames processed since the start of the stream.
+    pub frames: u64,
+    /// Chunks processed since the start of the stream.
+    pub chunks: u64,
+}
 
-/// Tracks rolling state for pre-vocoder watermarking.
-pub struct ChunkState;
+impl ChunkState {
+    /// State at the start of a stream.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a processed chunk of `frames` frames.
+    pub fn advance(&mut self, frames: usize) {
+        self.frames += frames as u64;
+        self.chunks += 1;
+    }
+}
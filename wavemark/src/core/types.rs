@@ -1,12 +1,330 @@
-#![allow(dead_code)]
+//! Shared types for the library.
 
-//! Placeholder shared types for the library.
+use std::fmt;
 
-/// Represents an abstract unit of audio handled by the pipeline.
-pub struct AudioChunk;
+use crate::core::sample::{Dither, DitherGenerator, Sample};
+
+/// Arrangement of the channels in multichannel audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelLayout {
+    /// One channel.
+    Mono,
+    /// Left and right, in that order.
+    Stereo,
+    /// Any number of channels without a defined speaker arrangement.
+    Discrete(usize),
+}
+
+impl ChannelLayout {
+    /// Layout for `channels` channels, using the named layouts where possible.
+    pub fn from_channels(channels: usize) -> Self {
+        match channels {
+            1 => ChannelLayout::Mono,
+            2 => ChannelLayout::Stereo,
+            other => ChannelLayout::Discrete(other),
+        }
+    }
+
+    /// Number of channels.
+    pub fn channels(&self) -> usize {
+        match self {
+            ChannelLayout::Mono => 1,
+            ChannelLayout::Stereo => 2,
+            ChannelLayout::Discrete(channels) => *channels,
+        }
+    }
+}
+
+/// Borrowed view of a caller's audio buffer in its native sample format.
+///
+/// The view never copies the buffer. Samples are converted to `f32` as they
+/// are read, and [`AudioChunk::set_channel`] converts back to `S` as it
+/// writes, quantizing integer formats with the chunk's [`Dither`]. A run of
+/// one sample per channel is a *frame*. Buffers are either
+///
+/// - **interleaved**: one slice where sample `i` of channel `c` sits at
+///   `i * channels + c`, or
+/// - **planar**: one equally long slice per channel.
+///
+/// Views made from shared slices can be read, downmixed and detected in;
+/// writing to them fails with [`AudioChunkError::ReadOnly`].
+///
+/// ```ignore
+/// use wavemark::core::types::{AudioChunk, ChannelLayout};
+///
+/// let mut pcm: Vec<i16> = telephony_frames();
+/// let mut chunk = AudioChunk::interleaved_mut(&mut pcm, ChannelLayout::Mono)?;
+/// embedder.embed_chunk(&mut chunk)?;
+/// println!("{} samples clipped", chunk.clipped_samples());
+/// ```
+#[derive(Debug)]
+pub struct AudioChunk<'a, S: Sample = f32> {
+    buffer: Buffer<'a, S>,
+    layout: ChannelLayout,
+    frames: usize,
+    dither: DitherGenerator,
+    clipped: usize,
+}
+
+/// Storage behind an [`AudioChunk`].
+#[derive(Debug)]
+enum Buffer<'a, S> {
+    Interleaved(&'a [S]),
+    InterleavedMut(&'a mut [S]),
+    Planar(Vec<&'a [S]>),
+    PlanarMut(Vec<&'a mut [S]>),
+}
+
+impl<'a, S: Sample> AudioChunk<'a, S> {
+    /// Views interleaved `samples` laid out as `layout`, read-only.
+    pub fn interleaved(samples: &'a [S], layout: ChannelLayout) -> Result<Self, AudioChunkError> {
+        let frames = interleaved_frames(samples.len(), layout)?;
+        Ok(Self::with_buffer(
+            Buffer::Interleaved(samples),
+            layout,
+            frames,
+        ))
+    }
+
+    /// Views interleaved `samples` laid out as `layout`, writable.
+    pub fn interleaved_mut(
+        samples: &'a mut [S],
+        layout: ChannelLayout,
+    ) -> Result<Self, AudioChunkError> {
+        let frames = interleaved_frames(samples.len(), layout)?;
+        Ok(Self::with_buffer(
+            Buffer::InterleavedMut(samples),
+            layout,
+            frames,
+        ))
+    }
+
+    /// Views one slice per channel, read-only.
+    pub fn planar(channels: Vec<&'a [S]>) -> Result<Self, AudioChunkError> {
+        let frames = planar_frames(channels.iter().map(|channel| channel.len()))?;
+        let layout = ChannelLayout::from_channels(channels.len());
+        Ok(Self::with_buffer(Buffer::Planar(channels), layout, frames))
+    }
+
+    /// Views one slice per channel, writable.
+    pub fn planar_mut(channels: Vec<&'a mut [S]>) -> Result<Self, AudioChunkError> {
+        let frames = planar_frames(channels.iter().map(|channel| channel.len()))?;
+        let layout = ChannelLayout::from_channels(channels.len());
+        Ok(Self::with_buffer(
+            Buffer::PlanarMut(channels),
+            layout,
+            frames,
+        ))
+    }
+
+    fn with_buffer(buffer: Buffer<'a, S>, layout: ChannelLayout, frames: usize) -> Self {
+        Self {
+            buffer,
+            layout,
+            frames,
+            dither: Dither::default().generator(),
+            clipped: 0,
+        }
+    }
+
+    /// Replaces the dither applied when writing back to integer formats.
+    pub fn with_dither(mut self, dither: Dither) -> Self {
+        self.dither = dither.generator();
+        self
+    }
+
+    /// Restarts the dither noise from `seed`, e.g. to decorrelate chunks.
+    pub fn with_dither_seed(mut self, seed: u32) -> Self {
+        self.dither = self.dither.with_seed(seed);
+        self
+    }
+
+    /// Channel layout of the samples.
+    pub fn layout(&self) -> ChannelLayout {
+        self.layout
+    }
+
+    /// Number of channels.
+    pub fn channels(&self) -> usize {
+        self.layout.channels()
+    }
+
+    /// Number of frames (samples per channel).
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Returns `true` when the view can be written to.
+    pub fn is_writable(&self) -> bool {
+        matches!(
+            self.buffer,
+            Buffer::InterleavedMut(_) | Buffer::PlanarMut(_)
+        )
+    }
+
+    /// Samples clamped to the native range by writes so far.
+    pub fn clipped_samples(&self) -> usize {
+        self.clipped
+    }
+
+    /// Native sample of channel `channel` in frame `frame`.
+    fn native(&self, channel: usize, frame: usize) -> S {
+        let channels = self.channels();
+        match &self.buffer {
+            Buffer::Interleaved(samples) => samples[frame * channels + channel],
+            Buffer::InterleavedMut(samples) => samples[frame * channels + channel],
+            Buffer::Planar(planes) => planes[channel][frame],
+            Buffer::PlanarMut(planes) => planes[channel][frame],
+        }
+    }
+
+    /// Sample of channel `channel` in frame `frame`, converted to `f32`.
+    pub fn sample(&self, channel: usize, frame: usize) -> f32 {
+        self.native(channel, frame).to_f32()
+    }
+
+    /// Copies out the samples of channel `index`, converted to `f32`.
+    pub fn channel(&self, index: usize) -> Result<Vec<f32>, AudioChunkError> {
+        self.check_channel(index)?;
+        Ok((0..self.frames)
+            .map(|frame| self.sample(index, frame))
+            .collect())
+    }
+
+    /// Overwrites channel `index` with `samples`, one per frame, converting
+    /// to the native format.
+    ///
+    /// Samples equal to the value already stored are left untouched, so
+    /// writing back unchanged audio never adds dither noise.
+    pub fn set_channel(&mut self, index: usize, samples: &[f32]) -> Result<(), AudioChunkError> {
+        self.check_channel(index)?;
+        if samples.len() != self.frames {
+            return Err(AudioChunkError::ChannelLengthMismatch);
+        }
+        if !self.is_writable() {
+            return Err(AudioChunkError::ReadOnly);
+        }
+        let channels = self.channels();
+        for (frame, &value) in samples.iter().enumerate() {
+            if value == self.sample(index, frame) {
+                continue;
+            }
+            let (sample, clipped) = S::from_f32(value, self.dither.next_offset());
+            self.clipped += clipped as usize;
+            match &mut self.buffer {
+                Buffer::InterleavedMut(buffer) => buffer[frame * channels + index] = sample,
+                Buffer::PlanarMut(planes) => planes[index][frame] = sample,
+                Buffer::Interleaved(_) | Buffer::Planar(_) => unreachable!("checked writable"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Mean of all channels; the mid signal of stereo audio.
+    pub fn downmix(&self) -> Vec<f32> {
+        let weights = vec![1.0 / self.channels() as f32; self.channels()];
+        self.weighted_downmix(&weights)
+    }
+
+    /// Sum of the channels scaled by one weight each.
+    pub fn downmix_with(&self, weights: &[f32]) -> Result<Vec<f32>, AudioChunkError> {
+        if weights.len() != self.channels() {
+            return Err(AudioChunkError::WeightCountMismatch {
+                expected: self.channels(),
+                found: weights.len(),
+            });
+        }
+        Ok(self.weighted_downmix(weights))
+    }
+
+    fn weighted_downmix(&self, weights: &[f32]) -> Vec<f32> {
+        (0..self.frames)
+            .map(|frame| {
+                weights
+                    .iter()
+                    .enumerate()
+                    .map(|(channel, weight)| self.sample(channel, frame) * weight)
+                    .sum()
+            })
+            .collect()
+    }
+
+    fn check_channel(&self, index: usize) -> Result<(), AudioChunkError> {
+        if index >= self.channels() {
+            return Err(AudioChunkError::ChannelOutOfRange {
+                index,
+                channels: self.channels(),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn interleaved_frames(len: usize, layout: ChannelLayout) -> Result<usize, AudioChunkError> {
+    let channels = layout.channels();
+    if channels == 0 {
+        return Err(AudioChunkError::NoChannels);
+    }
+    if !len.is_multiple_of(channels) {
+        return Err(AudioChunkError::PartialFrame {
+            samples: len,
+            channels,
+        });
+    }
+    Ok(len / channels)
+}
+
+fn planar_frames(mut lengths: impl Iterator<Item = usize>) -> Result<usize, AudioChunkError> {
+    let frames = lengths.next().ok_or(AudioChunkError::NoChannels)?;
+    if lengths.any(|len| len != frames) {
+        return Err(AudioChunkError::ChannelLengthMismatch);
+    }
+    Ok(frames)
+}
+
+/// Errors raised when building or accessing an [`AudioChunk`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AudioChunkError {
+    /// The layout has no channels.
+    NoChannels,
+    /// The sample count is not a whole number of frames.
+    PartialFrame { samples: usize, channels: usize },
+    /// Channels or replacement samples differ in length.
+    ChannelLengthMismatch,
+    /// A channel index beyond the layout was requested.
+    ChannelOutOfRange { index: usize, channels: usize },
+    /// A downmix was given the wrong number of weights.
+    WeightCountMismatch { expected: usize, found: usize },
+    /// The chunk views a shared buffer and cannot be written.
+    ReadOnly,
+}
+
+impl fmt::Display for AudioChunkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioChunkError::NoChannels => write!(f, "audio chunk layout has no channels"),
+            AudioChunkError::PartialFrame { samples, channels } => write!(
+                f,
+                "{} samples do not divide into frames of {} channels",
+                samples, channels
+            ),
+            AudioChunkError::ChannelLengthMismatch => {
+                write!(f, "channels must all have the same length")
+            }
+            AudioChunkError::ChannelOutOfRange { index, channels } => write!(
+                f,
+                "channel {} is out of range for {} channels",
+                index, channels
+            ),
+            AudioChunkError::WeightCountMismatch { expected, found } => {
+                write!(f, "downmix needs {} weights, got {}", expected, found)
+            }
+            AudioChunkError::ReadOnly => write!(f, "audio chunk views a read-only buffer"),
+        }
+    }
+}
+
+impl std::error::Error for AudioChunkError {}
 
 /// Placeholder payload type embedded into audio.
 pub struct WatermarkPayload;
-
-/// Configuration placeholder for pre-vocoder embedding.
-pub struct PreVocoderConfig;
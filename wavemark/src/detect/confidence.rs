@@ -1,6 +1,245 @@
-#![allow(dead_code)]
+//! Statistical confidence for detection results.
+//!
+//! The correlator reports one z-score per payload bit: on unmarked audio each
+//! is approximately standard normal, and on marked audio its mean is pushed
+//! toward `±μ` by the embedded bit. [`DetectionConfidence`] turns those soft
+//! bits into calibrated numbers:
+//!
+//! - **Log-likelihood ratios.** Under the Gaussian model,
+//!   `LLR_i = ln P(z_i | 1) / P(z_i | 0) = 2 μ̂ z_i`, where the signal amplitude
+//!   `μ̂` is estimated as the mean absolute soft bit.
+//! - **Aggregate z-score.** Only bits whose values are known before decoding
+//!   (the mapper's preamble and length symbols, see
+//!   [`PayloadMapper::known_prefix`]) are used, so the statistic is not
+//!   inflated by the decoder agreeing with itself:
+//!   `Z = Σ s_i z_i / √K` with `s_i = ±1` the expected bit.
+//! - **False-positive probability.** Under the null hypothesis of unmarked
+//!   audio `Z` is standard normal for one alignment. The detector searches many
+//!   alignments (slot offsets, payload lengths, rotations, polarities), so the
+//!   tail probability is Bonferroni-corrected:
+//!   `P_fa = min(1, H · Q(Z))` for `H` hypotheses.
+//! - **Decision.** [`Decision::Present`] when `P_fa` does not exceed the
+//!   configured false-alarm rate, [`Decision::Absent`] when `P_fa` is at least
+//!   the configured absence threshold (chance explains the best match), and
+//!   [`Decision::Inconclusive`] in between.
+//!
+//! ```ignore
+//! use wavemark::detect::confidence::{ConfidenceConfig, Decision};
+//!
+//! let config = ConfidenceConfig::new(1e-9, 0.05)?;
+//! let mut correlator = Correlator::from_key_context(&session, params)?.with_confidence(config);
+//! let detection = correlator.detect(&received, &codec, &context)?;
+//! if detection.confidence.decision() == Decision::Present {
+//!     report(detection.frame, detection.confidence.false_positive_probability());
+//! }
+//! ```
+//!
+//! [`PayloadMapper::known_prefix`]: crate::embed::payload_mapper::PayloadMapper::known_prefix
 
-//! Placeholder confidence scoring logic.
+use std::f64::consts::SQRT_2;
 
-/// Represents the outcome of a detection pass.
-pub struct DetectionConfidence;
+use crate::detect::correlator::DetectError;
+
+/// Outcome of a detection pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// The watermark is present at the configured false-alarm rate.
+    Present,
+    /// The best match is explained by chance.
+    Absent,
+    /// Evidence falls between the two thresholds.
+    Inconclusive,
+}
+
+/// Thresholds used to turn a false-positive probability into a [`Decision`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConfidenceConfig {
+    false_alarm_rate: f64,
+    absent_threshold: f64,
+}
+
+impl ConfidenceConfig {
+    /// Default maximum false-positive probability for a `Present` decision.
+    pub const DEFAULT_FALSE_ALARM_RATE: f64 = 1e-6;
+    /// Default minimum false-positive probability for an `Absent` decision.
+    pub const DEFAULT_ABSENT_THRESHOLD: f64 = 0.05;
+
+    /// Creates a configuration. Requires `0 < false_alarm_rate < absent_threshold <= 1`.
+    pub fn new(false_alarm_rate: f64, absent_threshold: f64) -> Result<Self, DetectError> {
+        if !(false_alarm_rate > 0.0 && false_alarm_rate < 1.0) {
+            return Err(DetectError::InvalidParams(
+                "false-alarm rate must lie in (0, 1)",
+            ));
+        }
+        if !(absent_threshold > false_alarm_rate && absent_threshold <= 1.0) {
+            return Err(DetectError::InvalidParams(
+                "absent threshold must exceed the false-alarm rate and be at most 1",
+            ));
+        }
+        Ok(Self {
+            false_alarm_rate,
+            absent_threshold,
+        })
+    }
+
+    /// Maximum false-positive probability for a `Present` decision.
+    pub fn false_alarm_rate(&self) -> f64 {
+        self.false_alarm_rate
+    }
+
+    /// Minimum false-positive probability for an `Absent` decision.
+    pub fn absent_threshold(&self) -> f64 {
+        self.absent_threshold
+    }
+
+    /// Classifies a false-positive probability.
+    pub fn decide(&self, false_positive_probability: f64) -> Decision {
+        if false_positive_probability <= self.false_alarm_rate {
+            Decision::Present
+        } else if false_positive_probability >= self.absent_threshold {
+            Decision::Absent
+        } else {
+            Decision::Inconclusive
+        }
+    }
+}
+
+impl Default for ConfidenceConfig {
+    fn default() -> Self {
+        Self {
+            false_alarm_rate: Self::DEFAULT_FALSE_ALARM_RATE,
+            absent_threshold: Self::DEFAULT_ABSENT_THRESHOLD,
+        }
+    }
+}
+
+/// Calibrated confidence attached to a detection result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectionConfidence {
+    llrs: Vec<f64>,
+    z_score: f64,
+    known_bits: usize,
+    hypotheses: u64,
+    false_positive_probability: f64,
+    decision: Decision,
+}
+
+impl DetectionConfidence {
+    /// Scores `soft_bits` whose leading bits are expected to equal `known_prefix`.
+    ///
+    /// `hypotheses` is the number of alignments searched to obtain the soft
+    /// bits and is used for the multiple-comparison correction.
+    pub fn evaluate(
+        soft_bits: &[f32],
+        known_prefix: &[bool],
+        hypotheses: u64,
+        config: &ConfidenceConfig,
+    ) -> Self {
+        let amplitude = if soft_bits.is_empty() {
+            0.0
+        } else {
+            soft_bits.iter().map(|&z| (z as f64).abs()).sum::<f64>() / soft_bits.len() as f64
+        };
+        let llrs = soft_bits
+            .iter()
+            .map(|&z| 2.0 * amplitude * z as f64)
+            .collect();
+
+        let known_bits = known_prefix.len().min(soft_bits.len());
+        let z_score = if known_bits == 0 {
+            0.0
+        } else {
+            known_prefix
+                .iter()
+                .zip(soft_bits)
+                .map(|(&bit, &z)| if bit { z as f64 } else { -(z as f64) })
+                .sum::<f64>()
+                / (known_bits as f64).sqrt()
+        };
+
+        let hypotheses = hypotheses.max(1);
+        let false_positive_probability = (hypotheses as f64 * normal_tail(z_score)).min(1.0);
+
+        Self {
+            llrs,
+            z_score,
+            known_bits,
+            hypotheses,
+            false_positive_probability,
+            decision: config.decide(false_positive_probability),
+        }
+    }
+
+    /// Confidence for a search in which no alignment matched at all.
+    pub fn no_match(hypotheses: u64, config: &ConfidenceConfig) -> Self {
+        Self::evaluate(&[], &[], hypotheses, config)
+    }
+
+    /// The same match corrected for `factor` times as many hypotheses, as
+    /// when it is the best of `factor` independent searches.
+    pub(crate) fn widened(mut self, factor: u64, config: &ConfidenceConfig) -> Self {
+        self.hypotheses = self.hypotheses.saturating_mul(factor.max(1));
+        self.false_positive_probability =
+            (self.hypotheses as f64 * normal_tail(self.z_score)).min(1.0);
+        self.decision = config.decide(self.false_positive_probability);
+        self
+    }
+
+    /// Per-bit log-likelihood ratios; positive values favour a `1` bit.
+    pub fn llrs(&self) -> &[f64] {
+        &self.llrs
+    }
+
+    /// Aggregate z-score over the known bits.
+    pub fn z_score(&self) -> f64 {
+        self.z_score
+    }
+
+    /// Number of known bits contributing to the z-score.
+    pub fn known_bits(&self) -> usize {
+        self.known_bits
+    }
+
+    /// Number of hypotheses covered by the multiple-comparison correction.
+    pub fn hypotheses(&self) -> u64 {
+        self.hypotheses
+    }
+
+    /// Probability of a match at least this strong on unmarked audio.
+    pub fn false_positive_probability(&self) -> f64 {
+        self.false_positive_probability
+    }
+
+    /// Three-way decision under the configured thresholds.
+    pub fn decision(&self) -> Decision {
+        self.decision
+    }
+}
+
+/// Upper tail `Q(z) = P(N(0, 1) > z)` of the standard normal distribution.
+pub fn normal_tail(z: f64) -> f64 {
+    0.5 * erfc(z / SQRT_2)
+}
+
+/// Complementary error function with fractional error below 1.2e-7.
+///
+/// Chebyshev approximation from Numerical Recipes (`erfcc`), accurate in the
+/// far tail where `1 - erf(x)` would cancel.
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = -z * z - 1.265_512_23
+        + t * (1.000_023_68
+            + t * (0.374_091_96
+                + t * (0.096_784_18
+                    + t * (-0.186_288_06
+                        + t * (0.278_868_07
+                            + t * (-1.135_203_98
+                                + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))));
+    let result = t * poly.exp();
+    if x >= 0.0 {
+        result
+    } else {
+        2.0 - result
+    }
+}
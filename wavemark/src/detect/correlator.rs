@@ -1,6 +1,935 @@
-#![allow(dead_code)]
+//! Correlation detector for spread-spectrum watermarks.
+//!
+//! The correlator mirrors [`SpreadSpectrumEmbedder`](crate::embed::spread_spectrum::SpreadSpectrumEmbedder):
+//! it regenerates the keyed chip sequence from the same [`KeyContext`] and
+//! [`EmbedParams`], and measures how strongly each carrier of each symbol slot
+//! follows it.
+//!
+//! 1. Log-magnitude spectra of the embedding band are taken every `hop / 2`
+//!    samples.
+//! 2. Each frame is correlated with the chip sequence per carrier. The same is
+//!    done with a decoy sequence to measure the host-only noise level.
+//! 3. Because the start of the audio is unknown, every slot boundary candidate
+//!    (in steps of `hop / 2`) is tried and the one with the most correlation
+//!    energy wins. Each frame joins the half-slot of the embedding frame it
+//!    overlaps most.
+//! 4. The second half of each slot is subtracted from the first, undoing the
+//!    bi-phase coding and cancelling stationary host structure, and the result
+//!    is scaled to a z-score. Carriers sharing a payload bit are summed, which
+//!    gives one soft decision per bit and slot; positive values indicate a `1`
+//!    bit.
+//!
+//! [`Correlator::correlate`] stops there and returns a [`Correlation`], which
+//! can be folded into soft bits for any payload length. When the parameters
+//! enable a [sync pattern](crate::embed::sync_pattern), step 3 instead
+//! picks the slot boundary at which the pattern matches best, and the decoder
+//! reads the payload at the position the pattern marks.
+//! [`Correlator::detect`] additionally searches payload lengths and bit
+//! rotations for the preamble of a [`PayloadMapper`] frame, checks its CRC and
+//! decodes the codec frame it carries.
+//!
+//! ```ignore
+//! use wavemark::detect::correlator::Correlator;
+//!
+//! let mut correlator = Correlator::from_key_context(&session, EmbedParams::default())?;
+//! let detection = correlator.detect(&received, &codec, &EncryptionContext::default())?;
+//! println!("payload starts at sample {}", detection.payload_start);
+//! ```
 
-//! Placeholder correlator used during detection.
+use std::fmt;
 
-/// Performs correlation against expected watermark patterns.
-pub struct Correlator;
+use crate::detect::confidence::{ConfidenceConfig, DetectionConfidence};
+use crate::detect::stretch::{self, StretchSearch};
+use crate::detect::sync::{self, SyncMatch};
+use crate::embed::params::EmbedParams;
+use crate::embed::payload_mapper::{DemappedPayload, PayloadMapper};
+use crate::embed::spread_spectrum::{EmbedError, SpreadingCode};
+use crate::format::codec::{FrameCodec, HEADER_LEN};
+use crate::format::encryption::EncryptionContext;
+use crate::format::fec::FecReport;
+use crate::format::payload::PayloadFrame;
+use crate::key::derivation::KeyContext;
+use crate::transforms::fft::{Complex, FftBackend, FftError};
+use crate::transforms::window::AnalysisWindow;
+
+/// Smallest codec frame: header plus the field count.
+const MIN_FRAME_BYTES: usize = HEADER_LEN + 2;
+/// Longest payload considered by [`Correlator::detect`].
+const MAX_FRAME_BYTES: usize = 1024;
+/// Floor added to bin power before taking the logarithm.
+const POWER_FLOOR: f32 = 1e-12;
+
+/// Regenerates the keyed spreading code and correlates it against audio.
+pub struct Correlator {
+    params: EmbedParams,
+    code: SpreadingCode,
+    confidence: ConfidenceConfig,
+    window: Vec<f32>,
+    fft: FftBackend<f32>,
+    frame: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    stretch: Option<StretchSearch>,
+    /// Factor by which embedded bins are expected to have moved up.
+    warp: f32,
+    /// Scratch frame resampled by [`Correlator::warp_frame`].
+    warped: Vec<f32>,
+}
+
+impl Correlator {
+    /// Creates a correlator keyed by the PN-sequence seed of a [`KeyContext`].
+    ///
+    /// `params` must match the parameters used when embedding.
+    pub fn from_key_context(
+        context: &KeyContext,
+        params: EmbedParams,
+    ) -> Result<Self, DetectError> {
+        let code = SpreadingCode::new(&context.pn_seed(), &params);
+        let window = AnalysisWindow::sqrt_hann(params.frame_len())
+            .map_err(EmbedError::from)?
+            .coefficients()
+            .iter()
+            .map(|&w| w as f32)
+            .collect();
+
+        Ok(Self {
+            frame: vec![0.0; params.frame_len()],
+            spectrum: vec![
+                Complex::new(0.0, 0.0);
+                FftBackend::<f32>::spectrum_len(params.frame_len())
+            ],
+            params,
+            code,
+            confidence: ConfidenceConfig::default(),
+            window,
+            fft: FftBackend::new(),
+            stretch: None,
+            warp: 1.0,
+            warped: Vec::new(),
+        })
+    }
+
+    /// Uses `config` to classify detections.
+    pub fn with_confidence(mut self, config: ConfidenceConfig) -> Self {
+        self.confidence = config;
+        self
+    }
+
+    /// Searches `search` for speed, tempo or pitch changes in [`Correlator::detect`].
+    pub fn with_stretch_search(mut self, search: StretchSearch) -> Result<Self, DetectError> {
+        search.validate()?;
+        self.stretch = Some(search);
+        Ok(self)
+    }
+
+    /// Returns the stretch search, if one is configured.
+    pub fn stretch_search(&self) -> Option<&StretchSearch> {
+        self.stretch.as_ref()
+    }
+
+    /// Returns the embedding parameters the correlator expects.
+    pub fn params(&self) -> &EmbedParams {
+        &self.params
+    }
+
+    /// Returns the thresholds used to classify detections.
+    pub fn confidence_config(&self) -> &ConfidenceConfig {
+        &self.confidence
+    }
+
+    /// Number of samples needed before [`Correlator::correlate`] can run.
+    pub fn min_samples(&self) -> usize {
+        self.params.frame_len().max(self.params.symbol_len())
+    }
+
+    /// Correlates `samples` against the spreading code, searching the slot offset.
+    pub fn correlate(&mut self, samples: &[f32]) -> Result<Correlation, DetectError> {
+        let (signal, decoy) = self.analyze(samples)?;
+        self.align(&signal, &decoy, None)
+    }
+
+    /// Folds `samples` into `bit_count` channel soft bits of a payload whose
+    /// embedding schedule started at input sample `origin`.
+    ///
+    /// Unlike [`Correlator::detect`], nothing is searched: slot boundaries,
+    /// the bit rotation and the repetition start all follow from `origin`,
+    /// which is negative when the start of the marked audio was cut off. The
+    /// soft bits are returned whether or not they decode, which makes them
+    /// suitable for measuring raw bit errors against the mapped payload.
+    pub fn soft_bits_at(
+        &mut self,
+        samples: &[f32],
+        origin: isize,
+        bit_count: usize,
+    ) -> Result<Vec<f32>, DetectError> {
+        if bit_count == 0 {
+            return Ok(Vec::new());
+        }
+        let (signal, decoy) = self.analyze(samples)?;
+        let symbol_len = self.params.symbol_len() as isize;
+        let phase = origin.rem_euclid(symbol_len) as usize;
+        let correlation = self.align(&signal, &decoy, Some(phase))?;
+
+        // Slot of the embedding schedule in which correlation slot 0 falls.
+        let first = (correlation.slot_start(0) - origin).div_euclid(symbol_len);
+        let sync_symbols = self.code.sync().symbols();
+        if sync_symbols == 0 {
+            let shift = first * correlation.carriers() as isize;
+            let rotation = (-shift).rem_euclid(bit_count as isize) as usize;
+            return Ok(correlation.fold(bit_count, rotation, false));
+        }
+        let cycle = self.params.cycle_slots(bit_count);
+        let start = (-first).rem_euclid(cycle as isize) as usize;
+        Ok(correlation.fold_cycles(bit_count, start, sync_symbols, cycle))
+    }
+
+    /// Correlates every analysis frame of `samples` with the code and the
+    /// decoy, [`Correlator::carrier_count`] values per frame.
+    fn analyze(&mut self, samples: &[f32]) -> Result<(Vec<f32>, Vec<f32>), DetectError> {
+        let required = self.min_samples();
+        if samples.len() < required {
+            return Err(DetectError::InsufficientAudio {
+                required,
+                found: samples.len(),
+            });
+        }
+
+        let frame_len = self.params.frame_len();
+        let step = self.analysis_step();
+        let frame_count = (samples.len() - frame_len) / step + 1;
+        let carriers = self.code.carriers().len();
+
+        let mut signal = vec![0.0f32; frame_count * carriers];
+        let mut decoy = vec![0.0f32; frame_count * carriers];
+        for index in 0..frame_count {
+            let start = index * step;
+            let rows = index * carriers..(index + 1) * carriers;
+            if self.warp == 1.0 {
+                self.analyze_frame(
+                    &samples[start..start + frame_len],
+                    &mut signal[rows.clone()],
+                    &mut decoy[rows],
+                )?;
+            } else {
+                let mut warped = std::mem::take(&mut self.warped);
+                self.warp_frame(samples, start, &mut warped);
+                let result =
+                    self.analyze_frame(&warped, &mut signal[rows.clone()], &mut decoy[rows]);
+                self.warped = warped;
+                result?;
+            }
+        }
+        Ok((signal, decoy))
+    }
+
+    /// Samples between consecutive analysis frames.
+    pub(crate) fn analysis_step(&self) -> usize {
+        (self.params.hop() / 2).max(1)
+    }
+
+    /// Reads embedded bin `k` at `k * warp` in subsequent analyses.
+    pub(crate) fn set_warp(&mut self, warp: f32) {
+        self.warp = warp;
+    }
+
+    /// Resamples the `frame_len / warp` samples centred on the frame at
+    /// `start` to `frame_len`, which moves content at bin `k * warp` to `k`.
+    fn warp_frame(&self, samples: &[f32], start: usize, frame: &mut Vec<f32>) {
+        let frame_len = self.params.frame_len();
+        let spacing = self.warp.recip();
+        let center = start as f32 + frame_len as f32 / 2.0;
+        let first = center - spacing * frame_len as f32 / 2.0;
+        frame.clear();
+        frame.extend((0..frame_len).map(|index| {
+            let position = first + index as f32 * spacing;
+            if position < 0.0 {
+                return 0.0;
+            }
+            let base = position.floor() as usize;
+            let fraction = position - base as f32;
+            match (samples.get(base), samples.get(base + 1)) {
+                (Some(&low), Some(&high)) => low + (high - low) * fraction,
+                (Some(&low), None) => low * (1.0 - fraction),
+                _ => 0.0,
+            }
+        }));
+    }
+
+    /// Number of carriers correlated per analysis frame.
+    pub(crate) fn carrier_count(&self) -> usize {
+        self.code.carriers().len()
+    }
+
+    /// Number of slot alignments [`Correlator::align`] searches.
+    pub(crate) fn phase_count(&self) -> u64 {
+        self.params.symbol_len().div_ceil(self.analysis_step()) as u64
+    }
+
+    /// Correlates one analysis frame of `frame_len` samples with the code and
+    /// the decoy, writing one value per carrier.
+    pub(crate) fn analyze_frame(
+        &mut self,
+        samples: &[f32],
+        signal: &mut [f32],
+        decoy: &mut [f32],
+    ) -> Result<(), DetectError> {
+        for ((slot, &sample), &weight) in self.frame.iter_mut().zip(samples).zip(&self.window) {
+            *slot = sample * weight;
+        }
+        self.fft.forward(&self.frame, &mut self.spectrum)?;
+
+        // Log-magnitude of the embedding band, correlated per carrier.
+        for (carrier, bins) in self.code.carriers().iter().enumerate() {
+            let scale = (bins.len() as f32).recip();
+            let mut code_sum = 0.0;
+            let mut decoy_sum = 0.0;
+            for bin in bins.clone() {
+                let value = 10.0 * (self.spectrum[bin].norm_sqr() + POWER_FLOOR).log10();
+                code_sum += self.code.chip(bin) * value;
+                decoy_sum += self.code.decoy(bin) * value;
+            }
+            signal[carrier] = code_sum * scale;
+            decoy[carrier] = decoy_sum * scale;
+        }
+        Ok(())
+    }
+
+    /// Groups per-frame correlations into slots, searching the slot offset
+    /// unless `phase` fixes it.
+    ///
+    /// `signal` and `decoy` hold [`Correlator::carrier_count`] values per
+    /// analysis frame, with frames [`Correlator::analysis_step`] samples apart.
+    pub(crate) fn align(
+        &mut self,
+        signal: &[f32],
+        decoy: &[f32],
+        phase: Option<usize>,
+    ) -> Result<Correlation, DetectError> {
+        let carriers = self.carrier_count();
+        let step = self.analysis_step();
+        let layout = FrameLayout {
+            frame_len: self.params.frame_len(),
+            lag: -(self.params.hop() as isize / 2),
+            step,
+            frame_count: signal.len() / carriers,
+            carriers,
+            symbol_len: self.params.symbol_len(),
+        };
+
+        if !self.code.sync().is_empty() {
+            return self.align_sync(&layout, signal, decoy, phase);
+        }
+
+        let mut correlation = match phase {
+            Some(phase) => layout.aggregate(phase, signal, decoy),
+            None => {
+                let mut best: Option<(f32, Correlation)> = None;
+                for phase in (0..layout.symbol_len).step_by(step) {
+                    let candidate = layout.aggregate(phase, signal, decoy);
+                    let score = candidate.energy_ratio();
+                    if best
+                        .as_ref()
+                        .is_none_or(|(best_score, _)| score > *best_score)
+                    {
+                        best = Some((score, candidate));
+                    }
+                }
+                best.expect("symbol length is non-zero").1
+            }
+        };
+        correlation.normalize();
+        correlation.combine_redundant(self.params.bits_per_symbol());
+        Ok(correlation)
+    }
+
+    /// Like [`Correlator::align`], but picks the slot offset at which the sync
+    /// pattern matches best and records where it was found.
+    fn align_sync(
+        &mut self,
+        layout: &FrameLayout,
+        signal: &[f32],
+        decoy: &[f32],
+        phase: Option<usize>,
+    ) -> Result<Correlation, DetectError> {
+        let phases = match phase {
+            Some(phase) => phase..phase + 1,
+            None => 0..layout.symbol_len,
+        };
+        let mut best: Option<(f32, Correlation)> = None;
+        for phase in phases.step_by(layout.step) {
+            let mut candidate = layout.aggregate(phase, signal, decoy);
+            candidate.normalize();
+            candidate.combine_redundant(self.params.bits_per_symbol());
+            let located = sync::locate(self.code.sync(), &candidate.symbols, &mut self.fft)?;
+            candidate.sync = located.map(|(slot, score)| SyncMatch {
+                offset: candidate.slot_start(slot),
+                symbol_phase: phase,
+                slot,
+                score,
+            });
+
+            let score = candidate.sync.map_or(f32::NEG_INFINITY, |sync| sync.score);
+            if best
+                .as_ref()
+                .is_none_or(|(best_score, _)| score > *best_score)
+            {
+                best = Some((score, candidate));
+            }
+        }
+        Ok(best.expect("symbol length is non-zero").1)
+    }
+
+    /// Locates and decodes a [`FrameCodec`] payload in `samples`.
+    ///
+    /// Payload lengths are tried from shortest to longest; for each, every
+    /// bit rotation and polarity whose hard decisions reproduce the mapper
+    /// preamble within its tolerance is demapped, which checks the length
+    /// symbols and CRC, and passed to `codec` until one decodes. The returned
+    /// [`DetectionConfidence`] is scored on the mapper's known prefix and
+    /// corrected for every alignment of every payload length. When nothing
+    /// decodes, [`DetectError::NotDetected`] carries the confidence of the
+    /// strongest prefix match.
+    ///
+    /// With a [`StretchSearch`], every factor of its grid is tried as well.
+    pub fn detect(
+        &mut self,
+        samples: &[f32],
+        codec: &FrameCodec,
+        context: &EncryptionContext,
+    ) -> Result<Detection, DetectError> {
+        if let Some(search) = self.stretch.clone() {
+            return stretch::detect(self, &search, samples, codec, context);
+        }
+        let correlation = self.correlate(samples)?;
+        self.decode(&correlation, self.phase_count(), codec, context)
+    }
+
+    /// Searches `correlation` for a [`FrameCodec`] payload.
+    ///
+    /// Soft bits are folded at the channel length of every payload length,
+    /// aligned on the preamble and demapped by [`EmbedParams::mapper`], which
+    /// rejects frames whose CRC fails before they reach the codec.
+    ///
+    /// `phases` is the number of slot alignments tried to obtain
+    /// `correlation`, which the confidence is corrected for. Every payload
+    /// length, rotation, polarity and phase counts as a hypothesis, whether or
+    /// not the search stops early. With a sync pattern the alignment is chosen
+    /// without looking at the preamble, so only payload lengths count.
+    pub(crate) fn decode(
+        &self,
+        correlation: &Correlation,
+        phases: u64,
+        codec: &FrameCodec,
+        context: &EncryptionContext,
+    ) -> Result<Detection, DetectError> {
+        if !self.code.sync().is_empty() {
+            return self.decode_sync(correlation, codec, context);
+        }
+
+        let mapper = self.params.mapper();
+        let hypotheses = self
+            .payload_lengths(correlation)
+            .map(|payload_len| phases * 2 * mapper.channel_bits(payload_len) as u64)
+            .sum();
+        let mut best_match: Option<(Vec<f32>, Vec<bool>)> = None;
+        let mut best_score = f32::NEG_INFINITY;
+        for payload_len in self.payload_lengths(correlation) {
+            let bit_count = mapper.channel_bits(payload_len);
+            let prefix = mapper.known_prefix(payload_len);
+            let folded = correlation.fold(bit_count, 0, false);
+
+            for inverted in [false, true] {
+                for rotation in 0..bit_count {
+                    let preamble_errors = prefix[..PayloadMapper::PREAMBLE_BITS]
+                        .iter()
+                        .enumerate()
+                        .filter(|&(offset, &bit)| {
+                            ((folded[(rotation + offset) % bit_count] > 0.0) != inverted) != bit
+                        })
+                        .count();
+                    if preamble_errors > mapper.preamble_tolerance() {
+                        continue;
+                    }
+
+                    let soft_bits: Vec<f32> = (0..bit_count)
+                        .map(|offset| {
+                            let value = folded[(rotation + offset) % bit_count];
+                            if inverted {
+                                -value
+                            } else {
+                                value
+                            }
+                        })
+                        .collect();
+                    if let Some((frame, demapped)) =
+                        self.decode_frame(&soft_bits, payload_len, codec, context)
+                    {
+                        let confidence = DetectionConfidence::evaluate(
+                            &soft_bits,
+                            &prefix,
+                            hypotheses,
+                            &self.confidence,
+                        );
+                        return Ok(Detection {
+                            frame,
+                            bytes: demapped.bytes,
+                            soft_bits,
+                            payload_start: correlation
+                                .slot_start(rotation / correlation.carriers()),
+                            symbol_offset: correlation.symbol_offset(),
+                            sync: None,
+                            stretch: 1.0,
+                            confidence,
+                            fec: demapped.fec,
+                        });
+                    }
+
+                    let score = prefix_score(&soft_bits, &prefix);
+                    if score > best_score {
+                        best_score = score;
+                        best_match = Some((soft_bits, prefix.clone()));
+                    }
+                }
+            }
+        }
+
+        let confidence = match best_match {
+            Some((soft_bits, prefix)) => {
+                DetectionConfidence::evaluate(&soft_bits, &prefix, hypotheses, &self.confidence)
+            }
+            None => DetectionConfidence::no_match(hypotheses, &self.confidence),
+        };
+        Err(DetectError::NotDetected(confidence))
+    }
+}
+
+impl Correlator {
+    /// Decodes the payload repetition that follows the located sync pattern.
+    fn decode_sync(
+        &self,
+        correlation: &Correlation,
+        codec: &FrameCodec,
+        context: &EncryptionContext,
+    ) -> Result<Detection, DetectError> {
+        let Some(sync) = correlation.sync() else {
+            return Err(DetectError::NotDetected(DetectionConfidence::no_match(
+                1,
+                &self.confidence,
+            )));
+        };
+        let sync_symbols = self.code.sync().symbols();
+        let mapper = self.params.mapper();
+
+        let hypotheses = self.payload_lengths(correlation).count() as u64;
+        let mut best_match: Option<(Vec<f32>, Vec<bool>)> = None;
+        let mut best_score = f32::NEG_INFINITY;
+        for payload_len in self.payload_lengths(correlation) {
+            let bit_count = mapper.channel_bits(payload_len);
+            let prefix = mapper.known_prefix(payload_len);
+            let cycle = self.params.cycle_slots(bit_count);
+            let soft_bits = correlation.fold_cycles(bit_count, sync.slot, sync_symbols, cycle);
+
+            let Some((frame, demapped)) =
+                self.decode_frame(&soft_bits, payload_len, codec, context)
+            else {
+                let score = prefix_score(&soft_bits, &prefix);
+                if score > best_score {
+                    best_score = score;
+                    best_match = Some((soft_bits, prefix));
+                }
+                continue;
+            };
+            let confidence =
+                DetectionConfidence::evaluate(&soft_bits, &prefix, hypotheses, &self.confidence);
+            return Ok(Detection {
+                frame,
+                bytes: demapped.bytes,
+                soft_bits,
+                payload_start: correlation.slot_start((sync.slot + sync_symbols) % cycle),
+                symbol_offset: correlation.symbol_offset(),
+                sync: Some(*sync),
+                stretch: 1.0,
+                confidence,
+                fec: demapped.fec,
+            });
+        }
+
+        let confidence = match best_match {
+            Some((soft_bits, prefix)) => {
+                DetectionConfidence::evaluate(&soft_bits, &prefix, hypotheses, &self.confidence)
+            }
+            None => DetectionConfidence::no_match(hypotheses, &self.confidence),
+        };
+        Err(DetectError::NotDetected(confidence))
+    }
+
+    /// Codec frame lengths whose channel frame fits into `correlation`.
+    fn payload_lengths(&self, correlation: &Correlation) -> impl Iterator<Item = usize> + '_ {
+        let available = correlation.symbols().len();
+        (MIN_FRAME_BYTES..=MAX_FRAME_BYTES)
+            .take_while(move |&len| self.params.mapper().channel_bits(len) <= available)
+    }
+
+    /// Demaps channel soft bits and decodes the codec frame of `payload_len`
+    /// bytes they carry.
+    fn decode_frame(
+        &self,
+        soft_bits: &[f32],
+        payload_len: usize,
+        codec: &FrameCodec,
+        context: &EncryptionContext,
+    ) -> Option<(PayloadFrame, DemappedPayload)> {
+        let demapped = self.params.mapper().demap(soft_bits).ok()?;
+        if demapped.bytes.len() != payload_len {
+            return None;
+        }
+        let frame = codec.decode(&demapped.bytes, context).ok()?;
+        Some((frame, demapped))
+    }
+}
+
+/// Agreement of `soft_bits` with the known channel bits, summed as z-scores.
+fn prefix_score(soft_bits: &[f32], prefix: &[bool]) -> f32 {
+    prefix
+        .iter()
+        .zip(soft_bits)
+        .map(|(&bit, &z)| if bit { z } else { -z })
+        .sum()
+}
+
+impl fmt::Debug for Correlator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Correlator")
+            .field("params", &self.params)
+            .field("pn_seed", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Soft decisions for every carrier of every symbol slot found in the input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Correlation {
+    symbol_offset: usize,
+    symbol_len: usize,
+    first_slot_start: isize,
+    carriers: usize,
+    symbols: Vec<f32>,
+    decoys: Vec<f32>,
+    sync: Option<SyncMatch>,
+}
+
+impl Correlation {
+    /// Sample offset in `[0, symbol_len)` of the slot boundaries in the input.
+    ///
+    /// The estimate is quantised to `hop / 2` and may be off by one step.
+    pub fn symbol_offset(&self) -> usize {
+        self.symbol_offset
+    }
+
+    /// Number of soft decisions per slot, after combining redundant carriers.
+    pub fn carriers(&self) -> usize {
+        self.carriers
+    }
+
+    /// Number of slots with at least one analysed frame.
+    pub fn slots(&self) -> usize {
+        self.symbols.len() / self.carriers
+    }
+
+    /// Start sample of `slot` within the input; negative for a partial leading slot.
+    pub fn slot_start(&self, slot: usize) -> isize {
+        self.first_slot_start + (slot * self.symbol_len) as isize
+    }
+
+    /// Sync pattern located in the input, when the parameters enable one.
+    pub fn sync(&self) -> Option<&SyncMatch> {
+        self.sync.as_ref()
+    }
+
+    /// Z-scored soft decisions, slot-major.
+    pub fn symbols(&self) -> &[f32] {
+        &self.symbols
+    }
+
+    /// Folds the symbols into `bit_count` soft bits.
+    ///
+    /// Symbol `u` contributes to bit `(u - rotation) mod bit_count`. Each
+    /// returned value is the sum of its contributions divided by the square
+    /// root of their count, so it stays a unit-variance z-score on unmarked
+    /// audio. Bits that received no symbols are `0.0`.
+    pub fn fold(&self, bit_count: usize, rotation: usize, inverted: bool) -> Vec<f32> {
+        let mut sums = vec![0.0f32; bit_count];
+        let mut counts = vec![0u32; bit_count];
+        if bit_count == 0 {
+            return sums;
+        }
+        for (index, &value) in self.symbols.iter().enumerate() {
+            let bit = (index + bit_count - rotation % bit_count) % bit_count;
+            sums[bit] += if inverted { -value } else { value };
+            counts[bit] += 1;
+        }
+        for (sum, &count) in sums.iter_mut().zip(&counts) {
+            if count > 0 {
+                *sum /= (count as f32).sqrt();
+            }
+        }
+        sums
+    }
+
+    /// Folds symbols sent in repetitions of `cycle` slots into `bit_count`
+    /// soft bits, like [`Correlation::fold`].
+    ///
+    /// A repetition starts at `slot`; the first `sync_symbols` slots of every
+    /// repetition carry the sync pattern and are skipped.
+    fn fold_cycles(
+        &self,
+        bit_count: usize,
+        slot: usize,
+        sync_symbols: usize,
+        cycle: usize,
+    ) -> Vec<f32> {
+        let mut sums = vec![0.0f32; bit_count];
+        let mut counts = vec![0u32; bit_count];
+        for (index, &value) in self.symbols.iter().enumerate() {
+            let (symbol, carrier) = (index / self.carriers, index % self.carriers);
+            let position = (symbol as isize - slot as isize).rem_euclid(cycle as isize) as usize;
+            if position < sync_symbols {
+                continue;
+            }
+            let bit = ((position - sync_symbols) * self.carriers + carrier) % bit_count;
+            sums[bit] += value;
+            counts[bit] += 1;
+        }
+        for (sum, &count) in sums.iter_mut().zip(&counts) {
+            if count > 0 {
+                *sum /= (count as f32).sqrt();
+            }
+        }
+        sums
+    }
+
+    /// Correlation energy relative to the decoy; about 1 on unmarked audio.
+    pub(crate) fn energy_ratio(&self) -> f32 {
+        let signal: f32 = self.symbols.iter().map(|v| v * v).sum();
+        let noise: f32 = self.decoys.iter().map(|v| v * v).sum();
+        if noise > 0.0 {
+            signal / noise
+        } else {
+            0.0
+        }
+    }
+
+    /// Sums the carriers that share a payload bit, keeping unit variance.
+    fn combine_redundant(&mut self, bits_per_symbol: usize) {
+        if bits_per_symbol == 0 || bits_per_symbol >= self.carriers {
+            return;
+        }
+        let slots = self.slots();
+        let carriers = self.carriers;
+        let scale = ((carriers / bits_per_symbol) as f32).sqrt().recip();
+        let combine = |values: &[f32]| {
+            let mut combined = vec![0.0f32; slots * bits_per_symbol];
+            for (index, &value) in values.iter().enumerate() {
+                let (slot, carrier) = (index / carriers, index % carriers);
+                combined[slot * bits_per_symbol + carrier % bits_per_symbol] += value * scale;
+            }
+            combined
+        };
+        self.symbols = combine(&self.symbols);
+        self.decoys = combine(&self.decoys);
+        self.carriers = bits_per_symbol;
+    }
+
+    /// Scales each carrier by the RMS of its decoy correlations.
+    fn normalize(&mut self) {
+        let slots = self.slots();
+        for carrier in 0..self.carriers {
+            let power = (0..slots)
+                .map(|slot| self.decoys[slot * self.carriers + carrier].powi(2))
+                .sum::<f32>()
+                / slots.max(1) as f32;
+            let scale = if power > 0.0 {
+                power.sqrt().recip()
+            } else {
+                0.0
+            };
+            for slot in 0..slots {
+                self.symbols[slot * self.carriers + carrier] *= scale;
+                self.decoys[slot * self.carriers + carrier] *= scale;
+            }
+        }
+    }
+}
+
+/// Geometry of the analysis frames relative to candidate slot boundaries.
+struct FrameLayout {
+    frame_len: usize,
+    /// Shift of the analysis grid so each frame joins the half-slot of the
+    /// embedding frame it overlaps most.
+    lag: isize,
+    step: usize,
+    frame_count: usize,
+    carriers: usize,
+    symbol_len: usize,
+}
+
+impl FrameLayout {
+    /// Averages per-frame correlations into slots whose boundaries fall at `phase`.
+    fn aggregate(&self, phase: usize, signal: &[f32], decoy: &[f32]) -> Correlation {
+        let symbol_len = self.symbol_len as isize;
+        let half_len = symbol_len / 2;
+        let span = (self.frame_count - 1) * self.step + self.frame_len;
+        // Slot 0 is the (possibly partial) slot ending at `phase`.
+        let slot_capacity = (span + self.symbol_len) / self.symbol_len + 2;
+        let first_slot_start = phase as isize - symbol_len;
+        let grid_start = first_slot_start + self.lag;
+
+        // Accumulators are laid out as [slot][half][carrier].
+        let mut sums = vec![0.0f32; slot_capacity * 2 * self.carriers];
+        let mut decoys = vec![0.0f32; slot_capacity * 2 * self.carriers];
+        let mut counts = vec![0u32; slot_capacity * 2];
+        for index in 0..self.frame_count {
+            let offset = (index * self.step) as isize - grid_start;
+            if offset < 0 {
+                continue;
+            }
+            let slot = (offset / symbol_len) as usize;
+            let half = ((offset % symbol_len) / half_len).min(1) as usize;
+            let cell = slot * 2 + half;
+            counts[cell] += 1;
+            for carrier in 0..self.carriers {
+                sums[cell * self.carriers + carrier] += signal[index * self.carriers + carrier];
+                decoys[cell * self.carriers + carrier] += decoy[index * self.carriers + carrier];
+            }
+        }
+
+        // Only slots with frames in both halves produce a decision.
+        let complete = |slot: usize| counts[slot * 2] > 0 && counts[slot * 2 + 1] > 0;
+        let first = (0..slot_capacity).find(|&slot| complete(slot)).unwrap_or(0);
+        let last = (0..slot_capacity)
+            .rev()
+            .find(|&slot| complete(slot))
+            .map_or(first, |last| last + 1);
+
+        let mut symbols = Vec::with_capacity((last - first) * self.carriers);
+        let mut decoy_symbols = Vec::with_capacity((last - first) * self.carriers);
+        for slot in first..last {
+            let (early, late) = if complete(slot) {
+                (
+                    (counts[slot * 2] as f32).recip(),
+                    (counts[slot * 2 + 1] as f32).recip(),
+                )
+            } else {
+                (0.0, 0.0)
+            };
+            for carrier in 0..self.carriers {
+                let first_half = (slot * 2) * self.carriers + carrier;
+                let second_half = (slot * 2 + 1) * self.carriers + carrier;
+                symbols.push(sums[first_half] * early - sums[second_half] * late);
+                decoy_symbols.push(decoys[first_half] * early - decoys[second_half] * late);
+            }
+        }
+
+        Correlation {
+            symbol_offset: phase,
+            symbol_len: self.symbol_len,
+            first_slot_start: first_slot_start + (first * self.symbol_len) as isize,
+            carriers: self.carriers,
+            symbols,
+            decoys: decoy_symbols,
+            sync: None,
+        }
+    }
+}
+
+/// Payload recovered by [`Correlator::detect`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detection {
+    /// Decoded payload frame.
+    pub frame: PayloadFrame,
+    /// Codec bytes carried by the demapped channel frame.
+    pub bytes: Vec<u8>,
+    /// Folded z-score per channel bit, preamble first; positive means `1`.
+    pub soft_bits: Vec<f32>,
+    /// Input sample at which the slot carrying the first payload bit starts.
+    ///
+    /// Negative when that slot began before the first input sample.
+    pub payload_start: isize,
+    /// Sample offset in `[0, symbol_len)` of the slot boundaries in the input.
+    pub symbol_offset: usize,
+    /// Sync pattern the payload was located with, when the parameters enable one.
+    pub sync: Option<SyncMatch>,
+    /// Factor of the [`StretchSearch`] the payload was decoded at; `1.0`
+    /// without a search.
+    ///
+    /// Sample positions are given in the input. Symbol phases refer to the
+    /// input with the factor undone.
+    pub stretch: f32,
+    /// Statistical confidence that the payload is not a chance match.
+    pub confidence: DetectionConfidence,
+    /// Shards repaired by forward error correction; `None` when the mapper
+    /// does not use FEC.
+    pub fec: Option<FecReport>,
+}
+
+/// Errors raised during detection.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DetectError {
+    /// Detection parameters are inconsistent.
+    InvalidParams(&'static str),
+    /// Input is shorter than one symbol slot or one analysis frame.
+    InsufficientAudio { required: usize, found: usize },
+    /// No decodable payload was found; carries the confidence of the best match.
+    NotDetected(DetectionConfidence),
+    /// STFT analysis failed.
+    Transform(FftError),
+}
+
+impl fmt::Display for DetectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DetectError::InvalidParams(reason) => {
+                write!(f, "invalid detection parameters: {}", reason)
+            }
+            DetectError::InsufficientAudio { required, found } => write!(
+                f,
+                "detection requires at least {} samples, got {}",
+                required, found
+            ),
+            DetectError::NotDetected(confidence) => write!(
+                f,
+                "no watermark payload detected (false-positive probability {:.3e})",
+                confidence.false_positive_probability()
+            ),
+            DetectError::Transform(err) => write!(f, "transform error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for DetectError {}
+
+impl From<EmbedError> for DetectError {
+    fn from(err: EmbedError) -> Self {
+        match err {
+            EmbedError::InvalidParams(reason) => DetectError::InvalidParams(reason),
+            EmbedError::Transform(err) => DetectError::Transform(err),
+            EmbedError::Window(_) => {
+                DetectError::InvalidParams("window does not reconstruct at the configured hop")
+            }
+            EmbedError::Masking(_) => DetectError::InvalidParams("invalid masking configuration"),
+            EmbedError::EmptyPayload => DetectError::InvalidParams("payload must not be empty"),
+            EmbedError::Map(_) => {
+                DetectError::InvalidParams("payload cannot be framed for the channel")
+            }
+            EmbedError::PayloadCount { .. } => {
+                DetectError::InvalidParams("payload count does not match the channels")
+            }
+            EmbedError::Chunk(_) => DetectError::InvalidParams("invalid audio chunk"),
+        }
+    }
+}
+
+impl From<FftError> for DetectError {
+    fn from(err: FftError) -> Self {
+        DetectError::Transform(err)
+    }
+}
@@ -1,4 +1,7 @@
-//! Watermark detection algorithm placeholders.
+//! Watermark detection algorithms.
 
 pub mod confidence;
 pub mod correlator;
+pub mod streaming;
+pub mod stretch;
+pub mod sync;
@@ -0,0 +1,306 @@
+//! Incremental detection on live audio.
+//!
+//! [`StreamingDetector`] is the streaming counterpart to [`Correlator`]. Audio
+//! is pushed in packets of any size. Each analysis frame is correlated with
+//! the spreading code as soon as its samples have arrived, and only the
+//! per-carrier correlations are kept, for a sliding window of
+//! [`StreamingDetector::with_window`] samples. Every
+//! [`StreamingDetector::with_interval`] samples the window is aligned to symbol
+//! slots and searched for a payload, exactly as [`Correlator::detect`] searches
+//! a clip.
+//!
+//! When a payload decodes with a [`Decision::Present`] confidence, a
+//! [`DetectionEvent`] reports the frame and the range of stream samples it
+//! was decoded from. The window is then cleared, so each event is backed by
+//! fresh audio. The slot alignment found is kept as sync state, and later
+//! windows are decoded at that alignment, which is faster and, with fewer
+//! hypotheses, more confident. Decodes that are not confident enough neither
+//! raise an event nor take the lock. The lock is dropped when a full window
+//! passes without a decode, e.g. after the call switches to another source.
+//!
+//! ```ignore
+//! use wavemark::detect::streaming::StreamingDetector;
+//!
+//! let mut monitor = StreamingDetector::from_key_context(&session, params, FrameCodec::new(CodecOptions::default()))?;
+//! while let Some(packet) = call.next_packet() {
+//!     for event in monitor.push(&packet)? {
+//!         let range = event.time_range_secs(params.sample_rate());
+//!         alert(event.frame.account_id(), range);
+//!     }
+//! }
+//! ```
+
+use std::ops::Range;
+
+use crate::detect::confidence::{ConfidenceConfig, Decision, DetectionConfidence};
+use crate::detect::correlator::{Correlator, DetectError};
+use crate::embed::params::EmbedParams;
+use crate::format::codec::FrameCodec;
+use crate::format::encryption::EncryptionContext;
+use crate::format::fec::FecReport;
+use crate::format::payload::PayloadFrame;
+use crate::key::derivation::KeyContext;
+
+/// Default sliding window, in seconds.
+const DEFAULT_WINDOW_SECS: u32 = 10;
+/// Default spacing of decode attempts, in seconds.
+const DEFAULT_INTERVAL_SECS: u32 = 1;
+
+/// Payload decoded from a live stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectionEvent {
+    /// Decoded payload frame.
+    pub frame: PayloadFrame,
+    /// Codec bytes carried by the demapped channel frame.
+    pub bytes: Vec<u8>,
+    /// Stream samples the payload was decoded from.
+    pub samples: Range<u64>,
+    /// Stream sample at which the slot carrying the first payload bit starts.
+    ///
+    /// Negative when that slot began before the stream.
+    pub payload_start: i64,
+    /// Statistical confidence that the payload is not a chance match.
+    pub confidence: DetectionConfidence,
+    /// Shards repaired by forward error correction; `None` without FEC.
+    pub fec: Option<FecReport>,
+}
+
+impl DetectionEvent {
+    /// Range of [`DetectionEvent::samples`] in seconds.
+    pub fn time_range_secs(&self, sample_rate: u32) -> Range<f64> {
+        let rate = sample_rate as f64;
+        self.samples.start as f64 / rate..self.samples.end as f64 / rate
+    }
+}
+
+/// Packet-driven detector keeping running correlation and sync state.
+#[derive(Debug)]
+pub struct StreamingDetector {
+    correlator: Correlator,
+    codec: FrameCodec,
+    context: EncryptionContext,
+    window_len: usize,
+    interval: usize,
+    /// Samples from the start of the next analysis frame onwards.
+    pending: Vec<f32>,
+    /// Per-carrier correlations of the retained analysis frames.
+    signal: Vec<f32>,
+    decoy: Vec<f32>,
+    /// Stream index of the first retained analysis frame, or of the first
+    /// frame clear of the last event while none are retained.
+    first_frame: u64,
+    /// Stream index of the next analysis frame to compute.
+    next_frame: u64,
+    samples: u64,
+    since_attempt: usize,
+    /// Slot boundary offset in `[0, symbol_len)` of the stream, once locked.
+    lock: Option<usize>,
+}
+
+impl StreamingDetector {
+    /// Creates a detector keyed by the PN-sequence seed of a [`KeyContext`].
+    ///
+    /// `params` must match the parameters used when embedding; `codec`
+    /// decodes the payload frames.
+    pub fn from_key_context(
+        context: &KeyContext,
+        params: EmbedParams,
+        codec: FrameCodec,
+    ) -> Result<Self, DetectError> {
+        let sample_rate = params.sample_rate() as usize;
+        let correlator = Correlator::from_key_context(context, params)?;
+        Ok(Self {
+            correlator,
+            codec,
+            context: EncryptionContext::default(),
+            window_len: sample_rate * DEFAULT_WINDOW_SECS as usize,
+            interval: sample_rate * DEFAULT_INTERVAL_SECS as usize,
+            pending: Vec::new(),
+            signal: Vec::new(),
+            decoy: Vec::new(),
+            first_frame: 0,
+            next_frame: 0,
+            samples: 0,
+            since_attempt: 0,
+            lock: None,
+        })
+    }
+
+    /// Uses `context` to open encrypted payloads.
+    pub fn with_encryption_context(mut self, context: EncryptionContext) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// Uses `config` to classify detections.
+    pub fn with_confidence(mut self, config: ConfidenceConfig) -> Self {
+        self.correlator = self.correlator.with_confidence(config);
+        self
+    }
+
+    /// Keeps correlations for the last `samples` samples (10 s by default).
+    ///
+    /// The window must hold a few payload repetitions; longer windows detect
+    /// weaker marks but report less often.
+    pub fn with_window(mut self, samples: usize) -> Self {
+        self.window_len = samples.max(self.correlator.min_samples());
+        self
+    }
+
+    /// Attempts a decode every `samples` samples (1 s by default).
+    pub fn with_interval(mut self, samples: usize) -> Self {
+        self.interval = samples.max(1);
+        self
+    }
+
+    /// Returns the embedding parameters the detector expects.
+    pub fn params(&self) -> &EmbedParams {
+        self.correlator.params()
+    }
+
+    /// Samples pushed since the start of the stream.
+    pub fn samples_pushed(&self) -> u64 {
+        self.samples
+    }
+
+    /// Slot boundary offset of the stream, once a payload has been decoded.
+    pub fn sync_offset(&self) -> Option<usize> {
+        self.lock
+    }
+
+    /// Consumes a packet, returning the payloads decoded with it.
+    pub fn push(&mut self, packet: &[f32]) -> Result<Vec<DetectionEvent>, DetectError> {
+        self.pending.extend_from_slice(packet);
+        self.samples += packet.len() as u64;
+        self.analyze_pending()?;
+
+        self.since_attempt += packet.len();
+        if self.since_attempt < self.interval {
+            return Ok(Vec::new());
+        }
+        self.since_attempt = 0;
+        Ok(self.attempt()?.into_iter().collect())
+    }
+
+    /// Ends the stream, searching the remaining window once more.
+    ///
+    /// The detector is reset afterwards and can monitor a new stream.
+    pub fn finish(&mut self) -> Result<Vec<DetectionEvent>, DetectError> {
+        let event = self.attempt();
+        self.reset();
+        Ok(event?.into_iter().collect())
+    }
+
+    /// Discards all state, including the sync lock.
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.signal.clear();
+        self.decoy.clear();
+        self.first_frame = 0;
+        self.next_frame = 0;
+        self.samples = 0;
+        self.since_attempt = 0;
+        self.lock = None;
+    }
+
+    /// Correlates every analysis frame whose samples have all arrived.
+    fn analyze_pending(&mut self) -> Result<(), DetectError> {
+        let frame_len = self.params().frame_len();
+        let step = self.correlator.analysis_step();
+        let carriers = self.correlator.carrier_count();
+
+        let mut consumed = 0;
+        while self.pending.len() - consumed >= frame_len {
+            if self.next_frame < self.first_frame {
+                // Overlaps audio already used by the last event.
+                consumed += step;
+                self.next_frame += 1;
+                continue;
+            }
+            let rows = self.signal.len();
+            self.signal.resize(rows + carriers, 0.0);
+            self.decoy.resize(rows + carriers, 0.0);
+            self.correlator.analyze_frame(
+                &self.pending[consumed..consumed + frame_len],
+                &mut self.signal[rows..],
+                &mut self.decoy[rows..],
+            )?;
+            consumed += step;
+            self.next_frame += 1;
+        }
+        self.pending.drain(..consumed);
+
+        let excess = self.retained_frames().saturating_sub(self.max_frames());
+        if excess > 0 {
+            self.signal.drain(..excess * carriers);
+            self.decoy.drain(..excess * carriers);
+            self.first_frame += excess as u64;
+        }
+        Ok(())
+    }
+
+    /// Searches the window for a payload.
+    fn attempt(&mut self) -> Result<Option<DetectionEvent>, DetectError> {
+        let frames = self.retained_frames();
+        let step = self.correlator.analysis_step();
+        let frame_len = self.params().frame_len();
+        let span = match frames {
+            0 => 0,
+            frames => (frames - 1) * step + frame_len,
+        };
+        if span < self.correlator.min_samples() {
+            return Ok(None);
+        }
+
+        let symbol_len = self.params().symbol_len();
+        let offset = self.first_frame * step as u64;
+        let window_phase = (offset % symbol_len as u64) as usize;
+        let phase = self
+            .lock
+            .map(|lock| (lock + symbol_len - window_phase) % symbol_len);
+        let phases = if phase.is_some() {
+            1
+        } else {
+            self.correlator.phase_count()
+        };
+
+        let correlation = self.correlator.align(&self.signal, &self.decoy, phase)?;
+        match self
+            .correlator
+            .decode(&correlation, phases, &self.codec, &self.context)
+        {
+            Ok(detection) if detection.confidence.decision() == Decision::Present => {
+                self.lock = Some((window_phase + detection.symbol_offset) % symbol_len);
+                // Later events must be backed by audio not used for this one.
+                let end = offset + span as u64;
+                self.signal.clear();
+                self.decoy.clear();
+                self.first_frame = end.div_ceil(step as u64);
+                Ok(Some(DetectionEvent {
+                    frame: detection.frame,
+                    bytes: detection.bytes,
+                    samples: offset..end,
+                    payload_start: offset as i64 + detection.payload_start as i64,
+                    confidence: detection.confidence,
+                    fec: detection.fec,
+                }))
+            }
+            Ok(_) | Err(DetectError::NotDetected(_)) => {
+                if frames >= self.max_frames() {
+                    self.lock = None;
+                }
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn retained_frames(&self) -> usize {
+        self.signal.len() / self.correlator.carrier_count()
+    }
+
+    fn max_frames(&self) -> usize {
+        let frame_len = self.params().frame_len();
+        (self.window_len.saturating_sub(frame_len)) / self.correlator.analysis_step() + 1
+    }
+}
@@ -0,0 +1,256 @@
+//! Detection through speed, tempo and pitch changes.
+//!
+//! Re-uploads are often played back slightly faster or slower, or have their
+//! pitch shifted. Both move the watermark away from the slot timing and STFT
+//! bins the spreading code expects. The correlator tolerates only a fraction
+//! of a percent before the chips decorrelate, so a [`StretchSearch`] tries a
+//! grid of factors and undoes each candidate before correlating:
+//!
+//! | Mode                   | Modification                  | Undone by                               |
+//! |------------------------|-------------------------------|-----------------------------------------|
+//! | [`StretchMode::Speed`] | time and pitch scaled         | resampling the input                    |
+//! | [`StretchMode::Tempo`] | time scaled, pitch kept       | resampling the input and every frame    |
+//! | [`StretchMode::Pitch`] | pitch scaled, time kept       | resampling every analysis frame         |
+//!
+//! A factor above 1 means faster playback or higher pitch. Every factor of
+//! the grid is scored on a probe of the input by the correlation energy of its
+//! best slot alignment, which does not depend on the payload. The best
+//! candidates are refined on a five times finer grid and decoded on the whole
+//! input. The factor the payload was decoded at is reported as
+//! [`Detection::stretch`].
+//!
+//! The embedding itself is not scale-invariant; the search recovers marks
+//! only within the configured range.
+//!
+//! ```ignore
+//! use wavemark::detect::stretch::{StretchMode, StretchSearch};
+//!
+//! let mut correlator = Correlator::from_key_context(&session, params)?
+//!     .with_stretch_search(StretchSearch::default())?;
+//! let detection = correlator.detect(&reupload, &codec, &context)?;
+//! println!("played back at {:.1}% speed", detection.stretch * 100.0);
+//! ```
+//!
+//! [`Detection::stretch`]: crate::detect::correlator::Detection::stretch
+
+use crate::detect::correlator::{Correlator, DetectError, Detection};
+use crate::format::codec::FrameCodec;
+use crate::format::encryption::EncryptionContext;
+use crate::transforms::resample::Resampler;
+
+/// Seconds of input used to score grid candidates.
+const PROBE_SECS: f32 = 4.0;
+/// Coarse candidates refined and decoded.
+const REFINED_CANDIDATES: usize = 3;
+/// Refinement steps per coarse step.
+const REFINE_DIVISIONS: usize = 5;
+
+/// Kind of modification searched for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StretchMode {
+    /// Resampling: duration divided and pitch multiplied by the factor.
+    Speed,
+    /// Time-stretching: duration divided by the factor, pitch unchanged.
+    Tempo,
+    /// Pitch-shifting: pitch multiplied by the factor, duration unchanged.
+    Pitch,
+}
+
+/// Grid of factors searched by the detector.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StretchSearch {
+    /// Modification searched for.
+    pub mode: StretchMode,
+    /// Smallest factor tried.
+    pub min_factor: f32,
+    /// Largest factor tried.
+    pub max_factor: f32,
+    /// Spacing of the coarse grid.
+    pub step: f32,
+}
+
+impl Default for StretchSearch {
+    /// Speed changes of up to ±10% in steps of 0.5%.
+    fn default() -> Self {
+        Self {
+            mode: StretchMode::Speed,
+            min_factor: 0.9,
+            max_factor: 1.1,
+            step: 0.005,
+        }
+    }
+}
+
+impl StretchSearch {
+    /// Largest accepted number of coarse grid points.
+    pub const MAX_FACTORS: usize = 1024;
+
+    /// Searches `mode` over `1 - range ..= 1 + range` in steps of `step`.
+    pub fn symmetric(mode: StretchMode, range: f32, step: f32) -> Result<Self, DetectError> {
+        let search = Self {
+            mode,
+            min_factor: 1.0 - range,
+            max_factor: 1.0 + range,
+            step,
+        };
+        search.validate()?;
+        Ok(search)
+    }
+
+    /// Checks that the grid is non-empty and of manageable size.
+    pub fn validate(&self) -> Result<(), DetectError> {
+        if !(self.min_factor > 0.0 && self.min_factor <= 1.0 && self.max_factor >= 1.0) {
+            return Err(DetectError::InvalidParams(
+                "stretch range must be positive and include 1",
+            ));
+        }
+        if !(self.max_factor.is_finite() && self.step.is_finite() && self.step > 0.0) {
+            return Err(DetectError::InvalidParams("stretch step must be positive"));
+        }
+        if (self.max_factor - self.min_factor) / self.step >= Self::MAX_FACTORS as f32 {
+            return Err(DetectError::InvalidParams(
+                "stretch grid must not exceed 1024 factors",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Coarse grid factors: 1 and every `step` away from it within the range,
+    /// nearest first.
+    pub fn factors(&self) -> Vec<f32> {
+        let below = ((1.0 - self.min_factor) / self.step + 1e-3).floor() as usize;
+        let above = ((self.max_factor - 1.0) / self.step + 1e-3).floor() as usize;
+        let mut factors = vec![1.0];
+        for index in 1..=below.max(above) {
+            let offset = index as f32 * self.step;
+            if index <= above {
+                factors.push(1.0 + offset);
+            }
+            if index <= below {
+                factors.push(1.0 - offset);
+            }
+        }
+        factors
+    }
+
+    /// Resampling factor and bin warp that undo `factor`.
+    fn inverse(&self, factor: f32) -> (f32, f32) {
+        match self.mode {
+            StretchMode::Speed => (factor, 1.0),
+            StretchMode::Tempo => (factor, factor.recip()),
+            StretchMode::Pitch => (1.0, factor),
+        }
+    }
+}
+
+/// Searches the grid of `search` for a payload.
+pub(crate) fn detect(
+    correlator: &mut Correlator,
+    search: &StretchSearch,
+    samples: &[f32],
+    codec: &FrameCodec,
+    context: &EncryptionContext,
+) -> Result<Detection, DetectError> {
+    let probe_len = (PROBE_SECS * correlator.params().sample_rate() as f32) as usize;
+    let probe = &samples[..samples.len().min(probe_len)];
+    let factors = search.factors();
+
+    let mut scored = Vec::with_capacity(factors.len());
+    for &factor in &factors {
+        scored.push((score(correlator, search, probe, factor)?, factor));
+    }
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let fine_step = search.step / REFINE_DIVISIONS as f32;
+    let hypotheses = (factors.len() + REFINED_CANDIDATES * 2 * REFINE_DIVISIONS) as u64;
+    let mut failure = None;
+    for &(coarse_score, coarse) in scored.iter().take(REFINED_CANDIDATES) {
+        let mut best = (coarse_score, coarse);
+        for offset in 1..=REFINE_DIVISIONS {
+            for factor in [
+                coarse - offset as f32 * fine_step,
+                coarse + offset as f32 * fine_step,
+            ] {
+                if factor <= 0.0 {
+                    continue;
+                }
+                let candidate = score(correlator, search, probe, factor)?;
+                if candidate > best.0 {
+                    best = (candidate, factor);
+                }
+            }
+        }
+
+        let factor = best.1;
+        match decode(
+            correlator, search, samples, factor, hypotheses, codec, context,
+        ) {
+            Ok(detection) => return Ok(detection),
+            Err(DetectError::NotDetected(confidence)) => {
+                failure.get_or_insert(DetectError::NotDetected(confidence));
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    Err(failure.expect("the grid contains at least one factor"))
+}
+
+/// Correlation energy of the best slot alignment after undoing `factor`.
+fn score(
+    correlator: &mut Correlator,
+    search: &StretchSearch,
+    samples: &[f32],
+    factor: f32,
+) -> Result<f32, DetectError> {
+    let (rate, warp) = search.inverse(factor);
+    let (restored, _) = rescale(samples, correlator.params().sample_rate(), rate);
+    if restored.len() < correlator.min_samples() {
+        return Ok(0.0);
+    }
+    correlator.set_warp(warp);
+    let correlation = correlator.correlate(&restored);
+    correlator.set_warp(1.0);
+    Ok(correlation?.energy_ratio())
+}
+
+fn decode(
+    correlator: &mut Correlator,
+    search: &StretchSearch,
+    samples: &[f32],
+    factor: f32,
+    hypotheses: u64,
+    codec: &FrameCodec,
+    context: &EncryptionContext,
+) -> Result<Detection, DetectError> {
+    let (rate, warp) = search.inverse(factor);
+    let (restored, rate) = rescale(samples, correlator.params().sample_rate(), rate);
+    correlator.set_warp(warp);
+    let correlation = correlator.correlate(&restored);
+    correlator.set_warp(1.0);
+
+    let phases = correlator.phase_count() * hypotheses;
+    let mut detection = correlator.decode(&correlation?, phases, codec, context)?;
+    // Positions were measured on the restored timeline.
+    detection.payload_start = (detection.payload_start as f32 / rate).round() as isize;
+    if let Some(sync) = detection.sync.as_mut() {
+        sync.offset = (sync.offset as f32 / rate).round() as isize;
+    }
+    detection.stretch = factor;
+    Ok(detection)
+}
+
+/// Resamples `samples` to about `factor` times their length.
+///
+/// `factor` is rounded to the nearest ratio of `sample_rate` to another
+/// integer rate, which a band-limited [`Resampler`] converts between; the
+/// ratio used is returned with the result. Played at the original rate, the
+/// result runs `factor` times slower and `factor` times lower in pitch.
+fn rescale(samples: &[f32], sample_rate: u32, factor: f32) -> (Vec<f32>, f32) {
+    let target = ((sample_rate as f64 * factor as f64).round() as u32).max(1);
+    if target == sample_rate {
+        return (samples.to_vec(), 1.0);
+    }
+    let resampler = Resampler::new(sample_rate, target).expect("both rates are non-zero");
+    let ratio = (target as f64 / sample_rate as f64) as f32;
+    (resampler.process(samples), ratio)
+}
@@ -0,0 +1,76 @@
+//! Blind recovery of the payload position from the keyed sync pattern.
+//!
+//! When [`EmbedParams::sync_symbols`] is non-zero, the embedder sends the
+//! keyed [sync pattern](crate::embed::sync_pattern) ahead of every payload
+//! repetition, and detection does not need to know where an excerpt was cut.
+//! For every candidate slot alignment the correlator's soft symbols are
+//! cross-correlated with the pattern using [`FftBackend::cross_correlate`].
+//! The strongest peak gives the symbol phase and the slot at which a
+//! repetition starts, from which every payload bit's position follows. The
+//! decoder then no longer searches bit rotations or polarities, which both
+//! saves work and keeps the confidence correction small.
+//!
+//! ```text
+//! | sync | payload bits ... | sync | payload bits ... | sync | ...
+//!          ^ Detection::payload_start
+//!   ^ SyncMatch::offset
+//! ```
+//!
+//! ```ignore
+//! let params = EmbedParams::builder()
+//!     .preset(Preset::Balanced)
+//!     .sync_symbols(4)
+//!     .build()?;
+//! let detection = correlator.detect(&excerpt, &codec, &context)?;
+//! let sync = detection.sync.expect("sync enabled");
+//! println!("repetition at sample {}, phase {}", sync.offset, sync.symbol_phase);
+//! ```
+//!
+//! [`EmbedParams::sync_symbols`]: crate::embed::params::EmbedParams::sync_symbols
+//! [`FftBackend::cross_correlate`]: crate::transforms::fft::FftBackend::cross_correlate
+
+use crate::embed::sync_pattern::SyncPattern;
+use crate::transforms::fft::{FftBackend, FftError};
+
+/// Finds `pattern` in slot-major soft symbols with one value per distinct
+/// carrier.
+///
+/// Returns the slot at which the best match starts and its z-score, or
+/// `None` when the pattern is empty.
+pub(crate) fn locate(
+    pattern: &SyncPattern,
+    symbols: &[f32],
+    fft: &mut FftBackend<f32>,
+) -> Result<Option<(usize, f32)>, FftError> {
+    if pattern.is_empty() {
+        return Ok(None);
+    }
+    let template = pattern.template();
+    let bits_per_symbol = pattern.bits_per_symbol();
+    let lags = fft.cross_correlate(symbols, &template)?;
+
+    // Symbols are unit-variance z-scores, so the scaled sum is one too.
+    let scale = (template.len() as f32).sqrt().recip();
+    Ok(lags
+        .iter()
+        .enumerate()
+        .step_by(bits_per_symbol)
+        .map(|(lag, &value)| (lag / bits_per_symbol, value * scale))
+        .fold(None, |best: Option<(usize, f32)>, candidate| match best {
+            Some(best) if best.1 >= candidate.1 => Some(best),
+            _ => Some(candidate),
+        }))
+}
+
+/// Sync pattern located in the input by the correlator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SyncMatch {
+    /// Input sample at which the located pattern starts.
+    pub offset: isize,
+    /// Sample offset in `[0, symbol_len)` of the slot boundaries in the input.
+    pub symbol_phase: usize,
+    /// Correlation slot at which the pattern starts.
+    pub slot: usize,
+    /// Z-score of the match against the pattern.
+    pub score: f32,
+}
@@ -0,0 +1,503 @@
+//! Psychoacoustic masking model used to shape watermark energy.
+//!
+//! The model is a simplified form of ISO/IEC 11172-3 psychoacoustic model 1,
+//! evaluated on the STFT frames produced by [`FftBackend`]:
+//!
+//! 1. Bin power is converted to dB SPL, with a full-scale sine mapped to
+//!    [`MaskingConfig::full_scale_spl_db`].
+//! 2. Local maxima at least 7 dB above their neighbours are tonal maskers; the
+//!    remaining power in each critical band forms one noise masker.
+//! 3. Maskers below the absolute threshold of hearing, or within 0.5 Bark of a
+//!    stronger masker, are discarded.
+//! 4. Each masker spreads over the Bark scale with the model 1 two-slope
+//!    spreading function, lowered by `6.025 + 0.275 z` dB for tonal and
+//!    `2.025 + 0.175 z` dB for noise maskers.
+//! 5. Individual thresholds and the absolute threshold are summed in the power
+//!    domain to give the simultaneous masking threshold of every bin.
+//! 6. Temporal masking lets each frame's threshold decay exponentially into
+//!    the following frames (post-masking) and, much faster, into the preceding
+//!    frames (pre-masking).
+//!
+//! [`MaskingThresholds::allowed_strength_db`] converts a threshold into the
+//! largest magnitude change the spread-spectrum embedder may apply to a bin
+//! while the change itself stays below the threshold.
+//!
+//! ```ignore
+//! use wavemark::embed::masking::{MaskingConfig, MaskingModel};
+//! use wavemark::transforms::window::AnalysisWindow;
+//!
+//! let mut model = MaskingModel::new(44_100, AnalysisWindow::sqrt_hann(1024)?, MaskingConfig::default())?;
+//! let thresholds = model.analyze(&samples, 512)?;
+//! let headroom_db = thresholds.threshold_db(10)[40] - thresholds.level_db(10)[40];
+//! ```
+
+use std::f32::consts::LOG10_E;
+use std::fmt;
+
+use crate::transforms::fft::{Complex, FftBackend, FftError, Spectrogram};
+use crate::transforms::window::AnalysisWindow;
+
+/// Floor added to bin power before taking the logarithm.
+const POWER_FLOOR: f32 = 1e-20;
+/// Level difference that qualifies a local maximum as tonal.
+const TONAL_PROMINENCE_DB: f32 = 7.0;
+/// Maskers closer than this many Bark are merged into the stronger one.
+const MASKER_MERGE_BARK: f32 = 0.5;
+/// Spreading function support below and above the masker, in Bark.
+const SPREAD_BELOW_BARK: f32 = -3.0;
+const SPREAD_ABOVE_BARK: f32 = 8.0;
+
+/// Tunable constants of the masking model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaskingConfig {
+    /// Sound pressure level assigned to a full-scale sine, in dB SPL.
+    pub full_scale_spl_db: f32,
+    /// Safety margin subtracted from every threshold, in decibels.
+    pub margin_db: f32,
+    /// Upper bound on the per-bin embedding strength, in decibels.
+    pub max_strength_db: f32,
+    /// Time constant of post-masking decay, in milliseconds.
+    pub post_masking_ms: f32,
+    /// Time constant of pre-masking decay, in milliseconds.
+    pub pre_masking_ms: f32,
+}
+
+impl Default for MaskingConfig {
+    fn default() -> Self {
+        Self {
+            full_scale_spl_db: 96.0,
+            margin_db: 3.0,
+            max_strength_db: 6.0,
+            post_masking_ms: 50.0,
+            pre_masking_ms: 5.0,
+        }
+    }
+}
+
+impl MaskingConfig {
+    /// Checks that every constant is finite and in range.
+    pub fn validate(&self) -> Result<(), MaskingError> {
+        if !self.full_scale_spl_db.is_finite() || !self.margin_db.is_finite() {
+            return Err(MaskingError::InvalidConfig(
+                "levels and margins must be finite",
+            ));
+        }
+        if !(self.max_strength_db > 0.0 && self.max_strength_db <= 12.0) {
+            return Err(MaskingError::InvalidConfig(
+                "maximum strength must lie in (0, 12] dB",
+            ));
+        }
+        let positive = |ms: f32| ms > 0.0 && ms.is_finite();
+        if !positive(self.post_masking_ms) || !positive(self.pre_masking_ms) {
+            return Err(MaskingError::InvalidConfig(
+                "temporal masking time constants must be positive",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A tonal or noise masker found in one frame.
+#[derive(Debug, Clone, Copy)]
+struct Masker {
+    bin: usize,
+    bark: f32,
+    level_db: f32,
+    tonal: bool,
+}
+
+/// Per-bin masking thresholds for STFT frames of a fixed length.
+pub struct MaskingModel {
+    config: MaskingConfig,
+    sample_rate: u32,
+    window: AnalysisWindow,
+    /// Offset turning `10 log10 |X|^2` into dB SPL.
+    level_offset_db: f32,
+    bark: Vec<f32>,
+    absolute_threshold_db: Vec<f32>,
+    /// Critical band index of every bin.
+    band: Vec<usize>,
+    /// Neighbourhood checked for tonal prominence, in bins.
+    tonal_reach: Vec<usize>,
+    fft: FftBackend<f32>,
+}
+
+impl MaskingModel {
+    /// Creates a model for audio at `sample_rate` analysed with `window`.
+    pub fn new(
+        sample_rate: u32,
+        window: AnalysisWindow,
+        config: MaskingConfig,
+    ) -> Result<Self, MaskingError> {
+        config.validate()?;
+        if sample_rate == 0 {
+            return Err(MaskingError::InvalidConfig("sample rate must be non-zero"));
+        }
+        if window.len() < 2 {
+            return Err(MaskingError::InvalidConfig(
+                "window must span at least two samples",
+            ));
+        }
+
+        // A full-scale sine at a bin centre has magnitude `sum(w) / 2`.
+        let window_gain = window.coefficients().iter().sum::<f64>() as f32 / 2.0;
+        let level_offset_db = config.full_scale_spl_db - 20.0 * window_gain.log10();
+
+        let bins = FftBackend::<f32>::spectrum_len(window.len());
+        let resolution = sample_rate as f32 / window.len() as f32;
+        let frequencies: Vec<f32> = (0..bins).map(|bin| bin as f32 * resolution).collect();
+        let bark: Vec<f32> = frequencies.iter().map(|&f| bark(f)).collect();
+        let absolute_threshold_db = frequencies
+            .iter()
+            .map(|&f| absolute_threshold_db(f))
+            .collect();
+        let band = bark.iter().map(|&z| z.floor().max(0.0) as usize).collect();
+        let tonal_reach = frequencies
+            .iter()
+            .map(|&f| {
+                let reach_hz = if f < 5_500.0 {
+                    170.0
+                } else if f < 11_000.0 {
+                    260.0
+                } else {
+                    520.0
+                };
+                ((reach_hz / resolution).round() as usize).max(2)
+            })
+            .collect();
+
+        Ok(Self {
+            config,
+            sample_rate,
+            window,
+            level_offset_db,
+            bark,
+            absolute_threshold_db,
+            band,
+            tonal_reach,
+            fft: FftBackend::new(),
+        })
+    }
+
+    /// Returns the model configuration.
+    pub fn config(&self) -> &MaskingConfig {
+        &self.config
+    }
+
+    /// Returns the analysis window the model is calibrated for.
+    pub fn window(&self) -> &AnalysisWindow {
+        &self.window
+    }
+
+    /// Absolute threshold of hearing for every bin, in dB SPL.
+    pub fn absolute_threshold_db(&self) -> &[f32] {
+        &self.absolute_threshold_db
+    }
+
+    /// Analyses `samples` with the model's window and computes thresholds.
+    pub fn analyze(
+        &mut self,
+        samples: &[f32],
+        hop: usize,
+    ) -> Result<MaskingThresholds, MaskingError> {
+        let spectrogram = self.fft.stft(samples, &self.window, hop)?;
+        self.thresholds(&spectrogram)
+    }
+
+    /// Computes simultaneous and temporal masking thresholds for `spectrogram`.
+    ///
+    /// The spectrogram must have been produced with the model's window.
+    pub fn thresholds(
+        &self,
+        spectrogram: &Spectrogram<f32>,
+    ) -> Result<MaskingThresholds, MaskingError> {
+        if spectrogram.frame_len() != self.window.len() {
+            return Err(MaskingError::FrameLengthMismatch {
+                expected: self.window.len(),
+                found: spectrogram.frame_len(),
+            });
+        }
+
+        let bins = spectrogram.bins();
+        let mut levels_db = Vec::with_capacity(spectrogram.len() * bins);
+        let mut thresholds_db = Vec::with_capacity(spectrogram.len() * bins);
+        for spectrum in spectrogram.frames() {
+            let (levels, thresholds) = self.frame_threshold(spectrum);
+            levels_db.extend(levels);
+            thresholds_db.extend(thresholds);
+        }
+
+        // Exponential decay of each frame's threshold into its neighbours.
+        let hop_ms = 1_000.0 * spectrogram.hop() as f32 / self.sample_rate as f32;
+        let post_decay_db = 10.0 * LOG10_E * hop_ms / self.config.post_masking_ms;
+        let pre_decay_db = 10.0 * LOG10_E * hop_ms / self.config.pre_masking_ms;
+        let frames = spectrogram.len();
+        for frame in 1..frames {
+            for bin in 0..bins {
+                let carried = thresholds_db[(frame - 1) * bins + bin] - post_decay_db;
+                let current = &mut thresholds_db[frame * bins + bin];
+                *current = current.max(carried);
+            }
+        }
+        for frame in (0..frames.saturating_sub(1)).rev() {
+            for bin in 0..bins {
+                let carried = thresholds_db[(frame + 1) * bins + bin] - pre_decay_db;
+                let current = &mut thresholds_db[frame * bins + bin];
+                *current = current.max(carried);
+            }
+        }
+
+        for threshold in thresholds_db.iter_mut() {
+            *threshold -= self.config.margin_db;
+        }
+
+        Ok(MaskingThresholds {
+            bins,
+            max_strength_db: self.config.max_strength_db,
+            levels_db,
+            thresholds_db,
+        })
+    }
+
+    /// Bin levels and simultaneous masking threshold of one spectrum, in dB SPL.
+    pub fn frame_threshold(&self, spectrum: &[Complex<f32>]) -> (Vec<f32>, Vec<f32>) {
+        let levels: Vec<f32> = spectrum
+            .iter()
+            .map(|bin| 10.0 * (bin.norm_sqr() + POWER_FLOOR).log10() + self.level_offset_db)
+            .collect();
+        let maskers = self.find_maskers(&levels);
+
+        let mut power: Vec<f32> = self
+            .absolute_threshold_db
+            .iter()
+            .map(|&ath| db_to_power(ath))
+            .collect();
+        for masker in &maskers {
+            let offset = if masker.tonal {
+                -6.025 - 0.275 * masker.bark
+            } else {
+                -2.025 - 0.175 * masker.bark
+            };
+            for (bin, slot) in power.iter_mut().enumerate() {
+                let dz = self.bark[bin] - masker.bark;
+                if !(SPREAD_BELOW_BARK..SPREAD_ABOVE_BARK).contains(&dz) {
+                    continue;
+                }
+                let threshold = masker.level_db + offset + spreading_db(dz, masker.level_db);
+                *slot += db_to_power(threshold);
+            }
+        }
+
+        let thresholds = power.iter().map(|&p| power_to_db(p)).collect();
+        (levels, thresholds)
+    }
+
+    /// Tonal and noise maskers of one frame, after decimation.
+    fn find_maskers(&self, levels: &[f32]) -> Vec<Masker> {
+        let bins = levels.len();
+        let mut tonal = vec![false; bins];
+        let mut maskers = Vec::new();
+
+        for bin in 1..bins.saturating_sub(1) {
+            let level = levels[bin];
+            if level <= levels[bin - 1] || level < levels[bin + 1] {
+                continue;
+            }
+            let reach = self.tonal_reach[bin];
+            let prominent = (2..=reach).all(|distance| {
+                let below = bin
+                    .checked_sub(distance)
+                    .is_none_or(|other| level - levels[other] >= TONAL_PROMINENCE_DB);
+                let above = levels
+                    .get(bin + distance)
+                    .is_none_or(|&other| level - other >= TONAL_PROMINENCE_DB);
+                below && above
+            });
+            if !prominent {
+                continue;
+            }
+            let power =
+                db_to_power(levels[bin - 1]) + db_to_power(level) + db_to_power(levels[bin + 1]);
+            tonal[bin - 1] = true;
+            tonal[bin] = true;
+            tonal[bin + 1] = true;
+            maskers.push(Masker {
+                bin,
+                bark: self.bark[bin],
+                level_db: power_to_db(power),
+                tonal: true,
+            });
+        }
+
+        // Remaining power per critical band, placed at the band's centre.
+        let band_count = self.band.last().map_or(0, |&band| band + 1);
+        let mut band_power = vec![0.0f32; band_count];
+        let mut band_bins: Vec<(usize, usize)> = vec![(usize::MAX, 0); band_count];
+        for bin in 1..bins {
+            let band = self.band[bin];
+            let (first, last) = &mut band_bins[band];
+            *first = (*first).min(bin);
+            *last = (*last).max(bin);
+            if !tonal[bin] {
+                band_power[band] += db_to_power(levels[bin]);
+            }
+        }
+        for (band, &power) in band_power.iter().enumerate() {
+            let (first, last) = band_bins[band];
+            if power <= 0.0 || first > last {
+                continue;
+            }
+            let bin = (first + last) / 2;
+            maskers.push(Masker {
+                bin,
+                bark: self.bark[bin],
+                level_db: power_to_db(power),
+                tonal: false,
+            });
+        }
+
+        // Drop inaudible maskers and keep only the strongest within 0.5 Bark.
+        maskers.retain(|masker| masker.level_db >= self.absolute_threshold_db[masker.bin]);
+        maskers.sort_by_key(|masker| masker.bin);
+        let mut kept: Vec<Masker> = Vec::with_capacity(maskers.len());
+        for masker in maskers {
+            match kept.last_mut() {
+                Some(previous) if masker.bark - previous.bark < MASKER_MERGE_BARK => {
+                    if masker.level_db > previous.level_db {
+                        *previous = masker;
+                    }
+                }
+                _ => kept.push(masker),
+            }
+        }
+        kept
+    }
+}
+
+impl fmt::Debug for MaskingModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MaskingModel")
+            .field("config", &self.config)
+            .field("sample_rate", &self.sample_rate)
+            .field("frame_len", &self.window.len())
+            .finish()
+    }
+}
+
+/// Bin levels and masking thresholds for every frame of a spectrogram.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaskingThresholds {
+    bins: usize,
+    max_strength_db: f32,
+    levels_db: Vec<f32>,
+    thresholds_db: Vec<f32>,
+}
+
+impl MaskingThresholds {
+    /// Number of frames.
+    pub fn len(&self) -> usize {
+        self.levels_db.len() / self.bins.max(1)
+    }
+
+    /// Returns `true` when no frames were analysed.
+    pub fn is_empty(&self) -> bool {
+        self.levels_db.is_empty()
+    }
+
+    /// Number of bins per frame.
+    pub fn bins(&self) -> usize {
+        self.bins
+    }
+
+    /// Bin levels of `frame` in dB SPL.
+    pub fn level_db(&self, frame: usize) -> &[f32] {
+        &self.levels_db[frame * self.bins..(frame + 1) * self.bins]
+    }
+
+    /// Masking thresholds of `frame` in dB SPL, including the safety margin.
+    pub fn threshold_db(&self, frame: usize) -> &[f32] {
+        &self.thresholds_db[frame * self.bins..(frame + 1) * self.bins]
+    }
+
+    /// Largest magnitude change for `bin` of `frame` that stays below the threshold.
+    ///
+    /// Scaling a bin of level `L` by `10^(a / 20)` adds a component of level
+    /// `L + 20 log10(10^(a / 20) - 1)`. Solving for the threshold `T` gives
+    /// `a = 20 log10(1 + 10^((T - L) / 20))`, capped at
+    /// [`MaskingConfig::max_strength_db`].
+    pub fn allowed_strength_db(&self, frame: usize, bin: usize) -> f32 {
+        let index = frame * self.bins + bin;
+        let headroom_db = self.thresholds_db[index] - self.levels_db[index];
+        let strength = 20.0 * (1.0 + 10f32.powf(headroom_db / 20.0)).log10();
+        strength.min(self.max_strength_db)
+    }
+}
+
+/// Critical-band rate of `frequency_hz` in Bark (Zwicker and Terhardt).
+pub fn bark(frequency_hz: f32) -> f32 {
+    13.0 * (0.000_76 * frequency_hz).atan() + 3.5 * (frequency_hz / 7_500.0).powi(2).atan()
+}
+
+/// Absolute threshold of hearing at `frequency_hz` in dB SPL (Terhardt).
+///
+/// Frequencies below 20 Hz are evaluated at 20 Hz.
+pub fn absolute_threshold_db(frequency_hz: f32) -> f32 {
+    let khz = frequency_hz.max(20.0) / 1_000.0;
+    3.64 * khz.powf(-0.8) - 6.5 * (-0.6 * (khz - 3.3).powi(2)).exp() + 1e-3 * khz.powi(4)
+}
+
+/// Psychoacoustic model 1 spreading function for a maskee `dz` Bark away.
+fn spreading_db(dz: f32, masker_level_db: f32) -> f32 {
+    if dz < -1.0 {
+        17.0 * (dz + 1.0) - (0.4 * masker_level_db + 6.0)
+    } else if dz < 0.0 {
+        (0.4 * masker_level_db + 6.0) * dz
+    } else if dz < 1.0 {
+        -17.0 * dz
+    } else {
+        -(dz - 1.0) * (17.0 - 0.15 * masker_level_db) - 17.0
+    }
+}
+
+fn db_to_power(db: f32) -> f32 {
+    10f32.powf(db / 10.0)
+}
+
+fn power_to_db(power: f32) -> f32 {
+    10.0 * (power + POWER_FLOOR).log10()
+}
+
+/// Errors raised by the masking model.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MaskingError {
+    /// Model configuration is out of range.
+    InvalidConfig(&'static str),
+    /// Spectrogram frames do not match the model's window.
+    FrameLengthMismatch { expected: usize, found: usize },
+    /// STFT analysis failed.
+    Transform(FftError),
+}
+
+impl fmt::Display for MaskingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaskingError::InvalidConfig(reason) => {
+                write!(f, "invalid masking configuration: {}", reason)
+            }
+            MaskingError::FrameLengthMismatch { expected, found } => write!(
+                f,
+                "frame length mismatch: model expects {}, spectrogram has {}",
+                expected, found
+            ),
+            MaskingError::Transform(err) => write!(f, "transform error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for MaskingError {}
+
+impl From<FftError> for MaskingError {
+    fn from(err: FftError) -> Self {
+        MaskingError::Transform(err)
+    }
+}
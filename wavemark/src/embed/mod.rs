@@ -1,5 +1,8 @@
-//! Watermark embedding algorithm placeholders.
+//! Watermark embedding algorithms.
 
+pub mod masking;
+pub mod multichannel;
 pub mod params;
 pub mod payload_mapper;
 pub mod spread_spectrum;
+pub mod sync_pattern;
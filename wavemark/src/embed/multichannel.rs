@@ -0,0 +1,200 @@
+//! Watermarking of multichannel audio.
+//!
+//! Marking each channel of a stereo file on its own leaves the channels with
+//! watermarks of different strength, and with masking enabled even different
+//! shapes, so a mono downmix carries a distorted mixture of them.
+//! [`MultichannelEmbedder`] marks an [`AudioChunk`] in one of three
+//! [`ChannelMode`]s:
+//!
+//! | Mode          | Marks                                   | Payloads    |
+//! |---------------|-----------------------------------------|-------------|
+//! | `Mid`         | the mean of all channels                | 1           |
+//! | `Identical`   | every channel, same key and payload     | 1           |
+//! | `Independent` | every channel under its own channel key | one each    |
+//!
+//! In `Mid` mode the change the embedder makes to the mean is added to every
+//! channel, so every channel and every downmix whose weights sum to one carry
+//! exactly the same watermark. `Identical` mode scales each channel by the
+//! same keyed gains; without masking, a downmix then carries the watermark of
+//! the mixed signal as well.
+//!
+//! In `Independent` mode channel `c` is keyed by [`channel_context`], which
+//! is the session context itself for channel 0 and
+//! [`KeyContext::channel`] for the others. The spreading codes of different
+//! channels are uncorrelated, so each payload survives in a downmix as noise
+//! to the others and is found by a detector keyed for its channel. A payload
+//! reaches a downmix at a fraction of its strength, so detection there needs
+//! more audio than on the channel itself.
+//!
+//! ```ignore
+//! use wavemark::core::types::{AudioChunk, ChannelLayout};
+//! use wavemark::embed::multichannel::{ChannelMode, MultichannelEmbedder};
+//!
+//! let mut chunk = AudioChunk::interleaved_mut(&mut interleaved, ChannelLayout::Stereo)?;
+//! let mut embedder = MultichannelEmbedder::from_key_context(&session, params, ChannelMode::Mid)?;
+//! embedder.embed(&mut chunk, &[&payload_bytes])?;
+//! ```
+
+use crate::core::sample::Sample;
+use crate::core::types::{AudioChunk, AudioChunkError};
+use crate::embed::masking::MaskingConfig;
+use crate::embed::params::EmbedParams;
+use crate::embed::spread_spectrum::{EmbedError, SpreadSpectrumEmbedder};
+use crate::key::derivation::KeyContext;
+
+/// How a payload is distributed over the channels of an [`AudioChunk`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChannelMode {
+    /// Marks the mean of the channels and adds the change to every channel.
+    #[default]
+    Mid,
+    /// Marks every channel with the same key and payload.
+    Identical,
+    /// Marks every channel with its own channel key and payload.
+    Independent,
+}
+
+/// Key context used for channel `channel` in [`ChannelMode::Independent`].
+pub fn channel_context(context: &KeyContext, channel: usize) -> KeyContext {
+    match channel {
+        0 => context.clone(),
+        _ => context.channel(channel),
+    }
+}
+
+/// Embeds payloads into interleaved multichannel audio.
+#[derive(Debug)]
+pub struct MultichannelEmbedder {
+    context: KeyContext,
+    params: EmbedParams,
+    masking: Option<MaskingConfig>,
+    mode: ChannelMode,
+    /// One embedder per channel key, created as channels are first seen.
+    embedders: Vec<SpreadSpectrumEmbedder>,
+}
+
+impl MultichannelEmbedder {
+    /// Creates an embedder keyed by `context` that distributes payloads by `mode`.
+    pub fn from_key_context(
+        context: &KeyContext,
+        params: EmbedParams,
+        mode: ChannelMode,
+    ) -> Result<Self, EmbedError> {
+        let first = SpreadSpectrumEmbedder::from_key_context(context, params.clone())?;
+        Ok(Self {
+            context: context.clone(),
+            params,
+            masking: None,
+            mode,
+            embedders: vec![first],
+        })
+    }
+
+    /// Shapes the watermark of every channel with the masking model.
+    pub fn with_masking(mut self, config: MaskingConfig) -> Result<Self, EmbedError> {
+        self.embedders = self
+            .embedders
+            .into_iter()
+            .map(|embedder| embedder.with_masking(config.clone()))
+            .collect::<Result<_, _>>()?;
+        self.masking = Some(config);
+        Ok(self)
+    }
+
+    /// Returns the channel mode.
+    pub fn mode(&self) -> ChannelMode {
+        self.mode
+    }
+
+    /// Returns the embedding parameters.
+    pub fn params(&self) -> &EmbedParams {
+        &self.params
+    }
+
+    /// Number of payloads [`MultichannelEmbedder::embed`] expects for `channels` channels.
+    pub fn payload_count(&self, channels: usize) -> usize {
+        match self.mode {
+            ChannelMode::Mid | ChannelMode::Identical => 1,
+            ChannelMode::Independent => channels,
+        }
+    }
+
+    /// Watermarks `chunk` in place.
+    ///
+    /// `payloads` holds one payload in `Mid` and `Identical` mode and one per
+    /// channel, in channel order, in `Independent` mode. Integer chunks are
+    /// written back with their dither.
+    pub fn embed<S: Sample>(
+        &mut self,
+        chunk: &mut AudioChunk<'_, S>,
+        payloads: &[&[u8]],
+    ) -> Result<(), EmbedError> {
+        let expected = self.payload_count(chunk.channels());
+        if payloads.len() != expected {
+            return Err(EmbedError::PayloadCount {
+                expected,
+                found: payloads.len(),
+            });
+        }
+        if payloads.iter().any(|payload| payload.is_empty()) {
+            return Err(EmbedError::EmptyPayload);
+        }
+        if !chunk.is_writable() {
+            return Err(AudioChunkError::ReadOnly.into());
+        }
+
+        match self.mode {
+            ChannelMode::Mid => {
+                let mid = chunk.downmix();
+                let marked = self.embedders[0].embed(&mid, payloads[0])?;
+                for channel in 0..chunk.channels() {
+                    let mut samples = chunk.channel(channel)?;
+                    for (sample, (&before, &after)) in
+                        samples.iter_mut().zip(mid.iter().zip(&marked))
+                    {
+                        *sample += after - before;
+                    }
+                    chunk.set_channel(channel, &samples)?;
+                }
+            }
+            ChannelMode::Identical => {
+                for channel in 0..chunk.channels() {
+                    self.embed_channel(chunk, channel, 0, payloads[0])?;
+                }
+            }
+            ChannelMode::Independent => {
+                for (channel, payload) in payloads.iter().enumerate() {
+                    self.ensure_embedder(channel)?;
+                    self.embed_channel(chunk, channel, channel, payload)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn embed_channel<S: Sample>(
+        &mut self,
+        chunk: &mut AudioChunk<'_, S>,
+        channel: usize,
+        embedder: usize,
+        payload: &[u8],
+    ) -> Result<(), EmbedError> {
+        let samples = chunk.channel(channel)?;
+        let marked = self.embedders[embedder].embed(&samples, payload)?;
+        chunk.set_channel(channel, &marked)?;
+        Ok(())
+    }
+
+    fn ensure_embedder(&mut self, channel: usize) -> Result<(), EmbedError> {
+        while self.embedders.len() <= channel {
+            let context = channel_context(&self.context, self.embedders.len());
+            let mut embedder =
+                SpreadSpectrumEmbedder::from_key_context(&context, self.params.clone())?;
+            if let Some(config) = &self.masking {
+                embedder = embedder.with_masking(config.clone())?;
+            }
+            self.embedders.push(embedder);
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,445 @@
-#![allow(dead_code)]
+//! Parameters controlling where and how strongly the watermark is embedded.
+//!
+//! The embedder works on a short-time Fourier transform of the input. Frames of
+//! `frame_len` samples advance by `hop` samples, and `frames_per_symbol`
+//! consecutive frames form one symbol slot. Within a slot the frequency band
+//! `band_low_hz..band_high_hz` is split into `carriers` contiguous groups of
+//! bins. Every `redundancy` carriers share one payload bit, spread across the
+//! band so that a band-limited distortion does not remove all copies.
+//!
+//! Rather than tuning every field, start from a [`Preset`]:
+//!
+//! | Preset        | Strength | Band (Hz)    | Frames/symbol | Redundancy |
+//! |---------------|----------|--------------|---------------|------------|
+//! | `Transparent` | 1.0 dB   | 1000 – 6000  | 4             | 1          |
+//! | `Balanced`    | 1.5 dB   | 500 – 8000   | 4             | 1          |
+//! | `Robust`      | 3.0 dB   | 300 – 10000  | 8             | 2          |
+//!
+//! Band edges are lowered to 45% of the sample rate when the preset band would
+//! reach Nyquist.
+//!
+//! Setting [`EmbedParams::sync_symbols`] sends a keyed sync pattern ahead of
+//! every payload repetition. The detector then locates the payload directly
+//! instead of searching every bit rotation, which is what makes excerpts little
+//! longer than one repetition decodable. Presets leave it disabled so that
+//! audio marked without sync stays detectable with preset parameters.
+//!
+//! Codec bytes are framed for the channel by [`EmbedParams::mapper`] before
+//! they are spread, and the detector reverses the framing with the same
+//! mapper. Presets use BPSK; M-ary symbols trade bit rate for robustness.
+//!
+//! Parameters are only created through [`EmbedParamsBuilder::build`], which
+//! rejects impossible combinations, so every [`EmbedParams`] value describes
+//! a usable channel. Fields set on the builder override those of its preset,
+//! whatever order they are set in.
+//!
+//! ```ignore
+//! use wavemark::embed::params::{EmbedParams, Preset};
+//!
+//! let params = EmbedParams::preset(Preset::Robust, 48_000)?;
+//! assert_eq!(params.symbol_len(), 4096);
+//!
+//! // Hand-tuned parameters are checked when built.
+//! let custom = EmbedParams::builder()
+//!     .preset(Preset::Robust)
+//!     .sample_rate(48_000)
+//!     .strength_db(2.0)
+//!     .build()?;
+//! let stronger = custom.to_builder().strength_db(4.0).build()?;
+//! ```
 
-//! Placeholder parameters for embedding algorithms.
+use std::ops::Range;
 
-/// Configuration placeholder for embedding strength.
-pub struct EmbedParams;
+use crate::embed::payload_mapper::PayloadMapper;
+use crate::embed::spread_spectrum::EmbedError;
+use crate::transforms::window::{AnalysisWindow, OverlapMode};
+
+/// Named trade-offs between audibility and robustness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// Lowest audibility; suited to clean distribution channels.
+    Transparent,
+    /// Default trade-off; survives moderate lossy coding.
+    Balanced,
+    /// Strongest marking with frequency diversity; for noisy or re-recorded audio.
+    Robust,
+}
+
+/// Embedding configuration shared by the embedder and the detector.
+///
+/// Both sides must use identical parameters; the detector regenerates the
+/// same spreading code from the key and these values. Values are built with
+/// [`EmbedParams::builder`] or [`EmbedParams::preset`] and are always valid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbedParams {
+    /// Sample rate of the PCM audio in hertz.
+    sample_rate: u32,
+    /// Lower edge of the embedding band in hertz.
+    band_low_hz: f32,
+    /// Upper edge of the embedding band in hertz.
+    band_high_hz: f32,
+    /// Magnitude change applied to each bin relative to the host, in decibels.
+    strength_db: f32,
+    /// STFT frame length in samples.
+    frame_len: usize,
+    /// STFT hop size in samples.
+    hop: usize,
+    /// Number of STFT frames spanned by one symbol slot; must be even.
+    frames_per_symbol: usize,
+    /// Number of carriers per symbol slot.
+    carriers: usize,
+    /// Number of carriers sharing each payload bit; must divide `carriers`.
+    redundancy: usize,
+    /// Symbol slots of keyed sync pattern sent before every payload repetition;
+    /// `0` disables synchronization.
+    sync_symbols: usize,
+    /// Framing of the payload bytes as channel bits.
+    mapper: PayloadMapper,
+}
+
+impl Default for EmbedParams {
+    fn default() -> Self {
+        Self {
+            sample_rate: 44_100,
+            band_low_hz: 500.0,
+            band_high_hz: 8_000.0,
+            strength_db: 1.5,
+            frame_len: 1024,
+            hop: 512,
+            frames_per_symbol: 4,
+            carriers: 8,
+            redundancy: 1,
+            sync_symbols: 0,
+            mapper: PayloadMapper::bpsk(),
+        }
+    }
+}
+
+impl EmbedParams {
+    /// Largest accepted strength in decibels.
+    pub const MAX_STRENGTH_DB: f32 = 12.0;
+    /// Largest accepted number of sync symbols.
+    pub const MAX_SYNC_SYMBOLS: usize = 64;
+
+    /// Starts a builder from the default parameters.
+    pub fn builder() -> EmbedParamsBuilder {
+        EmbedParamsBuilder::default()
+    }
+
+    /// Starts a builder that keeps every field of these parameters.
+    pub fn to_builder(&self) -> EmbedParamsBuilder {
+        EmbedParamsBuilder {
+            preset: None,
+            sample_rate: Some(self.sample_rate),
+            band_low_hz: Some(self.band_low_hz),
+            band_high_hz: Some(self.band_high_hz),
+            strength_db: Some(self.strength_db),
+            frame_len: Some(self.frame_len),
+            hop: Some(self.hop),
+            frames_per_symbol: Some(self.frames_per_symbol),
+            carriers: Some(self.carriers),
+            redundancy: Some(self.redundancy),
+            sync_symbols: Some(self.sync_symbols),
+            mapper: Some(self.mapper.clone()),
+        }
+    }
+
+    /// Builds the parameters for `preset` at `sample_rate`.
+    pub fn preset(preset: Preset, sample_rate: u32) -> Result<Self, EmbedError> {
+        Self::builder()
+            .preset(preset)
+            .sample_rate(sample_rate)
+            .build()
+    }
+
+    /// Unvalidated parameters of `preset` at `sample_rate`.
+    fn preset_unchecked(preset: Preset, sample_rate: u32) -> Self {
+        let (band_low_hz, band_high_hz, strength_db, frames_per_symbol, redundancy) = match preset {
+            Preset::Transparent => (1_000.0, 6_000.0, 1.0, 4, 1),
+            Preset::Balanced => (500.0, 8_000.0, 1.5, 4, 1),
+            Preset::Robust => (300.0, 10_000.0, 3.0, 8, 2),
+        };
+        let usable_hz = 0.45 * sample_rate as f32;
+
+        Self {
+            sample_rate,
+            band_low_hz,
+            band_high_hz: f32::min(band_high_hz, usable_hz),
+            strength_db,
+            frames_per_symbol,
+            redundancy,
+            ..Self::default()
+        }
+    }
+
+    /// Checks that the parameters describe a usable embedding channel.
+    fn validate(&self) -> Result<(), EmbedError> {
+        if self.sample_rate == 0 {
+            return Err(EmbedError::InvalidParams("sample rate must be non-zero"));
+        }
+        let nyquist = self.sample_rate as f32 / 2.0;
+        if !(self.band_low_hz >= 0.0 && self.band_low_hz < self.band_high_hz) {
+            return Err(EmbedError::InvalidParams(
+                "band must be non-negative with its low edge below its high edge",
+            ));
+        }
+        if self.band_high_hz > nyquist {
+            return Err(EmbedError::InvalidParams(
+                "band must lie below the Nyquist frequency",
+            ));
+        }
+        if !self.strength_db.is_finite()
+            || self.strength_db < 0.0
+            || self.strength_db > Self::MAX_STRENGTH_DB
+        {
+            return Err(EmbedError::InvalidParams(
+                "strength must lie between 0 and 12 dB",
+            ));
+        }
+        if self.frame_len < 2 || self.hop == 0 || self.hop > self.frame_len {
+            return Err(EmbedError::InvalidParams(
+                "hop must be non-zero and no longer than the frame",
+            ));
+        }
+        AnalysisWindow::sqrt_hann(self.frame_len)?
+            .check_overlap_add(self.hop, OverlapMode::WeightedOverlapAdd)?;
+        if self.frames_per_symbol < 2 || !self.frames_per_symbol.is_multiple_of(2) {
+            return Err(EmbedError::InvalidParams(
+                "frames per symbol must be even and non-zero",
+            ));
+        }
+        if self.redundancy == 0 || !self.carriers.is_multiple_of(self.redundancy) {
+            return Err(EmbedError::InvalidParams(
+                "redundancy must be non-zero and divide the carrier count",
+            ));
+        }
+        if self.sync_symbols > Self::MAX_SYNC_SYMBOLS {
+            return Err(EmbedError::InvalidParams(
+                "sync pattern must not exceed 64 symbols",
+            ));
+        }
+        if self.carriers == 0 || self.band_bins().len() < self.carriers {
+            return Err(EmbedError::InvalidParams(
+                "band must contain at least one bin per carrier",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Sample rate of the PCM audio in hertz.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Lower edge of the embedding band in hertz.
+    pub fn band_low_hz(&self) -> f32 {
+        self.band_low_hz
+    }
+
+    /// Upper edge of the embedding band in hertz.
+    pub fn band_high_hz(&self) -> f32 {
+        self.band_high_hz
+    }
+
+    /// Magnitude change applied to each bin relative to the host, in decibels.
+    pub fn strength_db(&self) -> f32 {
+        self.strength_db
+    }
+
+    /// STFT frame length in samples.
+    pub fn frame_len(&self) -> usize {
+        self.frame_len
+    }
+
+    /// STFT hop size in samples.
+    pub fn hop(&self) -> usize {
+        self.hop
+    }
+
+    /// Number of STFT frames spanned by one symbol slot; must be even.
+    pub fn frames_per_symbol(&self) -> usize {
+        self.frames_per_symbol
+    }
+
+    /// Number of carriers per symbol slot.
+    pub fn carriers(&self) -> usize {
+        self.carriers
+    }
+
+    /// Number of carriers sharing each payload bit; must divide `carriers`.
+    pub fn redundancy(&self) -> usize {
+        self.redundancy
+    }
+
+    /// Symbol slots of keyed sync pattern sent before every payload repetition;
+    /// `0` disables synchronization.
+    pub fn sync_symbols(&self) -> usize {
+        self.sync_symbols
+    }
+
+    /// Framing of the payload bytes as channel bits.
+    pub fn mapper(&self) -> &PayloadMapper {
+        &self.mapper
+    }
+
+    /// Length of one symbol slot in samples.
+    pub fn symbol_len(&self) -> usize {
+        self.frames_per_symbol * self.hop
+    }
+
+    /// Duration of one symbol slot in seconds.
+    pub fn symbol_duration_secs(&self) -> f32 {
+        self.symbol_len() as f32 / self.sample_rate as f32
+    }
+
+    /// Distinct payload bits carried by each symbol slot.
+    pub fn bits_per_symbol(&self) -> usize {
+        self.carriers / self.redundancy.max(1)
+    }
+
+    /// STFT bins covered by the embedding band.
+    ///
+    /// DC and Nyquist are never included. The range is empty when the band
+    /// does not overlap any usable bin.
+    pub fn band_bins(&self) -> Range<usize> {
+        let resolution = self.sample_rate as f32 / self.frame_len as f32;
+        let nyquist_bin = self.frame_len / 2;
+        let low = ((self.band_low_hz / resolution).ceil().max(1.0) as usize).min(nyquist_bin);
+        let high = ((self.band_high_hz / resolution).floor().max(0.0) as usize + 1)
+            .min(nyquist_bin)
+            .max(low);
+        low..high
+    }
+
+    /// Symbol slots per payload repetition of `payload_bits` bits, including
+    /// the sync pattern.
+    pub fn cycle_slots(&self, payload_bits: usize) -> usize {
+        self.sync_symbols + payload_bits.div_ceil(self.bits_per_symbol().max(1))
+    }
+
+    /// Payload bits embedded per second of audio, excluding sync overhead.
+    pub fn bit_rate(&self) -> f32 {
+        self.bits_per_symbol() as f32 / self.symbol_duration_secs()
+    }
+}
+
+/// Builder for [`EmbedParams`].
+///
+/// Unset fields take the value of the preset, or of
+/// [`EmbedParams::default`] without one, at the configured sample rate.
+#[derive(Debug, Clone, Default)]
+pub struct EmbedParamsBuilder {
+    preset: Option<Preset>,
+    sample_rate: Option<u32>,
+    band_low_hz: Option<f32>,
+    band_high_hz: Option<f32>,
+    strength_db: Option<f32>,
+    frame_len: Option<usize>,
+    hop: Option<usize>,
+    frames_per_symbol: Option<usize>,
+    carriers: Option<usize>,
+    redundancy: Option<usize>,
+    sync_symbols: Option<usize>,
+    mapper: Option<PayloadMapper>,
+}
+
+impl EmbedParamsBuilder {
+    /// Starts from `preset`, resolved at the sample rate given to the builder.
+    pub fn preset(mut self, preset: Preset) -> Self {
+        self.preset = Some(preset);
+        self
+    }
+
+    /// Sets the sample rate of the PCM audio in hertz.
+    pub fn sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = Some(sample_rate);
+        self
+    }
+
+    /// Sets the lower edge of the embedding band in hertz.
+    pub fn band_low_hz(mut self, band_low_hz: f32) -> Self {
+        self.band_low_hz = Some(band_low_hz);
+        self
+    }
+
+    /// Sets the upper edge of the embedding band in hertz.
+    pub fn band_high_hz(mut self, band_high_hz: f32) -> Self {
+        self.band_high_hz = Some(band_high_hz);
+        self
+    }
+
+    /// Sets the magnitude change applied to each bin, in decibels.
+    pub fn strength_db(mut self, strength_db: f32) -> Self {
+        self.strength_db = Some(strength_db);
+        self
+    }
+
+    /// Sets the STFT frame length in samples.
+    pub fn frame_len(mut self, frame_len: usize) -> Self {
+        self.frame_len = Some(frame_len);
+        self
+    }
+
+    /// Sets the STFT hop size in samples.
+    pub fn hop(mut self, hop: usize) -> Self {
+        self.hop = Some(hop);
+        self
+    }
+
+    /// Sets the number of STFT frames per symbol slot.
+    pub fn frames_per_symbol(mut self, frames_per_symbol: usize) -> Self {
+        self.frames_per_symbol = Some(frames_per_symbol);
+        self
+    }
+
+    /// Sets the number of carriers per symbol slot.
+    pub fn carriers(mut self, carriers: usize) -> Self {
+        self.carriers = Some(carriers);
+        self
+    }
+
+    /// Sets the number of carriers sharing each payload bit.
+    pub fn redundancy(mut self, redundancy: usize) -> Self {
+        self.redundancy = Some(redundancy);
+        self
+    }
+
+    /// Sets the symbol slots of sync pattern per repetition; `0` disables sync.
+    pub fn sync_symbols(mut self, sync_symbols: usize) -> Self {
+        self.sync_symbols = Some(sync_symbols);
+        self
+    }
+
+    /// Sets the framing of the payload bytes as channel bits.
+    pub fn mapper(mut self, mapper: PayloadMapper) -> Self {
+        self.mapper = Some(mapper);
+        self
+    }
+
+    /// Resolves the preset, applies the fields that were set and validates
+    /// the result.
+    pub fn build(self) -> Result<EmbedParams, EmbedError> {
+        let sample_rate = self
+            .sample_rate
+            .unwrap_or_else(|| EmbedParams::default().sample_rate);
+        let base = match self.preset {
+            Some(preset) => EmbedParams::preset_unchecked(preset, sample_rate),
+            None => EmbedParams::default(),
+        };
+        let params = EmbedParams {
+            sample_rate,
+            band_low_hz: self.band_low_hz.unwrap_or(base.band_low_hz),
+            band_high_hz: self.band_high_hz.unwrap_or(base.band_high_hz),
+            strength_db: self.strength_db.unwrap_or(base.strength_db),
+            frame_len: self.frame_len.unwrap_or(base.frame_len),
+            hop: self.hop.unwrap_or(base.hop),
+            frames_per_symbol: self.frames_per_symbol.unwrap_or(base.frames_per_symbol),
+            carriers: self.carriers.unwrap_or(base.carriers),
+            redundancy: self.redundancy.unwrap_or(base.redundancy),
+            sync_symbols: self.sync_symbols.unwrap_or(base.sync_symbols),
+            mapper: self.mapper.unwrap_or(base.mapper),
+        };
+        params.validate()?;
+        Ok(params)
+    }
+}
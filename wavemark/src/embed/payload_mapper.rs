@@ -1,6 +1,543 @@
-#![allow(dead_code)]
+//! Mapping between codec bytes and watermark channel bits.
+//!
+//! The embedder carries a cyclic stream of binary chips. [`PayloadMapper`]
+//! frames codec output for that channel and reverses the process from the
+//! detector's soft bits:
+//!
+//! ```text
+//! preamble (16 bits) | symbols( length: u16 BE | payload | CRC-16 )
+//! ```
+//!
+//! The preamble is sent as raw bits and lets the detector confirm alignment
+//! before decoding. The length, payload and CRC are grouped into symbols of the
+//! configured [`SymbolAlphabet`]:
+//!
+//! - **BPSK** sends one bit per channel chip.
+//! - **M-ary** packs `k` bits into one of `M = 2^k` symbols and sends row `m`
+//!   of an `M × M` Walsh-Hadamard matrix. The rows are mutually orthogonal, so
+//!   the receiver picks the row with the largest correlation against the soft
+//!   chips. This trades rate for robustness against per-chip errors.
+//!
+//! The CRC-16/CCITT covers the length and payload, so a corrupted frame is
+//! rejected instead of being handed to the codec.
+//!
+//! With [`PayloadMapper::with_fec`] the payload field instead holds the
+//! Reed-Solomon shards of [`FecCodec::encode`]. A failed CRC then no longer
+//! rejects the frame: shards whose own CRC fails are rebuilt from parity,
+//! and shards whose soft bits are much weaker than the rest are erased
+//! before decoding. [`DemappedPayload::fec`] reports the repaired shards.
+//!
+//! The mapper is part of [`EmbedParams`]: every embedder frames its payload
+//! with [`EmbedParams::mapper`] before spreading it, and the correlator
+//! searches folded soft bits for the preamble and demaps them with the same
+//! mapper.
+//!
+//! ```ignore
+//! use wavemark::embed::payload_mapper::{PayloadMapper, SymbolAlphabet};
+//!
+//! let params = EmbedParams::builder()
+//!     .mapper(PayloadMapper::new(SymbolAlphabet::Mary { bits_per_symbol: 3 })?)
+//!     .build()?;
+//! let watermarked = SpreadSpectrumEmbedder::from_key_context(&session, params.clone())?
+//!     .embed(&samples, &format_output.bytes)?;
+//!
+//! // Detection side: fold the correlator output at the mapped length.
+//! let soft = correlation.fold(params.mapper().channel_bits(payload_len), rotation, false);
+//! let demapped = params.mapper().demap(&soft)?;
+//! let frame = codec.decode(&demapped.bytes, &EncryptionContext::default())?;
+//! ```
+//!
+//! [`EmbedParams`]: crate::embed::params::EmbedParams
+//! [`EmbedParams::mapper`]: crate::embed::params::EmbedParams::mapper
 
-//! Placeholder mapping logic for transforming payloads.
+use std::fmt;
 
-/// Placeholder mapper between payload bits and embedding symbols.
-pub struct PayloadMapper;
+use crate::embed::spread_spectrum::{bits_to_bytes, bytes_to_bits};
+use crate::format::fec::{crc16, FecCodec, FecConfig, FecError, FecOutput, FecReport};
+
+/// Symbol alphabet used for the length, payload and CRC fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolAlphabet {
+    /// One bit per channel chip.
+    Bpsk,
+    /// `2^bits_per_symbol` orthogonal Walsh-Hadamard symbols.
+    Mary {
+        /// Bits carried by each symbol; between 1 and 8.
+        bits_per_symbol: u32,
+    },
+}
+
+impl SymbolAlphabet {
+    /// Largest supported `bits_per_symbol` for M-ary signalling.
+    pub const MAX_BITS_PER_SYMBOL: u32 = 8;
+
+    /// Payload bits carried by one symbol.
+    pub fn bits_per_symbol(&self) -> usize {
+        match self {
+            SymbolAlphabet::Bpsk => 1,
+            SymbolAlphabet::Mary { bits_per_symbol } => *bits_per_symbol as usize,
+        }
+    }
+
+    /// Channel chips used to send one symbol.
+    pub fn chips_per_symbol(&self) -> usize {
+        match self {
+            SymbolAlphabet::Bpsk => 1,
+            SymbolAlphabet::Mary { bits_per_symbol } => 1 << bits_per_symbol,
+        }
+    }
+}
+
+/// Frames codec bytes for the watermark channel and recovers them from soft bits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayloadMapper {
+    alphabet: SymbolAlphabet,
+    preamble_tolerance: usize,
+    fec: Option<FecCodec>,
+}
+
+impl PayloadMapper {
+    /// Synchronisation word sent ahead of every frame.
+    pub const PREAMBLE: u16 = 0xEB90;
+    /// Number of channel bits in the preamble.
+    pub const PREAMBLE_BITS: usize = 16;
+    /// Largest payload the `u16` length field can describe.
+    pub const MAX_PAYLOAD_LEN: usize = u16::MAX as usize;
+    /// Default number of preamble bit errors accepted by [`PayloadMapper::demap`].
+    pub const DEFAULT_PREAMBLE_TOLERANCE: usize = 2;
+
+    /// Bytes of framing around the payload: the length field and the CRC.
+    const FRAMING_BYTES: usize = 4;
+    /// Shards whose mean decision margin is below this fraction of the median
+    /// shard's are erased before Reed-Solomon decoding.
+    const ERASURE_RATIO: f32 = 0.5;
+
+    /// Creates a mapper using `alphabet`.
+    pub fn new(alphabet: SymbolAlphabet) -> Result<Self, MapError> {
+        if let SymbolAlphabet::Mary { bits_per_symbol } = alphabet {
+            if bits_per_symbol == 0 || bits_per_symbol > SymbolAlphabet::MAX_BITS_PER_SYMBOL {
+                return Err(MapError::InvalidAlphabet(
+                    "M-ary symbols must carry between 1 and 8 bits",
+                ));
+            }
+        }
+        Ok(Self {
+            alphabet,
+            preamble_tolerance: Self::DEFAULT_PREAMBLE_TOLERANCE,
+            fec: None,
+        })
+    }
+
+    /// Creates a BPSK mapper.
+    pub fn bpsk() -> Self {
+        Self {
+            alphabet: SymbolAlphabet::Bpsk,
+            preamble_tolerance: Self::DEFAULT_PREAMBLE_TOLERANCE,
+            fec: None,
+        }
+    }
+
+    /// Wraps payloads in Reed-Solomon shards of `config` before framing them.
+    pub fn with_fec(mut self, config: FecConfig) -> Result<Self, MapError> {
+        self.fec = Some(FecCodec::new(config)?);
+        Ok(self)
+    }
+
+    /// Sets the number of preamble bit errors accepted when demapping.
+    pub fn with_preamble_tolerance(mut self, errors: usize) -> Self {
+        self.preamble_tolerance = errors;
+        self
+    }
+
+    /// Returns the symbol alphabet.
+    pub fn alphabet(&self) -> SymbolAlphabet {
+        self.alphabet
+    }
+
+    /// Returns the number of preamble bit errors accepted when demapping.
+    pub fn preamble_tolerance(&self) -> usize {
+        self.preamble_tolerance
+    }
+
+    /// Returns the Reed-Solomon shard configuration, if FEC is enabled.
+    pub fn fec(&self) -> Option<&FecConfig> {
+        self.fec.as_ref().map(FecCodec::config)
+    }
+
+    /// Channel bits produced for a payload of `payload_len` bytes.
+    ///
+    /// The count is rounded up to a whole number of bytes, matching the
+    /// length of [`MappedPayload::to_bytes`].
+    pub fn channel_bits(&self, payload_len: usize) -> usize {
+        self.frame_bits(self.field_len(payload_len))
+    }
+
+    /// Frames `payload` as channel bits.
+    pub fn map(&self, payload: &[u8]) -> Result<MappedPayload, MapError> {
+        if payload.is_empty() {
+            return Err(MapError::EmptyPayload);
+        }
+        if self.field_len(payload.len()) > Self::MAX_PAYLOAD_LEN {
+            return Err(MapError::PayloadTooLarge {
+                len: payload.len(),
+                max: Self::MAX_PAYLOAD_LEN,
+            });
+        }
+        let field = match &self.fec {
+            Some(fec) => fec.encode(payload)?,
+            None => payload.to_vec(),
+        };
+
+        let mut body = Vec::with_capacity(field.len() + Self::FRAMING_BYTES);
+        body.extend_from_slice(&(field.len() as u16).to_be_bytes());
+        body.extend_from_slice(&field);
+        let crc = crc16(&body);
+        body.extend_from_slice(&crc.to_be_bytes());
+
+        let mut bits = Vec::with_capacity(self.channel_bits(payload.len()));
+        bits.extend(preamble_bits());
+        self.push_symbols(&bytes_to_bits(&body), &mut bits);
+        bits.resize(bits.len().div_ceil(8) * 8, false);
+
+        Ok(MappedPayload { bits })
+    }
+
+    /// Leading channel bits that are fixed once the payload length is known.
+    ///
+    /// These are the preamble followed by the symbols of the length field that
+    /// carry no payload bits. The detector measures its confidence on them.
+    pub fn known_prefix(&self, payload_len: usize) -> Vec<bool> {
+        let field_len = self.field_len(payload_len).min(Self::MAX_PAYLOAD_LEN);
+        let length_bits = bytes_to_bits(&(field_len as u16).to_be_bytes());
+        let k = self.alphabet.bits_per_symbol();
+        let mut bits: Vec<bool> = preamble_bits().collect();
+        self.push_symbols(&length_bits[..length_bits.len() / k * k], &mut bits);
+        bits
+    }
+
+    /// Recovers the payload from soft channel bits aligned on the preamble.
+    ///
+    /// Positive values favour a `1` bit. Soft bits beyond the end of the frame
+    /// are ignored. With FEC, a frame whose CRC fails is still accepted when
+    /// its shards can be repaired.
+    pub fn demap(&self, soft_bits: &[f32]) -> Result<DemappedPayload, MapError> {
+        if soft_bits.len() < Self::PREAMBLE_BITS {
+            return Err(MapError::Truncated {
+                required: Self::PREAMBLE_BITS,
+                found: soft_bits.len(),
+            });
+        }
+
+        let preamble_score = preamble_score(soft_bits);
+        let preamble_errors = preamble_bits()
+            .zip(soft_bits)
+            .filter(|(bit, &z)| (z > 0.0) != *bit)
+            .count();
+        if preamble_errors > self.preamble_tolerance {
+            return Err(MapError::PreambleMismatch {
+                errors: preamble_errors,
+            });
+        }
+
+        let chips = self.alphabet.chips_per_symbol();
+        let mut bits = Vec::new();
+        let mut margins = Vec::new();
+        for symbol in soft_bits[Self::PREAMBLE_BITS..].chunks_exact(chips) {
+            let (value, margin) = self.demap_symbol(symbol);
+            margins.push(margin);
+            let k = self.alphabet.bits_per_symbol();
+            bits.extend((0..k).rev().map(|shift| (value >> shift) & 1 == 1));
+        }
+
+        let length_bytes = bits_to_bytes(&bits[..bits.len().min(16)]);
+        if length_bytes.len() < 2 {
+            return Err(MapError::Truncated {
+                required: self.channel_bits(1),
+                found: soft_bits.len(),
+            });
+        }
+        let len = u16::from_be_bytes([length_bytes[0], length_bytes[1]]) as usize;
+        if len == 0 {
+            return Err(MapError::EmptyPayload);
+        }
+        let body_bits = (len + Self::FRAMING_BYTES) * 8;
+        if bits.len() < body_bits {
+            return Err(MapError::Truncated {
+                required: self.frame_bits(len),
+                found: soft_bits.len(),
+            });
+        }
+
+        let body = bits_to_bytes(&bits[..body_bits]);
+        let (framed, crc_bytes) = body.split_at(body.len() - 2);
+        let expected = crc16(framed);
+        let found = u16::from_be_bytes([crc_bytes[0], crc_bytes[1]]);
+        let field = &framed[2..];
+        let (bytes, fec) = match &self.fec {
+            None if expected != found => {
+                return Err(MapError::ChecksumMismatch { expected, found });
+            }
+            None => (field.to_vec(), None),
+            Some(fec) => {
+                let erasures = if expected == found {
+                    Vec::new()
+                } else {
+                    self.weak_shards(fec, len, &margins)
+                };
+                let output = decode_shards(fec, field, &erasures)?;
+                (output.bytes, Some(output.report))
+            }
+        };
+
+        let min_margin = margins[..self.symbol_count(len)]
+            .iter()
+            .fold(f32::INFINITY, |min, &margin| min.min(margin));
+        Ok(DemappedPayload {
+            bytes,
+            preamble_score,
+            preamble_errors,
+            min_margin,
+            fec,
+        })
+    }
+
+    /// Bytes between the length field and the CRC for a payload of
+    /// `payload_len` bytes.
+    fn field_len(&self, payload_len: usize) -> usize {
+        match &self.fec {
+            Some(fec) => fec.encoded_len(payload_len),
+            None => payload_len,
+        }
+    }
+
+    /// Channel bits of a frame whose field holds `field_len` bytes.
+    fn frame_bits(&self, field_len: usize) -> usize {
+        let bits =
+            Self::PREAMBLE_BITS + self.symbol_count(field_len) * self.alphabet.chips_per_symbol();
+        bits.div_ceil(8) * 8
+    }
+
+    /// Shards of a `field_len`-byte field whose symbols have markedly smaller
+    /// decision margins than the median shard, weakest first.
+    fn weak_shards(&self, fec: &FecCodec, field_len: usize, margins: &[f32]) -> Vec<usize> {
+        let total = fec.config().total_shards();
+        if !field_len.is_multiple_of(total) {
+            return Vec::new();
+        }
+        let shard_bits = field_len / total * 8;
+        let k = self.alphabet.bits_per_symbol();
+        // Field bits follow the 16 bits of the length field.
+        let reliability: Vec<f32> = (0..total)
+            .map(|shard| {
+                let first = 16 + shard * shard_bits;
+                let symbols = &margins[first / k..(first + shard_bits).div_ceil(k)];
+                symbols.iter().sum::<f32>() / symbols.len() as f32
+            })
+            .collect();
+
+        let mut sorted = reliability.clone();
+        sorted.sort_by(f32::total_cmp);
+        let threshold = Self::ERASURE_RATIO * sorted[total / 2];
+        let mut weak: Vec<usize> = (0..total)
+            .filter(|&shard| reliability[shard] < threshold)
+            .collect();
+        weak.sort_by(|&a, &b| reliability[a].total_cmp(&reliability[b]));
+        weak.truncate(fec.config().parity_shards);
+        weak
+    }
+
+    /// Appends the channel chips of `bits`, grouped into symbols.
+    fn push_symbols(&self, bits: &[bool], out: &mut Vec<bool>) {
+        let k = self.alphabet.bits_per_symbol();
+        for chunk in bits.chunks(k) {
+            match self.alphabet {
+                SymbolAlphabet::Bpsk => out.push(chunk[0]),
+                SymbolAlphabet::Mary { .. } => {
+                    // A short final chunk is padded with zero bits.
+                    let symbol = (0..k).fold(0usize, |symbol, index| {
+                        (symbol << 1) | chunk.get(index).copied().unwrap_or(false) as usize
+                    });
+                    let chips = self.alphabet.chips_per_symbol();
+                    out.extend((0..chips).map(|chip| walsh_chip(symbol, chip)));
+                }
+            }
+        }
+    }
+
+    /// Number of symbols needed for a field of `field_len` bytes.
+    fn symbol_count(&self, field_len: usize) -> usize {
+        ((field_len + Self::FRAMING_BYTES) * 8).div_ceil(self.alphabet.bits_per_symbol())
+    }
+
+    /// Hard decision and decision margin for one symbol's soft chips.
+    fn demap_symbol(&self, chips: &[f32]) -> (usize, f32) {
+        match self.alphabet {
+            SymbolAlphabet::Bpsk => ((chips[0] > 0.0) as usize, chips[0].abs()),
+            SymbolAlphabet::Mary { .. } => {
+                let mut best = (0usize, f32::NEG_INFINITY);
+                let mut runner_up = f32::NEG_INFINITY;
+                for symbol in 0..chips.len() {
+                    let score: f32 = chips
+                        .iter()
+                        .enumerate()
+                        .map(|(chip, &z)| if walsh_chip(symbol, chip) { z } else { -z })
+                        .sum();
+                    if score > best.1 {
+                        runner_up = best.1;
+                        best = (symbol, score);
+                    } else if score > runner_up {
+                        runner_up = score;
+                    }
+                }
+                // Scaled so that unit-variance chips give a unit-variance margin.
+                let margin = (best.1 - runner_up) / (2.0 * chips.len() as f32).sqrt();
+                (best.0, margin)
+            }
+        }
+    }
+}
+
+impl Default for PayloadMapper {
+    fn default() -> Self {
+        Self::bpsk()
+    }
+}
+
+/// Channel bits produced by [`PayloadMapper::map`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MappedPayload {
+    bits: Vec<bool>,
+}
+
+impl MappedPayload {
+    /// Channel bits, preamble first.
+    pub fn bits(&self) -> &[bool] {
+        &self.bits
+    }
+
+    /// Number of channel bits; always a multiple of eight.
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// Returns `true` when no bits were produced.
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
+    /// Packs the channel bits, most-significant bit first, for the embedder.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bits_to_bytes(&self.bits)
+    }
+}
+
+/// Payload recovered by [`PayloadMapper::demap`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DemappedPayload {
+    /// Payload bytes with the framing removed.
+    pub bytes: Vec<u8>,
+    /// Correlation of the soft bits with the preamble, `Σ s_i z_i / √16`.
+    pub preamble_score: f32,
+    /// Hard-decision preamble bit errors.
+    pub preamble_errors: usize,
+    /// Smallest per-symbol decision margin over the frame.
+    pub min_margin: f32,
+    /// Shards repaired by forward error correction; `None` without FEC.
+    pub fec: Option<FecReport>,
+}
+
+/// Decodes `field`, dropping the strongest of the `erasures` until the
+/// remaining damage fits the parity shards.
+///
+/// Shards whose CRC fails are always rebuilt, so an erasure that turns out
+/// to be one too many is given back rather than failing the frame.
+fn decode_shards(fec: &FecCodec, field: &[u8], erasures: &[usize]) -> Result<FecOutput, MapError> {
+    let mut count = erasures.len();
+    loop {
+        match fec.decode(field, &erasures[..count]) {
+            Ok(output) => return Ok(output),
+            Err(FecError::Unrecoverable { .. }) if count > 0 => count -= 1,
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// Correlation of the leading soft bits with the preamble, `Σ s_i z_i / √K`.
+///
+/// For unit-variance soft bits this is approximately standard normal when the
+/// preamble is absent, so it can be used to scan for frame alignment.
+pub fn preamble_score(soft_bits: &[f32]) -> f32 {
+    let count = PayloadMapper::PREAMBLE_BITS.min(soft_bits.len());
+    if count == 0 {
+        return 0.0;
+    }
+    preamble_bits()
+        .zip(soft_bits)
+        .map(|(bit, &z)| if bit { z } else { -z })
+        .sum::<f32>()
+        / (count as f32).sqrt()
+}
+
+fn preamble_bits() -> impl Iterator<Item = bool> {
+    (0..PayloadMapper::PREAMBLE_BITS)
+        .rev()
+        .map(|shift| (PayloadMapper::PREAMBLE >> shift) & 1 == 1)
+}
+
+/// Entry `(symbol, chip)` of the Sylvester Hadamard matrix, `true` for `+1`.
+fn walsh_chip(symbol: usize, chip: usize) -> bool {
+    (symbol & chip).count_ones().is_multiple_of(2)
+}
+
+/// Errors raised while mapping or demapping payloads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapError {
+    /// Payload contains no bytes.
+    EmptyPayload,
+    /// Payload does not fit the length field.
+    PayloadTooLarge { len: usize, max: usize },
+    /// Symbol alphabet is not supported.
+    InvalidAlphabet(&'static str),
+    /// Too few soft bits for the frame.
+    Truncated { required: usize, found: usize },
+    /// Preamble has more bit errors than the configured tolerance.
+    PreambleMismatch { errors: usize },
+    /// CRC over the length and payload does not match.
+    ChecksumMismatch { expected: u16, found: u16 },
+    /// Reed-Solomon encoding or decoding failed.
+    Fec(FecError),
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapError::EmptyPayload => write!(f, "payload must contain at least one byte"),
+            MapError::PayloadTooLarge { len, max } => {
+                write!(f, "payload of {} bytes exceeds maximum of {}", len, max)
+            }
+            MapError::InvalidAlphabet(reason) => write!(f, "invalid symbol alphabet: {}", reason),
+            MapError::Truncated { required, found } => write!(
+                f,
+                "frame needs {} soft bits but only {} were provided",
+                required, found
+            ),
+            MapError::PreambleMismatch { errors } => {
+                write!(f, "preamble mismatch ({} bit errors)", errors)
+            }
+            MapError::ChecksumMismatch { expected, found } => write!(
+                f,
+                "frame checksum mismatch (expected {:#06x}, found {:#06x})",
+                expected, found
+            ),
+            MapError::Fec(err) => write!(f, "forward error correction failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for MapError {}
+
+impl From<FecError> for MapError {
+    fn from(err: FecError) -> Self {
+        MapError::Fec(err)
+    }
+}
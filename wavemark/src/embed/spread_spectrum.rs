@@ -1,6 +1,351 @@
-#![allow(dead_code)]
+//! Keyed spread-spectrum embedding in the STFT magnitude domain.
+//!
+//! The key's PN seed drives a ChaCha20 generator that assigns a `±1` chip to
+//! every bin of the embedding band. The band is split into contiguous carriers
+//! and time into symbol slots of [`EmbedParams::frames_per_symbol`] frames.
+//! With `B` [bits per symbol](EmbedParams::bits_per_symbol), slot `j`, carrier
+//! `c` carries payload bit `(j * B + c mod B) mod n`, so the payload repeats
+//! cyclically from the first sample and redundant carriers sit `B` carriers
+//! apart. With [`EmbedParams::sync_symbols`] set, each repetition is instead
+//! preceded by that many slots of a keyed
+//! [sync pattern](crate::embed::sync_pattern) and starts at carrier 0 of the
+//! slot that follows it. Slots are bi-phase
+//! coded:
+//! frames starting in the first half of a slot scale each bin by
+//!
+//! ```text
+//! 10^(strength_db * chip[k] * bit / 20)     bit ∈ {-1, +1}
+//! ```
+//!
+//! keeping its phase, and frames in the second half apply the inverse. The
+//! watermark therefore has no long-term component in any bin, and the detector
+//! cancels stationary host structure by differencing the two halves regardless
+//! of how balanced the payload bits are. Frames are analysed and resynthesised
+//! with a sqrt-Hann window, which reconstructs the input exactly when the
+//! strength is zero.
+//!
+//! With [`SpreadSpectrumEmbedder::with_masking`], the fixed `strength_db` is
+//! replaced per frame and bin by the largest change that stays under the
+//! [`MaskingModel`] threshold, so quiet passages are marked lightly and loud,
+//! dense passages up to [`MaskingConfig::max_strength_db`].
+//!
+//! ```ignore
+//! use wavemark::embed::params::EmbedParams;
+//! use wavemark::embed::spread_spectrum::SpreadSpectrumEmbedder;
+//!
+//! let mut embedder = SpreadSpectrumEmbedder::from_key_context(&session, EmbedParams::default())?;
+//! let watermarked = embedder.embed(&samples, &payload_bytes)?;
+//! assert_eq!(watermarked.len(), samples.len());
+//!
+//! let mut masked = SpreadSpectrumEmbedder::from_key_context(&session, params)?
+//!     .with_masking(MaskingConfig::default())?;
+//! ```
 
-//! Placeholder spread-spectrum embedding strategy.
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::fmt;
+use std::ops::Range;
 
-/// Placeholder struct for spread-spectrum embedding logic.
-pub struct SpreadSpectrumEmbedder;
+use crate::core::types::AudioChunkError;
+use crate::embed::masking::{MaskingConfig, MaskingError, MaskingModel};
+use crate::embed::params::EmbedParams;
+use crate::embed::payload_mapper::MapError;
+use crate::embed::sync_pattern::SyncPattern;
+use crate::key::derivation::{DerivedKey, KeyContext};
+use crate::transforms::fft::{Complex, FftBackend, FftError};
+use crate::transforms::window::{AnalysisWindow, OverlapMode, WindowError};
+
+/// Keyed chip sequence and carrier layout shared by the embedder and detector.
+#[derive(Debug, Clone)]
+pub(crate) struct SpreadingCode {
+    chips: Vec<f32>,
+    carriers: Vec<Range<usize>>,
+    first_bin: usize,
+    sync: SyncPattern,
+}
+
+impl SpreadingCode {
+    pub(crate) fn new(pn_seed: &DerivedKey, params: &EmbedParams) -> Self {
+        let band = params.band_bins();
+        let mut rng = ChaCha20Rng::from_seed(*pn_seed.as_bytes());
+        let mut chips = Vec::with_capacity(band.len());
+        let mut word = 0u32;
+        for index in 0..band.len() {
+            if index % 32 == 0 {
+                word = rng.next_u32();
+            }
+            chips.push(if word & 1 == 1 { 1.0 } else { -1.0 });
+            word >>= 1;
+        }
+
+        let carriers = (0..params.carriers())
+            .map(|carrier| {
+                let start = band.start + carrier * band.len() / params.carriers();
+                let end = band.start + (carrier + 1) * band.len() / params.carriers();
+                start..end
+            })
+            .collect();
+
+        Self {
+            chips,
+            carriers,
+            first_bin: band.start,
+            sync: SyncPattern::new(pn_seed, params),
+        }
+    }
+
+    /// Absolute STFT bins belonging to each carrier.
+    pub(crate) fn carriers(&self) -> &[Range<usize>] {
+        &self.carriers
+    }
+
+    /// Chip assigned to the absolute STFT bin `bin`.
+    pub(crate) fn chip(&self, bin: usize) -> f32 {
+        self.chips[bin - self.first_bin]
+    }
+
+    /// Reference chip for `bin`, near-orthogonal to the code within each carrier.
+    ///
+    /// Correlating against the decoy yields host-only noise with the same
+    /// statistics as the real correlation, which the detector uses for scaling.
+    pub(crate) fn decoy(&self, bin: usize) -> f32 {
+        if bin.is_multiple_of(2) {
+            self.chip(bin)
+        } else {
+            -self.chip(bin)
+        }
+    }
+
+    /// Keyed pattern sent ahead of every payload repetition.
+    pub(crate) fn sync(&self) -> &SyncPattern {
+        &self.sync
+    }
+
+    /// Marks the spectrum of the frame starting at signal index `start`.
+    ///
+    /// `boost(bin)` is the linear gain (at least 1) applied to bins whose chip
+    /// agrees with the coded bit; the others are divided by it.
+    pub(crate) fn modulate<F>(
+        &self,
+        params: &EmbedParams,
+        bits: &[bool],
+        start: isize,
+        frame: &mut [Complex<f32>],
+        boost: F,
+    ) where
+        F: Fn(usize) -> f32,
+    {
+        let bit_count = bits.len() as i64;
+        let bits_per_symbol = params.bits_per_symbol();
+        let sync_symbols = self.sync.symbols() as i64;
+        let cycle = params.cycle_slots(bits.len()) as i64;
+        let symbol_len = params.symbol_len() as isize;
+        // A frame belongs to the slot and half-slot in which it starts.
+        let slot = start.div_euclid(symbol_len) as i64;
+        let polarity = if start.rem_euclid(symbol_len) < symbol_len / 2 {
+            1.0
+        } else {
+            -1.0
+        };
+
+        for (carrier, bins) in self.carriers.iter().enumerate() {
+            let distinct = carrier % bits_per_symbol;
+            let bit = if sync_symbols == 0 {
+                let position = slot * bits_per_symbol as i64 + distinct as i64;
+                bits[position.rem_euclid(bit_count) as usize]
+            } else {
+                let slot = slot.rem_euclid(cycle);
+                if slot < sync_symbols {
+                    self.sync.bit(slot as usize, distinct)
+                } else {
+                    let position = (slot - sync_symbols) * bits_per_symbol as i64 + distinct as i64;
+                    bits[(position % bit_count) as usize]
+                }
+            };
+            let sign = if bit { polarity } else { -polarity };
+            for bin in bins.clone() {
+                let boost = boost(bin);
+                let gain = if self.chip(bin) * sign > 0.0 {
+                    boost
+                } else {
+                    boost.recip()
+                };
+                frame[bin] *= gain;
+            }
+        }
+    }
+}
+
+/// Spread-spectrum watermark embedder keyed by a [`KeyContext`].
+pub struct SpreadSpectrumEmbedder {
+    params: EmbedParams,
+    code: SpreadingCode,
+    window: AnalysisWindow,
+    masking: Option<MaskingModel>,
+    fft: FftBackend<f32>,
+}
+
+impl SpreadSpectrumEmbedder {
+    /// Creates an embedder keyed by the PN-sequence seed of a [`KeyContext`].
+    pub fn from_key_context(context: &KeyContext, params: EmbedParams) -> Result<Self, EmbedError> {
+        let code = SpreadingCode::new(&context.pn_seed(), &params);
+        let window = AnalysisWindow::sqrt_hann(params.frame_len())?;
+        window.check_overlap_add(params.hop(), OverlapMode::WeightedOverlapAdd)?;
+
+        Ok(Self {
+            params,
+            code,
+            window,
+            masking: None,
+            fft: FftBackend::new(),
+        })
+    }
+
+    /// Shapes the watermark to stay under the psychoacoustic masking threshold.
+    pub fn with_masking(mut self, config: MaskingConfig) -> Result<Self, EmbedError> {
+        let model = MaskingModel::new(self.params.sample_rate(), self.window.clone(), config)?;
+        self.masking = Some(model);
+        Ok(self)
+    }
+
+    /// Returns the embedding parameters.
+    pub fn params(&self) -> &EmbedParams {
+        &self.params
+    }
+
+    /// Returns the masking model, if masking is enabled.
+    pub fn masking(&self) -> Option<&MaskingModel> {
+        self.masking.as_ref()
+    }
+
+    /// Embeds `payload` into `samples`, returning watermarked audio of the same length.
+    ///
+    /// The payload is framed by [`EmbedParams::mapper`], and the resulting
+    /// channel bits repeat for the whole duration of the input.
+    pub fn embed(&mut self, samples: &[f32], payload: &[u8]) -> Result<Vec<f32>, EmbedError> {
+        if payload.is_empty() {
+            return Err(EmbedError::EmptyPayload);
+        }
+        if samples.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mapped = self.params.mapper().map(payload)?;
+        let bits = mapped.bits();
+        let boost = 10f32.powf(self.params.strength_db() / 20.0);
+
+        let mut spectrogram = self.fft.stft(samples, &self.window, self.params.hop())?;
+        let thresholds = match &self.masking {
+            Some(model) => Some(model.thresholds(&spectrogram)?),
+            None => None,
+        };
+        for index in 0..spectrogram.len() {
+            let start = spectrogram.frame_start(index);
+            let frame = &mut spectrogram.frames_mut()[index];
+            self.code
+                .modulate(&self.params, bits, start, frame, |bin| match &thresholds {
+                    Some(thresholds) => {
+                        10f32.powf(thresholds.allowed_strength_db(index, bin) / 20.0)
+                    }
+                    None => boost,
+                });
+        }
+
+        Ok(self.fft.istft(&spectrogram, &self.window)?)
+    }
+}
+
+impl fmt::Debug for SpreadSpectrumEmbedder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpreadSpectrumEmbedder")
+            .field("params", &self.params)
+            .field("masking", &self.masking)
+            .field("pn_seed", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Expands bytes into bits, most-significant bit first.
+pub(crate) fn bytes_to_bits(bytes: &[u8]) -> Vec<bool> {
+    bytes
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |shift| (byte >> shift) & 1 == 1))
+        .collect()
+}
+
+/// Packs bits, most-significant bit first, into bytes. Trailing bits are dropped.
+pub(crate) fn bits_to_bytes(bits: &[bool]) -> Vec<u8> {
+    bits.chunks_exact(8)
+        .map(|chunk| chunk.iter().fold(0u8, |byte, &bit| (byte << 1) | bit as u8))
+        .collect()
+}
+
+/// Errors raised while embedding a watermark.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmbedError {
+    /// Embedding parameters are inconsistent.
+    InvalidParams(&'static str),
+    /// Payload contains no bytes.
+    EmptyPayload,
+    /// Payload cannot be framed for the channel.
+    Map(MapError),
+    /// Number of payloads does not match what the channel mode expects.
+    PayloadCount { expected: usize, found: usize },
+    /// The audio chunk cannot be read or written as requested.
+    Chunk(AudioChunkError),
+    /// Window does not reconstruct at the configured hop.
+    Window(WindowError),
+    /// Masking model configuration or analysis failed.
+    Masking(MaskingError),
+    /// STFT analysis or synthesis failed.
+    Transform(FftError),
+}
+
+impl fmt::Display for EmbedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmbedError::InvalidParams(reason) => write!(f, "invalid embed parameters: {}", reason),
+            EmbedError::EmptyPayload => write!(f, "payload must contain at least one byte"),
+            EmbedError::Map(err) => write!(f, "payload mapping error: {}", err),
+            EmbedError::PayloadCount { expected, found } => {
+                write!(f, "expected {} payloads, got {}", expected, found)
+            }
+            EmbedError::Chunk(err) => write!(f, "audio chunk error: {}", err),
+            EmbedError::Window(err) => write!(f, "window error: {}", err),
+            EmbedError::Masking(err) => write!(f, "masking error: {}", err),
+            EmbedError::Transform(err) => write!(f, "transform error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for EmbedError {}
+
+impl From<AudioChunkError> for EmbedError {
+    fn from(err: AudioChunkError) -> Self {
+        EmbedError::Chunk(err)
+    }
+}
+
+impl From<MapError> for EmbedError {
+    fn from(err: MapError) -> Self {
+        EmbedError::Map(err)
+    }
+}
+
+impl From<WindowError> for EmbedError {
+    fn from(err: WindowError) -> Self {
+        EmbedError::Window(err)
+    }
+}
+
+impl From<MaskingError> for EmbedError {
+    fn from(err: MaskingError) -> Self {
+        EmbedError::Masking(err)
+    }
+}
+
+impl From<FftError> for EmbedError {
+    fn from(err: FftError) -> Self {
+        EmbedError::Transform(err)
+    }
+}
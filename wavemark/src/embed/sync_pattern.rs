@@ -0,0 +1,81 @@
+//! Keyed sync pattern sent ahead of every payload repetition.
+//!
+//! When [`EmbedParams::sync_symbols`] is non-zero, every payload repetition is
+//! preceded by `sync_symbols` slots whose carriers send a keyed pattern instead
+//! of payload bits. The pattern comes from the key's PN seed on a ChaCha20
+//! stream of its own, so it is unpredictable without the key and independent
+//! of the spreading chips. The embedder writes it and the detector
+//! [searches for it](crate::detect::sync) to recover the payload position.
+//!
+//! ```text
+//! | sync | payload bits ... | sync | payload bits ... | sync | ...
+//! ```
+//!
+//! [`EmbedParams::sync_symbols`]: crate::embed::params::EmbedParams::sync_symbols
+
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+use crate::embed::params::EmbedParams;
+use crate::key::derivation::DerivedKey;
+
+/// ChaCha20 stream reserved for the sync pattern.
+const SYNC_STREAM: u64 = 2;
+
+/// Keyed sync bits, slot-major with one bit per distinct carrier.
+#[derive(Debug, Clone)]
+pub(crate) struct SyncPattern {
+    bits: Vec<bool>,
+    bits_per_symbol: usize,
+}
+
+impl SyncPattern {
+    pub(crate) fn new(pn_seed: &DerivedKey, params: &EmbedParams) -> Self {
+        let bits_per_symbol = params.bits_per_symbol();
+        let mut rng = ChaCha20Rng::from_seed(*pn_seed.as_bytes());
+        rng.set_stream(SYNC_STREAM);
+        let mut word = 0u32;
+        let bits = (0..params.sync_symbols() * bits_per_symbol)
+            .map(|index| {
+                if index % 32 == 0 {
+                    word = rng.next_u32();
+                }
+                let bit = word & 1 == 1;
+                word >>= 1;
+                bit
+            })
+            .collect();
+        Self {
+            bits,
+            bits_per_symbol,
+        }
+    }
+
+    /// Number of slots the pattern occupies.
+    pub(crate) fn symbols(&self) -> usize {
+        self.bits.len() / self.bits_per_symbol.max(1)
+    }
+
+    /// Number of distinct carriers per slot.
+    pub(crate) fn bits_per_symbol(&self) -> usize {
+        self.bits_per_symbol
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
+    /// Bit sent on distinct carrier `carrier` of pattern slot `slot`.
+    pub(crate) fn bit(&self, slot: usize, carrier: usize) -> bool {
+        self.bits[slot * self.bits_per_symbol + carrier]
+    }
+
+    /// Pattern as slot-major `±1` values, the template the detector
+    /// correlates soft symbols against.
+    pub(crate) fn template(&self) -> Vec<f32> {
+        self.bits
+            .iter()
+            .map(|&bit| if bit { 1.0 } else { -1.0 })
+            .collect()
+    }
+}
@@ -0,0 +1,3 @@
+//! Objective evaluation of watermarked audio.
+
+pub mod quality;
@@ -0,0 +1,420 @@
+//! Objective quality of watermarked audio against its original.
+//!
+//! [`QualityAnalyzer::compare`] measures the difference between an original
+//! and a processed signal of the same length:
+//!
+//! - **SNR.** Original power over difference power for the whole signal.
+//! - **Segmental SNR.** The mean SNR of non-overlapping frames, each clamped
+//!   to `[-10, 35]` dB. Frames more than 60 dB below full scale are skipped,
+//!   so silence does not dominate the average.
+//! - **Log-spectral distance.** The RMS difference, in decibels, between the
+//!   STFT power spectra of the two signals, averaged over frames. Bins are
+//!   floored 60 dB below the peak of the original frame.
+//! - **Noise-to-mask ratio.** The difference spectrum is compared with the
+//!   masking threshold of the original from [`MaskingModel`]. Per frame, the
+//!   ratio is averaged over bins in the power domain. The total is the mean
+//!   over frames. A frame is *disturbed* when its loudest bin exceeds the
+//!   threshold by 1.5 dB.
+//! - **ODG estimate.** The total noise-to-mask ratio is mapped onto the PEAQ
+//!   objective difference grade scale, from `0` (imperceptible) to `-4` (very
+//!   annoying), with a logistic curve. The curve is not fitted to listening
+//!   tests and only ranks settings against each other. It does not replace
+//!   a PEAQ implementation.
+//!
+//! A [`QualityGate`] holds limits on these metrics.
+//! [`QualityGate::check_params`] embeds a payload with given
+//! [`EmbedParams`] and checks the result, which keeps presets honest in tests.
+//!
+//! ```ignore
+//! use wavemark::eval::quality::{QualityAnalyzer, QualityGate};
+//!
+//! let mut analyzer = QualityAnalyzer::new(44_100)?;
+//! let report = analyzer.compare(&original, &marked)?;
+//! println!("SNR {:.1} dB, ODG {:.2}", report.snr_db, report.odg);
+//! QualityGate::default().check(&report)?;
+//! ```
+
+use std::fmt;
+
+use crate::embed::masking::{MaskingConfig, MaskingError, MaskingModel};
+use crate::embed::params::{EmbedParams, Preset};
+use crate::embed::spread_spectrum::{EmbedError, SpreadSpectrumEmbedder};
+use crate::key::derivation::KeyContext;
+use crate::transforms::fft::{FftBackend, FftError};
+use crate::transforms::window::AnalysisWindow;
+
+/// STFT frame length of the spectral metrics.
+const FRAME_LEN: usize = 1024;
+/// STFT hop of the spectral metrics.
+const HOP: usize = FRAME_LEN / 2;
+/// Bounds of a single segment's SNR, in decibels.
+const SEGMENT_SNR_MIN_DB: f64 = -10.0;
+const SEGMENT_SNR_MAX_DB: f64 = 35.0;
+/// Segments quieter than this, relative to full scale, are skipped.
+const SILENT_SEGMENT_DB: f64 = -60.0;
+/// Power floor of the spectral metrics.
+const POWER_FLOOR: f64 = 1e-12;
+/// Range below each frame's peak over which spectra are compared (60 dB).
+const LSD_RANGE: f64 = 1e-6;
+/// Level above the masking threshold that marks a frame as disturbed.
+const DISTURBED_NMR_DB: f32 = 1.5;
+/// Centre and slope of the logistic ODG mapping, in decibels of NMR.
+const ODG_CENTER_DB: f64 = 2.0;
+const ODG_SLOPE_DB: f64 = 2.5;
+
+/// Quality metrics of a processed signal relative to its original.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityReport {
+    /// Signal-to-noise ratio in decibels; infinite for identical signals.
+    pub snr_db: f64,
+    /// Mean clamped per-segment SNR in decibels.
+    pub segmental_snr_db: f64,
+    /// Mean log-spectral distance in decibels.
+    pub log_spectral_distance_db: f64,
+    /// Total noise-to-mask ratio in decibels.
+    pub noise_to_mask_db: f64,
+    /// Fraction of frames with audible noise in at least one bin.
+    pub disturbed_frames: f64,
+    /// Objective difference grade estimate in `[-4, 0]`.
+    pub odg: f64,
+}
+
+/// Computes [`QualityReport`]s for audio at one sample rate.
+#[derive(Debug)]
+pub struct QualityAnalyzer {
+    sample_rate: u32,
+    window: AnalysisWindow,
+    model: MaskingModel,
+    fft: FftBackend<f32>,
+}
+
+impl QualityAnalyzer {
+    /// Creates an analyzer for audio at `sample_rate`.
+    pub fn new(sample_rate: u32) -> Result<Self, EvalError> {
+        let window = AnalysisWindow::hann(FRAME_LEN).expect("FRAME_LEN is non-zero");
+        // Measure against the threshold itself, without the embedder's margin.
+        let config = MaskingConfig {
+            margin_db: 0.0,
+            ..MaskingConfig::default()
+        };
+        let model = MaskingModel::new(sample_rate, window.clone(), config)?;
+        Ok(Self {
+            sample_rate,
+            window,
+            model,
+            fft: FftBackend::new(),
+        })
+    }
+
+    /// Sample rate the analyzer expects.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Compares `processed` with `original`; both must have the same length.
+    pub fn compare(
+        &mut self,
+        original: &[f32],
+        processed: &[f32],
+    ) -> Result<QualityReport, EvalError> {
+        if original.len() != processed.len() {
+            return Err(EvalError::LengthMismatch {
+                original: original.len(),
+                processed: processed.len(),
+            });
+        }
+        if original.is_empty() {
+            return Err(EvalError::EmptyInput);
+        }
+
+        let difference: Vec<f32> = processed
+            .iter()
+            .zip(original)
+            .map(|(&p, &o)| p - o)
+            .collect();
+        let (noise_to_mask_db, disturbed_frames) = self.noise_to_mask(original, &difference)?;
+        Ok(QualityReport {
+            snr_db: snr_db(original, &difference),
+            segmental_snr_db: segmental_snr_db(original, &difference),
+            log_spectral_distance_db: self.log_spectral_distance(original, processed)?,
+            noise_to_mask_db,
+            disturbed_frames,
+            odg: -4.0 / (1.0 + (-(noise_to_mask_db - ODG_CENTER_DB) / ODG_SLOPE_DB).exp()),
+        })
+    }
+
+    fn log_spectral_distance(
+        &mut self,
+        original: &[f32],
+        processed: &[f32],
+    ) -> Result<f64, EvalError> {
+        let reference = self.fft.stft(original, &self.window, HOP)?;
+        let test = self.fft.stft(processed, &self.window, HOP)?;
+        let total: f64 = reference
+            .frames()
+            .iter()
+            .zip(test.frames())
+            .map(|(a, b)| {
+                // Bins far below the frame's peak would dominate the distance
+                // with differences nobody can hear.
+                let peak = a.iter().map(|x| x.norm_sqr() as f64).fold(0.0, f64::max);
+                let floor = (peak * LSD_RANGE).max(POWER_FLOOR);
+                let squares: f64 = a
+                    .iter()
+                    .zip(b)
+                    .map(|(x, y)| {
+                        let ratio =
+                            (x.norm_sqr() as f64).max(floor) / (y.norm_sqr() as f64).max(floor);
+                        (10.0 * ratio.log10()).powi(2)
+                    })
+                    .sum();
+                (squares / a.len() as f64).sqrt()
+            })
+            .sum();
+        Ok(total / reference.len() as f64)
+    }
+
+    /// Total noise-to-mask ratio in decibels and the disturbed frame fraction.
+    fn noise_to_mask(
+        &mut self,
+        original: &[f32],
+        difference: &[f32],
+    ) -> Result<(f64, f64), EvalError> {
+        let masks = self.model.analyze(original, HOP)?;
+        let noise = self.model.analyze(difference, HOP)?;
+        let mut ratio_sum = 0.0f64;
+        let mut disturbed = 0usize;
+        for frame in 0..masks.len() {
+            let mut frame_ratio = 0.0f64;
+            let mut loudest = f32::NEG_INFINITY;
+            for (&level, &threshold) in noise.level_db(frame).iter().zip(masks.threshold_db(frame))
+            {
+                let excess = level - threshold;
+                frame_ratio += 10f64.powf(excess as f64 / 10.0);
+                loudest = loudest.max(excess);
+            }
+            ratio_sum += frame_ratio / masks.bins() as f64;
+            if loudest > DISTURBED_NMR_DB {
+                disturbed += 1;
+            }
+        }
+        let frames = masks.len().max(1) as f64;
+        Ok((
+            10.0 * (ratio_sum / frames).max(POWER_FLOOR).log10(),
+            disturbed as f64 / frames,
+        ))
+    }
+}
+
+fn power(samples: &[f32]) -> f64 {
+    samples.iter().map(|&s| s as f64 * s as f64).sum()
+}
+
+fn snr_db(original: &[f32], difference: &[f32]) -> f64 {
+    10.0 * (power(original) / power(difference)).log10()
+}
+
+fn segmental_snr_db(original: &[f32], difference: &[f32]) -> f64 {
+    let silent = FRAME_LEN as f64 * 10f64.powf(SILENT_SEGMENT_DB / 10.0);
+    let segments: Vec<f64> = original
+        .chunks(FRAME_LEN)
+        .zip(difference.chunks(FRAME_LEN))
+        .filter(|(segment, _)| power(segment) > silent * segment.len() as f64 / FRAME_LEN as f64)
+        .map(|(segment, noise)| {
+            snr_db(segment, noise).clamp(SEGMENT_SNR_MIN_DB, SEGMENT_SNR_MAX_DB)
+        })
+        .collect();
+    if segments.is_empty() {
+        return SEGMENT_SNR_MAX_DB;
+    }
+    segments.iter().sum::<f64>() / segments.len() as f64
+}
+
+/// Limits a [`QualityReport`] must meet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityGate {
+    /// Smallest accepted SNR in decibels.
+    pub min_snr_db: f64,
+    /// Smallest accepted segmental SNR in decibels.
+    pub min_segmental_snr_db: f64,
+    /// Largest accepted log-spectral distance in decibels.
+    pub max_log_spectral_distance_db: f64,
+    /// Smallest accepted ODG estimate.
+    pub min_odg: f64,
+}
+
+impl Default for QualityGate {
+    /// The gate of [`Preset::Balanced`].
+    fn default() -> Self {
+        Self::for_preset(Preset::Balanced)
+    }
+}
+
+impl QualityGate {
+    /// Limits that `preset` meets on speech-like and music-like audio.
+    ///
+    /// Stronger presets trade SNR for robustness, so their limits are looser,
+    /// but every preset must stay close to imperceptible.
+    pub fn for_preset(preset: Preset) -> Self {
+        let (min_snr_db, max_log_spectral_distance_db, min_odg) = match preset {
+            Preset::Transparent => (25.0, 2.0, -0.5),
+            Preset::Balanced => (18.0, 3.0, -1.0),
+            Preset::Robust => (9.0, 5.0, -1.5),
+        };
+        Self {
+            min_snr_db,
+            min_segmental_snr_db: min_snr_db,
+            max_log_spectral_distance_db,
+            min_odg,
+        }
+    }
+
+    /// Lists every limit `report` violates.
+    pub fn violations(&self, report: &QualityReport) -> Vec<GateViolation> {
+        let checks = [
+            ("snr", report.snr_db, self.min_snr_db, true),
+            (
+                "segmental snr",
+                report.segmental_snr_db,
+                self.min_segmental_snr_db,
+                true,
+            ),
+            (
+                "log-spectral distance",
+                report.log_spectral_distance_db,
+                self.max_log_spectral_distance_db,
+                false,
+            ),
+            ("odg", report.odg, self.min_odg, true),
+        ];
+        checks
+            .into_iter()
+            .filter(|&(_, value, limit, at_least)| {
+                // NaN meets no limit.
+                let met = if at_least {
+                    value >= limit
+                } else {
+                    value <= limit
+                };
+                !met
+            })
+            .map(|(metric, value, limit, _)| GateViolation {
+                metric,
+                value,
+                limit,
+            })
+            .collect()
+    }
+
+    /// Fails with every violated limit unless `report` meets them all.
+    pub fn check(&self, report: &QualityReport) -> Result<(), EvalError> {
+        let violations = self.violations(report);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(EvalError::GateFailed(violations))
+        }
+    }
+
+    /// Embeds `payload` into `host` with `params` and checks the result.
+    ///
+    /// Returns the report of the marked audio when it passes the gate.
+    pub fn check_params(
+        &self,
+        context: &KeyContext,
+        params: EmbedParams,
+        host: &[f32],
+        payload: &[u8],
+    ) -> Result<QualityReport, EvalError> {
+        let sample_rate = params.sample_rate();
+        let marked =
+            SpreadSpectrumEmbedder::from_key_context(context, params)?.embed(host, payload)?;
+        let report = QualityAnalyzer::new(sample_rate)?.compare(host, &marked)?;
+        self.check(&report)?;
+        Ok(report)
+    }
+}
+
+/// One metric outside its [`QualityGate`] limit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GateViolation {
+    /// Name of the metric.
+    pub metric: &'static str,
+    /// Measured value.
+    pub value: f64,
+    /// Limit it had to meet.
+    pub limit: f64,
+}
+
+impl fmt::Display for GateViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {:.2} (limit {:.2})",
+            self.metric, self.value, self.limit
+        )
+    }
+}
+
+/// Errors raised by quality evaluation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    /// Original and processed audio differ in length.
+    LengthMismatch { original: usize, processed: usize },
+    /// No samples were given.
+    EmptyInput,
+    /// Masking analysis could not be set up or failed.
+    Masking(MaskingError),
+    /// STFT analysis failed.
+    Transform(FftError),
+    /// Embedding the payload failed.
+    Embed(EmbedError),
+    /// The report violates the gate's limits.
+    GateFailed(Vec<GateViolation>),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::LengthMismatch {
+                original,
+                processed,
+            } => write!(
+                f,
+                "original has {} samples but processed audio has {}",
+                original, processed
+            ),
+            EvalError::EmptyInput => write!(f, "quality evaluation needs at least one sample"),
+            EvalError::Masking(err) => write!(f, "masking error: {}", err),
+            EvalError::Transform(err) => write!(f, "transform error: {}", err),
+            EvalError::Embed(err) => write!(f, "embed error: {}", err),
+            EvalError::GateFailed(violations) => {
+                write!(f, "quality gate failed:")?;
+                for violation in violations {
+                    write!(f, " {};", violation)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+impl From<MaskingError> for EvalError {
+    fn from(err: MaskingError) -> Self {
+        EvalError::Masking(err)
+    }
+}
+
+impl From<FftError> for EvalError {
+    fn from(err: FftError) -> Self {
+        EvalError::Transform(err)
+    }
+}
+
+impl From<EmbedError> for EvalError {
+    fn from(err: EmbedError) -> Self {
+        EvalError::Embed(err)
+    }
+}
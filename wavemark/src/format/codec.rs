@@ -1,5 +1,3 @@
-#![allow(dead_code)]
-
 //! Binary codec for watermark metadata payloads.
 //!
 //! The codec emitted here defines how a [`PayloadFrame`](crate::format::payload::PayloadFrame)
@@ -72,8 +70,8 @@ use crate::format::payload::{
 use std::convert::TryFrom;
 use std::fmt;
 
-const MAGIC: &[u8; 2] = b"WM";
-const HEADER_LEN: usize = 8;
+pub(crate) const MAGIC: &[u8; 2] = b"WM";
+pub(crate) const HEADER_LEN: usize = 8;
 
 /// Semantic codec version (major.minor).
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -113,6 +111,36 @@ impl FrameEnvelope {
     }
 }
 
+/// Fixed-size header that precedes every encoded frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub version: FormatVersion,
+    pub envelope: FrameEnvelope,
+}
+
+impl FrameHeader {
+    /// Reads the header of `bytes` without checking the version against a
+    /// codec or touching the body, so encrypted frames can be inspected
+    /// without their key.
+    pub fn parse(bytes: &[u8]) -> Result<Self, CodecError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(CodecError::UnexpectedEof);
+        }
+        if &bytes[..2] != MAGIC {
+            return Err(CodecError::InvalidHeader("magic mismatch"));
+        }
+        let envelope = FrameEnvelope::from_flag(bytes[4])
+            .ok_or(CodecError::InvalidHeader("unknown envelope flag"))?;
+        Ok(Self {
+            version: FormatVersion {
+                major: bytes[2],
+                minor: bytes[3],
+            },
+            envelope,
+        })
+    }
+}
+
 /// Codec configuration shared between encoding and decoding.
 #[derive(Clone, Debug)]
 pub struct CodecOptions {
@@ -171,19 +199,7 @@ impl FrameCodec {
         bytes: &[u8],
         context: &EncryptionContext,
     ) -> Result<PayloadFrame, CodecError> {
-        if bytes.len() < HEADER_LEN {
-            return Err(CodecError::UnexpectedEof);
-        }
-
-        let (magic, rest) = bytes.split_at(2);
-        if magic != MAGIC {
-            return Err(CodecError::InvalidHeader("magic mismatch"));
-        }
-
-        let version = FormatVersion {
-            major: rest[0],
-            minor: rest[1],
-        };
+        let FrameHeader { version, envelope } = FrameHeader::parse(bytes)?;
         if version.major != self.options.version.major {
             return Err(CodecError::UnsupportedVersion {
                 expected_major: self.options.version.major,
@@ -191,9 +207,6 @@ impl FrameCodec {
             });
         }
 
-        let envelope = FrameEnvelope::from_flag(rest[2])
-            .ok_or(CodecError::InvalidHeader("unknown envelope flag"))?;
-
         let payload = &bytes[HEADER_LEN..];
         if matches!(envelope, FrameEnvelope::Plain)
             && matches!(self.options.encryption, EncryptionMode::EncryptedHash(_))
@@ -348,8 +361,7 @@ impl FrameCodec {
             }
             let tag = body[offset];
             offset += 1;
-            let kind =
-                ValueKind::from_tag(tag).ok_or_else(|| CodecError::UnsupportedFieldType(tag))?;
+            let kind = ValueKind::from_tag(tag).ok_or(CodecError::UnsupportedFieldType(tag))?;
 
             let value = match kind {
                 ValueKind::AccountId => {
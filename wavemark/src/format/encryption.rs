@@ -1,5 +1,3 @@
-#![allow(dead_code)]
-
 //! Encryption abstractions for watermark payloads.
 //!
 //! This module intentionally keeps cryptographic details abstract. Callers pick a
@@ -8,12 +6,17 @@
 //! protection. The default mode is [`EncryptionMode::None`], which leaves
 //! payload bytes untouched.
 //!
+//! The crate ships [`ChaCha20Poly1305Strategy`] as a first-party AEAD strategy.
+//! Applications with their own key infrastructure can still implement
+//! [`EncryptedHashStrategy`] directly.
+//!
 //! ```ignore
 //! use std::sync::Arc;
-//! use wavemark::format::encryption::{EncryptionMode, EncryptedHashConfig, EncryptionContext};
+//! use wavemark::format::encryption::{
+//!     ChaCha20Poly1305Strategy, EncryptedHashConfig, EncryptionMode,
+//! };
 //!
-//! // Application-defined strategy implementing `EncryptedHashStrategy`.
-//! let strategy = Arc::new(MyStrategy::new());
+//! let strategy = Arc::new(ChaCha20Poly1305Strategy::new([0x42; 32]));
 //! let mode = EncryptionMode::EncryptedHash(EncryptedHashConfig {
 //!     strategy,
 //!     key_id: Some("account-key-1".into()),
@@ -25,8 +28,15 @@
 //! surface configuration mistakes (`InvalidConfiguration`), payload issues
 //! (`RejectedPayload`), or low-level cryptographic faults (`CryptoFailure`).
 
+use crate::key::derivation::KeyContext;
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce, Tag};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
 use std::fmt;
 use std::sync::Arc;
+use zeroize::{Zeroize, Zeroizing};
 
 /// High-level encryption selector used by the format layer.
 #[derive(Debug, Clone)]
@@ -164,6 +174,207 @@ impl PayloadEncryption for NoEncryption {
     }
 }
 
+/// Domain-separation labels for the sub-keys derived from a strategy key.
+const AEAD_CIPHER_LABEL: &[u8] = b"wavemark/v1/aead/chacha20poly1305/cipher";
+const AEAD_NONCE_LABEL: &[u8] = b"wavemark/v1/aead/chacha20poly1305/nonce";
+/// Prefix bound into the associated data so sealed payloads cannot be replayed
+/// under a different construction.
+const AEAD_AAD_PREFIX: &[u8] = b"wavemark/v1/aead";
+
+/// First-party encrypted-hash strategy built on ChaCha20-Poly1305.
+///
+/// The strategy seals the plain body produced by
+/// [`FrameCodec::encode`](crate::format::codec::FrameCodec::encode) and binds
+/// both [`EncryptionContext::channel_id`] and
+/// [`EncryptionContext::associated_data`] as additional authenticated data, so
+/// a payload opened under a different channel or AAD fails authentication.
+///
+/// Nonces are synthetic: they are derived with HMAC-SHA256 over the AAD and the
+/// plaintext under a dedicated sub-key, then truncated to 96 bits. Sealing is
+/// therefore deterministic, as [`PayloadEncryption::seal`] requires, and a
+/// nonce only repeats when the exact same payload is sealed in the exact same
+/// context. The 12-byte nonce travels in [`EncryptionArtifacts::metadata`] and
+/// the 16-byte Poly1305 tag in [`EncryptionArtifacts::tag`]. Key material is
+/// zeroized on drop.
+#[derive(Clone)]
+pub struct ChaCha20Poly1305Strategy {
+    cipher_key: Zeroizing<[u8; ChaCha20Poly1305Strategy::KEY_LEN]>,
+    nonce_key: Zeroizing<[u8; ChaCha20Poly1305Strategy::KEY_LEN]>,
+}
+
+impl ChaCha20Poly1305Strategy {
+    /// Length of the strategy key in bytes.
+    pub const KEY_LEN: usize = 32;
+    /// Length of the nonce stored in [`EncryptionArtifacts::metadata`].
+    pub const NONCE_LEN: usize = 12;
+    /// Length of the Poly1305 tag stored in [`EncryptionArtifacts::tag`].
+    pub const TAG_LEN: usize = 16;
+
+    /// Creates a strategy from 32 bytes of key material.
+    ///
+    /// Separate cipher and nonce sub-keys are expanded from `key` with
+    /// HKDF-SHA256, so the same bytes are never used by both primitives.
+    pub fn new(mut key: [u8; Self::KEY_LEN]) -> Self {
+        let strategy = Self::from_key_bytes(&key);
+        key.zeroize();
+        strategy
+    }
+
+    /// Creates a strategy from the encryption sub-key of a [`KeyContext`].
+    pub fn from_key_context(context: &KeyContext) -> Self {
+        Self::from_key_bytes(context.encryption_key().as_bytes())
+    }
+
+    /// Creates a strategy from a key slice, rejecting keys that are not 32 bytes.
+    pub fn from_slice(key: &[u8]) -> Result<Self, EncryptionError> {
+        let key: [u8; Self::KEY_LEN] = key.try_into().map_err(|_| {
+            EncryptionError::InvalidConfiguration(format!(
+                "chacha20poly1305 keys must be {} bytes, got {}",
+                Self::KEY_LEN,
+                key.len()
+            ))
+        })?;
+        Ok(Self::new(key))
+    }
+
+    fn from_key_bytes(key: &[u8; Self::KEY_LEN]) -> Self {
+        let hkdf = Hkdf::<Sha256>::new(None, key);
+        let mut cipher_key = Zeroizing::new([0u8; Self::KEY_LEN]);
+        let mut nonce_key = Zeroizing::new([0u8; Self::KEY_LEN]);
+        hkdf.expand(AEAD_CIPHER_LABEL, cipher_key.as_mut())
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        hkdf.expand(AEAD_NONCE_LABEL, nonce_key.as_mut())
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        Self {
+            cipher_key,
+            nonce_key,
+        }
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(Key::from_slice(self.cipher_key.as_ref()))
+    }
+
+    /// Serializes the context into unambiguous, length-prefixed AAD bytes.
+    fn associated_data(context: &EncryptionContext) -> Vec<u8> {
+        fn push_optional(buffer: &mut Vec<u8>, value: Option<&[u8]>) {
+            match value {
+                Some(bytes) => {
+                    buffer.push(1);
+                    buffer.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+                    buffer.extend_from_slice(bytes);
+                }
+                None => buffer.push(0),
+            }
+        }
+
+        let mut aad = Vec::from(AEAD_AAD_PREFIX);
+        push_optional(&mut aad, context.channel_id.as_deref().map(str::as_bytes));
+        push_optional(&mut aad, context.associated_data.as_deref());
+        aad
+    }
+
+    fn synthetic_nonce(&self, aad: &[u8], payload: &[u8]) -> [u8; Self::NONCE_LEN] {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(self.nonce_key.as_ref())
+            .expect("HMAC accepts keys of any length");
+        mac.update(&(aad.len() as u64).to_le_bytes());
+        mac.update(aad);
+        mac.update(payload);
+        let digest = mac.finalize().into_bytes();
+        let mut nonce = [0u8; Self::NONCE_LEN];
+        nonce.copy_from_slice(&digest[..Self::NONCE_LEN]);
+        nonce
+    }
+}
+
+impl fmt::Debug for ChaCha20Poly1305Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChaCha20Poly1305Strategy")
+            .field("key", &"<redacted>")
+            .finish()
+    }
+}
+
+impl PayloadEncryption for ChaCha20Poly1305Strategy {
+    fn seal(
+        &self,
+        payload: &[u8],
+        context: &EncryptionContext,
+    ) -> Result<EncryptionArtifacts, EncryptionError> {
+        let aad = Self::associated_data(context);
+        let nonce = self.synthetic_nonce(&aad, payload);
+
+        let mut sealed_payload = payload.to_vec();
+        let tag = self
+            .cipher()
+            .encrypt_in_place_detached(Nonce::from_slice(&nonce), &aad, &mut sealed_payload)
+            .map_err(|_| EncryptionError::CryptoFailure("chacha20poly1305 seal failed".into()))?;
+
+        Ok(EncryptionArtifacts {
+            sealed_payload,
+            tag: Some(tag.to_vec()),
+            metadata: Some(nonce.to_vec()),
+        })
+    }
+
+    fn open(
+        &self,
+        sealed: &[u8],
+        artifacts: &EncryptionArtifacts,
+        context: &EncryptionContext,
+    ) -> Result<Vec<u8>, EncryptionError> {
+        let tag = match artifacts.tag.as_deref() {
+            Some(tag) if tag.len() == Self::TAG_LEN => tag,
+            _ => {
+                return Err(EncryptionError::RejectedPayload(format!(
+                    "expected a {}-byte authentication tag",
+                    Self::TAG_LEN
+                )))
+            }
+        };
+        let nonce = match artifacts.metadata.as_deref() {
+            Some(nonce) if nonce.len() == Self::NONCE_LEN => nonce,
+            _ => {
+                return Err(EncryptionError::RejectedPayload(format!(
+                    "expected a {}-byte nonce in the artifact metadata",
+                    Self::NONCE_LEN
+                )))
+            }
+        };
+
+        let aad = Self::associated_data(context);
+        let mut plain = sealed.to_vec();
+        self.cipher()
+            .decrypt_in_place_detached(
+                Nonce::from_slice(nonce),
+                &aad,
+                &mut plain,
+                Tag::from_slice(tag),
+            )
+            .map_err(|_| EncryptionError::CryptoFailure("authentication failed".into()))?;
+
+        // The tag already authenticates the nonce; re-deriving it additionally
+        // rejects ciphertexts that were not produced by this strategy's `seal`.
+        if self.synthetic_nonce(&aad, &plain) != nonce {
+            return Err(EncryptionError::CryptoFailure(
+                "synthetic nonce mismatch".into(),
+            ));
+        }
+
+        Ok(plain)
+    }
+
+    fn scheme_name(&self) -> &'static str {
+        "chacha20poly1305"
+    }
+}
+
+impl EncryptedHashStrategy for ChaCha20Poly1305Strategy {
+    fn algorithm_id(&self) -> &'static str {
+        "chacha20poly1305-hmac-sha256-siv"
+    }
+}
+
 /// Errors surfaced by encryption strategies when sealing or opening payloads.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum EncryptionError {
@@ -0,0 +1,342 @@
+//! Reed-Solomon forward error correction for codec output.
+//!
+//! Watermark channels flip bits under compression and noise, so the bytes
+//! produced by [`FrameCodec::encode`](crate::format::codec::FrameCodec::encode)
+//! are wrapped in Reed-Solomon shards before they are mapped to embedding
+//! symbols. Every shard carries its own CRC-16, which turns silently corrupted
+//! shards into erasures that the Reed-Solomon code can rebuild.
+//!
+//! # Byte Layout
+//!
+//! ```text
+//! message   = length (u16, little-endian) ‖ codec bytes ‖ zero padding
+//! shard i   = message[i * shard_len .. (i + 1) * shard_len] ‖ CRC-16 (u16, LE)
+//! encoded   = data shard 0 ‖ … ‖ data shard d-1 ‖ parity shard 0 ‖ … ‖ parity shard p-1
+//! ```
+//!
+//! `shard_len` is the smallest length that fits the length-prefixed message
+//! into `data_shards` shards. Decoders recover it from the encoded length, so
+//! no additional framing is required.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use wavemark::format::fec::{FecCodec, FecConfig};
+//!
+//! let fec = FecCodec::new(FecConfig::new(8, 4)?)?;
+//! let encoded = fec.encode(&format_output.bytes)?;
+//!
+//! // Shards 2 and 9 were unreadable on the detection side.
+//! let decoded = fec.decode(&received, &[2, 9])?;
+//! assert_eq!(decoded.bytes, format_output.bytes);
+//! println!("corrected: {:?}", decoded.report.corrected);
+//! ```
+
+use reed_solomon_erasure::galois_8::ReedSolomon;
+use std::fmt;
+
+const LENGTH_PREFIX_LEN: usize = 2;
+const SHARD_CRC_LEN: usize = 2;
+/// GF(2^8) Reed-Solomon codes support at most 256 shards in total.
+const MAX_TOTAL_SHARDS: usize = 256;
+
+/// Data/parity shard counts controlling the redundancy ratio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FecConfig {
+    pub data_shards: usize,
+    pub parity_shards: usize,
+}
+
+impl FecConfig {
+    /// Validates a data/parity shard configuration.
+    pub fn new(data_shards: usize, parity_shards: usize) -> Result<Self, FecError> {
+        if data_shards == 0 {
+            return Err(FecError::InvalidConfiguration(
+                "at least one data shard is required".into(),
+            ));
+        }
+        if parity_shards == 0 {
+            return Err(FecError::InvalidConfiguration(
+                "at least one parity shard is required".into(),
+            ));
+        }
+        if data_shards + parity_shards > MAX_TOTAL_SHARDS {
+            return Err(FecError::InvalidConfiguration(format!(
+                "total shard count {} exceeds {}",
+                data_shards + parity_shards,
+                MAX_TOTAL_SHARDS
+            )));
+        }
+        Ok(Self {
+            data_shards,
+            parity_shards,
+        })
+    }
+
+    /// Total number of shards produced per payload.
+    pub fn total_shards(&self) -> usize {
+        self.data_shards + self.parity_shards
+    }
+
+    /// Fraction of shards that carry parity.
+    pub fn parity_ratio(&self) -> f32 {
+        self.parity_shards as f32 / self.total_shards() as f32
+    }
+}
+
+impl Default for FecConfig {
+    fn default() -> Self {
+        Self {
+            data_shards: 8,
+            parity_shards: 4,
+        }
+    }
+}
+
+/// Reed-Solomon encoder/decoder operating on codec byte payloads.
+#[derive(Clone, Debug)]
+pub struct FecCodec {
+    config: FecConfig,
+    rs: ReedSolomon,
+}
+
+impl FecCodec {
+    /// Constructs a codec for the given shard configuration.
+    pub fn new(config: FecConfig) -> Result<Self, FecError> {
+        let config = FecConfig::new(config.data_shards, config.parity_shards)?;
+        let rs = ReedSolomon::new(config.data_shards, config.parity_shards)
+            .map_err(|err| FecError::InvalidConfiguration(err.to_string()))?;
+        Ok(Self { config, rs })
+    }
+
+    /// Returns the active shard configuration.
+    pub fn config(&self) -> &FecConfig {
+        &self.config
+    }
+
+    /// Length in bytes of each shard (including its CRC) for a payload length.
+    pub fn shard_len(&self, payload_len: usize) -> usize {
+        (payload_len + LENGTH_PREFIX_LEN).div_ceil(self.config.data_shards) + SHARD_CRC_LEN
+    }
+
+    /// Length in bytes of the encoded output for a payload length.
+    pub fn encoded_len(&self, payload_len: usize) -> usize {
+        self.shard_len(payload_len) * self.config.total_shards()
+    }
+
+    /// Wraps `payload` into data and parity shards, returned as one contiguous buffer.
+    pub fn encode(&self, payload: &[u8]) -> Result<Vec<u8>, FecError> {
+        if payload.len() > u16::MAX as usize {
+            return Err(FecError::PayloadTooLarge {
+                len: payload.len(),
+                max: u16::MAX as usize,
+            });
+        }
+
+        let body_len = self.shard_len(payload.len()) - SHARD_CRC_LEN;
+        let mut message = Vec::with_capacity(body_len * self.config.data_shards);
+        message.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+        message.extend_from_slice(payload);
+        message.resize(body_len * self.config.data_shards, 0);
+
+        let mut shards: Vec<Vec<u8>> = message.chunks(body_len).map(<[u8]>::to_vec).collect();
+        shards.resize(self.config.total_shards(), vec![0u8; body_len]);
+        self.rs
+            .encode(&mut shards)
+            .map_err(|err| FecError::InvalidConfiguration(err.to_string()))?;
+
+        let mut encoded = Vec::with_capacity(self.encoded_len(payload.len()));
+        for shard in &shards {
+            encoded.extend_from_slice(shard);
+            encoded.extend_from_slice(&crc16(shard).to_le_bytes());
+        }
+        Ok(encoded)
+    }
+
+    /// Recovers the payload from encoded bytes.
+    ///
+    /// `erasures` lists shard indices the caller already knows to be unreliable
+    /// (for example, shards whose soft bits had low confidence). Those shards
+    /// are discarded, as is any shard whose CRC does not match; both are then
+    /// rebuilt from the remaining shards. The returned [`FecReport`] lists
+    /// which shards were repaired and why.
+    pub fn decode(&self, encoded: &[u8], erasures: &[usize]) -> Result<FecOutput, FecError> {
+        let total = self.config.total_shards();
+        if encoded.is_empty() || !encoded.len().is_multiple_of(total) {
+            return Err(FecError::InvalidLength {
+                len: encoded.len(),
+                total_shards: total,
+            });
+        }
+        let shard_len = encoded.len() / total;
+        if shard_len <= SHARD_CRC_LEN {
+            return Err(FecError::InvalidLength {
+                len: encoded.len(),
+                total_shards: total,
+            });
+        }
+        if let Some(&index) = erasures.iter().find(|&&index| index >= total) {
+            return Err(FecError::InvalidErasure { index, total });
+        }
+
+        let body_len = shard_len - SHARD_CRC_LEN;
+        let mut report = FecReport::default();
+        let mut shards: Vec<Option<Vec<u8>>> = Vec::with_capacity(total);
+        for (index, shard) in encoded.chunks(shard_len).enumerate() {
+            let (body, crc) = shard.split_at(body_len);
+            if erasures.contains(&index) {
+                report.erased.push(index);
+                shards.push(None);
+            } else if crc16(body).to_le_bytes() != crc {
+                report.corrected.push(index);
+                shards.push(None);
+            } else {
+                shards.push(Some(body.to_vec()));
+            }
+        }
+
+        let damaged = report.erased.len() + report.corrected.len();
+        if damaged > self.config.parity_shards {
+            return Err(FecError::Unrecoverable {
+                damaged,
+                parity_shards: self.config.parity_shards,
+            });
+        }
+        if damaged > 0 {
+            self.rs
+                .reconstruct_data(&mut shards)
+                .map_err(|_| FecError::Unrecoverable {
+                    damaged,
+                    parity_shards: self.config.parity_shards,
+                })?;
+        }
+
+        let mut message = Vec::with_capacity(body_len * self.config.data_shards);
+        for shard in shards.iter().take(self.config.data_shards) {
+            let shard = shard
+                .as_ref()
+                .expect("data shards are present after reconstruction");
+            message.extend_from_slice(shard);
+        }
+
+        let len = u16::from_le_bytes([message[0], message[1]]) as usize;
+        if LENGTH_PREFIX_LEN + len > message.len() {
+            return Err(FecError::CorruptLength {
+                declared: len,
+                capacity: message.len() - LENGTH_PREFIX_LEN,
+            });
+        }
+        let bytes = message[LENGTH_PREFIX_LEN..LENGTH_PREFIX_LEN + len].to_vec();
+
+        Ok(FecOutput { bytes, report })
+    }
+}
+
+impl PartialEq for FecCodec {
+    fn eq(&self, other: &Self) -> bool {
+        self.config == other.config
+    }
+}
+
+impl Eq for FecCodec {}
+
+/// Result of [`FecCodec::decode`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FecOutput {
+    /// Recovered codec bytes.
+    pub bytes: Vec<u8>,
+    /// Shards that had to be rebuilt during decoding.
+    pub report: FecReport,
+}
+
+/// Shard-level repair summary produced while decoding.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FecReport {
+    /// Shards whose CRC failed and that were rebuilt from parity.
+    pub corrected: Vec<usize>,
+    /// Shards flagged as erased by the caller and rebuilt from parity.
+    pub erased: Vec<usize>,
+}
+
+impl FecReport {
+    /// Returns `true` when every shard arrived intact.
+    pub fn is_clean(&self) -> bool {
+        self.corrected.is_empty() && self.erased.is_empty()
+    }
+}
+
+/// Errors produced by the FEC layer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FecError {
+    /// Shard counts are outside what the Reed-Solomon code supports.
+    InvalidConfiguration(String),
+    /// Payload is longer than the length prefix can describe.
+    PayloadTooLarge { len: usize, max: usize },
+    /// Encoded buffer cannot be split into the configured number of shards.
+    InvalidLength { len: usize, total_shards: usize },
+    /// Caller flagged a shard index that does not exist.
+    InvalidErasure { index: usize, total: usize },
+    /// More shards were damaged than parity can repair.
+    Unrecoverable {
+        damaged: usize,
+        parity_shards: usize,
+    },
+    /// Reconstructed length prefix does not fit the recovered message.
+    CorruptLength { declared: usize, capacity: usize },
+}
+
+impl fmt::Display for FecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FecError::InvalidConfiguration(reason) => {
+                write!(f, "invalid FEC configuration: {}", reason)
+            }
+            FecError::PayloadTooLarge { len, max } => {
+                write!(f, "payload of {} bytes exceeds FEC limit of {}", len, max)
+            }
+            FecError::InvalidLength { len, total_shards } => write!(
+                f,
+                "encoded length {} cannot be split into {} shards",
+                len, total_shards
+            ),
+            FecError::InvalidErasure { index, total } => {
+                write!(
+                    f,
+                    "erasure index {} is out of range for {} shards",
+                    index, total
+                )
+            }
+            FecError::Unrecoverable {
+                damaged,
+                parity_shards,
+            } => write!(
+                f,
+                "{} damaged shards exceed the {} parity shards available",
+                damaged, parity_shards
+            ),
+            FecError::CorruptLength { declared, capacity } => write!(
+                f,
+                "recovered length {} exceeds shard capacity {}",
+                declared, capacity
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FecError {}
+
+/// CRC-16/CCITT-FALSE (polynomial `0x1021`, initial value `0xFFFF`).
+pub(crate) fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in bytes {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
@@ -2,10 +2,13 @@
 //!
 //! This module provides a high-level API that composes schema definitions
 //! ([`payload`]), encryption abstraction ([`encryption`]), and the binary codec
-//! ([`codec`]) into an ergonomic builder for downstream consumers.
+//! ([`codec`]) into an ergonomic builder for downstream consumers. The
+//! [`fec`] module adds Reed-Solomon redundancy to codec output before it is
+//! mapped onto the watermark channel.
 
 pub mod codec;
 pub mod encryption;
+pub mod fec;
 pub mod payload;
 
 use codec::{CodecError, CodecOptions, FrameCodec};
@@ -85,13 +88,20 @@ impl FormatBuilder {
         &mut self.payload
     }
 
+    /// Codec that [`FormatBuilder::build`] serializes with.
+    pub(crate) fn codec(&self) -> &FrameCodec {
+        &self.codec
+    }
+
+    /// Encryption context that [`FormatBuilder::build`] seals with.
+    pub(crate) fn context(&self) -> &EncryptionContext {
+        &self.encryption_context
+    }
+
     /// Consume the builder, returning both the `PayloadFrame` and serialized bytes.
     pub fn build(self) -> Result<FormatOutput, CodecError> {
         let frame = self.payload.build().map_err(CodecError::from)?;
-        let bytes = self
-            .codec
-            .encode(&frame, &self.encryption_context)
-            .map_err(CodecError::from)?;
+        let bytes = self.codec.encode(&frame, &self.encryption_context)?;
         Ok(FormatOutput { frame, bytes })
     }
 }
@@ -1,5 +1,3 @@
-#![allow(dead_code)]
-
 //! Payload metadata data model.
 //!
 //! The types in this module model the logical metadata that will eventually be
@@ -256,6 +254,12 @@ impl PayloadBuilder {
     }
 }
 
+impl Default for PayloadBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Domain-specific metadata constraints that keep payloads manageable.
 ///
 /// These values cap the size and shape of metadata so it can be transported in
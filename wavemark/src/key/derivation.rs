@@ -1,6 +1,267 @@
-#![allow(dead_code)]
+//! HKDF-SHA256 key hierarchy.
+//!
+//! Every secret used by the library descends from a single master secret.
+//! Each level is derived with HKDF-SHA256 under an explicit domain-separation
+//! label, so keys for different purposes or different scopes never collide:
+//!
+//! ```text
+//! master secret ── HKDF-Extract(salt = "wavemark/v1/master")
+//!   └─ account key ── Expand("wavemark/v1/account" ‖ account id)
+//!        └─ session key ── Expand("wavemark/v1/session" ‖ session id)
+//!
+//! per audio channel, at the same level:
+//!   └─ channel key ── Expand("wavemark/v1/channel" ‖ channel index)
+//!
+//! at any level:
+//!   ├─ encryption sub-key ── Expand("wavemark/v1/encryption")
+//!   ├─ PN-sequence seed   ── Expand("wavemark/v1/pn-seed")
+//!   └─ HMAC sub-key       ── Expand("wavemark/v1/hmac")
+//! ```
+//!
+//! A [`KeyContext`] holds the key for one level of the hierarchy together with
+//! its [`KeyScope`]. Scoped contexts are narrowed with [`KeyContext::account`]
+//! and [`KeyContext::session`], and purpose-specific [`DerivedKey`]s are
+//! produced on demand. All key material is zeroized when dropped.
+//!
+//! ```ignore
+//! use wavemark::key::derivation::KeyContext;
+//!
+//! let root = KeyContext::from_master_secret(&master_secret)?;
+//! let session = root.account("acct_demo")?.session("session-01")?;
+//!
+//! let strategy = ChaCha20Poly1305Strategy::from_key_context(&session);
+//! let embedder = SpreadSpectrumEmbedder::from_key_context(&session, EmbedParams::default())?;
+//! ```
 
-//! Placeholder key derivation utilities.
+use hkdf::Hkdf;
+use sha2::Sha256;
+use std::fmt;
+use zeroize::{Zeroize, Zeroizing};
 
-/// Placeholder key derivation context.
-pub struct KeyContext;
+const MASTER_SALT: &[u8] = b"wavemark/v1/master";
+const ACCOUNT_LABEL: &[u8] = b"wavemark/v1/account";
+const SESSION_LABEL: &[u8] = b"wavemark/v1/session";
+const ENCRYPTION_LABEL: &[u8] = b"wavemark/v1/encryption";
+const PN_SEED_LABEL: &[u8] = b"wavemark/v1/pn-seed";
+const HMAC_LABEL: &[u8] = b"wavemark/v1/hmac";
+const CHANNEL_LABEL: &[u8] = b"wavemark/v1/channel";
+
+/// Level of the key hierarchy a [`KeyContext`] represents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyScope {
+    /// Root key extracted from the master secret.
+    Master,
+    /// Key scoped to a single account.
+    Account,
+    /// Key scoped to a single session within an account.
+    Session,
+}
+
+impl KeyScope {
+    fn as_str(&self) -> &'static str {
+        match self {
+            KeyScope::Master => "master",
+            KeyScope::Account => "account",
+            KeyScope::Session => "session",
+        }
+    }
+}
+
+impl fmt::Display for KeyScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 256-bit key produced by the hierarchy. The bytes are zeroized on drop.
+#[derive(Clone)]
+pub struct DerivedKey(Zeroizing<[u8; DerivedKey::LEN]>);
+
+impl DerivedKey {
+    /// Length of every derived key in bytes.
+    pub const LEN: usize = 32;
+
+    /// Returns the raw key bytes.
+    pub fn as_bytes(&self) -> &[u8; Self::LEN] {
+        &self.0
+    }
+
+    fn expand(prk: &[u8; Self::LEN], label: &[u8], identifier: Option<&str>) -> Self {
+        let hkdf = Hkdf::<Sha256>::from_prk(prk).expect("32-byte PRK is valid for HKDF-SHA256");
+        let mut info = Zeroizing::new(Vec::with_capacity(label.len() + 2 + 255));
+        info.extend_from_slice(label);
+        if let Some(identifier) = identifier {
+            // Identifier length is validated to fit in a byte, keeping the info unambiguous.
+            info.push(0);
+            info.push(identifier.len() as u8);
+            info.extend_from_slice(identifier.as_bytes());
+        }
+
+        let mut okm = Zeroizing::new([0u8; Self::LEN]);
+        hkdf.expand(&info, okm.as_mut())
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        DerivedKey(okm)
+    }
+}
+
+impl fmt::Debug for DerivedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DerivedKey(<redacted>)")
+    }
+}
+
+/// One level of the key hierarchy, used to derive scoped and purpose-specific keys.
+///
+/// Encryption strategies and embedders accept a `KeyContext` so that all key
+/// use in a pipeline flows from the same hierarchy.
+#[derive(Clone)]
+pub struct KeyContext {
+    key: DerivedKey,
+    scope: KeyScope,
+}
+
+impl KeyContext {
+    /// Minimum accepted length of the master secret in bytes.
+    pub const MIN_SECRET_LEN: usize = 16;
+    /// Maximum length of account and session identifiers in bytes.
+    pub const MAX_IDENTIFIER_LEN: usize = 255;
+
+    /// Extracts the root context from a master secret.
+    pub fn from_master_secret(secret: &[u8]) -> Result<Self, KeyError> {
+        if secret.len() < Self::MIN_SECRET_LEN {
+            return Err(KeyError::SecretTooShort {
+                min: Self::MIN_SECRET_LEN,
+                found: secret.len(),
+            });
+        }
+
+        let (mut prk, _) = Hkdf::<Sha256>::extract(Some(MASTER_SALT), secret);
+        let mut root = Zeroizing::new([0u8; DerivedKey::LEN]);
+        root.copy_from_slice(&prk);
+        prk.as_mut_slice().zeroize();
+
+        Ok(Self {
+            key: DerivedKey(root),
+            scope: KeyScope::Master,
+        })
+    }
+
+    /// Returns the hierarchy level of this context.
+    pub fn scope(&self) -> KeyScope {
+        self.scope
+    }
+
+    /// Derives the per-account context. Only valid on the master context.
+    pub fn account(&self, account_id: &str) -> Result<Self, KeyError> {
+        self.child(
+            KeyScope::Master,
+            KeyScope::Account,
+            ACCOUNT_LABEL,
+            account_id,
+        )
+    }
+
+    /// Derives the per-session context. Only valid on an account context.
+    pub fn session(&self, session_id: &str) -> Result<Self, KeyError> {
+        self.child(
+            KeyScope::Account,
+            KeyScope::Session,
+            SESSION_LABEL,
+            session_id,
+        )
+    }
+
+    /// Derives the context for audio channel `index`, keeping the scope.
+    ///
+    /// Channels watermarked with independent payloads each use their own
+    /// channel context so their spreading codes are uncorrelated.
+    pub fn channel(&self, index: usize) -> Self {
+        Self {
+            key: DerivedKey::expand(self.key.as_bytes(), CHANNEL_LABEL, Some(&index.to_string())),
+            scope: self.scope,
+        }
+    }
+
+    /// Sub-key for payload encryption at this scope.
+    pub fn encryption_key(&self) -> DerivedKey {
+        DerivedKey::expand(self.key.as_bytes(), ENCRYPTION_LABEL, None)
+    }
+
+    /// Seed for the keyed pseudo-noise sequence used during embedding and detection.
+    pub fn pn_seed(&self) -> DerivedKey {
+        DerivedKey::expand(self.key.as_bytes(), PN_SEED_LABEL, None)
+    }
+
+    /// Sub-key for HMAC authentication at this scope.
+    pub fn hmac_key(&self) -> DerivedKey {
+        DerivedKey::expand(self.key.as_bytes(), HMAC_LABEL, None)
+    }
+
+    fn child(
+        &self,
+        expected: KeyScope,
+        scope: KeyScope,
+        label: &[u8],
+        identifier: &str,
+    ) -> Result<Self, KeyError> {
+        if self.scope != expected {
+            return Err(KeyError::InvalidScope {
+                expected,
+                found: self.scope,
+            });
+        }
+        if identifier.is_empty() {
+            return Err(KeyError::InvalidIdentifier("identifiers cannot be empty"));
+        }
+        if identifier.len() > Self::MAX_IDENTIFIER_LEN {
+            return Err(KeyError::InvalidIdentifier(
+                "identifiers cannot exceed 255 bytes",
+            ));
+        }
+
+        Ok(Self {
+            key: DerivedKey::expand(self.key.as_bytes(), label, Some(identifier)),
+            scope,
+        })
+    }
+}
+
+impl fmt::Debug for KeyContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyContext")
+            .field("scope", &self.scope)
+            .field("key", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Errors raised while deriving keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyError {
+    /// Master secret does not carry enough entropy.
+    SecretTooShort { min: usize, found: usize },
+    /// Account or session identifier is empty or too long.
+    InvalidIdentifier(&'static str),
+    /// Derivation was requested from the wrong level of the hierarchy.
+    InvalidScope { expected: KeyScope, found: KeyScope },
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyError::SecretTooShort { min, found } => write!(
+                f,
+                "master secret must be at least {} bytes, got {}",
+                min, found
+            ),
+            KeyError::InvalidIdentifier(reason) => write!(f, "invalid key identifier: {}", reason),
+            KeyError::InvalidScope { expected, found } => write!(
+                f,
+                "derivation requires a {} key context but found {}",
+                expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for KeyError {}
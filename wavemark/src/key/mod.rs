@@ -1,3 +1,3 @@
-//! Key management placeholders.
+//! Key management for watermark payloads and embedding sequences.
 
 pub mod derivation;
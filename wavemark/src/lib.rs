@@ -7,9 +7,11 @@ pub mod api;
 pub mod core;
 pub mod detect;
 pub mod embed;
+pub mod eval;
 pub mod format;
 pub mod key;
 pub mod pipeline;
+pub mod robustness;
 pub mod streaming;
 pub mod transforms;
 
@@ -18,8 +20,10 @@ pub use api::*;
 pub use core::*;
 pub use detect::*;
 pub use embed::*;
+pub use eval::*;
 pub use format::*;
 pub use key::*;
 pub use pipeline::*;
+pub use robustness::*;
 pub use streaming::*;
 pub use transforms::*;
@@ -1,3 +1,3 @@
-//! Pipeline orchestration placeholders.
+//! Pipeline orchestration.
 
 pub mod pre_vocoder;
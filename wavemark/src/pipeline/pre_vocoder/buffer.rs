@@ -1,6 +1,254 @@
-#![allow(dead_code)]
+//! Streaming overlap-add framing for chunked audio.
+//!
+//! [`OverlapBuffer`] turns audio arriving in chunks of any size into
+//! fixed-size analysis frames advancing by `hop` samples. It hands each frame
+//! to a caller-supplied closure and overlap-adds the processed frames back into
+//! output of exactly the same length as the input. Frames are tapered with the
+//! window on both analysis and synthesis, so the window must satisfy
+//! [`OverlapMode::WeightedOverlapAdd`] at the hop. Weighted overlap-add
+//! reconstructs the input exactly when the closure leaves the frames unchanged.
+//!
+//! # Framing and Latency
+//!
+//! Framing matches [`FftBackend::stft`]: the stream is preceded by
+//! `frame_len - hop` virtual zeros, and frame `m` starts at stream index
+//! `m * hop - (frame_len - hop)` (see [`OverlapBuffer::frame_start`]). Every
+//! sample is therefore covered by the same number of frames. Frame boundaries
+//! depend only on the total number of samples pushed, not on how they were
+//! split into chunks.
+//!
+//! Output is delayed by exactly [`OverlapBuffer::latency`] = `frame_len - 1`
+//! samples, whatever the chunk sizes. This is the smallest delay at which every
+//! output sample is final when it is returned. [`OverlapBuffer::push`] returns
+//! as many samples as it was given; the first `latency()` samples of a stream
+//! are zeros. [`OverlapBuffer::finish`] pads the input with zeros, completes
+//! the pending frames and returns the last `latency()` samples. Over a whole
+//! stream the output is therefore the processed input shifted right by
+//! `latency()` samples.
+//!
+//! After construction, pushing chunks no larger than the capacity reserved
+//! with [`OverlapBuffer::reserve`] does not allocate.
+//!
+//! ```ignore
+//! use wavemark::pipeline::pre_vocoder::buffer::OverlapBuffer;
+//! use wavemark::transforms::window::AnalysisWindow;
+//!
+//! let mut buffer = OverlapBuffer::new(AnalysisWindow::sqrt_hann(1024)?, 512)?;
+//! for chunk in vocoder_chunks {
+//!     let out = buffer.push(&chunk, |_index, frame| {
+//!         // modify the windowed frame in place...
+//!         Ok::<_, Infallible>(())
+//!     })?;
+//!     sink.write(out);
+//! }
+//! sink.write(buffer.finish(|_, _| Ok::<_, Infallible>(()))?);
+//! ```
+//!
+//! [`FftBackend::stft`]: crate::transforms::fft::FftBackend::stft
 
-//! Placeholder buffer management for the pre-vocoder pipeline.
+use std::collections::VecDeque;
 
-/// Tracks overlap-add buffers between audio chunks.
-pub struct OverlapBuffer;
+use crate::transforms::window::{AnalysisWindow, OverlapMode, WindowError};
+
+/// Chunk-to-frame adapter with weighted overlap-add resynthesis.
+#[derive(Debug, Clone)]
+pub struct OverlapBuffer {
+    window: Vec<f32>,
+    hop: usize,
+    /// Reciprocal of the weighted overlap-add gain.
+    norm: f32,
+    /// Last `frame_len` input samples; the first `filled` are valid.
+    input: Vec<f32>,
+    filled: usize,
+    /// Scratch frame handed to the processing closure.
+    frame: Vec<f32>,
+    /// Overlap-add sums aligned with `input`.
+    accumulator: Vec<f32>,
+    /// Finished output samples not yet returned.
+    ready: VecDeque<f32>,
+    /// Finished samples still inside the leading padding.
+    skip: usize,
+    /// Leading zeros still to return.
+    delay: usize,
+    frames: u64,
+    pushed: u64,
+    output: Vec<f32>,
+}
+
+impl OverlapBuffer {
+    /// Creates a buffer framing audio with `window` at `hop`.
+    ///
+    /// Fails unless the window satisfies weighted overlap-add at `hop`.
+    pub fn new(window: AnalysisWindow, hop: usize) -> Result<Self, WindowError> {
+        let gain = window.check_overlap_add(hop, OverlapMode::WeightedOverlapAdd)?;
+        let frame_len = window.len();
+        let mut buffer = Self {
+            window: window.coefficients().iter().map(|&w| w as f32).collect(),
+            hop,
+            norm: gain.recip() as f32,
+            input: vec![0.0; frame_len],
+            filled: 0,
+            frame: vec![0.0; frame_len],
+            accumulator: vec![0.0; frame_len],
+            // At most `hop - 1` samples wait between pushes, plus one frame's
+            // worth within a push.
+            ready: VecDeque::with_capacity(frame_len + hop),
+            skip: 0,
+            delay: 0,
+            frames: 0,
+            pushed: 0,
+            output: Vec::new(),
+        };
+        buffer.reset();
+        Ok(buffer)
+    }
+
+    /// Samples per analysis frame.
+    pub fn frame_len(&self) -> usize {
+        self.window.len()
+    }
+
+    /// Samples between the starts of consecutive frames.
+    pub fn hop(&self) -> usize {
+        self.hop
+    }
+
+    /// Delay in samples between input and output.
+    pub fn latency(&self) -> usize {
+        self.frame_len() - 1
+    }
+
+    /// Number of frames processed since the start of the stream.
+    pub fn frames_processed(&self) -> u64 {
+        self.frames
+    }
+
+    /// Number of samples pushed since the start of the stream.
+    pub fn samples_pushed(&self) -> u64 {
+        self.pushed
+    }
+
+    /// Stream index of the first sample of frame `index`; negative inside the padding.
+    pub fn frame_start(&self, index: u64) -> i64 {
+        index as i64 * self.hop as i64 - self.padding() as i64
+    }
+
+    /// Reserves output space so that chunks of up to `max_chunk` samples (and
+    /// [`OverlapBuffer::finish`]) do not allocate.
+    pub fn reserve(&mut self, max_chunk: usize) {
+        let needed = max_chunk.max(self.latency());
+        self.output
+            .reserve(needed.saturating_sub(self.output.len()));
+    }
+
+    /// Discards all buffered audio and starts a new stream.
+    pub fn reset(&mut self) {
+        let padding = self.padding();
+        self.input.fill(0.0);
+        self.filled = padding;
+        self.accumulator.fill(0.0);
+        self.ready.clear();
+        self.skip = padding;
+        self.delay = self.latency();
+        self.frames = 0;
+        self.pushed = 0;
+    }
+
+    /// Appends `chunk`, processes every frame it completes and returns
+    /// `chunk.len()` output samples.
+    ///
+    /// `process` receives the frame index and the windowed frame, which it may
+    /// modify in place. Frames are processed in order; an error aborts the push
+    /// and leaves the stream in an unspecified state until [`OverlapBuffer::reset`].
+    pub fn push<F, E>(&mut self, chunk: &[f32], mut process: F) -> Result<&[f32], E>
+    where
+        F: FnMut(u64, &mut [f32]) -> Result<(), E>,
+    {
+        self.output.clear();
+        let mut remaining = chunk;
+        while !remaining.is_empty() {
+            let take = remaining.len().min(self.frame_len() - self.filled);
+            self.input[self.filled..self.filled + take].copy_from_slice(&remaining[..take]);
+            self.filled += take;
+            remaining = &remaining[take..];
+            if self.filled == self.frame_len() {
+                self.process_frame(&mut process)?;
+            }
+            self.emit(take);
+        }
+        self.pushed += chunk.len() as u64;
+        Ok(&self.output)
+    }
+
+    /// Ends the stream, returning the final [`OverlapBuffer::latency`] samples.
+    ///
+    /// The input is padded with zeros until every pushed sample has been
+    /// covered by all of its frames. The buffer is reset afterwards and can
+    /// start a new stream.
+    pub fn finish<F, E>(&mut self, mut process: F) -> Result<&[f32], E>
+    where
+        F: FnMut(u64, &mut [f32]) -> Result<(), E>,
+    {
+        self.output.clear();
+        // Stream samples before this index have received every frame.
+        while self.frame_start(self.frames) < self.pushed as i64 {
+            self.input[self.filled..].fill(0.0);
+            self.filled = self.frame_len();
+            self.process_frame(&mut process)?;
+        }
+        self.emit(self.latency());
+        self.reset();
+        Ok(&self.output)
+    }
+
+    fn padding(&self) -> usize {
+        self.frame_len() - self.hop
+    }
+
+    fn process_frame<F, E>(&mut self, process: &mut F) -> Result<(), E>
+    where
+        F: FnMut(u64, &mut [f32]) -> Result<(), E>,
+    {
+        for ((frame, &sample), &w) in self.frame.iter_mut().zip(&self.input).zip(&self.window) {
+            *frame = sample * w;
+        }
+        process(self.frames, &mut self.frame)?;
+        for ((sum, &sample), &w) in self
+            .accumulator
+            .iter_mut()
+            .zip(&self.frame)
+            .zip(&self.window)
+        {
+            *sum += sample * w;
+        }
+
+        // No later frame reaches the first `hop` samples.
+        for &sum in &self.accumulator[..self.hop] {
+            if self.skip > 0 {
+                self.skip -= 1;
+            } else {
+                self.ready.push_back(sum * self.norm);
+            }
+        }
+        let frame_len = self.frame_len();
+        self.accumulator.copy_within(self.hop.., 0);
+        self.accumulator[frame_len - self.hop..].fill(0.0);
+        self.input.copy_within(self.hop.., 0);
+        self.filled = frame_len - self.hop;
+        self.frames += 1;
+        Ok(())
+    }
+
+    fn emit(&mut self, count: usize) {
+        for _ in 0..count {
+            let sample = if self.delay > 0 {
+                self.delay -= 1;
+                0.0
+            } else {
+                // The latency guarantees a finished sample is waiting.
+                self.ready.pop_front().unwrap_or(0.0)
+            };
+            self.output.push(sample);
+        }
+    }
+}
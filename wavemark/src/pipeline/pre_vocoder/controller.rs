@@ -1,6 +1,359 @@
-#![allow(dead_code)]
+//! Keyed watermarking of spectrogram frames ahead of the vocoder.
+//!
+//! Text-to-speech systems usually split synthesis into an acoustic model
+//! that predicts mel (or linear) spectrogram frames and a vocoder that turns
+//! them into audio. [`PreVocoderController`] marks the frames in between, so
+//! the vocoder needs no changes.
+//!
+//! Detection works on spectrogram frames too: [`PreVocoderController::soft_bits`]
+//! reads the payload back from marked frames. Nothing here recovers it from
+//! the vocoder's audio output. That would need frames recomputed from the
+//! audio with the acoustic model's own analysis settings, and how much of the
+//! mark a given vocoder preserves has not been measured.
+//!
+//! The scheme mirrors the audio-domain embedder. The key's PN seed, on a
+//! ChaCha20 stream of its own, assigns a `±1` chip to every bin of
+//! [`PreVocoderConfig::band`]. The band is split into contiguous carriers,
+//! and time into slots of [`PreVocoderConfig::frames_per_symbol`] frames.
+//! Slot `j`, carrier `c` carries payload bit `(j * carriers + c) mod n`, so
+//! the payload repeats cyclically from the first frame. Frames in the first
+//! half of a slot change each bin's magnitude by
+//!
+//! ```text
+//! strength_db * chip[k] * bit     dB,  bit ∈ {-1, +1}
+//! ```
+//!
+//! and frames in the second half apply the inverse. Stationary spectral
+//! structure then cancels when the two halves are differenced, as in
+//! [`PreVocoderController::soft_bits`].
+//!
+//! Chunks may hold any number of frames. Scheduling depends only on the
+//! absolute frame index kept in [`ChunkState`], so chunk boundaries do not
+//! affect the result.
+//!
+//! ```ignore
+//! use wavemark::pipeline::pre_vocoder::controller::{PreVocoderConfig, PreVocoderController};
+//!
+//! let mut controller =
+//!     PreVocoderController::new(&session, PreVocoderConfig::default(), &payload_bytes)?;
+//! for mel in acoustic_model.chunks() {
+//!     // `mel` is row-major: frames × 80 natural-log mel bands.
+//!     controller.process_chunk(&mut mel)?;
+//!     vocoder.push(&mel);
+//! }
+//! ```
 
-//! Placeholder controller for coordinating pre-vocoder watermarking.
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::fmt;
+use std::ops::Range;
 
-/// Coordinates chunk processing and payload scheduling.
-pub struct PreVocoderController;
+use crate::core::state::ChunkState;
+use crate::embed::params::EmbedParams;
+use crate::embed::spread_spectrum::bytes_to_bits;
+use crate::key::derivation::KeyContext;
+
+/// ChaCha20 stream reserved for spectrogram-domain chips.
+const CHIP_STREAM: u64 = 1;
+
+/// Value scale of the spectrogram frames an acoustic model produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpectrogramScale {
+    /// Linear magnitudes (or powers); the watermark scales values.
+    Magnitude,
+    /// Natural logarithm of the magnitude, as most neural vocoders expect.
+    NaturalLog,
+    /// Decibels; the watermark offsets values.
+    Decibel,
+}
+
+/// Configuration for watermarking spectrogram frames before vocoding.
+///
+/// Frames are rows of `bins` values, e.g. 80 mel bands. The band is split into
+/// `carriers` contiguous groups and time into symbol slots of
+/// `frames_per_symbol` frames; each carrier of each slot holds one payload
+/// bit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreVocoderConfig {
+    /// Values per spectrogram frame.
+    pub bins: usize,
+    /// Bins carrying the watermark.
+    pub band: Range<usize>,
+    /// Scale of the frame values.
+    pub scale: SpectrogramScale,
+    /// Magnitude change applied to each marked bin, in dB.
+    pub strength_db: f32,
+    /// Frames per payload symbol; must be even for bi-phase coding.
+    pub frames_per_symbol: usize,
+    /// Number of carriers the band is split into.
+    pub carriers: usize,
+}
+
+impl PreVocoderConfig {
+    /// Checks the configuration for internal consistency.
+    pub fn validate(&self) -> Result<(), PreVocoderError> {
+        if self.bins == 0 {
+            return Err(PreVocoderError::InvalidConfig("frames must have bins"));
+        }
+        if self.band.start >= self.band.end || self.band.end > self.bins {
+            return Err(PreVocoderError::InvalidConfig(
+                "band must be a non-empty range within the frame",
+            ));
+        }
+        if !(self.strength_db > 0.0 && self.strength_db <= EmbedParams::MAX_STRENGTH_DB) {
+            return Err(PreVocoderError::InvalidConfig(
+                "strength must lie in (0, 12] dB",
+            ));
+        }
+        if self.frames_per_symbol < 2 || !self.frames_per_symbol.is_multiple_of(2) {
+            return Err(PreVocoderError::InvalidConfig(
+                "frames per symbol must be even and at least 2",
+            ));
+        }
+        if self.carriers == 0 || self.carriers > self.band.len() {
+            return Err(PreVocoderError::InvalidConfig(
+                "carriers must be between 1 and the band width",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for PreVocoderConfig {
+    /// 80-band natural-log mel frames, marking bands 8..72 at 1 dB.
+    fn default() -> Self {
+        Self {
+            bins: 80,
+            band: 8..72,
+            scale: SpectrogramScale::NaturalLog,
+            strength_db: 1.0,
+            frames_per_symbol: 16,
+            carriers: 8,
+        }
+    }
+}
+
+/// Schedules payload symbols across spectrogram frames and marks them.
+pub struct PreVocoderController {
+    config: PreVocoderConfig,
+    /// Chip per bin of the frame; zero outside the band.
+    chips: Vec<f32>,
+    carriers: Vec<Range<usize>>,
+    bits: Vec<bool>,
+    state: ChunkState,
+}
+
+impl PreVocoderController {
+    /// Creates a controller embedding `payload` with chips keyed by `context`.
+    pub fn new(
+        context: &KeyContext,
+        config: PreVocoderConfig,
+        payload: &[u8],
+    ) -> Result<Self, PreVocoderError> {
+        config.validate()?;
+        if payload.is_empty() {
+            return Err(PreVocoderError::EmptyPayload);
+        }
+
+        let mut rng = ChaCha20Rng::from_seed(*context.pn_seed().as_bytes());
+        rng.set_stream(CHIP_STREAM);
+        let mut chips = vec![0.0; config.bins];
+        let mut word = 0u32;
+        for (index, chip) in chips[config.band.clone()].iter_mut().enumerate() {
+            if index % 32 == 0 {
+                word = rng.next_u32();
+            }
+            *chip = if word & 1 == 1 { 1.0 } else { -1.0 };
+            word >>= 1;
+        }
+
+        let band = &config.band;
+        let carriers = (0..config.carriers)
+            .map(|carrier| {
+                let start = band.start + carrier * band.len() / config.carriers;
+                let end = band.start + (carrier + 1) * band.len() / config.carriers;
+                start..end
+            })
+            .collect();
+
+        Ok(Self {
+            chips,
+            carriers,
+            bits: bytes_to_bits(payload),
+            state: ChunkState::new(),
+            config,
+        })
+    }
+
+    /// Continues a stream from `state`, e.g. one saved by another controller.
+    pub fn with_state(mut self, state: ChunkState) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// Returns the configuration.
+    pub fn config(&self) -> &PreVocoderConfig {
+        &self.config
+    }
+
+    /// Returns the position within the stream.
+    pub fn state(&self) -> &ChunkState {
+        &self.state
+    }
+
+    /// Restarts the payload schedule for a new utterance.
+    pub fn reset(&mut self) {
+        self.state = ChunkState::new();
+    }
+
+    /// Number of payload bits.
+    pub fn payload_bits(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// Frames needed to carry every payload bit once.
+    pub fn frames_per_payload(&self) -> usize {
+        self.bits.len().div_ceil(self.config.carriers) * self.config.frames_per_symbol
+    }
+
+    /// Marks a row-major chunk of spectrogram frames in place.
+    ///
+    /// Returns the number of frames in the chunk.
+    pub fn process_chunk(&mut self, frames: &mut [f32]) -> Result<usize, PreVocoderError> {
+        let count = self.frame_count(frames.len())?;
+        let step = match self.config.scale {
+            SpectrogramScale::Magnitude => 10f32.powf(self.config.strength_db / 20.0),
+            SpectrogramScale::NaturalLog => {
+                self.config.strength_db * std::f32::consts::LN_10 / 20.0
+            }
+            SpectrogramScale::Decibel => self.config.strength_db,
+        };
+
+        for (offset, frame) in frames.chunks_exact_mut(self.config.bins).enumerate() {
+            let index = self.state.frames + offset as u64;
+            for (carrier, bins) in self.carriers.iter().enumerate() {
+                let sign = self.sign(index, carrier);
+                for bin in bins.clone() {
+                    let direction = self.chips[bin] * sign;
+                    match self.config.scale {
+                        SpectrogramScale::Magnitude if direction > 0.0 => frame[bin] *= step,
+                        SpectrogramScale::Magnitude => frame[bin] /= step,
+                        _ => frame[bin] += direction * step,
+                    }
+                }
+            }
+        }
+
+        self.state.advance(count);
+        Ok(count)
+    }
+
+    /// Correlates frames starting at absolute frame `first_frame` with the
+    /// schedule, returning one soft value per payload bit (positive for `1`).
+    ///
+    /// Only whole symbol slots contribute, so both halves of every slot are
+    /// differenced.
+    pub fn soft_bits(&self, frames: &[f32], first_frame: u64) -> Result<Vec<f32>, PreVocoderError> {
+        let count = self.frame_count(frames.len())? as u64;
+        let per_symbol = self.config.frames_per_symbol as u64;
+        let first_slot = first_frame.div_ceil(per_symbol);
+        let end_slot = (first_frame + count) / per_symbol;
+
+        let mut soft = vec![0.0f32; self.bits.len()];
+        for slot in first_slot..end_slot {
+            for index in slot * per_symbol..(slot + 1) * per_symbol {
+                let offset = (index - first_frame) as usize * self.config.bins;
+                let frame = &frames[offset..offset + self.config.bins];
+                let polarity = self.polarity(index);
+                for (carrier, bins) in self.carriers.iter().enumerate() {
+                    let correlation: f32 = bins
+                        .clone()
+                        .map(|bin| self.chips[bin] * self.log_value(frame[bin]))
+                        .sum();
+                    soft[self.bit_index(slot, carrier)] += polarity * correlation;
+                }
+            }
+        }
+        Ok(soft)
+    }
+
+    fn frame_count(&self, len: usize) -> Result<usize, PreVocoderError> {
+        if !len.is_multiple_of(self.config.bins) {
+            return Err(PreVocoderError::FrameLengthMismatch {
+                bins: self.config.bins,
+                found: len,
+            });
+        }
+        Ok(len / self.config.bins)
+    }
+
+    fn bit_index(&self, slot: u64, carrier: usize) -> usize {
+        ((slot * self.config.carriers as u64 + carrier as u64) % self.bits.len() as u64) as usize
+    }
+
+    /// `+1` in the first half of a slot, `-1` in the second.
+    fn polarity(&self, index: u64) -> f32 {
+        let per_symbol = self.config.frames_per_symbol as u64;
+        if index % per_symbol < per_symbol / 2 {
+            1.0
+        } else {
+            -1.0
+        }
+    }
+
+    fn sign(&self, index: u64, carrier: usize) -> f32 {
+        let slot = index / self.config.frames_per_symbol as u64;
+        let polarity = self.polarity(index);
+        if self.bits[self.bit_index(slot, carrier)] {
+            polarity
+        } else {
+            -polarity
+        }
+    }
+
+    fn log_value(&self, value: f32) -> f32 {
+        match self.config.scale {
+            SpectrogramScale::Magnitude => value.max(f32::MIN_POSITIVE).ln(),
+            SpectrogramScale::NaturalLog | SpectrogramScale::Decibel => value,
+        }
+    }
+}
+
+impl fmt::Debug for PreVocoderController {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PreVocoderController")
+            .field("config", &self.config)
+            .field("state", &self.state)
+            .field("chips", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Errors raised while marking spectrogram frames.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PreVocoderError {
+    /// Configuration is inconsistent.
+    InvalidConfig(&'static str),
+    /// Payload contains no bytes.
+    EmptyPayload,
+    /// Chunk length is not a whole number of frames.
+    FrameLengthMismatch { bins: usize, found: usize },
+}
+
+impl fmt::Display for PreVocoderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreVocoderError::InvalidConfig(reason) => {
+                write!(f, "invalid pre-vocoder configuration: {}", reason)
+            }
+            PreVocoderError::EmptyPayload => write!(f, "payload must contain at least one byte"),
+            PreVocoderError::FrameLengthMismatch { bins, found } => write!(
+                f,
+                "chunk of {} values is not a whole number of {}-bin frames",
+                found, bins
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PreVocoderError {}
@@ -1,4 +1,4 @@
-//! Pre-vocoder pipeline placeholders.
+//! Watermarking ahead of the vocoder: chunk framing and spectrogram marking.
 
 pub mod buffer;
 pub mod controller;
@@ -0,0 +1,403 @@
+//! Deterministic signal degradations for robustness benchmarks.
+//!
+//! Each [`Attack`] models a transformation that marked audio commonly goes
+//! through between release and detection: noise, requantization, resampling,
+//! filtering, level changes, editing, room echo and mixing. Attacks that draw
+//! random numbers use a ChaCha20 generator seeded by the caller, so a given
+//! seed always produces the same degraded audio. [`Attack::Chain`] applies
+//! several attacks in order, each with a seed derived from the chain's.
+//!
+//! Levels are relative to the input: noise and mixed signals are scaled to a
+//! target signal-to-noise ratio measured against the power of the whole
+//! input. Filters are linear-phase windowed-sinc FIRs with their delay
+//! compensated, so filtering alone does not shift the audio in time.
+//!
+//! ```ignore
+//! use wavemark::robustness::attack::Attack;
+//!
+//! let attack = Attack::Chain(vec![
+//!     Attack::LowPass { cutoff_hz: 4_000.0 },
+//!     Attack::WhiteNoise { snr_db: 20.0 },
+//! ]);
+//! let degraded = attack.apply(&marked, 44_100, 7)?;
+//! ```
+
+use std::f32::consts::PI;
+use std::fmt;
+
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+use crate::transforms::resample::Resampler;
+use crate::transforms::window::AnalysisWindow;
+
+/// Taps of the windowed-sinc filters; odd, so the delay is a whole sample.
+const FILTER_TAPS: usize = 255;
+/// Multiplier that decorrelates the seeds of chained attacks.
+const SEED_STRIDE: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// One degradation applied to mono audio.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attack {
+    /// Gaussian white noise at `snr_db` below the input power.
+    WhiteNoise { snr_db: f32 },
+    /// Pink (1/f) noise at `snr_db` below the input power.
+    PinkNoise { snr_db: f32 },
+    /// Rounding to a `bits`-bit integer grid without dither.
+    Requantize { bits: u32 },
+    /// Resampling to `rate` hertz and back to the input rate.
+    Resample { rate: u32 },
+    /// Removal of content above `cutoff_hz`.
+    LowPass { cutoff_hz: f32 },
+    /// Removal of content below `cutoff_hz`.
+    HighPass { cutoff_hz: f32 },
+    /// Level change by `db` decibels, saturating at full scale.
+    Gain { db: f32 },
+    /// Keeps `duration_secs` seconds starting `start_secs` into the input;
+    /// an infinite duration keeps the rest of the input.
+    Crop { start_secs: f32, duration_secs: f32 },
+    /// Delays the audio by `samples` samples, or advances it when negative,
+    /// keeping the length and filling with silence.
+    TimeShift { samples: isize },
+    /// Adds a copy delayed by `delay_secs` seconds and scaled by `gain`.
+    Echo { delay_secs: f32, gain: f32 },
+    /// Adds `other`, looped to the input length, at `snr_db` below the input
+    /// power.
+    Mix { other: Vec<f32>, snr_db: f32 },
+    /// Applies every attack in order; empty, it leaves the audio unchanged.
+    Chain(Vec<Attack>),
+}
+
+impl Attack {
+    /// Checks the attack's parameters against audio at `sample_rate`.
+    pub fn validate(&self, sample_rate: u32) -> Result<(), AttackError> {
+        let nyquist = sample_rate as f32 / 2.0;
+        match self {
+            Attack::WhiteNoise { snr_db }
+            | Attack::PinkNoise { snr_db }
+            | Attack::Mix { snr_db, .. }
+                if !snr_db.is_finite() =>
+            {
+                Err(AttackError::InvalidParameter("snr must be finite"))
+            }
+            Attack::Mix { other, .. } if other.is_empty() => Err(AttackError::InvalidParameter(
+                "mixed signal must not be empty",
+            )),
+            Attack::Requantize { bits } if !(2..=24).contains(bits) => Err(
+                AttackError::InvalidParameter("requantization must use 2 to 24 bits"),
+            ),
+            Attack::Resample { rate } if *rate == 0 => Err(AttackError::InvalidParameter(
+                "resampling rate must be positive",
+            )),
+            Attack::LowPass { cutoff_hz } | Attack::HighPass { cutoff_hz }
+                if !(*cutoff_hz > 0.0 && *cutoff_hz < nyquist) =>
+            {
+                Err(AttackError::InvalidParameter(
+                    "cutoff must lie between 0 Hz and Nyquist",
+                ))
+            }
+            Attack::Gain { db } if !db.is_finite() => {
+                Err(AttackError::InvalidParameter("gain must be finite"))
+            }
+            Attack::Crop {
+                start_secs,
+                duration_secs,
+            } if !(*start_secs >= 0.0 && *duration_secs > 0.0) => Err(
+                AttackError::InvalidParameter("crop must start at or after 0 and be non-empty"),
+            ),
+            Attack::Echo { delay_secs, gain } if !(*delay_secs >= 0.0 && gain.is_finite()) => Err(
+                AttackError::InvalidParameter("echo delay must be non-negative and gain finite"),
+            ),
+            Attack::Chain(attacks) => attacks
+                .iter()
+                .try_for_each(|attack| attack.validate(sample_rate)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Samples by which the attack moves the input later in time at
+    /// `sample_rate`; negative when it cuts off or advances the start.
+    pub fn delay(&self, sample_rate: u32) -> isize {
+        match self {
+            Attack::Crop { start_secs, .. } => -((start_secs * sample_rate as f32) as isize),
+            Attack::TimeShift { samples } => *samples,
+            Attack::Chain(attacks) => attacks.iter().map(|attack| attack.delay(sample_rate)).sum(),
+            _ => 0,
+        }
+    }
+
+    /// Returns `samples` at `sample_rate` after the attack, drawing any
+    /// randomness from `seed`.
+    pub fn apply(
+        &self,
+        samples: &[f32],
+        sample_rate: u32,
+        seed: u64,
+    ) -> Result<Vec<f32>, AttackError> {
+        if sample_rate == 0 {
+            return Err(AttackError::InvalidParameter(
+                "sample rate must be positive",
+            ));
+        }
+        self.validate(sample_rate)?;
+        Ok(self.apply_valid(samples, sample_rate, seed))
+    }
+
+    fn apply_valid(&self, samples: &[f32], sample_rate: u32, seed: u64) -> Vec<f32> {
+        match self {
+            Attack::WhiteNoise { snr_db } => {
+                let mut rng = ChaCha20Rng::seed_from_u64(seed);
+                let noise: Vec<f32> = (0..samples.len()).map(|_| gaussian(&mut rng)).collect();
+                add_at_snr(samples, &noise, *snr_db)
+            }
+            Attack::PinkNoise { snr_db } => {
+                let mut rng = ChaCha20Rng::seed_from_u64(seed);
+                let noise = pink_noise(&mut rng, samples.len());
+                add_at_snr(samples, &noise, *snr_db)
+            }
+            Attack::Requantize { bits } => {
+                let levels = (1u32 << (bits - 1)) as f32;
+                samples
+                    .iter()
+                    .map(|&sample| ((sample * levels).round() / levels).clamp(-1.0, 1.0))
+                    .collect()
+            }
+            Attack::Resample { rate } => {
+                let there = Resampler::new(sample_rate, *rate).expect("rates are validated");
+                let back = Resampler::new(*rate, sample_rate).expect("rates are validated");
+                let mut restored = back.process(&there.process(samples));
+                restored.resize(samples.len(), 0.0);
+                restored
+            }
+            Attack::LowPass { cutoff_hz } => low_pass(samples, *cutoff_hz, sample_rate),
+            Attack::HighPass { cutoff_hz } => low_pass(samples, *cutoff_hz, sample_rate)
+                .iter()
+                .zip(samples)
+                .map(|(&low, &sample)| sample - low)
+                .collect(),
+            Attack::Gain { db } => {
+                let gain = 10f32.powf(db / 20.0);
+                samples
+                    .iter()
+                    .map(|&sample| (sample * gain).clamp(-1.0, 1.0))
+                    .collect()
+            }
+            Attack::Crop {
+                start_secs,
+                duration_secs,
+            } => {
+                let start = ((start_secs * sample_rate as f32) as usize).min(samples.len());
+                // Saturates for an infinite duration.
+                let len = (duration_secs * sample_rate as f32) as usize;
+                samples[start..samples.len().min(start.saturating_add(len))].to_vec()
+            }
+            Attack::TimeShift { samples: shift } => {
+                let mut shifted = vec![0.0; samples.len()];
+                let amount = shift.unsigned_abs().min(samples.len());
+                if *shift >= 0 {
+                    shifted[amount..].copy_from_slice(&samples[..samples.len() - amount]);
+                } else {
+                    shifted[..samples.len() - amount].copy_from_slice(&samples[amount..]);
+                }
+                shifted
+            }
+            Attack::Echo { delay_secs, gain } => {
+                let delay = (delay_secs * sample_rate as f32).round() as usize;
+                let mut echoed = samples.to_vec();
+                for (index, sample) in echoed.iter_mut().enumerate().skip(delay) {
+                    *sample += gain * samples[index - delay];
+                }
+                echoed
+            }
+            Attack::Mix { other, snr_db } => {
+                let looped: Vec<f32> = other.iter().copied().cycle().take(samples.len()).collect();
+                add_at_snr(samples, &looped, *snr_db)
+            }
+            Attack::Chain(attacks) => {
+                let mut current = samples.to_vec();
+                for (index, attack) in attacks.iter().enumerate() {
+                    let step_seed = seed ^ (index as u64 + 1).wrapping_mul(SEED_STRIDE);
+                    current = attack.apply_valid(&current, sample_rate, step_seed);
+                }
+                current
+            }
+        }
+    }
+
+    /// Standard sweep of single attacks for audio at `sample_rate`.
+    ///
+    /// Covers every non-composite attack at a moderate level. Mixing is left
+    /// out, since it needs a signal from the caller.
+    pub fn standard_suite(sample_rate: u32) -> Vec<Attack> {
+        let nyquist = sample_rate as f32 / 2.0;
+        vec![
+            Attack::WhiteNoise { snr_db: 20.0 },
+            Attack::PinkNoise { snr_db: 20.0 },
+            Attack::Requantize { bits: 8 },
+            Attack::Resample {
+                rate: sample_rate / 2,
+            },
+            Attack::LowPass {
+                cutoff_hz: 0.5 * nyquist,
+            },
+            Attack::HighPass { cutoff_hz: 300.0 },
+            Attack::Gain { db: -6.0 },
+            Attack::Crop {
+                start_secs: 1.0,
+                duration_secs: f32::INFINITY,
+            },
+            Attack::TimeShift {
+                samples: sample_rate as isize / 10 + 7,
+            },
+            Attack::Echo {
+                delay_secs: 0.05,
+                gain: 0.3,
+            },
+        ]
+    }
+}
+
+impl fmt::Display for Attack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Attack::WhiteNoise { snr_db } => write!(f, "white noise {} dB", snr_db),
+            Attack::PinkNoise { snr_db } => write!(f, "pink noise {} dB", snr_db),
+            Attack::Requantize { bits } => write!(f, "requantize {} bit", bits),
+            Attack::Resample { rate } => write!(f, "resample {} Hz", rate),
+            Attack::LowPass { cutoff_hz } => write!(f, "low-pass {} Hz", cutoff_hz),
+            Attack::HighPass { cutoff_hz } => write!(f, "high-pass {} Hz", cutoff_hz),
+            Attack::Gain { db } => write!(f, "gain {} dB", db),
+            Attack::Crop {
+                start_secs,
+                duration_secs,
+            } if duration_secs.is_infinite() => write!(f, "crop from {} s", start_secs),
+            Attack::Crop {
+                start_secs,
+                duration_secs,
+            } => write!(f, "crop {} s from {} s", duration_secs, start_secs),
+            Attack::TimeShift { samples } => write!(f, "shift {} samples", samples),
+            Attack::Echo { delay_secs, gain } => {
+                write!(f, "echo {} ms x{}", delay_secs * 1000.0, gain)
+            }
+            Attack::Mix { snr_db, .. } => write!(f, "mix {} dB", snr_db),
+            Attack::Chain(attacks) if attacks.is_empty() => write!(f, "none"),
+            Attack::Chain(attacks) => {
+                for (index, attack) in attacks.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, " + ")?;
+                    }
+                    write!(f, "{}", attack)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Standard normal sample by the Box-Muller transform.
+fn gaussian(rng: &mut ChaCha20Rng) -> f32 {
+    let uniform = |rng: &mut ChaCha20Rng| (rng.next_u32() as f64 + 1.0) / (u32::MAX as f64 + 2.0);
+    let (u, v) = (uniform(rng), uniform(rng));
+    ((-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()) as f32
+}
+
+/// Pink noise from white noise with Paul Kellet's refined filter.
+fn pink_noise(rng: &mut ChaCha20Rng, len: usize) -> Vec<f32> {
+    let mut state = [0.0f32; 7];
+    (0..len)
+        .map(|_| {
+            let white = gaussian(rng);
+            state[0] = 0.99886 * state[0] + white * 0.055_517_9;
+            state[1] = 0.99332 * state[1] + white * 0.075_075_9;
+            state[2] = 0.969 * state[2] + white * 0.153_852;
+            state[3] = 0.86650 * state[3] + white * 0.310_485_6;
+            state[4] = 0.55 * state[4] + white * 0.532_952_2;
+            state[5] = -0.7616 * state[5] - white * 0.016_898;
+            let pink = state[..6].iter().sum::<f32>() + state[6] + white * 0.5362;
+            state[6] = white * 0.115_926;
+            pink
+        })
+        .collect()
+}
+
+fn mean_power(samples: &[f32]) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    samples.iter().map(|&s| s as f64 * s as f64).sum::<f64>() / samples.len() as f64
+}
+
+/// Adds `other` scaled to `snr_db` below the power of `samples`.
+fn add_at_snr(samples: &[f32], other: &[f32], snr_db: f32) -> Vec<f32> {
+    let (signal, interference) = (mean_power(samples), mean_power(other));
+    let scale = if interference > 0.0 {
+        (signal / interference / 10f64.powf(snr_db as f64 / 10.0)).sqrt() as f32
+    } else {
+        0.0
+    };
+    samples
+        .iter()
+        .zip(other)
+        .map(|(&sample, &noise)| sample + scale * noise)
+        .collect()
+}
+
+/// Zero-phase windowed-sinc low-pass filter with unit gain at DC.
+fn low_pass(samples: &[f32], cutoff_hz: f32, sample_rate: u32) -> Vec<f32> {
+    // A periodic window one longer than the filter is symmetric about its
+    // middle tap once the leading zero is dropped.
+    let window =
+        AnalysisWindow::blackman_harris(FILTER_TAPS + 1).expect("filter length is non-zero");
+    let center = FILTER_TAPS / 2;
+    let normalized = cutoff_hz / sample_rate as f32;
+    let mut taps: Vec<f32> = window.coefficients()[1..]
+        .iter()
+        .enumerate()
+        .map(|(index, &weight)| {
+            let offset = index as f32 - center as f32;
+            let sinc = if offset == 0.0 {
+                2.0 * normalized
+            } else {
+                (2.0 * PI * normalized * offset).sin() / (PI * offset)
+            };
+            sinc * weight as f32
+        })
+        .collect();
+    let dc: f32 = taps.iter().sum();
+    for tap in &mut taps {
+        *tap /= dc;
+    }
+
+    (0..samples.len())
+        .map(|index| {
+            taps.iter()
+                .enumerate()
+                .filter_map(|(tap, &weight)| {
+                    (index + tap)
+                        .checked_sub(center)
+                        .and_then(|source| samples.get(source))
+                        .map(|&sample| sample * weight)
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// Errors raised by invalid attack parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttackError {
+    /// An attack parameter is out of range.
+    InvalidParameter(&'static str),
+}
+
+impl fmt::Display for AttackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttackError::InvalidParameter(reason) => {
+                write!(f, "invalid attack parameter: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AttackError {}
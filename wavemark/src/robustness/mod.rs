@@ -0,0 +1,4 @@
+//! Attack simulation and robustness benchmarking.
+
+pub mod attack;
+pub mod runner;
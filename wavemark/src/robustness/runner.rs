@@ -0,0 +1,295 @@
+//! Sweeps attacks over a corpus and tabulates detection results.
+//!
+//! [`RobustnessRunner`] embeds the payload of a [`WatermarkBuilder`] into
+//! every clip of a corpus, applies each configured [`Attack`] and runs the
+//! matching detector on the result. The [`RobustnessReport`] holds one
+//! [`AttackOutcome`] per attack and clip:
+//!
+//! - **Detected.** The detector reported the payload as present.
+//! - **Recovered.** The decoded frame equals the embedded one.
+//! - **Bit error rate.** Fraction of channel bits from the payload mapper
+//!   whose hard decisions differ from the embedded ones, before error
+//!   correction. Decisions are read where the embedder placed the payload,
+//!   shifted by the attack's [delay](Attack::delay), so the rate is measured
+//!   whether or not the frame decoded. Audio too short to analyse counts as
+//!   `0.5`, the error rate of guessing.
+//!
+//! Attack seeds are derived from the runner's seed, the attack and the clip,
+//! so repeated runs over the same corpus give identical reports. Comparing
+//! reports before and after a change to [`EmbedParams`] shows whether it
+//! helped or hurt robustness.
+//!
+//! ```ignore
+//! use wavemark::robustness::attack::Attack;
+//! use wavemark::robustness::runner::RobustnessRunner;
+//!
+//! let report = RobustnessRunner::new(builder)
+//!     .attacks(Attack::standard_suite(44_100))
+//!     .run(&corpus)?;
+//! println!("{}", report);
+//! ```
+//!
+//! [`EmbedParams`]: crate::embed::params::EmbedParams
+
+use std::fmt;
+
+use crate::api::builder::{WatermarkBuilder, WatermarkError};
+use crate::detect::correlator::DetectError;
+use crate::embed::spread_spectrum::EmbedError;
+use crate::robustness::attack::{Attack, AttackError};
+
+/// Seed used when none is configured.
+const DEFAULT_SEED: u64 = 0x5eed;
+
+/// Runs attack sweeps for one watermark configuration.
+#[derive(Debug, Clone)]
+pub struct RobustnessRunner {
+    builder: WatermarkBuilder,
+    attacks: Vec<Attack>,
+    seed: u64,
+}
+
+impl RobustnessRunner {
+    /// Creates a runner that embeds and detects with `builder`.
+    pub fn new(builder: WatermarkBuilder) -> Self {
+        Self {
+            builder,
+            attacks: Vec::new(),
+            seed: DEFAULT_SEED,
+        }
+    }
+
+    /// Adds `attack` to the sweep.
+    pub fn attack(mut self, attack: Attack) -> Self {
+        self.attacks.push(attack);
+        self
+    }
+
+    /// Adds every attack of `attacks` to the sweep.
+    pub fn attacks(mut self, attacks: impl IntoIterator<Item = Attack>) -> Self {
+        self.attacks.extend(attacks);
+        self
+    }
+
+    /// Derives all attack randomness from `seed`.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Attacks in sweep order.
+    pub fn attack_list(&self) -> &[Attack] {
+        &self.attacks
+    }
+
+    /// Marks every clip of `corpus`, applies every attack and detects.
+    ///
+    /// Clips are mono samples at the builder's sample rate. Attacked audio too
+    /// short to analyse counts as not detected.
+    pub fn run(&self, corpus: &[Vec<f32>]) -> Result<RobustnessReport, RobustnessError> {
+        if corpus.is_empty() {
+            return Err(RobustnessError::EmptyCorpus);
+        }
+        let (mut embedder, mut detector) = self.builder.clone().build()?;
+        let sample_rate = embedder.sample_rate();
+        for attack in &self.attacks {
+            attack.validate(sample_rate)?;
+        }
+
+        let mapped = embedder
+            .params()
+            .mapper()
+            .map(embedder.payload_bytes())
+            .map_err(EmbedError::from)
+            .map_err(WatermarkError::from)?;
+
+        let mut outcomes = vec![AttackOutcome::MISSED; self.attacks.len() * corpus.len()];
+        for (clip, host) in corpus.iter().enumerate() {
+            let mut marked = host.clone();
+            embedder.embed(&mut marked)?;
+
+            for (index, attack) in self.attacks.iter().enumerate() {
+                let seed = self.seed ^ ((clip as u64) << 32 | index as u64);
+                let attacked = attack.apply(&marked, sample_rate, seed)?;
+                let report = match detector.detect(&attacked) {
+                    Ok(report) => report,
+                    Err(WatermarkError::Detect(DetectError::InsufficientAudio { .. })) => continue,
+                    Err(err) => return Err(err.into()),
+                };
+                let soft_bits =
+                    detector.soft_bits_at(&attacked, attack.delay(sample_rate), mapped.len())?;
+                outcomes[index * corpus.len() + clip] = AttackOutcome {
+                    detected: report.is_present(),
+                    recovered: report.frame.as_ref() == Some(embedder.frame()),
+                    bit_error_rate: bit_error_rate(mapped.bits(), &soft_bits),
+                    false_positive_probability: report.confidence.false_positive_probability(),
+                };
+            }
+        }
+
+        Ok(RobustnessReport {
+            attacks: self.attacks.iter().map(Attack::to_string).collect(),
+            clips: corpus.len(),
+            outcomes,
+        })
+    }
+}
+
+/// Fraction of soft bits whose sign disagrees with `expected`; bits without
+/// any evidence count as half an error.
+fn bit_error_rate(expected: &[bool], soft_bits: &[f32]) -> f32 {
+    if expected.is_empty() {
+        return 0.5;
+    }
+    let errors: f32 = expected
+        .iter()
+        .zip(soft_bits)
+        .map(|(&bit, &soft)| match soft {
+            0.0 => 0.5,
+            _ if (soft > 0.0) == bit => 0.0,
+            _ => 1.0,
+        })
+        .sum();
+    errors / expected.len() as f32
+}
+
+/// Detection result for one attacked clip.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AttackOutcome {
+    /// The detector reported the payload as present.
+    pub detected: bool,
+    /// The decoded frame equals the embedded frame.
+    pub recovered: bool,
+    /// Raw bit error rate of the channel bits at the embedded position.
+    pub bit_error_rate: f32,
+    /// False-positive probability of the best match.
+    pub false_positive_probability: f64,
+}
+
+impl AttackOutcome {
+    /// Outcome of a clip that could not be analysed.
+    const MISSED: Self = Self {
+        detected: false,
+        recovered: false,
+        bit_error_rate: 0.5,
+        false_positive_probability: 1.0,
+    };
+}
+
+/// Matrix of outcomes, one row per attack and one column per clip.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RobustnessReport {
+    attacks: Vec<String>,
+    clips: usize,
+    outcomes: Vec<AttackOutcome>,
+}
+
+impl RobustnessReport {
+    /// Names of the attacks, in row order.
+    pub fn attacks(&self) -> &[String] {
+        &self.attacks
+    }
+
+    /// Number of clips, the number of columns.
+    pub fn clip_count(&self) -> usize {
+        self.clips
+    }
+
+    /// Outcome of attack `attack` on clip `clip`.
+    pub fn outcome(&self, attack: usize, clip: usize) -> &AttackOutcome {
+        assert!(clip < self.clips, "clip index out of range");
+        &self.outcomes[attack * self.clips + clip]
+    }
+
+    /// Outcomes of attack `attack` on every clip.
+    pub fn row(&self, attack: usize) -> &[AttackOutcome] {
+        &self.outcomes[attack * self.clips..(attack + 1) * self.clips]
+    }
+
+    /// Fraction of clips in which the payload was detected after `attack`.
+    pub fn detection_rate(&self, attack: usize) -> f32 {
+        let row = self.row(attack);
+        row.iter().filter(|outcome| outcome.detected).count() as f32 / row.len() as f32
+    }
+
+    /// Fraction of clips whose frame was recovered exactly after `attack`.
+    pub fn recovery_rate(&self, attack: usize) -> f32 {
+        let row = self.row(attack);
+        row.iter().filter(|outcome| outcome.recovered).count() as f32 / row.len() as f32
+    }
+
+    /// Mean bit error rate over all clips after `attack`.
+    pub fn mean_bit_error_rate(&self, attack: usize) -> f32 {
+        let row = self.row(attack);
+        row.iter()
+            .map(|outcome| outcome.bit_error_rate)
+            .sum::<f32>()
+            / row.len() as f32
+    }
+}
+
+impl fmt::Display for RobustnessReport {
+    /// One line per attack with detection rate, recovery rate and mean BER.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .attacks
+            .iter()
+            .map(String::len)
+            .max()
+            .unwrap_or(0)
+            .max(6);
+        writeln!(
+            f,
+            "{:<width$}  detected  recovered  mean BER",
+            "attack",
+            width = width
+        )?;
+        for (index, name) in self.attacks.iter().enumerate() {
+            writeln!(
+                f,
+                "{:<width$}  {:>7.1}%  {:>8.1}%  {:>8.4}",
+                name,
+                100.0 * self.detection_rate(index),
+                100.0 * self.recovery_rate(index),
+                self.mean_bit_error_rate(index),
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Errors raised by a robustness run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RobustnessError {
+    /// The corpus holds no clips.
+    EmptyCorpus,
+    /// An attack has invalid parameters.
+    Attack(AttackError),
+    /// Building, embedding or detecting failed.
+    Watermark(WatermarkError),
+}
+
+impl fmt::Display for RobustnessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RobustnessError::EmptyCorpus => write!(f, "robustness corpus is empty"),
+            RobustnessError::Attack(err) => write!(f, "attack error: {}", err),
+            RobustnessError::Watermark(err) => write!(f, "watermark error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for RobustnessError {}
+
+impl From<AttackError> for RobustnessError {
+    fn from(err: AttackError) -> Self {
+        RobustnessError::Attack(err)
+    }
+}
+
+impl From<WatermarkError> for RobustnessError {
+    fn from(err: WatermarkError) -> Self {
+        RobustnessError::Watermark(err)
+    }
+}
@@ -1,3 +1,3 @@
-//! Streaming integration placeholders.
+//! Real-time streaming integration.
 
 pub mod session;
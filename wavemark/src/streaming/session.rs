@@ -1,6 +1,204 @@
-#![allow(dead_code)]
+//! Real-time watermarking of packetized audio.
+//!
+//! [`StreamingSession`] applies the same spread-spectrum watermark as
+//! [`SpreadSpectrumEmbedder`] to audio that arrives in small packets, such as
+//! the 20 ms frames of a voice call. Each push returns exactly as many samples
+//! as it was given. The packets are framed with an [`OverlapBuffer`], and
+//! each STFT frame is marked according to its absolute position in the
+//! stream. Over a whole stream the output therefore equals the batch
+//! embedder's output for the concatenated input, delayed by
+//! [`StreamingSession::latency`] samples.
+//!
+//! # Latency
+//!
+//! The session delays audio by exactly `frame_len - 1` samples, independent of
+//! packet sizes: 1023 samples (23.2 ms) with the default 1024-sample frames at
+//! 44.1 kHz. The first `latency()` output samples of a stream are silence.
+//! [`StreamingSession::finish`] flushes the last `latency()` samples.
+//!
+//! # Continuous Payload
+//!
+//! The payload is cycled from the first sample for as long as the stream
+//! runs, so detection does not depend on where a recording starts. It is
+//! framed by [`EmbedParams::mapper`] exactly as in the batch embedder. Every
+//! stretch of [`StreamingSession::cycle_len`] samples carries every channel
+//! bit once. The correlator searches slot alignment and bit rotation, so any
+//! excerpt a few cycles long can be detected.
+//!
+//! # Real-Time Use
+//!
+//! All buffers and FFT plans are allocated up front. Pushing packets no longer
+//! than [`StreamingSession::max_packet`] (20 ms by default) does not allocate.
+//! Psychoacoustic masking needs look-ahead across frames and is not applied;
+//! the fixed [`EmbedParams::strength_db`] is used instead.
+//!
+//! ```ignore
+//! use wavemark::streaming::session::StreamingSession;
+//!
+//! let mut session = StreamingSession::new(&session_key, params, &payload_bytes)?;
+//! while let Some(packet) = call.next_packet() {
+//!     outbound.send(session.push(&packet));
+//! }
+//! outbound.send(session.finish());
+//! ```
+//!
+//! [`SpreadSpectrumEmbedder`]: crate::embed::spread_spectrum::SpreadSpectrumEmbedder
 
-//! Placeholder streaming session abstractions.
+use std::fmt;
 
-/// Placeholder handle wrapping a streaming watermark session.
-pub struct StreamingSession;
+use crate::embed::params::EmbedParams;
+use crate::embed::spread_spectrum::{EmbedError, SpreadingCode};
+use crate::key::derivation::KeyContext;
+use crate::pipeline::pre_vocoder::buffer::OverlapBuffer;
+use crate::transforms::fft::{Complex, FftBackend, FftError};
+use crate::transforms::window::AnalysisWindow;
+
+/// Default packet duration reserved for, in milliseconds.
+const DEFAULT_PACKET_MS: u32 = 20;
+
+/// Packet-by-packet watermark embedder with fixed latency.
+pub struct StreamingSession {
+    marker: FrameMarker,
+    buffer: OverlapBuffer,
+    max_packet: usize,
+}
+
+impl StreamingSession {
+    /// Creates a session embedding `payload` with the PN seed of `context`.
+    pub fn new(
+        context: &KeyContext,
+        params: EmbedParams,
+        payload: &[u8],
+    ) -> Result<Self, EmbedError> {
+        if payload.is_empty() {
+            return Err(EmbedError::EmptyPayload);
+        }
+        let code = SpreadingCode::new(&context.pn_seed(), &params);
+        let buffer =
+            OverlapBuffer::new(AnalysisWindow::sqrt_hann(params.frame_len())?, params.hop())?;
+        let mut fft = FftBackend::new();
+        fft.prepare(params.frame_len())?;
+        let max_packet = (params.sample_rate() * DEFAULT_PACKET_MS / 1000) as usize;
+        let bits = params.mapper().map(payload)?.bits().to_vec();
+
+        let marker = FrameMarker {
+            boost: 10f32.powf(params.strength_db() / 20.0),
+            spectrum: vec![
+                Complex::new(0.0, 0.0);
+                FftBackend::<f32>::spectrum_len(params.frame_len())
+            ],
+            bits,
+            params,
+            code,
+            fft,
+        };
+        let mut session = Self {
+            marker,
+            buffer,
+            max_packet: 0,
+        };
+        session.reserve(max_packet);
+        Ok(session)
+    }
+
+    /// Reserves space so that packets of up to `samples` samples do not allocate.
+    pub fn with_max_packet(mut self, samples: usize) -> Self {
+        self.reserve(samples);
+        self
+    }
+
+    /// Returns the embedding parameters.
+    pub fn params(&self) -> &EmbedParams {
+        &self.marker.params
+    }
+
+    /// Longest packet that is processed without allocating.
+    pub fn max_packet(&self) -> usize {
+        self.max_packet
+    }
+
+    /// Delay between input and output in samples.
+    pub fn latency(&self) -> usize {
+        self.buffer.latency()
+    }
+
+    /// Delay between input and output in seconds.
+    pub fn latency_secs(&self) -> f32 {
+        self.latency() as f32 / self.params().sample_rate() as f32
+    }
+
+    /// Samples needed to carry every payload bit once.
+    pub fn cycle_len(&self) -> usize {
+        let params = self.params();
+        params.cycle_slots(self.marker.bits.len()) * params.symbol_len()
+    }
+
+    /// Samples pushed since the start of the stream.
+    pub fn samples_pushed(&self) -> u64 {
+        self.buffer.samples_pushed()
+    }
+
+    /// Watermarks one packet, returning the same number of delayed samples.
+    pub fn push(&mut self, packet: &[f32]) -> &[f32] {
+        let marker = &mut self.marker;
+        self.buffer
+            .push(packet, |index, frame| marker.mark(index, frame))
+            .expect("FFT plan matches the frame length")
+    }
+
+    /// Ends the stream, returning the final [`StreamingSession::latency`] samples.
+    ///
+    /// The session is ready for a new stream afterwards; the payload schedule
+    /// restarts at its first bit.
+    pub fn finish(&mut self) -> &[f32] {
+        let marker = &mut self.marker;
+        self.buffer
+            .finish(|index, frame| marker.mark(index, frame))
+            .expect("FFT plan matches the frame length")
+    }
+
+    /// Discards buffered audio and restarts the payload schedule.
+    pub fn reset(&mut self) {
+        self.buffer.reset();
+    }
+
+    fn reserve(&mut self, samples: usize) {
+        self.buffer.reserve(samples);
+        self.max_packet = self.max_packet.max(samples);
+    }
+}
+
+/// Marks windowed frames by their index in the stream.
+struct FrameMarker {
+    params: EmbedParams,
+    code: SpreadingCode,
+    bits: Vec<bool>,
+    boost: f32,
+    fft: FftBackend<f32>,
+    spectrum: Vec<Complex<f32>>,
+}
+
+impl FrameMarker {
+    fn mark(&mut self, index: u64, frame: &mut [f32]) -> Result<(), FftError> {
+        // Same framing as `FftBackend::stft`.
+        let padding = self.params.frame_len() - self.params.hop();
+        let start = index as isize * self.params.hop() as isize - padding as isize;
+        self.fft.forward(frame, &mut self.spectrum)?;
+        let boost = self.boost;
+        self.code
+            .modulate(&self.params, &self.bits, start, &mut self.spectrum, |_| {
+                boost
+            });
+        self.fft.inverse(&self.spectrum, frame)
+    }
+}
+
+impl fmt::Debug for StreamingSession {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamingSession")
+            .field("params", self.params())
+            .field("latency", &self.latency())
+            .field("samples_pushed", &self.samples_pushed())
+            .finish()
+    }
+}
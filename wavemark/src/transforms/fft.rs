@@ -1,6 +1,400 @@
-#![allow(dead_code)]
+//! Real FFT backend and short-time Fourier transform.
+//!
+//! [`FftBackend`] wraps `realfft` and caches one forward/inverse plan pair per
+//! transform size, together with the scratch buffers that plan needs. Once a
+//! size has been planned, [`FftBackend::forward`] and [`FftBackend::inverse`]
+//! do not allocate, which keeps them usable from real-time paths. The backend
+//! is generic over the sample type and works on both `f32` and `f64` buffers.
+//!
+//! The inverse transform is normalized by `1 / N`, so `inverse(forward(x)) == x`.
+//!
+//! [`FftBackend::cross_correlate`] slides a template along a signal via the
+//! correlation theorem, which the detector uses to locate sync patterns.
+//!
+//! # STFT Framing
+//!
+//! [`FftBackend::stft`] pads the signal with `frame_len - hop` leading zeros so
+//! that every input sample is covered by the same number of frames. Frame `m`
+//! therefore starts at signal index `m * hop - (frame_len - hop)`, and
+//! [`FftBackend::istft`] undoes that padding. The inverse uses least-squares
+//! overlap-add with the analysis window as the synthesis window, which
+//! reconstructs an unmodified spectrogram exactly.
+//!
+//! ```ignore
+//! use wavemark::transforms::fft::FftBackend;
+//! use wavemark::transforms::window::AnalysisWindow;
+//!
+//! let mut fft = FftBackend::<f32>::new();
+//! let window = AnalysisWindow::hann(1024)?;
+//! let mut spectrogram = fft.stft(&samples, &window, 512)?;
+//! for frame in spectrogram.frames_mut() {
+//!     // modify bins...
+//! }
+//! let output = fft.istft(&spectrogram, &window)?;
+//! assert_eq!(output.len(), samples.len());
+//! ```
 
-//! Placeholder FFT wrapper and related utilities.
+use crate::transforms::window::AnalysisWindow;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
 
-/// Placeholder type wrapping external FFT routines.
-pub struct FftBackend;
+pub use realfft::num_complex::Complex;
+pub use realfft::FftNum;
+
+/// Cached forward/inverse plans and the buffers they operate on.
+struct FftPlan<T: FftNum> {
+    forward: Arc<dyn RealToComplex<T>>,
+    inverse: Arc<dyn ComplexToReal<T>>,
+    real_buffer: Vec<T>,
+    complex_buffer: Vec<Complex<T>>,
+    scratch: Vec<Complex<T>>,
+}
+
+impl<T: FftNum> FftPlan<T> {
+    fn new(planner: &mut RealFftPlanner<T>, len: usize) -> Self {
+        let forward = planner.plan_fft_forward(len);
+        let inverse = planner.plan_fft_inverse(len);
+        let scratch_len = forward.get_scratch_len().max(inverse.get_scratch_len());
+        Self {
+            real_buffer: forward.make_input_vec(),
+            complex_buffer: inverse.make_input_vec(),
+            scratch: vec![Complex::new(T::zero(), T::zero()); scratch_len],
+            forward,
+            inverse,
+        }
+    }
+}
+
+/// Reusable real FFT with per-size plan caching.
+pub struct FftBackend<T: FftNum = f32> {
+    planner: RealFftPlanner<T>,
+    plans: HashMap<usize, FftPlan<T>>,
+}
+
+impl<T: FftNum> FftBackend<T> {
+    /// Creates a backend with an empty plan cache.
+    pub fn new() -> Self {
+        Self {
+            planner: RealFftPlanner::new(),
+            plans: HashMap::new(),
+        }
+    }
+
+    /// Number of complex bins produced for a real frame of `frame_len` samples.
+    pub fn spectrum_len(frame_len: usize) -> usize {
+        frame_len / 2 + 1
+    }
+
+    /// Plans a transform size ahead of time so later calls do not allocate.
+    pub fn prepare(&mut self, len: usize) -> Result<(), FftError> {
+        self.plan(len).map(|_| ())
+    }
+
+    /// Returns the transform sizes currently held in the plan cache.
+    pub fn cached_sizes(&self) -> Vec<usize> {
+        let mut sizes: Vec<usize> = self.plans.keys().copied().collect();
+        sizes.sort_unstable();
+        sizes
+    }
+
+    /// Forward real FFT of `input` into `spectrum` (`input.len() / 2 + 1` bins).
+    pub fn forward(&mut self, input: &[T], spectrum: &mut [Complex<T>]) -> Result<(), FftError> {
+        let len = input.len();
+        check_len(Self::spectrum_len(len), spectrum.len())?;
+        let plan = self.plan(len)?;
+        plan.real_buffer.copy_from_slice(input);
+        plan.forward
+            .process_with_scratch(&mut plan.real_buffer, spectrum, &mut plan.scratch)
+            .map_err(|err| FftError::Backend(err.to_string()))
+    }
+
+    /// Normalized inverse real FFT of `spectrum` into `output`.
+    ///
+    /// The imaginary parts of the DC bin (and the Nyquist bin for even sizes)
+    /// are ignored, since a real signal cannot carry them.
+    pub fn inverse(&mut self, spectrum: &[Complex<T>], output: &mut [T]) -> Result<(), FftError> {
+        let len = output.len();
+        check_len(Self::spectrum_len(len), spectrum.len())?;
+        let plan = self.plan(len)?;
+        plan.complex_buffer.copy_from_slice(spectrum);
+        plan.complex_buffer[0].im = T::zero();
+        if len.is_multiple_of(2) {
+            plan.complex_buffer[len / 2].im = T::zero();
+        }
+        plan.inverse
+            .process_with_scratch(&mut plan.complex_buffer, output, &mut plan.scratch)
+            .map_err(|err| FftError::Backend(err.to_string()))?;
+
+        let scale = T::one() / from_f64::<T>(len as f64);
+        for sample in output.iter_mut() {
+            *sample = *sample * scale;
+        }
+        Ok(())
+    }
+
+    /// Cross-correlates `template` against `signal` through the frequency domain.
+    ///
+    /// Returns `r[k] = Σ_j signal[k + j] * template[j]` for every lag `k` at
+    /// which the template lies entirely within the signal, i.e.
+    /// `signal.len() - template.len() + 1` values, or none when the template
+    /// is longer than the signal.
+    pub fn cross_correlate(&mut self, signal: &[T], template: &[T]) -> Result<Vec<T>, FftError> {
+        if template.is_empty() {
+            return Err(FftError::EmptyInput);
+        }
+        if signal.len() < template.len() {
+            return Ok(Vec::new());
+        }
+
+        // Zero padding to at least `signal.len()` keeps the valid lags free of
+        // circular wrap-around.
+        let len = signal.len().next_power_of_two();
+        let mut padded = vec![T::zero(); len];
+        let mut spectrum = vec![Complex::new(T::zero(), T::zero()); Self::spectrum_len(len)];
+        let mut reference = spectrum.clone();
+        padded[..signal.len()].copy_from_slice(signal);
+        self.forward(&padded, &mut spectrum)?;
+        padded.fill(T::zero());
+        padded[..template.len()].copy_from_slice(template);
+        self.forward(&padded, &mut reference)?;
+
+        for (bin, &reference) in spectrum.iter_mut().zip(&reference) {
+            *bin = *bin * reference.conj();
+        }
+        self.inverse(&spectrum, &mut padded)?;
+        padded.truncate(signal.len() - template.len() + 1);
+        Ok(padded)
+    }
+
+    /// Short-time Fourier transform of `signal` with the given window and hop size.
+    pub fn stft(
+        &mut self,
+        signal: &[T],
+        window: &AnalysisWindow,
+        hop: usize,
+    ) -> Result<Spectrogram<T>, FftError> {
+        let frame_len = window.len();
+        check_hop(hop, frame_len)?;
+        let coefficients = window_as::<T>(window);
+        let padding = frame_len - hop;
+        let frame_count = (signal.len() + padding).div_ceil(hop);
+
+        let mut frame = vec![T::zero(); frame_len];
+        let mut frames = Vec::with_capacity(frame_count);
+        for index in 0..frame_count {
+            let start = (index * hop) as isize - padding as isize;
+            for (offset, (slot, &weight)) in frame.iter_mut().zip(&coefficients).enumerate() {
+                let position = start + offset as isize;
+                *slot = if position >= 0 && (position as usize) < signal.len() {
+                    signal[position as usize] * weight
+                } else {
+                    T::zero()
+                };
+            }
+            let mut spectrum =
+                vec![Complex::new(T::zero(), T::zero()); Self::spectrum_len(frame_len)];
+            self.forward(&frame, &mut spectrum)?;
+            frames.push(spectrum);
+        }
+
+        Ok(Spectrogram {
+            frame_len,
+            hop,
+            signal_len: signal.len(),
+            frames,
+        })
+    }
+
+    /// Inverse STFT using least-squares overlap-add.
+    ///
+    /// `window` must be the window used for the forward transform.
+    pub fn istft(
+        &mut self,
+        spectrogram: &Spectrogram<T>,
+        window: &AnalysisWindow,
+    ) -> Result<Vec<T>, FftError> {
+        let frame_len = spectrogram.frame_len;
+        check_len(frame_len, window.len())?;
+        let coefficients = window_as::<T>(window);
+        let padding = spectrogram.padding();
+        let padded_len = spectrogram.signal_len + padding;
+
+        let mut output = vec![T::zero(); padded_len];
+        let mut norm = vec![T::zero(); padded_len];
+        let mut frame = vec![T::zero(); frame_len];
+        for (index, spectrum) in spectrogram.frames.iter().enumerate() {
+            self.inverse(spectrum, &mut frame)?;
+            let start = index * spectrogram.hop;
+            for (offset, (&sample, &weight)) in frame.iter().zip(&coefficients).enumerate() {
+                let position = start + offset;
+                if position >= padded_len {
+                    break;
+                }
+                output[position] = output[position] + sample * weight;
+                norm[position] = norm[position] + weight * weight;
+            }
+        }
+
+        let mut signal = output.split_off(padding);
+        for (sample, &weight) in signal.iter_mut().zip(&norm[padding..]) {
+            // Samples outside every window's support (e.g. the zero tap of a
+            // periodic window at the very start) are left as-is.
+            if !weight.is_zero() {
+                *sample = *sample / weight;
+            }
+        }
+        Ok(signal)
+    }
+
+    fn plan(&mut self, len: usize) -> Result<&mut FftPlan<T>, FftError> {
+        if len == 0 {
+            return Err(FftError::EmptyInput);
+        }
+        let planner = &mut self.planner;
+        Ok(self
+            .plans
+            .entry(len)
+            .or_insert_with(|| FftPlan::new(planner, len)))
+    }
+}
+
+impl<T: FftNum> Default for FftBackend<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: FftNum> fmt::Debug for FftBackend<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FftBackend")
+            .field("cached_sizes", &self.cached_sizes())
+            .finish()
+    }
+}
+
+/// Sequence of STFT frames produced by [`FftBackend::stft`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spectrogram<T: FftNum> {
+    frame_len: usize,
+    hop: usize,
+    signal_len: usize,
+    frames: Vec<Vec<Complex<T>>>,
+}
+
+impl<T: FftNum> Spectrogram<T> {
+    /// Frame length in samples.
+    pub fn frame_len(&self) -> usize {
+        self.frame_len
+    }
+
+    /// Hop size in samples.
+    pub fn hop(&self) -> usize {
+        self.hop
+    }
+
+    /// Length of the analyzed signal in samples.
+    pub fn signal_len(&self) -> usize {
+        self.signal_len
+    }
+
+    /// Number of leading zeros inserted before the first frame.
+    pub fn padding(&self) -> usize {
+        self.frame_len - self.hop
+    }
+
+    /// Number of complex bins per frame.
+    pub fn bins(&self) -> usize {
+        FftBackend::<T>::spectrum_len(self.frame_len)
+    }
+
+    /// Number of frames.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns `true` when no frames were produced.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Signal index of the first sample of frame `index`; negative inside the padding.
+    pub fn frame_start(&self, index: usize) -> isize {
+        (index * self.hop) as isize - self.padding() as isize
+    }
+
+    /// Returns all frames.
+    pub fn frames(&self) -> &[Vec<Complex<T>>] {
+        &self.frames
+    }
+
+    /// Returns all frames for in-place modification.
+    pub fn frames_mut(&mut self) -> &mut [Vec<Complex<T>>] {
+        &mut self.frames
+    }
+}
+
+/// Errors produced by the FFT backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FftError {
+    /// A transform of length zero was requested.
+    EmptyInput,
+    /// A buffer did not have the length implied by the transform size.
+    LengthMismatch { expected: usize, found: usize },
+    /// Hop size is zero or exceeds the frame length.
+    InvalidHop { hop: usize, frame_len: usize },
+    /// The underlying FFT implementation rejected the call.
+    Backend(String),
+}
+
+impl fmt::Display for FftError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FftError::EmptyInput => write!(f, "FFT input cannot be empty"),
+            FftError::LengthMismatch { expected, found } => write!(
+                f,
+                "buffer length mismatch: expected {} but found {}",
+                expected, found
+            ),
+            FftError::InvalidHop { hop, frame_len } => write!(
+                f,
+                "hop size {} is invalid for frame length {}",
+                hop, frame_len
+            ),
+            FftError::Backend(reason) => write!(f, "FFT backend failure: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for FftError {}
+
+fn check_len(expected: usize, found: usize) -> Result<(), FftError> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(FftError::LengthMismatch { expected, found })
+    }
+}
+
+fn check_hop(hop: usize, frame_len: usize) -> Result<(), FftError> {
+    if frame_len == 0 {
+        return Err(FftError::EmptyInput);
+    }
+    if hop == 0 || hop > frame_len {
+        return Err(FftError::InvalidHop { hop, frame_len });
+    }
+    Ok(())
+}
+
+fn from_f64<T: FftNum>(value: f64) -> T {
+    T::from_f64(value).expect("FFT sample types represent finite f64 values")
+}
+
+fn window_as<T: FftNum>(window: &AnalysisWindow) -> Vec<T> {
+    window
+        .coefficients()
+        .iter()
+        .map(|&weight| from_f64(weight))
+        .collect()
+}
@@ -1,4 +1,5 @@
-//! DSP utility placeholders.
+//! DSP utilities shared by embedding and detection.
 
 pub mod fft;
+pub mod resample;
 pub mod window;
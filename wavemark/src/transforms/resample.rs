@@ -0,0 +1,206 @@
+//! Band-limited sample-rate conversion.
+//!
+//! [`Resampler`] converts between two integer rates with a polyphase
+//! windowed-sinc filter. The ratio is reduced to `L / M`, and output sample
+//! `n` sits `n * M / L` input samples into the signal. Its value is the input
+//! convolved with a Blackman-Harris windowed sinc centred on that position:
+//!
+//! ```text
+//! y[n] = Σ_k x[i + k] · h(k - p / L)       i = ⌊n·M / L⌋,  p = n·M mod L
+//! ```
+//!
+//! The sinc cutoff sits at [`Resampler::ROLLOFF`] of the lower of the two
+//! Nyquist frequencies, so downsampling removes everything that would alias
+//! and upsampling removes the spectral images. The filter spans
+//! [`Resampler::ZERO_CROSSINGS`] zero crossings on either side and is
+//! normalised to unit gain at DC for every phase.
+//!
+//! Taps are tabulated once per phase `p`. Ratios with more than
+//! [`Resampler::MAX_PHASES`] phases round the position to the nearest
+//! tabulated phase, which keeps the table small for awkward rate pairs at an
+//! error far below the filter's stopband.
+//!
+//! The filter is symmetric, so the output is aligned with the input: sample
+//! `n` of the output and sample `n * from / to` of the input describe the
+//! same instant.
+//!
+//! ```ignore
+//! use wavemark::transforms::resample::Resampler;
+//!
+//! let resampler = Resampler::new(44_100, 16_000)?;
+//! let narrow = resampler.process(&samples);
+//! assert_eq!(narrow.len(), resampler.output_len(samples.len()));
+//! ```
+
+use std::f64::consts::PI;
+use std::fmt;
+
+/// Polyphase windowed-sinc converter from one sample rate to another.
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    from_rate: u32,
+    to_rate: u32,
+    up: usize,
+    down: usize,
+    /// Taps on each side of the output position.
+    half_taps: usize,
+    /// `phases` rows of `2 * half_taps` taps, for input offsets
+    /// `1 - half_taps..=half_taps` around the integer position.
+    bank: Vec<f32>,
+    phases: usize,
+}
+
+impl Resampler {
+    /// Zero crossings of the sinc on each side of its centre.
+    pub const ZERO_CROSSINGS: usize = 16;
+    /// Cutoff as a fraction of the lower Nyquist frequency.
+    pub const ROLLOFF: f64 = 0.94;
+    /// Largest number of tabulated filter phases.
+    pub const MAX_PHASES: usize = 1024;
+
+    /// Creates a converter from `from_rate` to `to_rate` hertz.
+    pub fn new(from_rate: u32, to_rate: u32) -> Result<Self, ResampleError> {
+        if from_rate == 0 || to_rate == 0 {
+            return Err(ResampleError::ZeroRate);
+        }
+        let divisor = gcd(from_rate, to_rate);
+        let (up, down) = ((to_rate / divisor) as usize, (from_rate / divisor) as usize);
+
+        // Cutoff in cycles per input sample, scaled down when decimating.
+        let cutoff = 0.5 * Self::ROLLOFF * f64::min(1.0, up as f64 / down as f64);
+        let half_taps = (Self::ZERO_CROSSINGS as f64 / (2.0 * cutoff)).ceil() as usize;
+        let phases = up.min(Self::MAX_PHASES);
+
+        let mut bank = Vec::with_capacity(phases * 2 * half_taps);
+        for phase in 0..phases {
+            let fraction = phase as f64 / phases as f64;
+            let row: Vec<f64> = (0..2 * half_taps)
+                .map(|tap| {
+                    let distance = tap as f64 + 1.0 - half_taps as f64 - fraction;
+                    windowed_sinc(distance, cutoff, half_taps as f64)
+                })
+                .collect();
+            let gain: f64 = row.iter().sum();
+            bank.extend(row.iter().map(|&tap| (tap / gain) as f32));
+        }
+
+        Ok(Self {
+            from_rate,
+            to_rate,
+            up,
+            down,
+            half_taps,
+            bank,
+            phases,
+        })
+    }
+
+    /// Input sample rate in hertz.
+    pub fn from_rate(&self) -> u32 {
+        self.from_rate
+    }
+
+    /// Output sample rate in hertz.
+    pub fn to_rate(&self) -> u32 {
+        self.to_rate
+    }
+
+    /// Returns `true` when input and output rates are equal.
+    pub fn is_identity(&self) -> bool {
+        self.up == self.down
+    }
+
+    /// Number of output samples produced for `input_len` input samples.
+    pub fn output_len(&self, input_len: usize) -> usize {
+        (input_len * self.up).div_ceil(self.down)
+    }
+
+    /// Converts `samples`, treating the signal as zero outside the slice.
+    pub fn process(&self, samples: &[f32]) -> Vec<f32> {
+        if self.is_identity() {
+            return samples.to_vec();
+        }
+        let taps = 2 * self.half_taps;
+        (0..self.output_len(samples.len()))
+            .map(|index| {
+                let position = index * self.down;
+                let base = (position / self.up) as isize;
+                let phase = (position % self.up * self.phases + self.up / 2) / self.up;
+                // Rounding up to the next whole phase moves to the next sample.
+                let (base, phase) = match phase {
+                    phase if phase == self.phases => (base + 1, 0),
+                    phase => (base, phase),
+                };
+                let row = &self.bank[phase * taps..(phase + 1) * taps];
+                let first = base + 1 - self.half_taps as isize;
+                if let Some(window) = usize::try_from(first)
+                    .ok()
+                    .and_then(|first| samples.get(first..first + taps))
+                {
+                    return window
+                        .iter()
+                        .zip(row)
+                        .map(|(sample, weight)| sample * weight)
+                        .sum();
+                }
+                row.iter()
+                    .enumerate()
+                    .filter_map(|(tap, &weight)| {
+                        let source = usize::try_from(first + tap as isize).ok()?;
+                        samples.get(source).map(|&sample| sample * weight)
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+}
+
+/// Converts `samples` from `from_rate` to `to_rate` hertz.
+pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Result<Vec<f32>, ResampleError> {
+    Ok(Resampler::new(from_rate, to_rate)?.process(samples))
+}
+
+/// Sinc with `cutoff` cycles per sample under a Blackman-Harris window
+/// reaching zero at `±half_width` samples.
+fn windowed_sinc(distance: f64, cutoff: f64, half_width: f64) -> f64 {
+    let ratio = distance / half_width;
+    if ratio.abs() >= 1.0 {
+        return 0.0;
+    }
+    let phase = PI * ratio;
+    let window = 0.35875
+        + 0.48829 * phase.cos()
+        + 0.14128 * (2.0 * phase).cos()
+        + 0.01168 * (3.0 * phase).cos();
+    let argument = 2.0 * PI * cutoff * distance;
+    let sinc = if argument == 0.0 {
+        1.0
+    } else {
+        argument.sin() / argument
+    };
+    sinc * window
+}
+
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Errors raised while configuring a [`Resampler`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResampleError {
+    /// One of the sample rates is zero.
+    ZeroRate,
+}
+
+impl fmt::Display for ResampleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResampleError::ZeroRate => write!(f, "sample rates must be non-zero"),
+        }
+    }
+}
+
+impl std::error::Error for ResampleError {}
@@ -1,6 +1,274 @@
-#![allow(dead_code)]
+//! Analysis windows applied to STFT frames, and overlap-add validation.
+//!
+//! All named windows are periodic (DFT-even): a window of length `N` is one
+//! period of an `N`-periodic function, which is what overlap-add
+//! reconstruction requires. Use [`AnalysisWindow::new`] with a
+//! [`WindowKind`] or one of the shorthand constructors.
+//!
+//! # Constant Overlap-Add
+//!
+//! Overlap-add processing only reconstructs its input when the shifted
+//! windows sum to a constant. [`AnalysisWindow::check_overlap_add`] verifies
+//! this for a hop size and returns the constant gain, or
+//! [`WindowError::NotConstantOverlapAdd`] with the measured ripple. Check the
+//! window once when configuring a pipeline rather than discovering amplitude
+//! modulation in the output:
+//!
+//! - [`OverlapMode::Analysis`] checks `Σ w[n - mH]`, for pipelines that window
+//!   only on analysis.
+//! - [`OverlapMode::WeightedOverlapAdd`] checks `Σ w²[n - mH]`, for pipelines
+//!   that apply the window on both analysis and synthesis (e.g. sqrt-Hann).
+//!
+//! ```ignore
+//! use wavemark::transforms::window::{AnalysisWindow, OverlapMode};
+//!
+//! let window = AnalysisWindow::sqrt_hann(1024)?;
+//! let gain = window.check_overlap_add(512, OverlapMode::WeightedOverlapAdd)?;
+//! assert!((gain - 1.0).abs() < 1e-9);
+//! ```
 
-//! Placeholder windowing utilities.
+use std::f64::consts::PI;
+use std::fmt;
 
-/// Placeholder type describing an analysis window.
-pub struct AnalysisWindow;
+/// Relative ripple tolerated by [`AnalysisWindow::check_overlap_add`].
+pub const DEFAULT_COLA_TOLERANCE: f64 = 1e-6;
+
+/// Named window shapes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowKind {
+    /// Raised cosine; COLA for hops of `N/2`, `N/4`, ...
+    Hann,
+    /// Raised cosine on a pedestal; COLA for hops of `N/2`, `N/4`, ...
+    Hamming,
+    /// Four-term Blackman-Harris; COLA for hops of `N/4` and below.
+    BlackmanHarris,
+    /// Kaiser-Bessel window with shape parameter `beta` (not exactly COLA).
+    Kaiser { beta: f64 },
+    /// Square root of Hann; satisfies weighted overlap-add for hops of `N/2`, `N/4`, ...
+    SqrtHann,
+}
+
+impl WindowKind {
+    fn coefficient(&self, n: usize, len: usize) -> f64 {
+        let phase = 2.0 * PI * n as f64 / len as f64;
+        match *self {
+            WindowKind::Hann => 0.5 - 0.5 * phase.cos(),
+            WindowKind::Hamming => 0.54 - 0.46 * phase.cos(),
+            WindowKind::BlackmanHarris => {
+                0.35875 - 0.48829 * phase.cos() + 0.14128 * (2.0 * phase).cos()
+                    - 0.01168 * (3.0 * phase).cos()
+            }
+            WindowKind::Kaiser { beta } => {
+                let ratio = 2.0 * n as f64 / len as f64 - 1.0;
+                bessel_i0(beta * (1.0 - ratio * ratio).max(0.0).sqrt()) / bessel_i0(beta)
+            }
+            WindowKind::SqrtHann => (0.5 - 0.5 * phase.cos()).max(0.0).sqrt(),
+        }
+    }
+}
+
+/// Overlap-add constraint checked by [`AnalysisWindow::check_overlap_add`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlapMode {
+    /// Shifted windows must sum to a constant (window applied once).
+    Analysis,
+    /// Shifted squared windows must sum to a constant (window applied twice).
+    WeightedOverlapAdd,
+}
+
+/// Analysis/synthesis window used to taper STFT frames.
+///
+/// Coefficients are stored in double precision and converted to the working
+/// sample type when a frame is processed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalysisWindow {
+    kind: Option<WindowKind>,
+    coefficients: Vec<f64>,
+}
+
+impl AnalysisWindow {
+    /// Builds a named window of `len` samples.
+    pub fn new(kind: WindowKind, len: usize) -> Result<Self, WindowError> {
+        if len == 0 {
+            return Err(WindowError::EmptyWindow);
+        }
+        if let WindowKind::Kaiser { beta } = kind {
+            if !beta.is_finite() || beta < 0.0 {
+                return Err(WindowError::InvalidParameter(
+                    "kaiser beta must be finite and non-negative",
+                ));
+            }
+        }
+        let coefficients = (0..len).map(|n| kind.coefficient(n, len)).collect();
+        Ok(Self {
+            kind: Some(kind),
+            coefficients,
+        })
+    }
+
+    /// Periodic Hann window of `len` samples.
+    pub fn hann(len: usize) -> Result<Self, WindowError> {
+        Self::new(WindowKind::Hann, len)
+    }
+
+    /// Periodic Hamming window of `len` samples.
+    pub fn hamming(len: usize) -> Result<Self, WindowError> {
+        Self::new(WindowKind::Hamming, len)
+    }
+
+    /// Periodic four-term Blackman-Harris window of `len` samples.
+    pub fn blackman_harris(len: usize) -> Result<Self, WindowError> {
+        Self::new(WindowKind::BlackmanHarris, len)
+    }
+
+    /// Periodic square-root Hann window of `len` samples.
+    pub fn sqrt_hann(len: usize) -> Result<Self, WindowError> {
+        Self::new(WindowKind::SqrtHann, len)
+    }
+
+    /// Kaiser window of `len` samples with shape parameter `beta`.
+    pub fn kaiser(len: usize, beta: f64) -> Result<Self, WindowError> {
+        Self::new(WindowKind::Kaiser { beta }, len)
+    }
+
+    /// Wraps caller-supplied window coefficients.
+    pub fn from_coefficients(coefficients: Vec<f64>) -> Result<Self, WindowError> {
+        if coefficients.is_empty() {
+            return Err(WindowError::EmptyWindow);
+        }
+        Ok(Self {
+            kind: None,
+            coefficients,
+        })
+    }
+
+    /// Shape of the window, or `None` for caller-supplied coefficients.
+    pub fn kind(&self) -> Option<WindowKind> {
+        self.kind
+    }
+
+    /// Number of samples covered by the window.
+    pub fn len(&self) -> usize {
+        self.coefficients.len()
+    }
+
+    /// Returns `true` when the window has no coefficients.
+    pub fn is_empty(&self) -> bool {
+        self.coefficients.is_empty()
+    }
+
+    /// Returns the window coefficients.
+    pub fn coefficients(&self) -> &[f64] {
+        &self.coefficients
+    }
+
+    /// Verifies the overlap-add property for `hop` with the default tolerance.
+    ///
+    /// Returns the constant the shifted windows sum to, which callers divide
+    /// out to obtain unity gain.
+    pub fn check_overlap_add(&self, hop: usize, mode: OverlapMode) -> Result<f64, WindowError> {
+        self.check_overlap_add_with_tolerance(hop, mode, DEFAULT_COLA_TOLERANCE)
+    }
+
+    /// Verifies the overlap-add property, allowing `tolerance` relative ripple.
+    pub fn check_overlap_add_with_tolerance(
+        &self,
+        hop: usize,
+        mode: OverlapMode,
+        tolerance: f64,
+    ) -> Result<f64, WindowError> {
+        if hop == 0 || hop > self.len() {
+            return Err(WindowError::InvalidHop {
+                hop,
+                window_len: self.len(),
+            });
+        }
+
+        let mut min = f64::INFINITY;
+        let mut max = f64::NEG_INFINITY;
+        let mut total = 0.0;
+        for phase in 0..hop {
+            let sum: f64 = self
+                .coefficients
+                .iter()
+                .skip(phase)
+                .step_by(hop)
+                .map(|&w| match mode {
+                    OverlapMode::Analysis => w,
+                    OverlapMode::WeightedOverlapAdd => w * w,
+                })
+                .sum();
+            min = min.min(sum);
+            max = max.max(sum);
+            total += sum;
+        }
+
+        let gain = total / hop as f64;
+        if gain <= 0.0 {
+            return Err(WindowError::NotConstantOverlapAdd {
+                hop,
+                mode,
+                ripple: f64::INFINITY,
+            });
+        }
+        let ripple = (max - min) / gain;
+        if ripple > tolerance {
+            return Err(WindowError::NotConstantOverlapAdd { hop, mode, ripple });
+        }
+        Ok(gain)
+    }
+}
+
+/// Errors raised while building or validating windows.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowError {
+    /// Window length is zero.
+    EmptyWindow,
+    /// Window parameter is out of range.
+    InvalidParameter(&'static str),
+    /// Hop size is zero or longer than the window.
+    InvalidHop { hop: usize, window_len: usize },
+    /// Shifted windows do not sum to a constant; `ripple` is relative to the mean.
+    NotConstantOverlapAdd {
+        hop: usize,
+        mode: OverlapMode,
+        ripple: f64,
+    },
+}
+
+impl fmt::Display for WindowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WindowError::EmptyWindow => write!(f, "window length must be non-zero"),
+            WindowError::InvalidParameter(reason) => {
+                write!(f, "invalid window parameter: {}", reason)
+            }
+            WindowError::InvalidHop { hop, window_len } => write!(
+                f,
+                "hop size {} is invalid for window length {}",
+                hop, window_len
+            ),
+            WindowError::NotConstantOverlapAdd { hop, mode, ripple } => write!(
+                f,
+                "window does not satisfy {:?} overlap-add at hop {} (relative ripple {:.3e})",
+                mode, hop, ripple
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WindowError {}
+
+/// Zeroth-order modified Bessel function of the first kind (power series).
+fn bessel_i0(x: f64) -> f64 {
+    let half = x / 2.0;
+    let mut term = 1.0;
+    let mut sum = 1.0;
+    let mut k = 1.0;
+    while term > sum * 1e-17 {
+        term *= (half / k) * (half / k);
+        sum += term;
+        k += 1.0;
+    }
+    sum
+}
@@ -0,0 +1,139 @@
+//! Tests for embedding and detecting at a fixed internal sample rate.
+
+mod common;
+
+use std::error::Error;
+
+use wavemark::api::builder::WatermarkBuilder;
+use wavemark::core::types::{AudioChunk, ChannelLayout};
+use wavemark::embed::params::{EmbedParams, Preset};
+use wavemark::format::FormatBuilder;
+use wavemark::transforms::resample::resample;
+
+use common::{session, Noise};
+
+const MASTER_SECRET: &[u8] = b"internal-rate-master-secret";
+const INTERNAL_RATE: u32 = WatermarkBuilder::CANONICAL_RATE;
+
+/// Builder that embeds and detects at the rate of the audio.
+fn native(sample_rate: u32) -> WatermarkBuilder {
+    let session = session(MASTER_SECRET, "acct_rate", "s1");
+    let mut payload = FormatBuilder::new();
+    payload.payload_builder().account_id("acct_rate").unwrap();
+    WatermarkBuilder::new(sample_rate)
+        .payload(payload)
+        .key(session)
+        .params(
+            EmbedParams::builder()
+                .preset(Preset::Balanced)
+                .sample_rate(INTERNAL_RATE)
+                .strength_db(6.0)
+                .build()
+                .unwrap(),
+        )
+}
+
+fn builder(sample_rate: u32) -> WatermarkBuilder {
+    native(sample_rate).internal_rate(INTERNAL_RATE)
+}
+
+fn host(seconds: usize, sample_rate: u32) -> Vec<f32> {
+    let mut noise = Noise::new(0x4a7e_0001);
+    (0..sample_rate as usize * seconds)
+        .map(|n| {
+            let t = n as f32 / sample_rate as f32;
+            0.25 * (2.0 * std::f32::consts::PI * 150.0 * t).sin() + 0.3 * noise.sample()
+        })
+        .collect()
+}
+
+#[test]
+fn marks_survive_conversion_to_other_rates() -> Result<(), Box<dyn Error>> {
+    let mut audio = host(12, 24_000);
+    let mut embedder = builder(24_000).build_embedder()?;
+    embedder.embed(&mut audio)?;
+    assert_eq!(embedder.sample_rate(), 24_000);
+    assert_eq!(embedder.params().sample_rate(), INTERNAL_RATE);
+
+    let original = builder(24_000).build_detector()?.detect(&audio)?;
+    assert!(original.is_present());
+    assert_eq!(original.frame.as_ref(), Some(embedder.frame()));
+
+    for rate in [44_100, 16_000, 48_000, 22_050] {
+        let copy = resample(&audio, 24_000, rate)?;
+        let report = builder(rate).build_detector()?.detect(&copy)?;
+        assert!(report.is_present(), "{} Hz", rate);
+        assert_eq!(report.frame.as_ref(), Some(embedder.frame()));
+
+        // Positions are reported in samples of the copy.
+        let start = report.payload_start.unwrap() as f64 / rate as f64;
+        let expected = original.payload_start.unwrap() as f64 / 24_000.0;
+        assert!((start - expected).abs() < 0.005, "{} Hz: {} s", rate, start);
+    }
+    Ok(())
+}
+
+#[test]
+fn detection_at_the_wrong_rate_fails_without_an_internal_rate() -> Result<(), Box<dyn Error>> {
+    let mut audio = host(12, 24_000);
+    native(24_000).build_embedder()?.embed(&mut audio)?;
+    assert!(native(24_000)
+        .build_detector()?
+        .detect(&audio)?
+        .is_present());
+
+    let copy = resample(&audio, 24_000, 44_100)?;
+    assert!(!native(44_100).build_detector()?.detect(&copy)?.is_present());
+    Ok(())
+}
+
+#[test]
+fn stereo_chunks_are_marked_at_the_internal_rate() -> Result<(), Box<dyn Error>> {
+    let (left, right) = (host(12, 48_000), host(12, 48_000));
+    let mut samples: Vec<f32> = left
+        .iter()
+        .zip(right.iter().rev())
+        .flat_map(|(&l, &r)| [l, r])
+        .collect();
+    let unmarked = AudioChunk::interleaved(&samples, ChannelLayout::Stereo)?.downmix();
+    let mut chunk = AudioChunk::interleaved_mut(&mut samples, ChannelLayout::Stereo)?;
+    let (mut embedder, mut detector) = builder(48_000).build()?;
+    embedder.embed_chunk(&mut chunk)?;
+
+    // Apart from the filters' transition band, only content below the
+    // internal Nyquist frequency is changed.
+    let change: Vec<f32> = chunk
+        .downmix()
+        .iter()
+        .zip(unmarked)
+        .map(|(a, b)| a - b)
+        .collect();
+    let residue = resample(
+        &resample(&change, 48_000, INTERNAL_RATE)?,
+        INTERNAL_RATE,
+        48_000,
+    )?;
+    let power = |x: &[f32]| x.iter().map(|v| v * v).sum::<f32>();
+    let outside: Vec<f32> = change.iter().zip(&residue).map(|(a, b)| a - b).collect();
+    assert!(power(&outside) < 0.01 * power(&change));
+
+    assert!(detector.detect_chunk(&chunk)?.is_present());
+    let mono = resample(&chunk.downmix(), 48_000, 16_000)?;
+    assert!(builder(16_000)
+        .build_detector()?
+        .detect(&mono)?
+        .is_present());
+    Ok(())
+}
+
+#[test]
+fn min_samples_are_counted_at_the_input_rate() -> Result<(), Box<dyn Error>> {
+    let internal = builder(INTERNAL_RATE).build_detector()?;
+    let fast = builder(48_000).build_detector()?;
+    assert_eq!(fast.sample_rate(), 48_000);
+    assert_eq!(fast.min_samples(), 3 * internal.min_samples());
+    assert_eq!(fast.params(), internal.params());
+
+    assert!(builder(0).build_detector().is_err());
+    Ok(())
+}
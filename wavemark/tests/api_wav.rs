@@ -0,0 +1,260 @@
+//! Tests for WAV file watermarking.
+
+mod common;
+
+use std::error::Error;
+use std::io::Cursor;
+
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use wavemark::api::builder::WatermarkBuilder;
+use wavemark::api::wav::{self, WavError};
+use wavemark::embed::params::{EmbedParams, Preset};
+use wavemark::format::FormatBuilder;
+use wavemark::transforms::resample::resample;
+
+use common::{session, Noise};
+
+const MASTER_SECRET: &[u8] = b"wav-test-master-secret";
+
+fn builder() -> WatermarkBuilder {
+    let session = session(MASTER_SECRET, "acct_wav", "s1");
+    let mut payload = FormatBuilder::new();
+    payload.payload_builder().account_id("acct_wav").unwrap();
+
+    // The sample rate is taken from each file.
+    WatermarkBuilder::new(0)
+        .payload(payload)
+        .key(session)
+        .params_builder(
+            EmbedParams::builder()
+                .preset(Preset::Balanced)
+                .strength_db(3.0),
+        )
+}
+
+/// Interleaved test signal in `[-0.5, 0.5]`, distinct per channel.
+fn signal(frames: usize, channels: u16, sample_rate: u32) -> Vec<f32> {
+    let mut noise = Noise::new(0x0f1e_2d3c);
+    (0..frames * channels as usize)
+        .map(|index| {
+            let channel = (index % channels as usize) as f32;
+            let t = (index / channels as usize) as f32 / sample_rate as f32;
+            0.25 * (2.0 * std::f32::consts::PI * (220.0 + 110.0 * channel) * t).sin()
+                + 0.4 * noise.sample()
+        })
+        .collect()
+}
+
+fn encode(spec: WavSpec, samples: &[f32]) -> Vec<u8> {
+    let mut cursor = Cursor::new(Vec::new());
+    let mut writer = WavWriter::new(&mut cursor, spec).unwrap();
+    let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+    for &sample in samples {
+        match spec.sample_format {
+            SampleFormat::Float => writer.write_sample(sample).unwrap(),
+            SampleFormat::Int => writer
+                .write_sample((sample * scale).round() as i32)
+                .unwrap(),
+        }
+    }
+    writer.finalize().unwrap();
+    cursor.into_inner()
+}
+
+fn spec(
+    channels: u16,
+    sample_rate: u32,
+    bits_per_sample: u16,
+    sample_format: SampleFormat,
+) -> WavSpec {
+    WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample,
+        sample_format,
+    }
+}
+
+#[test]
+fn stereo_round_trip_through_streams() -> Result<(), Box<dyn Error>> {
+    let spec = spec(2, 48_000, 16, SampleFormat::Int);
+    let input = encode(spec, &signal(48_000 * 8, 2, 48_000));
+
+    let mut output = Cursor::new(Vec::new());
+    let report = wav::embed_wav(Cursor::new(&input), &mut output, builder())?;
+    assert_eq!(report.spec, spec);
+    assert_eq!(report.frames, 48_000 * 8);
+    assert_eq!(report.clipped_samples, 0);
+
+    let marked = output.into_inner();
+    assert_eq!(marked.len(), input.len());
+    assert_ne!(marked, input);
+    let detection = wav::detect_wav(Cursor::new(&marked), builder())?;
+    assert!(detection.is_present());
+    assert_eq!(
+        detection.frame.unwrap().account_id().unwrap().as_str(),
+        "acct_wav"
+    );
+
+    let clean = wav::detect_wav(Cursor::new(&input), builder())?;
+    assert!(!clean.is_present());
+
+    // The downmix and both channels are searched, so the correction covers
+    // three times the hypotheses of a mono file of the same length.
+    let mono = encode(
+        WavSpec {
+            channels: 1,
+            ..spec
+        },
+        &signal(48_000 * 8, 1, 48_000),
+    );
+    let single = wav::detect_wav(Cursor::new(&mono), builder())?;
+    assert_eq!(
+        clean.confidence.hypotheses(),
+        3 * single.confidence.hypotheses()
+    );
+    Ok(())
+}
+
+#[test]
+fn every_pcm_format_is_preserved() -> Result<(), Box<dyn Error>> {
+    let formats = [
+        (8, SampleFormat::Int),
+        (16, SampleFormat::Int),
+        (24, SampleFormat::Int),
+        (32, SampleFormat::Int),
+        (32, SampleFormat::Float),
+    ];
+    for (bits, format) in formats {
+        for channels in [1, 3] {
+            let spec = spec(channels, 22_050, bits, format);
+            let original = signal(8_000, channels, 22_050);
+            let mut output = Cursor::new(Vec::new());
+            let report =
+                wav::embed_wav(Cursor::new(encode(spec, &original)), &mut output, builder())?;
+            assert_eq!(report.frames, 8_000);
+
+            let mut reader = WavReader::new(Cursor::new(output.into_inner()))?;
+            assert_eq!(reader.spec(), spec);
+            assert_eq!(reader.duration(), 8_000);
+            let scale = match format {
+                SampleFormat::Float => 1.0,
+                SampleFormat::Int => ((1i64 << (bits - 1)) as f32).recip(),
+            };
+            let decoded: Vec<f32> = match format {
+                SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+                SampleFormat::Int => reader
+                    .samples::<i32>()
+                    .map(|sample| sample.map(|value| value as f32 * scale))
+                    .collect::<Result<_, _>>()?,
+            };
+            // The watermark is a small perturbation of the original samples.
+            let error: f32 = decoded
+                .iter()
+                .zip(&original)
+                .map(|(a, b)| (a - b).powi(2))
+                .sum::<f32>()
+                / original.iter().map(|s| s * s).sum::<f32>();
+            assert!(
+                error > 0.0 && error < 0.1,
+                "{}-bit {:?}: {}",
+                bits,
+                format,
+                error
+            );
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn integer_output_is_dithered() -> Result<(), Box<dyn Error>> {
+    // A quiet host keeps most watermark changes below one LSB.
+    let quiet: Vec<f32> = signal(16_000 * 3, 1, 16_000)
+        .iter()
+        .map(|sample| (sample * 64.0).round() / 32_768.0)
+        .collect();
+    // Both files must carry the same payload, issue time included.
+    let builder = builder();
+    let marked = |spec: WavSpec| -> Result<Vec<f32>, Box<dyn Error>> {
+        let mut output = Cursor::new(Vec::new());
+        wav::embed_wav(
+            Cursor::new(encode(spec, &quiet)),
+            &mut output,
+            builder.clone(),
+        )?;
+        let mut reader = WavReader::new(Cursor::new(output.into_inner()))?;
+        Ok(match spec.sample_format {
+            SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+            SampleFormat::Int => reader
+                .samples::<i16>()
+                .map(|sample| sample.map(|value| value as f32 / 32_768.0))
+                .collect::<Result<_, _>>()?,
+        })
+    };
+    let exact = marked(spec(1, 16_000, 32, SampleFormat::Float))?;
+    let pcm = marked(spec(1, 16_000, 16, SampleFormat::Int))?;
+
+    // Rounding alone would stay within half an LSB of the exact result.
+    let errors: Vec<f32> = pcm
+        .iter()
+        .zip(&exact)
+        .map(|(a, b)| (a - b) * 32_768.0)
+        .collect();
+    assert!(errors.iter().all(|error| error.abs() <= 1.5));
+    assert!(errors.iter().any(|error| error.abs() > 0.5));
+    let mean = errors.iter().sum::<f32>() / errors.len() as f32;
+    assert!(mean.abs() < 0.05, "{}", mean);
+    Ok(())
+}
+
+#[test]
+fn files_on_disk_round_trip() -> Result<(), Box<dyn Error>> {
+    let dir = std::env::temp_dir().join(format!("wavemark-wav-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let input = dir.join("input.wav");
+    let output = dir.join("output.wav");
+
+    let spec = spec(1, 44_100, 24, SampleFormat::Int);
+    std::fs::write(&input, encode(spec, &signal(44_100 * 12, 1, 44_100)))?;
+    let report = wav::embed_wav_file(&input, &output, builder())?;
+    assert_eq!(report.spec, spec);
+    let detection = wav::detect_wav_file(&output, builder())?;
+    assert!(detection.is_present());
+
+    // Files are marked at the canonical rate, so a converted copy is found too.
+    let marked: Vec<f32> = WavReader::open(&output)?
+        .samples::<i32>()
+        .map(|sample| sample.map(|value| value as f32 / (1 << 23) as f32))
+        .collect::<Result<_, _>>()?;
+    let copy = resample(&marked, 44_100, 22_050)?;
+    let copy = encode(self::spec(1, 22_050, 16, SampleFormat::Int), &copy);
+    assert!(wav::detect_wav(Cursor::new(&copy), builder())?.is_present());
+
+    let missing = wav::detect_wav_file(dir.join("missing.wav"), builder());
+    std::fs::remove_dir_all(&dir)?;
+    assert!(matches!(missing, Err(WavError::Io(_))));
+    Ok(())
+}
+
+#[test]
+fn hot_masters_report_clipping() -> Result<(), Box<dyn Error>> {
+    let spec = spec(1, 44_100, 16, SampleFormat::Int);
+    let square: Vec<f32> = (0..44_100)
+        .map(|n| if (n / 50) % 2 == 0 { 0.999 } else { -0.999 })
+        .collect();
+    let mut output = Cursor::new(Vec::new());
+    let report = wav::embed_wav(Cursor::new(encode(spec, &square)), &mut output, builder())?;
+    assert!(report.clipped_samples > 0);
+
+    let mut reader = WavReader::new(Cursor::new(output.into_inner()))?;
+    assert!(reader
+        .samples::<i32>()
+        .all(|sample| (-32_768..=32_767).contains(&sample.unwrap())));
+
+    assert!(matches!(
+        wav::detect_wav(Cursor::new(b"not a wav file".to_vec()), builder()),
+        Err(WavError::Wav(_))
+    ));
+    Ok(())
+}
@@ -0,0 +1,44 @@
+//! Fixtures shared by the integration tests.
+//!
+//! Each test crate uses only some of these, hence the `dead_code` allowance.
+
+#![allow(dead_code)]
+
+use wavemark::key::derivation::KeyContext;
+
+/// Session key context for session `id` of `account` under `secret`.
+pub fn session(secret: &[u8], account: &str, id: &str) -> KeyContext {
+    KeyContext::from_master_secret(secret)
+        .unwrap()
+        .account(account)
+        .unwrap()
+        .session(id)
+        .unwrap()
+}
+
+/// Deterministic white noise from a 32-bit linear congruential generator.
+#[derive(Debug, Clone)]
+pub struct Noise {
+    state: u32,
+}
+
+impl Noise {
+    pub fn new(seed: u32) -> Self {
+        Self { state: seed }
+    }
+
+    /// Next sample, uniform in `[-0.5, 0.5)`.
+    pub fn sample(&mut self) -> f32 {
+        self.state = self
+            .state
+            .wrapping_mul(1_664_525)
+            .wrapping_add(1_013_904_223);
+        (self.state >> 8) as f32 / (1u32 << 24) as f32 - 0.5
+    }
+}
+
+/// `len` samples of [`Noise`] seeded with `seed`, scaled by `amplitude`.
+pub fn noise(len: usize, amplitude: f32, seed: u32) -> Vec<f32> {
+    let mut noise = Noise::new(seed);
+    (0..len).map(|_| amplitude * noise.sample()).collect()
+}
@@ -0,0 +1,164 @@
+//! Tests for audio chunks over native sample formats.
+
+mod common;
+
+use std::error::Error;
+
+use wavemark::api::builder::{WatermarkBuilder, WatermarkError};
+use wavemark::core::sample::{Dither, Sample, I24};
+use wavemark::core::types::{AudioChunk, AudioChunkError, ChannelLayout};
+use wavemark::embed::params::{EmbedParams, Preset};
+use wavemark::embed::spread_spectrum::EmbedError;
+use wavemark::format::FormatBuilder;
+
+use common::{session, Noise};
+
+const MASTER_SECRET: &[u8] = b"sample-format-master-secret";
+const SAMPLE_RATE: u32 = 16_000;
+
+fn builder() -> WatermarkBuilder {
+    let session = session(MASTER_SECRET, "acct_pcm", "s1");
+    let mut payload = FormatBuilder::new();
+    payload.payload_builder().account_id("acct_pcm").unwrap();
+    WatermarkBuilder::new(SAMPLE_RATE)
+        .payload(payload)
+        .key(session)
+        .params(
+            EmbedParams::builder()
+                .preset(Preset::Balanced)
+                .sample_rate(SAMPLE_RATE)
+                .strength_db(6.0)
+                .build()
+                .unwrap(),
+        )
+}
+
+/// Host signal in `[-0.55, 0.55]`, offset per channel.
+fn host(frames: usize, channels: usize) -> Vec<f32> {
+    let mut noise = Noise::new(0x9c3d_0001);
+    (0..frames * channels)
+        .map(|index| {
+            let (frame, channel) = (index / channels, index % channels);
+            let t = frame as f32 / SAMPLE_RATE as f32;
+            let hz = 150.0 + 90.0 * channel as f32;
+            0.25 * (2.0 * std::f32::consts::PI * hz * t).sin() + 0.3 * noise.sample()
+        })
+        .collect()
+}
+
+fn to_native<S: Sample>(samples: &[f32]) -> Vec<S> {
+    samples.iter().map(|&x| S::from_f32(x, 0.0).0).collect()
+}
+
+#[test]
+fn integer_and_double_buffers_are_marked_in_place() -> Result<(), Box<dyn Error>> {
+    let (mut embedder, mut detector) = builder().build()?;
+    let frames = SAMPLE_RATE as usize * 12;
+
+    let mut telephony: Vec<i16> = to_native(&host(frames, 1));
+    let original = telephony.clone();
+    let mut chunk = AudioChunk::interleaved_mut(&mut telephony, ChannelLayout::Mono)?;
+    embedder.embed_chunk(&mut chunk)?;
+    assert_eq!(chunk.clipped_samples(), 0);
+    assert_ne!(telephony, original);
+    let view = AudioChunk::interleaved(&telephony, ChannelLayout::Mono)?;
+    assert!(detector.detect_chunk(&view)?.is_present());
+
+    let mut studio: Vec<I24> = to_native(&host(frames, 2));
+    let mut chunk = AudioChunk::interleaved_mut(&mut studio, ChannelLayout::Stereo)?;
+    embedder.embed_chunk(&mut chunk)?;
+    assert!(detector.detect_chunk(&chunk)?.is_present());
+
+    let interleaved: Vec<f64> = to_native(&host(frames, 2));
+    let (mut left, mut right): (Vec<f64>, Vec<f64>) = interleaved
+        .chunks_exact(2)
+        .map(|frame| (frame[0], frame[1]))
+        .unzip();
+    let mut chunk = AudioChunk::planar_mut(vec![&mut left, &mut right])?;
+    embedder.embed_chunk(&mut chunk)?;
+    let report = detector.detect_chunk(&chunk)?;
+    assert!(report.is_present());
+    assert_eq!(report.frame.as_ref(), Some(embedder.frame()));
+    Ok(())
+}
+
+#[test]
+fn dither_spreads_sub_lsb_changes_and_spares_untouched_samples() -> Result<(), Box<dyn Error>> {
+    let original: Vec<i16> = (0..20_000).map(|n| ((n % 200) as i16 - 100) * 37).collect();
+    let nudged: Vec<f32> = original
+        .iter()
+        .map(|sample| sample.to_f32() + 0.3 / 32_768.0)
+        .collect();
+    let mean_change = |written: &[i16]| {
+        written
+            .iter()
+            .zip(&original)
+            .map(|(&a, &b)| (a - b) as f64)
+            .sum::<f64>()
+            / original.len() as f64
+    };
+
+    // Rounding alone drops a change of 0.3 LSB; dither keeps it on average.
+    let mut rounded = original.clone();
+    AudioChunk::interleaved_mut(&mut rounded, ChannelLayout::Mono)?
+        .with_dither(Dither::None)
+        .set_channel(0, &nudged)?;
+    assert_eq!(rounded, original);
+
+    let mut dithered = original.clone();
+    AudioChunk::interleaved_mut(&mut dithered, ChannelLayout::Mono)?.set_channel(0, &nudged)?;
+    assert!((mean_change(&dithered) - 0.3).abs() < 0.03);
+    assert!(dithered
+        .iter()
+        .zip(&original)
+        .all(|(a, b)| (a - b).abs() <= 2));
+
+    // Writing back what was read leaves the buffer bit-exact.
+    let mut untouched = original.clone();
+    let mut chunk = AudioChunk::interleaved_mut(&mut untouched, ChannelLayout::Mono)?;
+    let samples = chunk.channel(0)?;
+    chunk.set_channel(0, &samples)?;
+    assert_eq!(untouched, original);
+    Ok(())
+}
+
+#[test]
+fn write_back_clamps_to_the_native_range() -> Result<(), Box<dyn Error>> {
+    let mut pcm = [0i16, 0, 0, 0];
+    let mut chunk = AudioChunk::interleaved_mut(&mut pcm, ChannelLayout::Stereo)?;
+    chunk.set_channel(0, &[1.5, -0.5])?;
+    chunk.set_channel(1, &[-2.0, 0.25])?;
+    assert_eq!(chunk.clipped_samples(), 2);
+    assert_eq!((pcm[0], pcm[1]), (i16::MAX, i16::MIN));
+    // In-range samples are only off by the dither.
+    assert!((pcm[3] - 8_192).abs() <= 1);
+
+    assert_eq!(I24::new(1 << 23), None);
+    assert_eq!(I24::saturating(1 << 23), I24::MAX);
+    assert_eq!(I24::MIN.to_f32(), -1.0);
+    assert_eq!(I24::from_f32(4.0, 0.0), (I24::MAX, true));
+    assert_eq!(i8::from_f32(-0.5, 0.0), (-64, false));
+    assert_eq!(i8::from_f32(1.0, 0.0), (i8::MAX, true));
+    assert_eq!(i32::MIN.to_f32(), -1.0);
+    assert_eq!(f64::from_f32(4.0, 0.7), (4.0, false));
+    Ok(())
+}
+
+#[test]
+fn shared_buffers_are_read_only() -> Result<(), Box<dyn Error>> {
+    let mut embedder = builder().build_embedder()?;
+    let samples = vec![0.1f32; 4_096];
+    let mut chunk = AudioChunk::interleaved(&samples, ChannelLayout::Mono)?;
+    assert!(!chunk.is_writable());
+    assert_eq!(
+        chunk.set_channel(0, &samples),
+        Err(AudioChunkError::ReadOnly)
+    );
+    assert_eq!(
+        embedder.embed_chunk(&mut chunk),
+        Err(WatermarkError::Embed(EmbedError::Chunk(
+            AudioChunkError::ReadOnly
+        )))
+    );
+    Ok(())
+}
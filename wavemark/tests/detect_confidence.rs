@@ -0,0 +1,144 @@
+mod common;
+
+use std::error::Error;
+
+use wavemark::detect::confidence::{normal_tail, ConfidenceConfig, Decision, DetectionConfidence};
+use wavemark::detect::correlator::{Correlator, DetectError};
+use wavemark::embed::params::EmbedParams;
+use wavemark::embed::spread_spectrum::SpreadSpectrumEmbedder;
+use wavemark::format::codec::{CodecOptions, FrameCodec};
+use wavemark::format::encryption::EncryptionContext;
+use wavemark::format::FormatBuilder;
+
+use common::{session, Noise};
+
+const MASTER_SECRET: &[u8] = b"confidence-test-master-secret";
+
+fn host(len: usize) -> Vec<f32> {
+    let mut noise = Noise::new(0x0bad_f00d);
+    (0..len)
+        .map(|n| 0.2 * noise.sample() + 0.2 * (n as f32 * 0.031).sin())
+        .collect()
+}
+
+#[test]
+fn confidence_statistics() {
+    assert!((normal_tail(0.0) - 0.5).abs() < 1e-7);
+    assert!((normal_tail(1.959_964) - 0.025).abs() < 1e-6);
+    assert!((normal_tail(-1.0) - 0.841_344_7).abs() < 1e-6);
+    assert!((normal_tail(8.0) / 6.220_96e-16 - 1.0).abs() < 1e-5);
+
+    let known = [true, false, true, true];
+    let soft = [2.0f32, -2.0, 2.0, 2.0, -1.0, 0.5];
+    let confidence = DetectionConfidence::evaluate(&soft, &known, 1, &ConfidenceConfig::default());
+
+    // Z = (2 + 2 + 2 + 2) / sqrt(4)
+    assert!((confidence.z_score() - 4.0).abs() < 1e-12);
+    assert_eq!(confidence.known_bits(), 4);
+    assert!((confidence.false_positive_probability() - normal_tail(4.0)).abs() < 1e-12);
+
+    // LLR = 2 * mean|z| * z, mean|z| = 9.5 / 6
+    let amplitude = 9.5 / 6.0;
+    assert_eq!(confidence.llrs().len(), 6);
+    assert!((confidence.llrs()[4] + 2.0 * amplitude).abs() < 1e-9);
+    assert!(confidence.llrs()[5] > 0.0);
+}
+
+#[test]
+fn confidence_decisions_respect_thresholds() -> Result<(), Box<dyn Error>> {
+    let known = vec![true; 24];
+    let strong = vec![2.0f32; 24];
+    let weak = vec![0.6f32; 24];
+    let config = ConfidenceConfig::default();
+
+    let present = DetectionConfidence::evaluate(&strong, &known, 1_000, &config);
+    assert_eq!(present.decision(), Decision::Present);
+    assert!(present.false_positive_probability() < 1e-9);
+
+    // The Bonferroni correction scales the tail probability with the search size.
+    let one = DetectionConfidence::evaluate(&weak, &known, 1, &config);
+    let many = DetectionConfidence::evaluate(&weak, &known, 100, &config);
+    assert!(
+        (many.false_positive_probability() / one.false_positive_probability() - 100.0).abs() < 1e-6
+    );
+    assert_eq!(one.decision(), Decision::Inconclusive);
+    assert_eq!(many.decision(), Decision::Absent);
+
+    let strict = ConfidenceConfig::new(1e-30, 0.5)?;
+    assert_eq!(
+        DetectionConfidence::evaluate(&strong, &known, 1_000, &strict).decision(),
+        Decision::Inconclusive
+    );
+    assert_eq!(
+        DetectionConfidence::no_match(10, &config).decision(),
+        Decision::Absent
+    );
+    Ok(())
+}
+
+#[test]
+fn confidence_config_validation() {
+    assert!(matches!(
+        ConfidenceConfig::new(0.0, 0.1),
+        Err(DetectError::InvalidParams(_))
+    ));
+    assert!(matches!(
+        ConfidenceConfig::new(0.2, 0.1),
+        Err(DetectError::InvalidParams(_))
+    ));
+    assert!(matches!(
+        ConfidenceConfig::new(1e-6, 1.5),
+        Err(DetectError::InvalidParams(_))
+    ));
+    let config = ConfidenceConfig::new(1e-9, 0.01).unwrap();
+    assert_eq!(config.false_alarm_rate(), 1e-9);
+    assert_eq!(config.decide(1e-10), Decision::Present);
+    assert_eq!(config.decide(1e-3), Decision::Inconclusive);
+    assert_eq!(config.decide(0.2), Decision::Absent);
+}
+
+#[test]
+fn detector_reports_calibrated_confidence() -> Result<(), Box<dyn Error>> {
+    let mut builder = FormatBuilder::new();
+    builder
+        .payload_builder()
+        .account_id("acct_confidence")?
+        .text_field("content.title", "Calibrated")?;
+    let bytes = builder.build()?.bytes;
+
+    let params = EmbedParams::builder().strength_db(3.0).build()?;
+    let clean = host(44_100 * 14);
+    let marked = SpreadSpectrumEmbedder::from_key_context(
+        &session(MASTER_SECRET, "acct_confidence", "s1"),
+        params.clone(),
+    )?
+    .embed(&clean, &bytes)?;
+
+    let codec = FrameCodec::new(CodecOptions::default());
+    let mut correlator = Correlator::from_key_context(
+        &session(MASTER_SECRET, "acct_confidence", "s1"),
+        params.clone(),
+    )?;
+    let detection = correlator.detect(&marked, &codec, &EncryptionContext::default())?;
+    let confidence = &detection.confidence;
+    assert_eq!(confidence.decision(), Decision::Present);
+    assert!(confidence.false_positive_probability() <= 1e-6);
+    // The preamble and the length field are known once the length is.
+    assert_eq!(confidence.known_bits(), 32);
+    assert_eq!(
+        confidence.llrs().len(),
+        params.mapper().channel_bits(bytes.len())
+    );
+    assert!(confidence.hypotheses() > 1_000);
+
+    match correlator.detect(&clean, &codec, &EncryptionContext::default()) {
+        Err(DetectError::NotDetected(confidence)) => {
+            assert_eq!(confidence.decision(), Decision::Absent);
+            assert!(confidence.false_positive_probability() >= 0.05);
+            // The same search space is counted whether or not it succeeds.
+            assert_eq!(confidence.hypotheses(), detection.confidence.hypotheses());
+        }
+        other => panic!("expected no detection, got {:?}", other),
+    }
+    Ok(())
+}
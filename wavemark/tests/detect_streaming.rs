@@ -0,0 +1,197 @@
+//! Tests for incremental detection on live audio.
+
+mod common;
+
+use std::error::Error;
+
+use wavemark::detect::confidence::{ConfidenceConfig, Decision};
+use wavemark::detect::correlator::DetectError;
+use wavemark::detect::streaming::{DetectionEvent, StreamingDetector};
+use wavemark::embed::params::{EmbedParams, Preset};
+use wavemark::embed::spread_spectrum::{EmbedError, SpreadSpectrumEmbedder};
+use wavemark::format::codec::{CodecOptions, FrameCodec};
+use wavemark::format::payload::AccountId;
+use wavemark::format::FormatBuilder;
+
+use common::{session, Noise};
+
+const MASTER_SECRET: &[u8] = b"monitor-master-secret";
+const SAMPLE_RATE: u32 = 16_000;
+/// 20 ms at 16 kHz.
+const PACKET: usize = 320;
+
+fn params() -> EmbedParams {
+    EmbedParams::builder()
+        .preset(Preset::Balanced)
+        .sample_rate(SAMPLE_RATE)
+        .strength_db(3.0)
+        .build()
+        .unwrap()
+}
+
+fn codec() -> FrameCodec {
+    FrameCodec::new(CodecOptions::default())
+}
+
+fn host(len: usize, seed: u32) -> Vec<f32> {
+    let mut noise = Noise::new(seed);
+    (0..len)
+        .map(|n| {
+            let t = n as f32 / SAMPLE_RATE as f32;
+            0.25 * (2.0 * std::f32::consts::PI * 220.0 * t).sin() + 0.3 * noise.sample()
+        })
+        .collect()
+}
+
+fn marked(seconds: usize) -> Result<(Vec<f32>, Vec<u8>), Box<dyn Error>> {
+    let mut builder = FormatBuilder::new();
+    builder.payload_builder().account_id("acct_live")?;
+    let bytes = builder.build()?.bytes;
+    let audio = SpreadSpectrumEmbedder::from_key_context(
+        &session(MASTER_SECRET, "acct_live", "s1"),
+        params(),
+    )?
+    .embed(&host(SAMPLE_RATE as usize * seconds, 0x5eed), &bytes)?;
+    Ok((audio, bytes))
+}
+
+fn monitor(
+    detector: &mut StreamingDetector,
+    audio: &[f32],
+    packet: usize,
+) -> Result<Vec<DetectionEvent>, DetectError> {
+    let mut events = Vec::new();
+    for chunk in audio.chunks(packet) {
+        events.extend(detector.push(chunk)?);
+    }
+    events.extend(detector.finish()?);
+    Ok(events)
+}
+
+#[test]
+fn reports_each_payload_with_its_time_range() -> Result<(), Box<dyn Error>> {
+    let (audio, bytes) = marked(32)?;
+    let mut detector = StreamingDetector::from_key_context(
+        &session(MASTER_SECRET, "acct_live", "s1"),
+        params(),
+        codec(),
+    )?;
+    let events = monitor(&mut detector, &audio, PACKET)?;
+
+    assert!(events.len() >= 2, "{} events", events.len());
+    let mut previous_end = 0;
+    for event in &events {
+        assert_eq!(event.bytes, bytes);
+        assert_eq!(
+            event.frame.account_id().map(AccountId::as_str),
+            Some("acct_live")
+        );
+        assert_eq!(event.confidence.decision(), Decision::Present);
+        // Events are backed by disjoint, ordered stretches of the stream.
+        assert!(event.samples.start >= previous_end);
+        assert!(event.samples.end <= audio.len() as u64);
+        previous_end = event.samples.end;
+
+        let range = event.time_range_secs(SAMPLE_RATE);
+        assert!((range.start - event.samples.start as f64 / 16_000.0).abs() < 1e-9);
+        assert!(range.end - range.start <= 10.0);
+    }
+    assert_eq!(detector.samples_pushed(), 0);
+    Ok(())
+}
+
+#[test]
+fn packet_size_does_not_change_events() -> Result<(), Box<dyn Error>> {
+    let (audio, _) = marked(24)?;
+    let mut detector = StreamingDetector::from_key_context(
+        &session(MASTER_SECRET, "acct_live", "s1"),
+        params(),
+        codec(),
+    )?
+    .with_interval(SAMPLE_RATE as usize / 2);
+    let small = monitor(&mut detector, &audio, 160)?;
+    let large = monitor(&mut detector, &audio, 800)?;
+    assert!(!small.is_empty());
+    assert_eq!(small, large);
+    Ok(())
+}
+
+#[test]
+fn locks_sync_and_decodes_the_tail_on_finish() -> Result<(), Box<dyn Error>> {
+    let (audio, bytes) = marked(22)?;
+
+    // Without periodic attempts, only `finish` searches the window.
+    let mut detector = StreamingDetector::from_key_context(
+        &session(MASTER_SECRET, "acct_live", "s1"),
+        params(),
+        codec(),
+    )?
+    .with_interval(usize::MAX);
+    for chunk in audio[..SAMPLE_RATE as usize * 11].chunks(PACKET) {
+        assert!(detector.push(chunk)?.is_empty());
+    }
+    assert_eq!(detector.sync_offset(), None);
+    let tail = detector.finish()?;
+    assert_eq!(tail.len(), 1);
+    assert_eq!(tail[0].bytes, bytes);
+
+    let mut detector = StreamingDetector::from_key_context(
+        &session(MASTER_SECRET, "acct_live", "s1"),
+        params(),
+        codec(),
+    )?;
+    let mut events = Vec::new();
+    for chunk in audio.chunks(PACKET) {
+        events.extend(detector.push(chunk)?);
+    }
+    assert!(!events.is_empty());
+    let offset = detector.sync_offset().expect("locked after a detection");
+    assert!(offset < params().symbol_len());
+
+    // Once locked, only one slot alignment is corrected for.
+    if let [first, second, ..] = events.as_slice() {
+        assert!(second.confidence.hypotheses() < first.confidence.hypotheses());
+    }
+    detector.reset();
+    assert_eq!(detector.sync_offset(), None);
+    Ok(())
+}
+
+#[test]
+fn ignores_unmarked_audio_and_other_keys() -> Result<(), Box<dyn Error>> {
+    let mut detector = StreamingDetector::from_key_context(
+        &session(MASTER_SECRET, "acct_live", "s1"),
+        params(),
+        codec(),
+    )?;
+    let clean = host(SAMPLE_RATE as usize * 20, 0xfeed);
+    assert!(monitor(&mut detector, &clean, PACKET)?.is_empty());
+
+    let (audio, _) = marked(20)?;
+    let mut other = StreamingDetector::from_key_context(
+        &session(MASTER_SECRET, "acct_live", "s2"),
+        params(),
+        codec(),
+    )?;
+    assert!(monitor(&mut other, &audio, PACKET)?.is_empty());
+
+    // Payloads that decode without a `Present` decision raise no event and
+    // take no lock.
+    let strict = ConfidenceConfig::new(f64::MIN_POSITIVE, 1.0)?;
+    let mut wary = StreamingDetector::from_key_context(
+        &session(MASTER_SECRET, "acct_live", "s1"),
+        params(),
+        codec(),
+    )?
+    .with_confidence(strict);
+    for chunk in audio.chunks(PACKET) {
+        assert!(wary.push(chunk)?.is_empty());
+        assert_eq!(wary.sync_offset(), None);
+    }
+
+    assert!(matches!(
+        params().to_builder().frames_per_symbol(3).build(),
+        Err(EmbedError::InvalidParams(_))
+    ));
+    Ok(())
+}
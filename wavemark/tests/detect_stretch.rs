@@ -0,0 +1,216 @@
+//! Tests for detection through speed, tempo and pitch changes.
+
+mod common;
+
+use std::error::Error;
+
+use wavemark::api::builder::{DetectionReport, WatermarkBuilder};
+use wavemark::detect::correlator::DetectError;
+use wavemark::detect::stretch::{StretchMode, StretchSearch};
+use wavemark::embed::params::{EmbedParams, Preset};
+use wavemark::format::payload::MetadataTimestamp;
+use wavemark::format::FormatBuilder;
+use wavemark::transforms::fft::{Complex, FftBackend};
+use wavemark::transforms::window::AnalysisWindow;
+
+use common::{session, Noise};
+
+const MASTER_SECRET: &[u8] = b"stretch-test-master-secret";
+const SAMPLE_RATE: u32 = 16_000;
+
+fn builder() -> WatermarkBuilder {
+    let mut payload = FormatBuilder::new();
+    // A fixed timestamp keeps the marked audio identical between runs.
+    payload
+        .payload_builder()
+        .account_id("acct_stretch")
+        .unwrap()
+        .issued_at(MetadataTimestamp::from_unix_seconds(1_700_000_000).unwrap())
+        .unwrap();
+    WatermarkBuilder::new(SAMPLE_RATE)
+        .payload(payload)
+        .key(session(MASTER_SECRET, "acct_stretch", "s1"))
+        .params(
+            EmbedParams::builder()
+                .preset(Preset::Balanced)
+                .sample_rate(SAMPLE_RATE)
+                .strength_db(6.0)
+                .build()
+                .unwrap(),
+        )
+}
+
+fn marked(seconds: usize) -> Result<(Vec<f32>, Vec<u8>), Box<dyn Error>> {
+    let mut noise = Noise::new(0x7e57_0001);
+    let mut audio: Vec<f32> = (0..SAMPLE_RATE as usize * seconds)
+        .map(|n| {
+            let t = n as f32 / SAMPLE_RATE as f32;
+            0.25 * (2.0 * std::f32::consts::PI * 150.0 * t).sin() + 0.3 * noise.sample()
+        })
+        .collect();
+    let mut embedder = builder().build_embedder()?;
+    embedder.embed(&mut audio)?;
+    Ok((audio, embedder.payload_bytes().to_vec()))
+}
+
+/// Plays `samples` back `speed` times faster, by linear interpolation.
+fn speed_up(samples: &[f32], speed: f32) -> Vec<f32> {
+    let len = (samples.len() as f32 / speed) as usize;
+    (0..len)
+        .map(|index| {
+            let position = index as f32 * speed;
+            let base = (position as usize).min(samples.len() - 2);
+            let fraction = position - base as f32;
+            samples[base] * (1.0 - fraction) + samples[base + 1] * fraction
+        })
+        .collect()
+}
+
+/// Makes `samples` `ratio` times longer at the same pitch with a phase
+/// vocoder.
+fn stretch_time(samples: &[f32], ratio: f32) -> Vec<f32> {
+    const FRAME: usize = 1024;
+    const HOP: usize = FRAME / 4;
+    let window = AnalysisWindow::sqrt_hann(FRAME).unwrap();
+    let mut fft = FftBackend::<f32>::new();
+    let spectrum = |start: usize, fft: &mut FftBackend<f32>| {
+        let frame: Vec<f32> = samples[start..start + FRAME]
+            .iter()
+            .zip(window.coefficients())
+            .map(|(&sample, &weight)| sample * weight as f32)
+            .collect();
+        let mut bins = vec![Complex::new(0.0, 0.0); FRAME / 2 + 1];
+        fft.forward(&frame, &mut bins).unwrap();
+        bins
+    };
+
+    let frames = ((samples.len() - FRAME - HOP) as f32 * ratio) as usize / HOP;
+    let mut phase = vec![0.0f32; FRAME / 2 + 1];
+    let mut output = vec![0.0; frames * HOP + FRAME];
+    let mut frame = vec![0.0; FRAME];
+    for index in 0..frames {
+        let start = (index as f32 * HOP as f32 / ratio) as usize;
+        let (current, next) = (spectrum(start, &mut fft), spectrum(start + HOP, &mut fft));
+        // Advance each bin by the phase it turned through over one hop.
+        let bins: Vec<Complex<f32>> = current
+            .iter()
+            .zip(&next)
+            .zip(&mut phase)
+            .map(|((&now, &then), phase)| {
+                let value = Complex::from_polar(now.norm(), *phase);
+                *phase += then.arg() - now.arg();
+                value
+            })
+            .collect();
+        fft.inverse(&bins, &mut frame).unwrap();
+        let target = &mut output[index * HOP..index * HOP + FRAME];
+        for ((out, &sample), &weight) in target.iter_mut().zip(&frame).zip(window.coefficients()) {
+            // Squared sqrt-Hann windows a quarter apart sum to 2.
+            *out += sample * weight as f32 / 2.0;
+        }
+    }
+    output
+}
+
+/// Shifts the pitch by `factor`, keeping the duration.
+fn shift_pitch(samples: &[f32], factor: f32) -> Vec<f32> {
+    speed_up(&stretch_time(samples, factor), factor)
+}
+
+fn detect(audio: &[f32], search: Option<StretchSearch>) -> Result<DetectionReport, Box<dyn Error>> {
+    let builder = match search {
+        Some(search) => builder().stretch_search(search),
+        None => builder(),
+    };
+    Ok(builder.build_detector()?.detect(audio)?)
+}
+
+#[test]
+fn speed_change_is_found_and_reported() -> Result<(), Box<dyn Error>> {
+    let (audio, bytes) = marked(12)?;
+    let faster = speed_up(&audio, 1.05);
+
+    assert!(!detect(&faster, None)?.is_present());
+
+    let report = detect(&faster, Some(StretchSearch::default()))?;
+    assert!(report.is_present());
+    assert_eq!(report.bytes.as_deref(), Some(bytes.as_slice()));
+    assert!(
+        (report.stretch - 1.05).abs() < 0.002,
+        "stretch {}",
+        report.stretch
+    );
+
+    // Unmodified audio is reported at its original speed.
+    let report = detect(&audio, Some(StretchSearch::default()))?;
+    assert!(report.is_present());
+    assert!((report.stretch - 1.0).abs() < 0.002);
+    Ok(())
+}
+
+#[test]
+fn pitch_and_tempo_changes_are_found() -> Result<(), Box<dyn Error>> {
+    let (audio, bytes) = marked(12)?;
+    let higher = shift_pitch(&audio, 1.04);
+    let quicker = stretch_time(&audio, 1.04f32.recip());
+
+    for (mode, modified) in [(StretchMode::Pitch, higher), (StretchMode::Tempo, quicker)] {
+        let search = StretchSearch::symmetric(mode, 0.06, 0.005)?;
+        let report = detect(&modified, Some(search))?;
+        assert!(report.is_present(), "{:?}", mode);
+        assert_eq!(report.bytes.as_deref(), Some(bytes.as_slice()));
+        // The score peak of vocoded audio can sit a few fine steps off.
+        assert!(
+            (report.stretch - 1.04).abs() < 0.004,
+            "{:?} {}",
+            mode,
+            report.stretch
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn unmarked_audio_is_not_found_at_any_factor() -> Result<(), Box<dyn Error>> {
+    let (audio, _) = marked(6)?;
+    let other_key = WatermarkBuilder::new(SAMPLE_RATE)
+        .key(session(b"another-master-secret", "acct_stretch", "s1"))
+        .stretch_search(StretchSearch::default());
+    let report = other_key
+        .build_detector()?
+        .detect(&speed_up(&audio, 0.95))?;
+    assert!(!report.is_present());
+    assert!(report.frame.is_none());
+    Ok(())
+}
+
+#[test]
+fn grid_covers_the_range_and_rejects_invalid_searches() {
+    let factors = StretchSearch::default().factors();
+    assert_eq!(factors.len(), 41);
+    assert_eq!(factors[0], 1.0);
+    let extreme = |pick: fn(f32, f32) -> f32| factors.iter().copied().fold(1.0, pick);
+    assert!((extreme(f32::min) - 0.9).abs() < 1e-4);
+    assert!((extreme(f32::max) - 1.1).abs() < 1e-4);
+
+    for search in [
+        StretchSearch {
+            min_factor: 1.01,
+            ..StretchSearch::default()
+        },
+        StretchSearch {
+            step: 0.0,
+            ..StretchSearch::default()
+        },
+        StretchSearch {
+            step: 1e-5,
+            ..StretchSearch::default()
+        },
+    ] {
+        assert!(matches!(
+            search.validate(),
+            Err(DetectError::InvalidParams(_))
+        ));
+    }
+    assert!(StretchSearch::symmetric(StretchMode::Speed, 1.0, 0.01).is_err());
+}
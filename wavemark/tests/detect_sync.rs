@@ -0,0 +1,168 @@
+//! Tests for blind synchronization with the keyed sync pattern.
+
+mod common;
+
+use std::error::Error;
+
+use wavemark::api::builder::WatermarkBuilder;
+use wavemark::detect::correlator::{Correlator, DetectError};
+use wavemark::embed::params::{EmbedParams, Preset};
+use wavemark::embed::spread_spectrum::{EmbedError, SpreadSpectrumEmbedder};
+use wavemark::format::codec::{CodecOptions, FrameCodec};
+use wavemark::format::encryption::EncryptionContext;
+use wavemark::format::FormatBuilder;
+
+use common::{session, Noise};
+
+const MASTER_SECRET: &[u8] = b"sync-test-master-secret";
+const SAMPLE_RATE: u32 = 44_100;
+
+fn payload() -> FormatBuilder {
+    let mut builder = FormatBuilder::new();
+    builder.payload_builder().account_id("acct_sync").unwrap();
+    builder
+}
+
+fn params() -> EmbedParams {
+    EmbedParams::builder()
+        .preset(Preset::Balanced)
+        .sample_rate(SAMPLE_RATE)
+        .strength_db(6.0)
+        .sync_symbols(4)
+        .build()
+        .unwrap()
+}
+
+fn host(len: usize) -> Vec<f32> {
+    let mut noise = Noise::new(0x51c0_ffee);
+    (0..len)
+        .map(|n| {
+            let t = n as f32 / SAMPLE_RATE as f32;
+            0.25 * (2.0 * std::f32::consts::PI * 196.0 * t).sin() + 0.3 * noise.sample()
+        })
+        .collect()
+}
+
+/// Distance between `a` and `b` modulo `period`.
+fn circular_distance(a: isize, b: isize, period: usize) -> usize {
+    let difference = (a - b).rem_euclid(period as isize) as usize;
+    difference.min(period - difference)
+}
+
+#[test]
+fn three_second_excerpts_recover_offset_and_phase() -> Result<(), Box<dyn Error>> {
+    let builder = WatermarkBuilder::new(SAMPLE_RATE)
+        .payload(payload())
+        .key(session(MASTER_SECRET, "acct_sync", "s1"))
+        .params(params());
+    let (mut embedder, mut detector) = builder.build()?;
+    let mut audio = host(SAMPLE_RATE as usize * 20);
+    embedder.embed(&mut audio)?;
+
+    let bits = params()
+        .mapper()
+        .channel_bits(embedder.payload_bytes().len());
+    let symbol_len = params().symbol_len();
+    let cycle_len = params().cycle_slots(bits) * symbol_len;
+    let step = params().hop() / 2;
+    assert!(cycle_len < SAMPLE_RATE as usize * 3);
+
+    for start in [
+        SAMPLE_RATE as usize * 7 + 1_234,
+        SAMPLE_RATE as usize * 11 + 777,
+    ] {
+        let excerpt = &audio[start..start + SAMPLE_RATE as usize * 3];
+        let report = detector.detect(excerpt)?;
+        assert!(report.is_present(), "excerpt at {}", start);
+        assert_eq!(report.bytes.as_deref(), Some(embedder.payload_bytes()));
+
+        // Repetitions start every `cycle_len` samples of the original file.
+        let sync = report.sync.expect("sync is enabled");
+        assert!(circular_distance(sync.offset, -(start as isize), cycle_len) <= step);
+        assert!(
+            circular_distance(sync.symbol_phase as isize, -(start as isize), symbol_len) <= step
+        );
+        assert!(sync.offset >= 0 && (sync.offset as usize) < excerpt.len());
+        assert_eq!(
+            report
+                .payload_start
+                .map(|payload| (payload - sync.offset).rem_euclid(cycle_len as isize)),
+            Some((params().sync_symbols() * symbol_len) as isize)
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn sync_replaces_the_rotation_search() -> Result<(), Box<dyn Error>> {
+    let bytes = payload().build()?.bytes;
+    let codec = FrameCodec::new(CodecOptions::default());
+    let input = host(SAMPLE_RATE as usize * 8);
+    let excerpt = SAMPLE_RATE as usize * 3..SAMPLE_RATE as usize * 8;
+
+    let detect = |params: EmbedParams| -> Result<_, Box<dyn Error>> {
+        let marked = SpreadSpectrumEmbedder::from_key_context(
+            &session(MASTER_SECRET, "acct_sync", "s1"),
+            params.clone(),
+        )?
+        .embed(&input, &bytes)?;
+        Ok(
+            Correlator::from_key_context(&session(MASTER_SECRET, "acct_sync", "s1"), params)?
+                .detect(
+                    &marked[excerpt.clone()],
+                    &codec,
+                    &EncryptionContext::default(),
+                )?,
+        )
+    };
+    let synced = detect(params())?;
+    let blind = detect(params().to_builder().sync_symbols(0).build()?)?;
+    assert_eq!(synced.bytes, bytes);
+    assert_eq!(blind.bytes, bytes);
+    assert!(blind.sync.is_none());
+
+    // Only payload lengths, not rotations, are counted against the synced
+    // search, and longer lengths count even though a shorter one decoded.
+    assert!(synced.confidence.hypotheses() > bytes.len() as u64);
+    assert!(synced.confidence.hypotheses() * 100 < blind.confidence.hypotheses());
+    assert!(
+        synced.confidence.false_positive_probability()
+            < blind.confidence.false_positive_probability()
+    );
+    Ok(())
+}
+
+#[test]
+fn unmarked_audio_and_other_keys_do_not_sync() -> Result<(), Box<dyn Error>> {
+    let bytes = payload().build()?.bytes;
+    let codec = FrameCodec::new(CodecOptions::default());
+    let clean = host(SAMPLE_RATE as usize * 4);
+    let marked = SpreadSpectrumEmbedder::from_key_context(
+        &session(MASTER_SECRET, "acct_sync", "s1"),
+        params(),
+    )?
+    .embed(&clean, &bytes)?;
+
+    for (key, audio) in [("s1", &clean), ("s2", &marked)] {
+        let mut correlator =
+            Correlator::from_key_context(&session(MASTER_SECRET, "acct_sync", key), params())?;
+        let correlation = correlator.correlate(audio)?;
+        let score = correlation.sync().expect("a full pattern fits").score;
+        assert!(score < 6.0, "sync score {} with key {}", score, key);
+        assert!(matches!(
+            correlator.detect(audio, &codec, &EncryptionContext::default()),
+            Err(DetectError::NotDetected(_))
+        ));
+    }
+    Ok(())
+}
+
+#[test]
+fn rejects_oversized_sync_patterns() {
+    let oversized = params()
+        .to_builder()
+        .sync_symbols(EmbedParams::MAX_SYNC_SYMBOLS + 1)
+        .build();
+    assert!(matches!(oversized, Err(EmbedError::InvalidParams(_))));
+    assert_eq!(params().cycle_slots(100), 4 + 13);
+}
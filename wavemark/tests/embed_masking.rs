@@ -0,0 +1,189 @@
+//! Tests for the psychoacoustic masking model and masked embedding.
+
+mod common;
+
+use std::error::Error;
+
+use wavemark::detect::correlator::Correlator;
+use wavemark::embed::masking::{absolute_threshold_db, MaskingConfig, MaskingError, MaskingModel};
+use wavemark::embed::params::EmbedParams;
+use wavemark::embed::spread_spectrum::SpreadSpectrumEmbedder;
+use wavemark::format::codec::{CodecOptions, FrameCodec};
+use wavemark::format::encryption::EncryptionContext;
+use wavemark::format::FormatBuilder;
+use wavemark::transforms::window::AnalysisWindow;
+
+use common::{noise, session};
+
+const MASTER_SECRET: &[u8] = b"masking-test-master-secret";
+const SAMPLE_RATE: u32 = 44_100;
+const FRAME_LEN: usize = 1024;
+const HOP: usize = 512;
+
+fn model() -> MaskingModel {
+    MaskingModel::new(
+        SAMPLE_RATE,
+        AnalysisWindow::sqrt_hann(FRAME_LEN).unwrap(),
+        MaskingConfig::default(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn tone_masks_neighbouring_bins() -> Result<(), Box<dyn Error>> {
+    // Bin-centred tone at half of full scale.
+    let bin = 24;
+    let frequency = bin as f32 * SAMPLE_RATE as f32 / FRAME_LEN as f32;
+    let tone: Vec<f32> = (0..FRAME_LEN * 8)
+        .map(|n| {
+            0.5 * (2.0 * std::f32::consts::PI * frequency * n as f32 / SAMPLE_RATE as f32).sin()
+        })
+        .collect();
+
+    let mut model = model();
+    let thresholds = model.analyze(&tone, HOP)?;
+    let frame = thresholds.len() / 2;
+    let levels = thresholds.level_db(frame);
+    let masked = thresholds.threshold_db(frame);
+    let quiet = model.absolute_threshold_db();
+
+    assert!((levels[bin] - 90.0).abs() < 1.0, "level {}", levels[bin]);
+    // The tone itself sits above its own threshold...
+    assert!(masked[bin] < levels[bin] - 6.0);
+    // ...but raises the threshold far above the threshold in quiet around it...
+    for neighbour in [bin - 3, bin + 3, bin + 10] {
+        assert!(masked[neighbour] > quiet[neighbour] + 30.0);
+    }
+    // ...and leaves distant bins at the threshold in quiet.
+    let far = 350;
+    let margin = MaskingConfig::default().margin_db;
+    assert!((masked[far] - (quiet[far] - margin)).abs() < 1.0);
+    Ok(())
+}
+
+#[test]
+fn silence_falls_back_to_absolute_threshold() -> Result<(), Box<dyn Error>> {
+    let mut model = model();
+    let thresholds = model.analyze(&vec![0.0; FRAME_LEN * 4], HOP)?;
+    let config = MaskingConfig::default();
+    for frame in 0..thresholds.len() {
+        for (bin, &threshold) in thresholds.threshold_db(frame).iter().enumerate() {
+            let expected = model.absolute_threshold_db()[bin] - config.margin_db;
+            assert!((threshold - expected).abs() < 0.01);
+            assert_eq!(
+                thresholds.allowed_strength_db(frame, bin),
+                config.max_strength_db
+            );
+        }
+    }
+
+    // The threshold in quiet is lowest in the ear's most sensitive region.
+    assert!(absolute_threshold_db(3_300.0) < absolute_threshold_db(500.0));
+    assert!(absolute_threshold_db(3_300.0) < absolute_threshold_db(15_000.0));
+
+    let invalid = MaskingConfig {
+        post_masking_ms: 0.0,
+        ..MaskingConfig::default()
+    };
+    assert!(matches!(
+        MaskingModel::new(
+            SAMPLE_RATE,
+            AnalysisWindow::sqrt_hann(FRAME_LEN).unwrap(),
+            invalid
+        ),
+        Err(MaskingError::InvalidConfig(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn temporal_masking_extends_thresholds() -> Result<(), Box<dyn Error>> {
+    let burst_start = 22_050;
+    let burst_end = burst_start + 8_820;
+    let mut samples = vec![0.0f32; 44_100];
+    samples[burst_start..burst_end].copy_from_slice(&noise(burst_end - burst_start, 0.5, 11));
+
+    let mut model = model();
+    let thresholds = model.analyze(&samples, HOP)?;
+    let padding = (FRAME_LEN - HOP) as isize;
+    let frame_start = |frame: usize| frame as isize * HOP as isize - padding;
+
+    // First frame entirely after the burst, and last frame entirely before it.
+    let after = (0..thresholds.len())
+        .find(|&frame| frame_start(frame) >= burst_end as isize)
+        .unwrap();
+    let before = (0..thresholds.len())
+        .rev()
+        .find(|&frame| frame_start(frame) + FRAME_LEN as isize <= burst_start as isize)
+        .unwrap();
+
+    let bin = 100;
+    let quiet = model.absolute_threshold_db()[bin] - MaskingConfig::default().margin_db;
+    let post: Vec<f32> = (after..after + 5)
+        .map(|frame| thresholds.threshold_db(frame)[bin])
+        .collect();
+    assert!(post[0] > quiet + 20.0, "post-masking {:?}", post);
+    assert!(post.windows(2).all(|pair| pair[1] < pair[0]));
+
+    let pre = thresholds.threshold_db(before)[bin];
+    assert!(pre > quiet);
+    assert!(pre - quiet < post[0] - quiet);
+    Ok(())
+}
+
+#[test]
+fn masked_embedding_stays_under_threshold_and_detectable() -> Result<(), Box<dyn Error>> {
+    let session = session(MASTER_SECRET, "acct_masking", "s1");
+    let params = EmbedParams::default();
+
+    // Quiet and loud passages with a tonal component throughout.
+    let len = 44_100 * 10;
+    let mut host = noise(len, 0.4, 5);
+    for (n, sample) in host.iter_mut().enumerate() {
+        let envelope = if (n / 44_100) % 2 == 0 { 1.0 } else { 0.05 };
+        *sample = envelope * (*sample + 0.3 * (n as f32 * 0.07).sin());
+    }
+
+    let mut builder = FormatBuilder::new();
+    builder.payload_builder().account_id("acct_masking")?;
+    let bytes = builder.build()?.bytes;
+
+    let mut embedder = SpreadSpectrumEmbedder::from_key_context(&session, params.clone())?
+        .with_masking(MaskingConfig::default())?;
+    let marked = embedder.embed(&host, &bytes)?;
+
+    // The watermark, re-analysed on its own, rarely exceeds the host threshold
+    // by more than the safety margin.
+    let mut model = model();
+    let host_thresholds = model.analyze(&host, HOP)?;
+    let residual: Vec<f32> = marked.iter().zip(&host).map(|(m, h)| m - h).collect();
+    let residual_levels = model.analyze(&residual, HOP)?;
+    let band = params.band_bins();
+    let mut audible = 0usize;
+    let mut total = 0usize;
+    for frame in 0..host_thresholds.len() {
+        for bin in band.clone() {
+            total += 1;
+            let slack = MaskingConfig::default().margin_db;
+            if residual_levels.level_db(frame)[bin]
+                > host_thresholds.threshold_db(frame)[bin] + slack
+            {
+                audible += 1;
+            }
+        }
+    }
+    assert!(
+        (audible as f32) < 0.005 * total as f32,
+        "{} of {} bins above threshold",
+        audible,
+        total
+    );
+
+    let detection = Correlator::from_key_context(&session, params)?.detect(
+        &marked,
+        &FrameCodec::new(CodecOptions::default()),
+        &EncryptionContext::default(),
+    )?;
+    assert_eq!(detection.bytes, bytes);
+    Ok(())
+}
@@ -0,0 +1,197 @@
+//! Tests for channel layouts and multichannel embedding modes.
+
+mod common;
+
+use std::error::Error;
+
+use wavemark::api::builder::WatermarkBuilder;
+use wavemark::core::types::{AudioChunk, AudioChunkError, ChannelLayout};
+use wavemark::embed::multichannel::{ChannelMode, MultichannelEmbedder};
+use wavemark::embed::params::{EmbedParams, Preset};
+use wavemark::embed::spread_spectrum::EmbedError;
+use wavemark::format::FormatBuilder;
+
+use common::{session, Noise};
+
+const MASTER_SECRET: &[u8] = b"multichannel-test-master-secret";
+const SAMPLE_RATE: u32 = 16_000;
+
+fn params() -> EmbedParams {
+    EmbedParams::builder()
+        .preset(Preset::Balanced)
+        .sample_rate(SAMPLE_RATE)
+        .strength_db(6.0)
+        .build()
+        .unwrap()
+}
+
+fn payload(account_id: &str) -> FormatBuilder {
+    let mut payload = FormatBuilder::new();
+    payload.payload_builder().account_id(account_id).unwrap();
+    payload
+}
+
+fn builder(mode: ChannelMode) -> WatermarkBuilder {
+    WatermarkBuilder::new(SAMPLE_RATE)
+        .payload(payload("acct_stereo"))
+        .key(session(MASTER_SECRET, "acct_stereo", "s1"))
+        .params(params())
+        .channel_mode(mode)
+}
+
+/// Interleaved stereo host whose channels hold different tones and
+/// independent noise.
+fn stereo(seconds: usize) -> Vec<f32> {
+    let mut noise = Noise::new(0x5e7e_0001);
+    (0..SAMPLE_RATE as usize * seconds)
+        .flat_map(|n| {
+            let t = n as f32 / SAMPLE_RATE as f32;
+            let tone = |hz: f32| 0.25 * (2.0 * std::f32::consts::PI * hz * t).sin();
+            [
+                tone(150.0) + 0.3 * noise.sample(),
+                tone(260.0) + 0.3 * noise.sample(),
+            ]
+        })
+        .collect()
+}
+
+#[test]
+fn mid_and_identical_marks_survive_any_downmix() -> Result<(), Box<dyn Error>> {
+    for mode in [ChannelMode::Mid, ChannelMode::Identical] {
+        let (mut embedder, mut detector) = builder(mode).build()?;
+        let mut samples = stereo(12);
+        let mut chunk = AudioChunk::interleaved_mut(&mut samples, ChannelLayout::Stereo)?;
+        embedder.embed_chunk(&mut chunk)?;
+
+        let report = detector.detect_chunk(&chunk)?;
+        assert!(report.is_present(), "{:?}", mode);
+        assert_eq!(report.frame.as_ref(), Some(embedder.frame()));
+
+        let left_heavy = chunk.downmix_with(&[0.8, 0.2])?;
+        let summed = chunk.downmix_with(&[1.0, 1.0])?;
+        for signal in [left_heavy, summed, chunk.channel(0)?, chunk.channel(1)?] {
+            let report = detector.detect(&signal)?;
+            assert!(report.is_present(), "{:?}", mode);
+            assert_eq!(report.bytes.as_deref(), Some(embedder.payload_bytes()));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn independent_payloads_are_found_with_their_channel_keys() -> Result<(), Box<dyn Error>> {
+    let left = payload("acct_left").build()?.bytes;
+    let right = payload("acct_right").build()?.bytes;
+    let mut embedder = MultichannelEmbedder::from_key_context(
+        &session(MASTER_SECRET, "acct_stereo", "s1"),
+        params(),
+        ChannelMode::Independent,
+    )?;
+    let mut samples = stereo(24);
+    let mut chunk = AudioChunk::interleaved_mut(&mut samples, ChannelLayout::Stereo)?;
+    embedder.embed(&mut chunk, &[&left, &right])?;
+
+    let downmix = chunk.downmix();
+    let builder = builder(ChannelMode::Independent);
+    for (channel, payload) in [left, right].iter().enumerate() {
+        let mut detector = builder.build_channel_detector(channel)?;
+        let own = detector.detect(&chunk.channel(channel)?)?;
+        assert_eq!(own.bytes.as_deref(), Some(payload.as_slice()));
+        let mixed = detector.detect(&downmix)?;
+        assert_eq!(
+            mixed.bytes.as_deref(),
+            Some(payload.as_slice()),
+            "channel {}",
+            channel
+        );
+    }
+
+    // The right channel's key does not match the left channel alone.
+    let report = builder
+        .build_channel_detector(1)?
+        .detect(&chunk.channel(0)?)?;
+    assert!(!report.is_present());
+    Ok(())
+}
+
+#[test]
+fn payload_count_must_match_the_mode() -> Result<(), Box<dyn Error>> {
+    let mut samples = stereo(1);
+    let mut chunk = AudioChunk::interleaved_mut(&mut samples, ChannelLayout::Stereo)?;
+    let mut independent = MultichannelEmbedder::from_key_context(
+        &session(MASTER_SECRET, "acct_stereo", "s1"),
+        params(),
+        ChannelMode::Independent,
+    )?;
+    assert_eq!(
+        independent.embed(&mut chunk, &[b"one".as_slice()]),
+        Err(EmbedError::PayloadCount {
+            expected: 2,
+            found: 1
+        })
+    );
+
+    let mut mid = MultichannelEmbedder::from_key_context(
+        &session(MASTER_SECRET, "acct_stereo", "s1"),
+        params(),
+        ChannelMode::Mid,
+    )?;
+    assert_eq!(
+        mid.embed(&mut chunk, &[b"one".as_slice(), b"two".as_slice()]),
+        Err(EmbedError::PayloadCount {
+            expected: 1,
+            found: 2
+        })
+    );
+    assert_eq!(
+        mid.embed(&mut chunk, &[b"".as_slice()]),
+        Err(EmbedError::EmptyPayload)
+    );
+    drop(chunk);
+    assert_eq!(samples, stereo(1));
+    Ok(())
+}
+
+#[test]
+fn chunks_validate_their_layout() {
+    assert_eq!(
+        AudioChunk::interleaved(&[0.0f32; 5], ChannelLayout::Stereo).err(),
+        Some(AudioChunkError::PartialFrame {
+            samples: 5,
+            channels: 2
+        })
+    );
+    assert_eq!(
+        AudioChunk::<f32>::interleaved(&[], ChannelLayout::Discrete(0)).err(),
+        Some(AudioChunkError::NoChannels)
+    );
+    assert_eq!(
+        AudioChunk::planar(vec![[0.0f32; 3].as_slice(), &[0.0; 2]]).err(),
+        Some(AudioChunkError::ChannelLengthMismatch)
+    );
+
+    let interleaved = [1.0f32, 3.0, 5.0, 2.0, 4.0, 6.0];
+    let chunk = AudioChunk::interleaved(&interleaved, ChannelLayout::Discrete(3)).unwrap();
+    let planes = [[1.0f32, 2.0], [3.0, 4.0], [5.0, 6.0]];
+    let planar = AudioChunk::planar(planes.iter().map(|plane| plane.as_slice()).collect()).unwrap();
+    assert_eq!(planar.layout(), ChannelLayout::Discrete(3));
+    for chunk in [chunk, planar] {
+        assert_eq!(chunk.frames(), 2);
+        assert_eq!(chunk.channel(1).unwrap(), vec![3.0, 4.0]);
+        assert_eq!(chunk.downmix(), vec![3.0, 4.0]);
+        assert_eq!(
+            chunk.channel(3),
+            Err(AudioChunkError::ChannelOutOfRange {
+                index: 3,
+                channels: 3
+            })
+        );
+        assert_eq!(
+            chunk.downmix_with(&[1.0]),
+            Err(AudioChunkError::WeightCountMismatch {
+                expected: 3,
+                found: 1
+            })
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! Tests for embedding parameter presets and validation.
+
+mod common;
+
+use std::error::Error;
+
+use wavemark::detect::correlator::Correlator;
+use wavemark::embed::params::{EmbedParams, Preset};
+use wavemark::embed::spread_spectrum::{EmbedError, SpreadSpectrumEmbedder};
+use wavemark::format::codec::{CodecOptions, FrameCodec};
+use wavemark::format::encryption::EncryptionContext;
+use wavemark::format::FormatBuilder;
+use wavemark::transforms::window::WindowError;
+
+use common::{session, Noise};
+
+const MASTER_SECRET: &[u8] = b"params-test-master-secret";
+
+#[test]
+fn presets_are_valid_across_sample_rates() -> Result<(), Box<dyn Error>> {
+    assert_eq!(
+        EmbedParams::preset(Preset::Balanced, 44_100)?,
+        EmbedParams::default()
+    );
+
+    for sample_rate in [8_000, 16_000, 22_050, 44_100, 48_000, 96_000] {
+        for preset in [Preset::Transparent, Preset::Balanced, Preset::Robust] {
+            let params = EmbedParams::preset(preset, sample_rate)?;
+            assert_eq!(params.sample_rate(), sample_rate);
+            assert!(params.band_high_hz() < sample_rate as f32 / 2.0);
+        }
+    }
+
+    let transparent = EmbedParams::preset(Preset::Transparent, 48_000)?;
+    let robust = EmbedParams::preset(Preset::Robust, 48_000)?;
+    assert!(transparent.strength_db() < robust.strength_db());
+    assert!(transparent.bit_rate() > robust.bit_rate());
+
+    // Fields set on the builder win over the preset in either order.
+    let tuned = EmbedParams::builder()
+        .strength_db(6.0)
+        .preset(Preset::Robust)
+        .sample_rate(16_000)
+        .build()?;
+    assert_eq!(tuned.strength_db(), 6.0);
+    assert_eq!(tuned.redundancy(), 2);
+    assert_eq!(tuned.band_high_hz(), 7_200.0);
+    assert_eq!(tuned.to_builder().build()?, tuned);
+    Ok(())
+}
+
+#[test]
+fn impossible_combinations_are_rejected() {
+    let invalid = [
+        EmbedParams::builder()
+            .sample_rate(16_000)
+            .band_high_hz(9_000.0),
+        EmbedParams::builder()
+            .band_low_hz(6_000.0)
+            .band_high_hz(2_000.0),
+        EmbedParams::builder().strength_db(40.0),
+        EmbedParams::builder().redundancy(3),
+        EmbedParams::builder().frames_per_symbol(3),
+        EmbedParams::builder()
+            .carriers(64)
+            .band_low_hz(1_000.0)
+            .band_high_hz(1_500.0),
+    ];
+    for builder in invalid {
+        assert!(
+            matches!(builder.clone().build(), Err(EmbedError::InvalidParams(_))),
+            "accepted {:?}",
+            builder
+        );
+    }
+
+    // Presets fail when no usable band remains below Nyquist.
+    assert!(matches!(
+        EmbedParams::preset(Preset::Transparent, 2_000),
+        Err(EmbedError::InvalidParams(_))
+    ));
+
+    // The window must reconstruct at the hop, not only fit inside the frame.
+    assert!(matches!(
+        EmbedParams::builder().frame_len(1_024).hop(300).build(),
+        Err(EmbedError::Window(WindowError::NotConstantOverlapAdd {
+            hop: 300,
+            ..
+        }))
+    ));
+}
+
+#[test]
+fn derived_quantities_follow_the_fields() {
+    let params = EmbedParams::builder()
+        .sample_rate(48_000)
+        .frame_len(960)
+        .hop(480)
+        .frames_per_symbol(10)
+        .carriers(12)
+        .redundancy(3)
+        .build()
+        .unwrap();
+    assert_eq!(params.symbol_len(), 4_800);
+    assert!((params.symbol_duration_secs() - 0.1).abs() < 1e-6);
+    assert_eq!(params.bits_per_symbol(), 4);
+    assert!((params.bit_rate() - 40.0).abs() < 1e-3);
+}
+
+#[test]
+fn redundant_carriers_round_trip() -> Result<(), Box<dyn Error>> {
+    let session = session(MASTER_SECRET, "acct_params", "s1");
+    let params = EmbedParams::preset(Preset::Robust, 44_100)?;
+    assert_eq!(params.redundancy(), 2);
+
+    let mut builder = FormatBuilder::new();
+    builder.payload_builder().account_id("acct_params")?;
+    let bytes = builder.build()?.bytes;
+
+    let mut noise = Noise::new(0x0bad_f00d);
+    let host: Vec<f32> = (0..44_100 * 12)
+        .map(|n| 0.3 * (n as f32 * 0.017).sin() + 0.2 * noise.sample())
+        .collect();
+    let marked =
+        SpreadSpectrumEmbedder::from_key_context(&session, params.clone())?.embed(&host, &bytes)?;
+
+    let mut correlator = Correlator::from_key_context(&session, params)?;
+    assert_eq!(correlator.correlate(&marked)?.carriers(), 4);
+    let detection = correlator.detect(
+        &marked[7_000..],
+        &FrameCodec::new(CodecOptions::default()),
+        &EncryptionContext::default(),
+    )?;
+    assert_eq!(detection.bytes, bytes);
+    Ok(())
+}
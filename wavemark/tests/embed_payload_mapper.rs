@@ -0,0 +1,217 @@
+//! Tests for mapping codec bytes onto watermark channel bits.
+
+mod common;
+
+use std::error::Error;
+
+use wavemark::detect::correlator::Correlator;
+use wavemark::embed::params::EmbedParams;
+use wavemark::embed::payload_mapper::{MapError, PayloadMapper, SymbolAlphabet};
+use wavemark::embed::spread_spectrum::SpreadSpectrumEmbedder;
+use wavemark::format::codec::{CodecOptions, FrameCodec};
+use wavemark::format::encryption::EncryptionContext;
+use wavemark::format::fec::{FecCodec, FecConfig, FecError};
+use wavemark::format::FormatBuilder;
+
+use common::{session, Noise};
+
+const MASTER_SECRET: &[u8] = b"mapper-test-master-secret";
+
+/// Converts hard channel bits into soft bits of amplitude `amplitude` plus
+/// deterministic noise of roughly unit variance.
+fn soft_bits(bits: &[bool], amplitude: f32, seed: u32) -> Vec<f32> {
+    let mut noise = Noise::new(seed);
+    bits.iter()
+        .map(|&bit| {
+            let jitter: f32 = (0..3).map(|_| noise.sample()).sum();
+            let sign = if bit { 1.0 } else { -1.0 };
+            sign * amplitude + 2.0 * jitter
+        })
+        .collect()
+}
+
+#[test]
+fn bpsk_round_trip_through_soft_bits() -> Result<(), Box<dyn Error>> {
+    let payload = b"WM\x01 mapper payload".to_vec();
+    let mapper = PayloadMapper::bpsk();
+    let mapped = mapper.map(&payload)?;
+
+    assert_eq!(mapped.len(), mapper.channel_bits(payload.len()));
+    assert_eq!(mapped.len(), 16 + (payload.len() + 4) * 8);
+    assert_eq!(
+        mapped.to_bytes()[..2],
+        PayloadMapper::PREAMBLE.to_be_bytes()
+    );
+
+    // Extra trailing soft bits, as produced by a longer fold, are ignored.
+    let mut soft = soft_bits(mapped.bits(), 4.0, 7);
+    soft.extend([0.3, -0.2, 0.9]);
+    let demapped = mapper.demap(&soft)?;
+    assert_eq!(demapped.bytes, payload);
+    assert_eq!(demapped.preamble_errors, 0);
+    assert!(demapped.preamble_score > 10.0);
+    assert!(demapped.min_margin > 0.0);
+    Ok(())
+}
+
+#[test]
+fn mary_symbols_correct_chip_errors() -> Result<(), Box<dyn Error>> {
+    let payload: Vec<u8> = (0u8..40).map(|n| n.wrapping_mul(37)).collect();
+    for bits_per_symbol in [1, 3, 4, 6] {
+        let mapper = PayloadMapper::new(SymbolAlphabet::Mary { bits_per_symbol })?;
+        let mapped = mapper.map(&payload)?;
+        assert_eq!(mapped.len(), mapper.channel_bits(payload.len()));
+        assert_eq!(mapped.len() % 8, 0);
+
+        // Flip one chip in every symbol; the orthogonal code still decodes.
+        let chips = mapper.alphabet().chips_per_symbol();
+        let mut bits = mapped.bits().to_vec();
+        for index in (PayloadMapper::PREAMBLE_BITS..bits.len()).step_by(chips) {
+            if chips >= 4 {
+                bits[index] = !bits[index];
+            }
+        }
+        let soft: Vec<f32> = bits.iter().map(|&b| if b { 1.0 } else { -1.0 }).collect();
+        assert_eq!(mapper.demap(&soft)?.bytes, payload);
+
+        // Only whole length-field symbols count as known.
+        let prefix = mapper.known_prefix(payload.len());
+        assert_eq!(prefix[..], mapped.bits()[..prefix.len()]);
+        assert_eq!(
+            prefix.len(),
+            PayloadMapper::PREAMBLE_BITS + 16 / bits_per_symbol as usize * chips
+        );
+    }
+
+    assert!(matches!(
+        PayloadMapper::new(SymbolAlphabet::Mary { bits_per_symbol: 9 }),
+        Err(MapError::InvalidAlphabet(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn corrupted_frames_are_rejected() -> Result<(), Box<dyn Error>> {
+    let payload = b"checksum".to_vec();
+    let mapper = PayloadMapper::bpsk();
+    let mapped = mapper.map(&payload)?;
+    let soft: Vec<f32> = mapped
+        .bits()
+        .iter()
+        .map(|&b| if b { 1.0 } else { -1.0 })
+        .collect();
+
+    let mut corrupted = soft.clone();
+    corrupted[40] = -corrupted[40];
+    assert!(matches!(
+        mapper.demap(&corrupted),
+        Err(MapError::ChecksumMismatch { .. })
+    ));
+
+    let mut shifted = soft.clone();
+    shifted.rotate_left(5);
+    assert!(matches!(
+        mapper.demap(&shifted),
+        Err(MapError::PreambleMismatch { .. })
+    ));
+
+    assert_eq!(
+        mapper.demap(&soft[..60]),
+        Err(MapError::Truncated {
+            required: mapper.channel_bits(payload.len()),
+            found: 60,
+        })
+    );
+    assert_eq!(mapper.map(&[]), Err(MapError::EmptyPayload));
+    Ok(())
+}
+
+#[test]
+fn fec_frames_repair_corrupted_and_weak_shards() -> Result<(), Box<dyn Error>> {
+    let payload: Vec<u8> = (0u8..40).map(|n| n.wrapping_mul(53)).collect();
+    let mapper = PayloadMapper::bpsk().with_fec(FecConfig::new(4, 2)?)?;
+    let mapped = mapper.map(&payload)?;
+    assert_eq!(mapped.len(), mapper.channel_bits(payload.len()));
+    assert!(mapped.len() > PayloadMapper::bpsk().channel_bits(payload.len()));
+
+    let soft: Vec<f32> = mapped
+        .bits()
+        .iter()
+        .map(|&b| if b { 1.0 } else { -1.0 })
+        .collect();
+    let clean = mapper.demap(&soft)?;
+    assert_eq!(clean.bytes, payload);
+    assert!(clean.fec.as_ref().is_some_and(|report| report.is_clean()));
+
+    // Shards follow the preamble and the 16-bit length field.
+    let shard_bits = FecCodec::new(FecConfig::new(4, 2)?)?.shard_len(payload.len()) * 8;
+    let shard = |index: usize| {
+        let first = PayloadMapper::PREAMBLE_BITS + 16 + index * shard_bits;
+        first..first + shard_bits
+    };
+
+    // Shard 1 is confidently wrong; shard 4 is barely heard and half wrong.
+    let mut damaged = soft.clone();
+    for z in &mut damaged[shard(1)].iter_mut().step_by(3) {
+        *z = -*z;
+    }
+    for (n, z) in damaged[shard(4)].iter_mut().enumerate() {
+        *z *= if n % 2 == 0 { -0.05 } else { 0.05 };
+    }
+    let repaired = mapper.demap(&damaged)?;
+    assert_eq!(repaired.bytes, payload);
+    let report = repaired.fec.unwrap();
+    assert_eq!(report.corrected, vec![1]);
+    assert_eq!(report.erased, vec![4]);
+
+    // Damage beyond the parity shards cannot be repaired.
+    for index in [0, 2, 5] {
+        for z in &mut damaged[shard(index)].iter_mut().step_by(5) {
+            *z = -*z;
+        }
+    }
+    assert!(matches!(
+        mapper.demap(&damaged),
+        Err(MapError::Fec(FecError::Unrecoverable { .. }))
+    ));
+    Ok(())
+}
+
+#[test]
+fn mapped_payload_survives_embedding() -> Result<(), Box<dyn Error>> {
+    let session = session(MASTER_SECRET, "acct_mapper", "s1");
+    let mapper = PayloadMapper::new(SymbolAlphabet::Mary { bits_per_symbol: 3 })?;
+    let params = EmbedParams::builder()
+        .strength_db(3.0)
+        .mapper(mapper.clone())
+        .build()?;
+
+    let mut builder = FormatBuilder::new();
+    builder.payload_builder().account_id("acct_mapper")?;
+    let bytes = builder.build()?.bytes;
+
+    let mut noise = Noise::new(0x1357_9bdf);
+    let host: Vec<f32> = (0..44_100 * 10)
+        .map(|n| 0.3 * (n as f32 * 0.021).sin() + 0.2 * noise.sample())
+        .collect();
+    let marked =
+        SpreadSpectrumEmbedder::from_key_context(&session, params.clone())?.embed(&host, &bytes)?;
+
+    // The input starts on a slot boundary, so the preamble sits at rotation 0.
+    let mut correlator = Correlator::from_key_context(&session, params)?;
+    let correlation = correlator.correlate(&marked)?;
+    let soft = correlation.fold(mapper.channel_bits(bytes.len()), 0, false);
+    let demapped = mapper.demap(&soft)?;
+    assert_eq!(demapped.bytes, bytes);
+
+    // The detector searches for the same framing.
+    let codec = FrameCodec::new(CodecOptions::default());
+    let detection = correlator.detect(&marked, &codec, &EncryptionContext::default())?;
+    assert_eq!(detection.bytes, bytes);
+    assert_eq!(
+        detection.frame.account_id().unwrap().as_str(),
+        "acct_mapper"
+    );
+    assert_eq!(detection.soft_bits.len(), mapper.channel_bits(bytes.len()));
+    Ok(())
+}
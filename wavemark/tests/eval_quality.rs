@@ -0,0 +1,145 @@
+//! Tests for objective quality metrics and the preset quality gate.
+
+mod common;
+
+use std::f32::consts::PI;
+
+use wavemark::embed::params::{EmbedParams, Preset};
+use wavemark::eval::quality::{EvalError, QualityAnalyzer, QualityGate};
+use wavemark::robustness::attack::Attack;
+
+use common::session;
+
+const MASTER_SECRET: &[u8] = b"quality-master-secret";
+
+/// Voiced speech-like host: a gliding harmonic series with a syllable envelope.
+fn speech(sample_rate: u32, seconds: f32) -> Vec<f32> {
+    (0..(sample_rate as f32 * seconds) as usize)
+        .map(|n| {
+            let t = n as f32 / sample_rate as f32;
+            let envelope = (0.5 + 0.5 * (2.0 * PI * 3.0 * t).sin()).powi(2);
+            let pitch = 140.0 + 20.0 * (2.0 * PI * 0.7 * t).sin();
+            envelope
+                * (1..20)
+                    .map(|h| 0.3 / h as f32 * (2.0 * PI * pitch * h as f32 * t).sin())
+                    .sum::<f32>()
+        })
+        .collect()
+}
+
+/// Music-like host: a chord over a pink noise bed.
+fn music(sample_rate: u32, seconds: f32) -> Vec<f32> {
+    let chord: Vec<f32> = (0..(sample_rate as f32 * seconds) as usize)
+        .map(|n| {
+            let t = n as f32 / sample_rate as f32;
+            [220.0, 277.2, 329.6, 440.0, 880.0, 1760.0]
+                .iter()
+                .map(|f| 0.08 * (2.0 * PI * f * t).sin())
+                .sum()
+        })
+        .collect();
+    Attack::PinkNoise { snr_db: 15.0 }
+        .apply(&chord, sample_rate, 1)
+        .unwrap()
+}
+
+#[test]
+fn identical_audio_is_transparent() {
+    let host = music(44_100, 1.0);
+    let report = QualityAnalyzer::new(44_100)
+        .unwrap()
+        .compare(&host, &host)
+        .unwrap();
+    assert_eq!(report.snr_db, f64::INFINITY);
+    assert_eq!(report.segmental_snr_db, 35.0);
+    assert_eq!(report.log_spectral_distance_db, 0.0);
+    assert_eq!(report.disturbed_frames, 0.0);
+    assert!(report.odg > -0.01);
+    assert!(QualityGate::for_preset(Preset::Transparent)
+        .check(&report)
+        .is_ok());
+}
+
+#[test]
+fn metrics_rank_noise_levels() {
+    let host = music(44_100, 2.0);
+    let mut analyzer = QualityAnalyzer::new(44_100).unwrap();
+    let reports: Vec<_> = [30.0, 20.0, 10.0]
+        .into_iter()
+        .map(|snr_db| {
+            let noisy = Attack::WhiteNoise { snr_db }
+                .apply(&host, 44_100, 5)
+                .unwrap();
+            (snr_db, analyzer.compare(&host, &noisy).unwrap())
+        })
+        .collect();
+
+    for (snr_db, report) in &reports {
+        assert!((report.snr_db - *snr_db as f64).abs() < 0.05);
+        assert!((report.segmental_snr_db - *snr_db as f64).abs() < 0.5);
+    }
+    for pair in reports.windows(2) {
+        let (quieter, louder) = (&pair[0].1, &pair[1].1);
+        assert!(louder.log_spectral_distance_db > quieter.log_spectral_distance_db);
+        assert!(louder.noise_to_mask_db > quieter.noise_to_mask_db + 5.0);
+        assert!(louder.odg <= quieter.odg);
+    }
+    assert!(reports[0].1.odg > -0.5);
+    assert!(reports[2].1.odg < -1.5);
+}
+
+#[test]
+fn presets_pass_their_quality_gates() {
+    for sample_rate in [16_000, 44_100] {
+        for host in [speech(sample_rate, 2.0), music(sample_rate, 2.0)] {
+            for preset in [Preset::Transparent, Preset::Balanced, Preset::Robust] {
+                let params = EmbedParams::preset(preset, sample_rate).unwrap();
+                let result = QualityGate::for_preset(preset).check_params(
+                    &session(MASTER_SECRET, "acct_quality", "s1"),
+                    params,
+                    &host,
+                    &[0x5a; 40],
+                );
+                assert!(
+                    result.is_ok(),
+                    "{:?} at {} Hz: {:?}",
+                    preset,
+                    sample_rate,
+                    result
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn gate_reports_every_violation() {
+    let host = speech(44_100, 2.0);
+    let robust = EmbedParams::preset(Preset::Robust, 44_100).unwrap();
+    let strict = QualityGate::for_preset(Preset::Transparent);
+    match strict.check_params(
+        &session(MASTER_SECRET, "acct_quality", "s1"),
+        robust,
+        &host,
+        &[0x5a; 40],
+    ) {
+        Err(EvalError::GateFailed(violations)) => {
+            let metrics: Vec<_> = violations.iter().map(|v| v.metric).collect();
+            assert!(metrics.contains(&"snr") && metrics.contains(&"segmental snr"));
+            let snr = violations.iter().find(|v| v.metric == "snr").unwrap();
+            assert!(snr.value < snr.limit);
+            assert!(snr.to_string().starts_with("snr "));
+        }
+        other => panic!("expected a gate failure, got {:?}", other),
+    }
+
+    let mut analyzer = QualityAnalyzer::new(44_100).unwrap();
+    assert_eq!(
+        analyzer.compare(&host, &host[1..]),
+        Err(EvalError::LengthMismatch {
+            original: host.len(),
+            processed: host.len() - 1,
+        })
+    );
+    assert_eq!(analyzer.compare(&[], &[]), Err(EvalError::EmptyInput));
+}
@@ -0,0 +1,137 @@
+use std::error::Error;
+use std::sync::Arc;
+
+use wavemark::format::codec::{CodecError, CodecOptions, FrameCodec};
+use wavemark::format::encryption::{
+    ChaCha20Poly1305Strategy, EncryptedHashConfig, EncryptedHashStrategy, EncryptionContext,
+    EncryptionError, EncryptionMode, PayloadEncryption,
+};
+use wavemark::format::payload::MetadataTimestamp;
+use wavemark::format::FormatBuilder;
+
+fn aead_mode(key: [u8; 32]) -> EncryptionMode {
+    let strategy: Arc<dyn EncryptedHashStrategy> = Arc::new(ChaCha20Poly1305Strategy::new(key));
+    EncryptionMode::EncryptedHash(EncryptedHashConfig {
+        strategy,
+        key_id: Some("aead-key".into()),
+        nonce: None,
+    })
+}
+
+fn session_context() -> EncryptionContext {
+    EncryptionContext {
+        channel_id: Some("session-01".into()),
+        associated_data: Some(b"pipeline-AAD".to_vec()),
+    }
+}
+
+#[test]
+fn chacha20poly1305_round_trips_through_codec() -> Result<(), Box<dyn Error>> {
+    let mode = aead_mode([0x42; 32]);
+    let mut builder = FormatBuilder::new();
+    builder
+        .payload_builder()
+        .account_id("acct_secure")?
+        .issued_at(MetadataTimestamp::from_unix_seconds(1_700_000_000)?)?
+        .text_field("content.id", "track-42")?;
+    let output = builder
+        .encryption_mode(mode.clone())
+        .encryption_context(session_context())
+        .build()?;
+
+    // Encrypted envelope flag, 16-byte tag and 12-byte nonce in the envelope header.
+    assert_eq!(output.bytes[4], 1);
+    assert_eq!(u16::from_le_bytes([output.bytes[8], output.bytes[9]]), 16);
+    assert_eq!(u16::from_le_bytes([output.bytes[10], output.bytes[11]]), 12);
+
+    let codec = FrameCodec::new(CodecOptions {
+        encryption: mode,
+        ..CodecOptions::default()
+    });
+    let decoded = codec.decode(&output.bytes, &session_context())?;
+    assert_eq!(decoded, output.frame);
+
+    Ok(())
+}
+
+#[test]
+fn chacha20poly1305_binds_channel_and_associated_data() -> Result<(), Box<dyn Error>> {
+    let strategy = ChaCha20Poly1305Strategy::new([0x07; 32]);
+    let context = session_context();
+    let artifacts = strategy.seal(b"plain body", &context)?;
+    assert_ne!(artifacts.sealed_payload, b"plain body");
+
+    let other_channel = EncryptionContext {
+        channel_id: Some("session-02".into()),
+        ..context.clone()
+    };
+    let other_aad = EncryptionContext {
+        associated_data: Some(b"other-AAD".to_vec()),
+        ..context.clone()
+    };
+    for wrong in [other_channel, other_aad, EncryptionContext::default()] {
+        let err = strategy
+            .open(&artifacts.sealed_payload, &artifacts, &wrong)
+            .unwrap_err();
+        assert!(matches!(err, EncryptionError::CryptoFailure(_)));
+    }
+
+    let opened = strategy.open(&artifacts.sealed_payload, &artifacts, &context)?;
+    assert_eq!(opened, b"plain body");
+
+    Ok(())
+}
+
+#[test]
+fn chacha20poly1305_rejects_tampering_and_wrong_keys() -> Result<(), Box<dyn Error>> {
+    let mode = aead_mode([0x11; 32]);
+    let output = FormatBuilder::new()
+        .encryption_mode(mode.clone())
+        .encryption_context(session_context())
+        .build()?;
+
+    let codec = FrameCodec::new(CodecOptions {
+        encryption: mode,
+        ..CodecOptions::default()
+    });
+    let mut tampered = output.bytes.clone();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 0x01;
+    let err = codec.decode(&tampered, &session_context()).unwrap_err();
+    assert!(matches!(
+        err,
+        CodecError::Encryption(EncryptionError::CryptoFailure(_))
+    ));
+
+    let wrong_key = FrameCodec::new(CodecOptions {
+        encryption: aead_mode([0x12; 32]),
+        ..CodecOptions::default()
+    });
+    let err = wrong_key
+        .decode(&output.bytes, &session_context())
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        CodecError::Encryption(EncryptionError::CryptoFailure(_))
+    ));
+
+    let err = ChaCha20Poly1305Strategy::from_slice(&[0u8; 16]).unwrap_err();
+    assert!(matches!(err, EncryptionError::InvalidConfiguration(_)));
+
+    Ok(())
+}
+
+#[test]
+fn chacha20poly1305_sealing_is_deterministic_per_payload() -> Result<(), Box<dyn Error>> {
+    let strategy = ChaCha20Poly1305Strategy::new([0x5A; 32]);
+    let context = session_context();
+
+    let first = strategy.seal(b"payload-a", &context)?;
+    let again = strategy.seal(b"payload-a", &context)?;
+    let other = strategy.seal(b"payload-b", &context)?;
+
+    assert_eq!(first, again);
+    assert_ne!(first.metadata, other.metadata);
+
+    Ok(())
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use std::error::Error;
+
+use wavemark::api::builder::WatermarkBuilder;
+use wavemark::embed::params::EmbedParams;
+use wavemark::embed::payload_mapper::PayloadMapper;
+use wavemark::format::codec::{CodecOptions, FrameCodec};
+use wavemark::format::encryption::EncryptionContext;
+use wavemark::format::fec::{FecCodec, FecConfig, FecError};
+use wavemark::format::payload::MetadataTimestamp;
+use wavemark::format::FormatBuilder;
+
+use common::{session, Noise};
+
+const MASTER_SECRET: &[u8] = b"fec-test-master-secret";
+
+fn codec_bytes() -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut builder = FormatBuilder::new();
+    builder
+        .payload_builder()
+        .account_id("acct_fec")?
+        .issued_at(MetadataTimestamp::from_unix_seconds(1_700_000_000)?)?
+        .text_field("content.title", "Forward Error Correction")?;
+    Ok(builder.build()?.bytes)
+}
+
+#[test]
+fn fec_round_trip_is_clean() -> Result<(), Box<dyn Error>> {
+    let bytes = codec_bytes()?;
+    let fec = FecCodec::new(FecConfig::new(6, 3)?)?;
+
+    let encoded = fec.encode(&bytes)?;
+    assert_eq!(encoded.len(), fec.encoded_len(bytes.len()));
+
+    let decoded = fec.decode(&encoded, &[])?;
+    assert_eq!(decoded.bytes, bytes);
+    assert!(decoded.report.is_clean());
+
+    Ok(())
+}
+
+#[test]
+fn fec_repairs_corrupted_and_erased_shards() -> Result<(), Box<dyn Error>> {
+    let bytes = codec_bytes()?;
+    let fec = FecCodec::new(FecConfig::new(8, 4)?)?;
+    let shard_len = fec.shard_len(bytes.len());
+    let mut encoded = fec.encode(&bytes)?;
+
+    // Flip bits inside data shards 1 and 5 and parity shard 9.
+    for shard in [1, 5, 9] {
+        encoded[shard * shard_len + 1] ^= 0xA5;
+    }
+    // The caller also knows shard 3 is unreliable.
+    let decoded = fec.decode(&encoded, &[3])?;
+
+    assert_eq!(decoded.bytes, bytes);
+    assert_eq!(decoded.report.corrected, vec![1, 5, 9]);
+    assert_eq!(decoded.report.erased, vec![3]);
+
+    let frame = FrameCodec::new(CodecOptions::default())
+        .decode(&decoded.bytes, &EncryptionContext::default())?;
+    assert_eq!(frame.account_id().unwrap().as_str(), "acct_fec");
+
+    Ok(())
+}
+
+#[test]
+fn fec_reports_unrecoverable_damage() -> Result<(), Box<dyn Error>> {
+    let bytes = codec_bytes()?;
+    let fec = FecCodec::new(FecConfig::new(4, 2)?)?;
+    let shard_len = fec.shard_len(bytes.len());
+    let mut encoded = fec.encode(&bytes)?;
+    encoded[0] ^= 0xFF;
+    encoded[2 * shard_len] ^= 0xFF;
+
+    let err = fec.decode(&encoded, &[4]).unwrap_err();
+    assert_eq!(
+        err,
+        FecError::Unrecoverable {
+            damaged: 3,
+            parity_shards: 2,
+        }
+    );
+
+    assert!(matches!(
+        fec.decode(&encoded[1..], &[]),
+        Err(FecError::InvalidLength { .. })
+    ));
+    assert!(matches!(
+        fec.decode(&encoded, &[6]),
+        Err(FecError::InvalidErasure { index: 6, total: 6 })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn detection_reports_fec_repairs() -> Result<(), Box<dyn Error>> {
+    let session = session(MASTER_SECRET, "acct_fec", "s1");
+    let mut payload = FormatBuilder::new();
+    payload
+        .payload_builder()
+        .account_id("acct_fec")?
+        .issued_at(MetadataTimestamp::from_unix_seconds(1_700_000_000)?)?;
+    let params = EmbedParams::builder()
+        .strength_db(3.0)
+        .mapper(PayloadMapper::bpsk().with_fec(FecConfig::new(4, 2)?)?)
+        .build()?;
+    let (mut embedder, mut detector) = WatermarkBuilder::new(44_100)
+        .payload(payload)
+        .key(session)
+        .params(params)
+        .build()?;
+
+    let mut noise = Noise::new(0x2468_ace1);
+    let mut audio: Vec<f32> = (0..44_100 * 12)
+        .map(|n| 0.3 * (n as f32 * 0.021).sin() + 0.2 * noise.sample())
+        .collect();
+    embedder.embed(&mut audio)?;
+
+    let report = detector.detect(&audio)?;
+    assert!(report.is_present());
+    assert_eq!(report.frame.as_ref(), Some(embedder.frame()));
+    assert!(report.fec.is_some());
+    Ok(())
+}
+
+#[test]
+fn fec_config_validation() {
+    assert!(matches!(
+        FecConfig::new(0, 2),
+        Err(FecError::InvalidConfiguration(_))
+    ));
+    assert!(matches!(
+        FecConfig::new(4, 0),
+        Err(FecError::InvalidConfiguration(_))
+    ));
+    assert!(matches!(
+        FecConfig::new(200, 57),
+        Err(FecError::InvalidConfiguration(_))
+    ));
+
+    let config = FecConfig::new(8, 4).unwrap();
+    assert_eq!(config.total_shards(), 12);
+    assert!((config.parity_ratio() - 1.0 / 3.0).abs() < 1e-6);
+}
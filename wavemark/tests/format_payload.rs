@@ -111,8 +111,10 @@ fn encrypted_hashes_are_deterministic_with_seed() -> Result<(), Box<dyn Error>>
         nonce: Some(vec![0x01, 0x02, 0x03]),
     };
 
-    let mut options = CodecOptions::default();
-    options.encryption = EncryptionMode::EncryptedHash(config.clone());
+    let options = CodecOptions {
+        encryption: EncryptionMode::EncryptedHash(config.clone()),
+        ..CodecOptions::default()
+    };
 
     let context = EncryptionContext {
         channel_id: Some("channel-42".into()),
@@ -179,12 +181,14 @@ fn builder_and_codec_error_conditions() {
     let output = FormatBuilder::new().build().expect("payload should build");
 
     let strategy: Arc<dyn EncryptedHashStrategy> = Arc::new(TestStrategy::new([0xAA]));
-    let mut options = CodecOptions::default();
-    options.encryption = EncryptionMode::EncryptedHash(EncryptedHashConfig {
-        strategy,
-        key_id: None,
-        nonce: None,
-    });
+    let options = CodecOptions {
+        encryption: EncryptionMode::EncryptedHash(EncryptedHashConfig {
+            strategy,
+            key_id: None,
+            nonce: None,
+        }),
+        ..CodecOptions::default()
+    };
     let codec = FrameCodec::new(options);
 
     let err = codec
@@ -0,0 +1,108 @@
+use std::error::Error;
+
+use wavemark::format::encryption::{
+    ChaCha20Poly1305Strategy, EncryptionContext, EncryptionError, PayloadEncryption,
+};
+use wavemark::key::derivation::{KeyContext, KeyError, KeyScope};
+
+const MASTER_SECRET: &[u8] = b"wavemark-test-master-secret-0001";
+
+#[test]
+fn hierarchy_is_deterministic_and_scoped() -> Result<(), Box<dyn Error>> {
+    let root = KeyContext::from_master_secret(MASTER_SECRET)?;
+    let session_a = root.account("acct_demo")?.session("session-01")?;
+    let session_b = KeyContext::from_master_secret(MASTER_SECRET)?
+        .account("acct_demo")?
+        .session("session-01")?;
+
+    assert_eq!(root.scope(), KeyScope::Master);
+    assert_eq!(session_a.scope(), KeyScope::Session);
+    assert_eq!(
+        session_a.encryption_key().as_bytes(),
+        session_b.encryption_key().as_bytes()
+    );
+
+    let other_session = root.account("acct_demo")?.session("session-02")?;
+    let other_account = root.account("acct_other")?.session("session-01")?;
+    assert_ne!(
+        session_a.pn_seed().as_bytes(),
+        other_session.pn_seed().as_bytes()
+    );
+    assert_ne!(
+        session_a.pn_seed().as_bytes(),
+        other_account.pn_seed().as_bytes()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn purpose_labels_separate_sub_keys() -> Result<(), Box<dyn Error>> {
+    let account = KeyContext::from_master_secret(MASTER_SECRET)?.account("acct_demo")?;
+
+    let encryption = account.encryption_key();
+    let pn_seed = account.pn_seed();
+    let hmac = account.hmac_key();
+
+    assert_ne!(encryption.as_bytes(), pn_seed.as_bytes());
+    assert_ne!(encryption.as_bytes(), hmac.as_bytes());
+    assert_ne!(pn_seed.as_bytes(), hmac.as_bytes());
+    assert_eq!(format!("{:?}", encryption), "DerivedKey(<redacted>)");
+
+    Ok(())
+}
+
+#[test]
+fn derivation_errors() -> Result<(), Box<dyn Error>> {
+    let err = KeyContext::from_master_secret(b"short").unwrap_err();
+    assert_eq!(err, KeyError::SecretTooShort { min: 16, found: 5 });
+
+    let root = KeyContext::from_master_secret(MASTER_SECRET)?;
+    let err = root.session("session-01").unwrap_err();
+    assert_eq!(
+        err,
+        KeyError::InvalidScope {
+            expected: KeyScope::Account,
+            found: KeyScope::Master,
+        }
+    );
+
+    let account = root.account("acct_demo")?;
+    assert!(matches!(
+        account.account("acct_nested"),
+        Err(KeyError::InvalidScope { .. })
+    ));
+    assert!(matches!(
+        account.session(""),
+        Err(KeyError::InvalidIdentifier(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn encryption_strategy_accepts_key_context() -> Result<(), Box<dyn Error>> {
+    let root = KeyContext::from_master_secret(MASTER_SECRET)?;
+    let session = root.account("acct_demo")?.session("session-01")?;
+    let strategy = ChaCha20Poly1305Strategy::from_key_context(&session);
+    let context = EncryptionContext::default();
+
+    let artifacts = strategy.seal(b"payload", &context)?;
+    let reopened = ChaCha20Poly1305Strategy::from_key_context(
+        &KeyContext::from_master_secret(MASTER_SECRET)?
+            .account("acct_demo")?
+            .session("session-01")?,
+    );
+    assert_eq!(
+        reopened.open(&artifacts.sealed_payload, &artifacts, &context)?,
+        b"payload"
+    );
+
+    let other = ChaCha20Poly1305Strategy::from_key_context(&root.account("acct_demo")?);
+    assert!(matches!(
+        other.open(&artifacts.sealed_payload, &artifacts, &context),
+        Err(EncryptionError::CryptoFailure(_))
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,149 @@
+//! Tests for the streaming overlap-add buffer.
+
+mod common;
+
+use std::convert::Infallible;
+
+use wavemark::pipeline::pre_vocoder::buffer::OverlapBuffer;
+use wavemark::transforms::fft::FftBackend;
+use wavemark::transforms::window::{AnalysisWindow, WindowError};
+
+use common::noise;
+
+const FRAME_LEN: usize = 256;
+const HOP: usize = 64;
+
+/// Pushes `signal` in chunks of the given sizes (cycled) and finishes the stream.
+fn run<F>(buffer: &mut OverlapBuffer, signal: &[f32], sizes: &[usize], mut process: F) -> Vec<f32>
+where
+    F: FnMut(u64, &mut [f32]),
+{
+    let mut output = Vec::new();
+    let mut rest = signal;
+    for &size in sizes.iter().cycle() {
+        if rest.is_empty() {
+            break;
+        }
+        let (chunk, tail) = rest.split_at(size.min(rest.len()));
+        let out = buffer
+            .push(chunk, |index, frame| {
+                process(index, frame);
+                Ok::<_, Infallible>(())
+            })
+            .unwrap();
+        assert_eq!(out.len(), chunk.len());
+        output.extend_from_slice(out);
+        rest = tail;
+    }
+    let tail = buffer
+        .finish(|index, frame| {
+            process(index, frame);
+            Ok::<_, Infallible>(())
+        })
+        .unwrap();
+    output.extend_from_slice(tail);
+    output
+}
+
+#[test]
+fn identity_reconstructs_input_after_fixed_latency() {
+    let signal = noise(5_000, 1.0, 7);
+    let mut buffer =
+        OverlapBuffer::new(AnalysisWindow::sqrt_hann(FRAME_LEN).unwrap(), HOP).unwrap();
+    let latency = buffer.latency();
+    assert_eq!(latency, FRAME_LEN - 1);
+
+    let output = run(&mut buffer, &signal, &[1, 17, 480, 3, 64, 1000], |_, _| {});
+    assert_eq!(output.len(), signal.len() + latency);
+    assert!(output[..latency].iter().all(|&sample| sample == 0.0));
+    for (index, (&out, &input)) in output[latency..].iter().zip(&signal).enumerate() {
+        assert!(
+            (out - input).abs() < 1e-5,
+            "sample {}: {} vs {}",
+            index,
+            out,
+            input
+        );
+    }
+}
+
+#[test]
+fn output_does_not_depend_on_chunking() {
+    let signal = noise(3_000, 1.0, 11);
+    let mut buffer =
+        OverlapBuffer::new(AnalysisWindow::sqrt_hann(FRAME_LEN).unwrap(), HOP).unwrap();
+    // Frame-dependent processing exposes any misaligned frame boundaries.
+    let shape = |index: u64, frame: &mut [f32]| {
+        let gain = 1.0 + 0.1 * (index % 5) as f32;
+        for (n, sample) in frame.iter_mut().enumerate() {
+            *sample *= if n % 2 == 0 { gain } else { 1.0 / gain };
+        }
+    };
+
+    let whole = run(&mut buffer, &signal, &[signal.len()], shape);
+    let packets = run(&mut buffer, &signal, &[160], shape);
+    let ragged = run(&mut buffer, &signal, &[7, 333, 1, 90], shape);
+    assert_eq!(whole, packets);
+    assert_eq!(whole, ragged);
+}
+
+#[test]
+fn frames_match_stft_framing() {
+    let signal = noise(2_000, 1.0, 23);
+    let window = AnalysisWindow::sqrt_hann(FRAME_LEN).unwrap();
+    let spectrogram = FftBackend::<f32>::new()
+        .stft(&signal, &window, HOP)
+        .unwrap();
+    let mut buffer = OverlapBuffer::new(window.clone(), HOP).unwrap();
+
+    let mut seen = Vec::new();
+    run(&mut buffer, &signal, &[100, 250], |index, frame| {
+        let start = buffer_frame_start(index);
+        for (n, &sample) in frame.iter().enumerate() {
+            let position = start + n as i64;
+            let input = if (0..signal.len() as i64).contains(&position) {
+                signal[position as usize]
+            } else {
+                0.0
+            };
+            let expected = input * window.coefficients()[n] as f32;
+            assert!(
+                (sample - expected).abs() < 1e-6,
+                "frame {} sample {}",
+                index,
+                n
+            );
+        }
+        seen.push(index);
+    });
+    assert_eq!(seen, (0..spectrogram.len() as u64).collect::<Vec<_>>());
+    assert_eq!(buffer.frames_processed(), 0);
+    assert_eq!(buffer.frame_start(3), spectrogram.frame_start(3) as i64);
+}
+
+fn buffer_frame_start(index: u64) -> i64 {
+    index as i64 * HOP as i64 - (FRAME_LEN - HOP) as i64
+}
+
+#[test]
+fn rejects_windows_without_weighted_overlap_add() {
+    assert!(matches!(
+        OverlapBuffer::new(AnalysisWindow::hann(FRAME_LEN).unwrap(), FRAME_LEN / 2),
+        Err(WindowError::NotConstantOverlapAdd { .. })
+    ));
+    assert!(matches!(
+        OverlapBuffer::new(AnalysisWindow::sqrt_hann(FRAME_LEN).unwrap(), 0),
+        Err(WindowError::InvalidHop { .. })
+    ));
+
+    let mut buffer =
+        OverlapBuffer::new(AnalysisWindow::sqrt_hann(FRAME_LEN).unwrap(), HOP).unwrap();
+    let failed = buffer.push(&noise(FRAME_LEN, 1.0, 3), |index, _| {
+        if index == 2 {
+            Err("frame rejected")
+        } else {
+            Ok(())
+        }
+    });
+    assert_eq!(failed, Err("frame rejected"));
+}
@@ -0,0 +1,187 @@
+//! Tests for spectrogram-domain watermarking ahead of the vocoder.
+
+mod common;
+
+use wavemark::core::state::ChunkState;
+use wavemark::pipeline::pre_vocoder::controller::{
+    PreVocoderConfig, PreVocoderController, PreVocoderError, SpectrogramScale,
+};
+
+use common::{session, Noise};
+
+const MASTER_SECRET: &[u8] = b"pre-vocoder-master-secret";
+const PAYLOAD: &[u8] = b"tts1";
+
+/// Log-mel-like frames: a sloped spectral envelope plus frame-to-frame noise.
+fn log_mel(frames: usize, bins: usize, seed: u32) -> Vec<f32> {
+    let mut noise = Noise::new(seed);
+    (0..frames * bins)
+        .map(|index| -2.0 - 6.0 * (index % bins) as f32 / bins as f32 + noise.sample())
+        .collect()
+}
+
+fn bits(soft: &[f32]) -> Vec<u8> {
+    soft.chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .fold(0u8, |byte, &value| (byte << 1) | (value > 0.0) as u8)
+        })
+        .collect()
+}
+
+#[test]
+fn marked_frames_yield_payload_bits() -> Result<(), PreVocoderError> {
+    let config = PreVocoderConfig::default();
+    let mut frames = log_mel(1_200, config.bins, 5);
+    let host = frames.clone();
+
+    let mut controller = PreVocoderController::new(
+        &session(MASTER_SECRET, "acct_tts", "s1"),
+        config.clone(),
+        PAYLOAD,
+    )?;
+    assert_eq!(controller.process_chunk(&mut frames)?, 1_200);
+    assert_eq!(controller.frames_per_payload(), 64);
+    assert_eq!(bits(&controller.soft_bits(&frames, 0)?), PAYLOAD);
+
+    // The change per bin is exactly the configured strength.
+    let step = config.strength_db * std::f32::consts::LN_10 / 20.0;
+    for (marked, original) in frames.iter().zip(&host).take(config.bins) {
+        let delta = (marked - original).abs();
+        assert!(delta < 1e-6 || (delta - step).abs() < 1e-5);
+    }
+
+    // A different session's chips see only noise.
+    let other =
+        PreVocoderController::new(&session(MASTER_SECRET, "acct_tts", "s2"), config, PAYLOAD)?;
+    assert_ne!(bits(&other.soft_bits(&frames, 0)?), PAYLOAD);
+    Ok(())
+}
+
+#[test]
+fn chunking_and_handoff_do_not_change_output() -> Result<(), PreVocoderError> {
+    let config = PreVocoderConfig::default();
+    let host = log_mel(300, config.bins, 9);
+
+    let mut whole = host.clone();
+    PreVocoderController::new(
+        &session(MASTER_SECRET, "acct_tts", "s1"),
+        config.clone(),
+        PAYLOAD,
+    )?
+    .process_chunk(&mut whole)?;
+
+    let mut chunked = host.clone();
+    let mut controller = PreVocoderController::new(
+        &session(MASTER_SECRET, "acct_tts", "s1"),
+        config.clone(),
+        PAYLOAD,
+    )?;
+    let mut rest = chunked.as_mut_slice();
+    for frames in [1, 37, 5, 100].iter().cycle() {
+        if rest.is_empty() {
+            break;
+        }
+        let len = (frames * config.bins).min(rest.len());
+        let (chunk, tail) = rest.split_at_mut(len);
+        controller.process_chunk(chunk)?;
+        rest = tail;
+    }
+    assert_eq!(whole, chunked);
+    assert_eq!(controller.state().frames, 300);
+
+    // A second controller resumes from saved state mid-stream.
+    let mut handed = host.clone();
+    let (head, tail) = handed.split_at_mut(123 * config.bins);
+    let mut first = PreVocoderController::new(
+        &session(MASTER_SECRET, "acct_tts", "s1"),
+        config.clone(),
+        PAYLOAD,
+    )?;
+    first.process_chunk(head)?;
+    let state: ChunkState = *first.state();
+    PreVocoderController::new(&session(MASTER_SECRET, "acct_tts", "s1"), config, PAYLOAD)?
+        .with_state(state)
+        .process_chunk(tail)?;
+    assert_eq!(whole, handed);
+    Ok(())
+}
+
+#[test]
+fn magnitude_scale_survives_partial_windows() -> Result<(), PreVocoderError> {
+    let config = PreVocoderConfig {
+        bins: 64,
+        band: 4..60,
+        scale: SpectrogramScale::Magnitude,
+        strength_db: 1.5,
+        frames_per_symbol: 8,
+        carriers: 7,
+    };
+    let mut frames: Vec<f32> = log_mel(2_000, config.bins, 17)
+        .iter()
+        .map(|value| value.exp())
+        .collect();
+    let mut controller = PreVocoderController::new(
+        &session(MASTER_SECRET, "acct_tts", "s1"),
+        config.clone(),
+        PAYLOAD,
+    )?;
+    controller.process_chunk(&mut frames)?;
+
+    // A window cut at arbitrary frames still decodes with its absolute offset.
+    let start = 333;
+    let window = &frames[start * config.bins..1_900 * config.bins];
+    let soft = controller.soft_bits(window, start as u64)?;
+    assert_eq!(bits(&soft), PAYLOAD);
+    Ok(())
+}
+
+#[test]
+fn rejects_invalid_configuration_and_chunks() {
+    let invalid = |config: PreVocoderConfig| {
+        matches!(
+            PreVocoderController::new(&session(MASTER_SECRET, "acct_tts", "s1"), config, PAYLOAD),
+            Err(PreVocoderError::InvalidConfig(_))
+        )
+    };
+    assert!(invalid(PreVocoderConfig {
+        band: 8..81,
+        ..PreVocoderConfig::default()
+    }));
+    assert!(invalid(PreVocoderConfig {
+        frames_per_symbol: 7,
+        ..PreVocoderConfig::default()
+    }));
+    assert!(invalid(PreVocoderConfig {
+        strength_db: 0.0,
+        ..PreVocoderConfig::default()
+    }));
+    assert!(invalid(PreVocoderConfig {
+        carriers: 65,
+        ..PreVocoderConfig::default()
+    }));
+    assert!(matches!(
+        PreVocoderController::new(
+            &session(MASTER_SECRET, "acct_tts", "s1"),
+            PreVocoderConfig::default(),
+            b""
+        ),
+        Err(PreVocoderError::EmptyPayload)
+    ));
+
+    let mut controller = PreVocoderController::new(
+        &session(MASTER_SECRET, "acct_tts", "s1"),
+        PreVocoderConfig::default(),
+        PAYLOAD,
+    )
+    .unwrap();
+    assert_eq!(
+        controller.process_chunk(&mut [0.0; 81]),
+        Err(PreVocoderError::FrameLengthMismatch {
+            bins: 80,
+            found: 81
+        })
+    );
+    assert_eq!(controller.state().frames, 0);
+}
@@ -0,0 +1,176 @@
+//! Tests for the deterministic attack transforms.
+
+use wavemark::robustness::attack::{Attack, AttackError};
+
+const SAMPLE_RATE: u32 = 16_000;
+
+fn tone(frequency: f32, amplitude: f32, len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|n| {
+            let t = n as f32 / SAMPLE_RATE as f32;
+            amplitude * (2.0 * std::f32::consts::PI * frequency * t).sin()
+        })
+        .collect()
+}
+
+fn power(samples: &[f32]) -> f64 {
+    samples.iter().map(|&s| s as f64 * s as f64).sum::<f64>() / samples.len() as f64
+}
+
+/// Power of `degraded - original`, relative to the original, in decibels.
+fn snr_db(original: &[f32], degraded: &[f32]) -> f64 {
+    let difference: Vec<f32> = degraded.iter().zip(original).map(|(d, o)| d - o).collect();
+    10.0 * (power(original) / power(&difference)).log10()
+}
+
+#[test]
+fn seeded_attacks_are_deterministic() {
+    let input = tone(440.0, 0.5, 8_000);
+    let chain = Attack::Chain(vec![
+        Attack::PinkNoise { snr_db: 15.0 },
+        Attack::Echo {
+            delay_secs: 0.01,
+            gain: 0.5,
+        },
+        Attack::WhiteNoise { snr_db: 15.0 },
+    ]);
+    let first = chain.apply(&input, SAMPLE_RATE, 7).unwrap();
+    assert_eq!(first, chain.apply(&input, SAMPLE_RATE, 7).unwrap());
+    assert_ne!(first, chain.apply(&input, SAMPLE_RATE, 8).unwrap());
+    assert_eq!(
+        chain.to_string(),
+        "pink noise 15 dB + echo 10 ms x0.5 + white noise 15 dB"
+    );
+    assert_eq!(
+        Attack::Chain(Vec::new())
+            .apply(&input, SAMPLE_RATE, 7)
+            .unwrap(),
+        input
+    );
+}
+
+#[test]
+fn noise_and_mixing_reach_the_target_snr() {
+    let input = tone(440.0, 0.5, 32_000);
+    let other = tone(3_000.0, 1.0, 1_000);
+    for attack in [
+        Attack::WhiteNoise { snr_db: 20.0 },
+        Attack::PinkNoise { snr_db: 20.0 },
+        Attack::Mix {
+            other,
+            snr_db: 20.0,
+        },
+    ] {
+        let degraded = attack.apply(&input, SAMPLE_RATE, 1).unwrap();
+        assert_eq!(degraded.len(), input.len());
+        assert!(
+            (snr_db(&input, &degraded) - 20.0).abs() < 0.05,
+            "{}",
+            attack
+        );
+    }
+
+    // Pink noise concentrates its power at low frequencies.
+    let noise = |attack: Attack| {
+        let quiet = tone(1.0, 1e-3, 32_000);
+        let noisy = attack.apply(&quiet, SAMPLE_RATE, 3).unwrap();
+        let high = Attack::HighPass { cutoff_hz: 4_000.0 }
+            .apply(&noisy, SAMPLE_RATE, 0)
+            .unwrap();
+        power(&high) / power(&noisy)
+    };
+    assert!(noise(Attack::WhiteNoise { snr_db: -40.0 }) > 0.45);
+    assert!(noise(Attack::PinkNoise { snr_db: -40.0 }) < 0.15);
+}
+
+#[test]
+fn filters_and_resampling_remove_the_expected_band() {
+    let low = tone(500.0, 0.4, 16_000);
+    let high = tone(6_000.0, 0.4, 16_000);
+    let mixed: Vec<f32> = low.iter().zip(&high).map(|(a, b)| a + b).collect();
+    // Filter edges are excluded from the comparison.
+    let middle = 1_000..15_000;
+
+    for (attack, kept) in [
+        (Attack::LowPass { cutoff_hz: 2_000.0 }, &low),
+        (Attack::HighPass { cutoff_hz: 2_000.0 }, &high),
+        (Attack::Resample { rate: 8_000 }, &low),
+    ] {
+        let filtered = attack.apply(&mixed, SAMPLE_RATE, 0).unwrap();
+        assert_eq!(filtered.len(), mixed.len());
+        let snr = snr_db(&kept[middle.clone()], &filtered[middle.clone()]);
+        assert!(snr > 30.0, "{}: {:.1} dB", attack, snr);
+    }
+
+    let round_trip = Attack::Resample { rate: 48_000 }
+        .apply(&low, SAMPLE_RATE, 0)
+        .unwrap();
+    assert!(snr_db(&low[..15_000], &round_trip[..15_000]) > 30.0);
+}
+
+#[test]
+fn editing_attacks_and_validation() {
+    let input = tone(440.0, 0.5, 16_000);
+    let apply = |attack: Attack| attack.apply(&input, SAMPLE_RATE, 0).unwrap();
+
+    let quieter = apply(Attack::Gain { db: -6.0 });
+    assert!((quieter[10] / input[10] - 0.501).abs() < 1e-3);
+    let louder = apply(Attack::Gain { db: 20.0 });
+    assert!(louder.iter().all(|&s| s.abs() <= 1.0));
+
+    let cropped = apply(Attack::Crop {
+        start_secs: 0.25,
+        duration_secs: 0.5,
+    });
+    assert_eq!(cropped, input[4_000..12_000]);
+    let tail = apply(Attack::Crop {
+        start_secs: 0.75,
+        duration_secs: f32::INFINITY,
+    });
+    assert_eq!(tail, input[12_000..]);
+
+    let delayed = apply(Attack::TimeShift { samples: 100 });
+    assert_eq!(delayed[..100], [0.0; 100]);
+    assert_eq!(delayed[100..], input[..15_900]);
+    let advanced = apply(Attack::TimeShift { samples: -100 });
+    assert_eq!(advanced[..15_900], input[100..]);
+    let edit = Attack::Chain(vec![
+        Attack::Crop {
+            start_secs: 0.25,
+            duration_secs: 0.5,
+        },
+        Attack::TimeShift { samples: 100 },
+        Attack::Gain { db: -6.0 },
+    ]);
+    assert_eq!(edit.delay(SAMPLE_RATE), -3_900);
+
+    let echoed = apply(Attack::Echo {
+        delay_secs: 0.01,
+        gain: 0.5,
+    });
+    assert_eq!(echoed[..160], input[..160]);
+    assert_eq!(echoed[200], input[200] + 0.5 * input[40]);
+
+    let coarse = apply(Attack::Requantize { bits: 4 });
+    assert!(coarse.iter().all(|&s| (s * 8.0).fract() == 0.0));
+
+    for invalid in [
+        Attack::Requantize { bits: 1 },
+        Attack::Resample { rate: 0 },
+        Attack::HighPass { cutoff_hz: 9_000.0 },
+        Attack::WhiteNoise { snr_db: f32::NAN },
+        Attack::Mix {
+            other: Vec::new(),
+            snr_db: 10.0,
+        },
+        Attack::Chain(vec![Attack::Crop {
+            start_secs: -1.0,
+            duration_secs: 1.0,
+        }]),
+    ] {
+        assert!(matches!(
+            invalid.apply(&input, SAMPLE_RATE, 0),
+            Err(AttackError::InvalidParameter(_))
+        ));
+    }
+}
@@ -0,0 +1,142 @@
+//! Tests for the robustness sweep runner.
+
+mod common;
+
+use std::error::Error;
+
+use wavemark::api::builder::WatermarkBuilder;
+use wavemark::embed::params::{EmbedParams, Preset};
+use wavemark::format::FormatBuilder;
+use wavemark::robustness::attack::{Attack, AttackError};
+use wavemark::robustness::runner::{RobustnessError, RobustnessRunner};
+
+use common::{session, Noise};
+
+const MASTER_SECRET: &[u8] = b"robustness-master-secret";
+const SAMPLE_RATE: u32 = 16_000;
+
+fn builder() -> WatermarkBuilder {
+    let mut payload = FormatBuilder::new();
+    payload.payload_builder().account_id("acct_bench").unwrap();
+    WatermarkBuilder::new(SAMPLE_RATE)
+        .payload(payload)
+        .key(session(MASTER_SECRET, "acct_bench", "s1"))
+        .params(
+            EmbedParams::builder()
+                .preset(Preset::Balanced)
+                .sample_rate(SAMPLE_RATE)
+                .strength_db(6.0)
+                .build()
+                .unwrap(),
+        )
+}
+
+fn clip(seed: u32, seconds: usize) -> Vec<f32> {
+    let mut noise = Noise::new(seed);
+    (0..SAMPLE_RATE as usize * seconds)
+        .map(|n| {
+            let t = n as f32 / SAMPLE_RATE as f32;
+            0.25 * (2.0 * std::f32::consts::PI * 180.0 * t).sin() + 0.3 * noise.sample()
+        })
+        .collect()
+}
+
+#[test]
+fn sweep_tabulates_detection_and_bit_errors() -> Result<(), Box<dyn Error>> {
+    let corpus = vec![clip(1, 8), clip(2, 8)];
+    let report = RobustnessRunner::new(builder())
+        .attack(Attack::Chain(Vec::new()))
+        .attacks([
+            Attack::WhiteNoise { snr_db: 20.0 },
+            Attack::LowPass { cutoff_hz: 2_000.0 },
+            Attack::Crop {
+                start_secs: 1.0,
+                duration_secs: 0.05,
+            },
+        ])
+        .run(&corpus)?;
+
+    assert_eq!(report.clip_count(), 2);
+    assert_eq!(
+        report.attacks(),
+        [
+            "none",
+            "white noise 20 dB",
+            "low-pass 2000 Hz",
+            "crop 0.05 s from 1 s"
+        ]
+    );
+    for attack in 0..2 {
+        assert_eq!(report.detection_rate(attack), 1.0);
+        assert_eq!(report.recovery_rate(attack), 1.0);
+        assert!(report.mean_bit_error_rate(attack) < 0.02);
+    }
+    // Most of the embedding band is filtered out.
+    assert_eq!(report.detection_rate(2), 0.0);
+    assert!(report.mean_bit_error_rate(2) > 0.3);
+    // Too short to analyse.
+    let cropped = report.outcome(3, 1);
+    assert!(!cropped.detected && !cropped.recovered);
+    assert_eq!(cropped.false_positive_probability, 1.0);
+
+    let table = report.to_string();
+    assert_eq!(table.lines().count(), 5);
+    assert!(table.lines().nth(1).unwrap().starts_with("none"));
+    Ok(())
+}
+
+#[test]
+fn bit_errors_are_measured_at_the_embedded_position() -> Result<(), Box<dyn Error>> {
+    let report = RobustnessRunner::new(builder())
+        .attacks([
+            Attack::TimeShift { samples: 1_234 },
+            Attack::Crop {
+                start_secs: 1.3,
+                duration_secs: f32::INFINITY,
+            },
+            Attack::WhiteNoise { snr_db: 0.0 },
+        ])
+        .run(&[clip(5, 8)])?;
+
+    let shifted = report.outcome(0, 0);
+    assert!(shifted.recovered);
+    assert_eq!(shifted.bit_error_rate, 0.0);
+    // Neither frame decodes, but most channel bits still come through.
+    for attack in 1..3 {
+        let outcome = report.outcome(attack, 0);
+        assert!(!outcome.recovered, "{}", report.attacks()[attack]);
+        assert!(outcome.bit_error_rate < 0.2, "{}", report.attacks()[attack]);
+    }
+    Ok(())
+}
+
+#[test]
+fn runs_are_reproducible() -> Result<(), Box<dyn Error>> {
+    let corpus = vec![clip(3, 6)];
+    let runner = RobustnessRunner::new(builder())
+        .attacks([
+            Attack::PinkNoise { snr_db: 10.0 },
+            Attack::Chain(vec![
+                Attack::Gain { db: -12.0 },
+                Attack::WhiteNoise { snr_db: 6.0 },
+            ]),
+        ])
+        .with_seed(42);
+    assert_eq!(runner.attack_list().len(), 2);
+    assert_eq!(runner.run(&corpus)?, runner.run(&corpus)?);
+    Ok(())
+}
+
+#[test]
+fn rejects_empty_corpora_and_invalid_attacks() {
+    let runner = RobustnessRunner::new(builder());
+    assert!(matches!(runner.run(&[]), Err(RobustnessError::EmptyCorpus)));
+
+    let runner = runner.attack(Attack::LowPass {
+        cutoff_hz: SAMPLE_RATE as f32,
+    });
+    assert!(matches!(
+        runner.run(&[clip(4, 2)]),
+        Err(RobustnessError::Attack(AttackError::InvalidParameter(_)))
+    ));
+}
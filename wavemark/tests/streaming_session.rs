@@ -0,0 +1,167 @@
+//! Tests for packet-by-packet streaming embedding.
+
+mod common;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use wavemark::api::builder::WatermarkBuilder;
+use wavemark::embed::params::{EmbedParams, Preset};
+use wavemark::embed::spread_spectrum::{EmbedError, SpreadSpectrumEmbedder};
+use wavemark::format::FormatBuilder;
+use wavemark::streaming::session::StreamingSession;
+
+use common::{session, Noise};
+
+const MASTER_SECRET: &[u8] = b"streaming-master-secret";
+const SAMPLE_RATE: u32 = 16_000;
+/// 20 ms at 16 kHz.
+const PACKET: usize = 320;
+
+/// Counts allocations made by threads that opted in.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if COUNTING.with(Cell::get) {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn payload() -> FormatBuilder {
+    let mut builder = FormatBuilder::new();
+    builder.payload_builder().account_id("acct_live").unwrap();
+    builder
+}
+
+fn params() -> EmbedParams {
+    EmbedParams::builder()
+        .preset(Preset::Balanced)
+        .sample_rate(SAMPLE_RATE)
+        .strength_db(3.0)
+        .build()
+        .unwrap()
+}
+
+fn host(len: usize) -> Vec<f32> {
+    let mut noise = Noise::new(0x0bad_cafe);
+    (0..len)
+        .map(|n| {
+            let t = n as f32 / SAMPLE_RATE as f32;
+            0.25 * (2.0 * std::f32::consts::PI * 180.0 * t).sin() + 0.3 * noise.sample()
+        })
+        .collect()
+}
+
+fn stream(session: &mut StreamingSession, input: &[f32], packet: usize) -> Vec<f32> {
+    let mut output = Vec::with_capacity(input.len() + session.latency());
+    for chunk in input.chunks(packet) {
+        let out = session.push(chunk);
+        assert_eq!(out.len(), chunk.len());
+        output.extend_from_slice(out);
+    }
+    output.extend_from_slice(session.finish());
+    output
+}
+
+#[test]
+fn matches_batch_embedding_after_fixed_latency() -> Result<(), Box<dyn Error>> {
+    let bytes = payload().build()?.bytes;
+    let input = host(SAMPLE_RATE as usize * 3);
+    let batch = SpreadSpectrumEmbedder::from_key_context(
+        &session(MASTER_SECRET, "acct_live", "s1"),
+        params(),
+    )?
+    .embed(&input, &bytes)?;
+
+    let mut live =
+        StreamingSession::new(&session(MASTER_SECRET, "acct_live", "s1"), params(), &bytes)?;
+    let latency = live.latency();
+    assert_eq!(latency, params().frame_len() - 1);
+    assert!((live.latency_secs() - latency as f32 / SAMPLE_RATE as f32).abs() < 1e-9);
+
+    let output = stream(&mut live, &input, PACKET);
+    assert_eq!(output.len(), input.len() + latency);
+    assert!(output[..latency].iter().all(|&sample| sample == 0.0));
+    for (streamed, expected) in output[latency..].iter().zip(&batch) {
+        assert!((streamed - expected).abs() < 1e-4);
+    }
+
+    // `finish` restarts the schedule, so a second stream is marked identically.
+    assert_eq!(stream(&mut live, &input, 97), output);
+    Ok(())
+}
+
+#[test]
+fn any_excerpt_of_a_long_stream_is_detectable() -> Result<(), Box<dyn Error>> {
+    let builder = WatermarkBuilder::new(SAMPLE_RATE)
+        .payload(payload())
+        .key(session(MASTER_SECRET, "acct_live", "s1"))
+        .params(params());
+    let bytes = payload().build()?.bytes;
+    let mut live =
+        StreamingSession::new(&session(MASTER_SECRET, "acct_live", "s1"), params(), &bytes)?;
+    let output = stream(&mut live, &host(SAMPLE_RATE as usize * 24), PACKET);
+
+    let mut detector = builder.build_detector()?;
+    let excerpt_len = SAMPLE_RATE as usize * 10;
+    for start in [4_321, SAMPLE_RATE as usize * 11 + 77] {
+        let report = detector.detect(&output[start..start + excerpt_len])?;
+        assert!(report.is_present(), "excerpt at {}", start);
+        assert_eq!(report.bytes.as_deref(), Some(bytes.as_slice()));
+    }
+    assert!(live.cycle_len() < excerpt_len);
+    Ok(())
+}
+
+#[test]
+fn pushing_packets_does_not_allocate() -> Result<(), Box<dyn Error>> {
+    let bytes = payload().build()?.bytes;
+    let mut live =
+        StreamingSession::new(&session(MASTER_SECRET, "acct_live", "s1"), params(), &bytes)?
+            .with_max_packet(1_000);
+    assert_eq!(live.max_packet(), 1_000);
+    let input = host(SAMPLE_RATE as usize);
+
+    COUNTING.with(|counting| counting.set(true));
+    for (index, chunk) in input.chunks(PACKET).enumerate() {
+        live.push(chunk);
+        if index % 7 == 0 {
+            live.push(&input[..1_000]);
+        }
+    }
+    live.finish();
+    COUNTING.with(|counting| counting.set(false));
+
+    assert_eq!(ALLOCATIONS.load(Ordering::Relaxed), 0);
+    Ok(())
+}
+
+#[test]
+fn rejects_empty_payload_and_invalid_params() {
+    assert!(matches!(
+        StreamingSession::new(&session(MASTER_SECRET, "acct_live", "s1"), params(), b""),
+        Err(EmbedError::EmptyPayload)
+    ));
+    assert!(matches!(
+        params().to_builder().frames_per_symbol(3).build(),
+        Err(EmbedError::InvalidParams(_))
+    ));
+}
@@ -1,17 +1,174 @@
-//! Build verification test for wavemark api module
+//! Tests for the high-level watermarking API
 //!
-//! This test ensures the api module builds and its basic functionality works.
+//! These tests drive embedding and detection through `WatermarkBuilder` only,
+//! the way integrators are expected to use the library.
+
+mod common;
+
+use std::error::Error;
 
 use wavemark::api;
+use wavemark::api::builder::{WatermarkBuilder, WatermarkError};
+use wavemark::detect::confidence::Decision;
+use wavemark::embed::params::{EmbedParams, Preset};
+use wavemark::embed::spread_spectrum::EmbedError;
+use wavemark::format::FormatBuilder;
+
+use common::{session, Noise};
+
+const MASTER_SECRET: &[u8] = b"api-test-master-secret";
+
+fn payload() -> FormatBuilder {
+    let mut builder = FormatBuilder::new();
+    builder
+        .payload_builder()
+        .account_id("acct_api")
+        .unwrap()
+        .text_field("content.title", "API")
+        .unwrap();
+    builder
+}
+
+fn host(len: usize, sample_rate: u32) -> Vec<f32> {
+    let mut noise = Noise::new(0x5eed_1234);
+    (0..len)
+        .map(|n| {
+            let t = n as f32 / sample_rate as f32;
+            0.25 * (2.0 * std::f32::consts::PI * 220.0 * t).sin() + 0.2 * noise.sample()
+        })
+        .collect()
+}
+
+fn params(sample_rate: u32) -> EmbedParams {
+    EmbedParams::builder()
+        .preset(Preset::Balanced)
+        .sample_rate(sample_rate)
+        .strength_db(3.0)
+        .build()
+        .unwrap()
+}
 
 #[test]
-fn test_api_crate_builds() {
-    // Test that the api module can be imported and used
-    api::process();
+fn test_embed_and_detect_round_trip() -> Result<(), Box<dyn Error>> {
+    let (mut embedder, mut detector) = WatermarkBuilder::new(48_000)
+        .payload(payload())
+        .key(session(MASTER_SECRET, "acct_api", "s1"))
+        .params(params(48_000))
+        .build()?;
+
+    let mut samples = host(48_000 * 8, 48_000);
+    let original = samples.clone();
+    embedder.embed(&mut samples)?;
+    assert_ne!(samples, original);
+    assert_eq!(embedder.params(), detector.params());
+
+    let report = detector.detect(&samples[10_000..])?;
+    assert!(report.is_present());
+    assert_eq!(report.bytes.as_deref(), Some(embedder.payload_bytes()));
+    let frame = report.frame.unwrap();
+    assert_eq!(frame.account_id(), embedder.frame().account_id());
+    assert_eq!(frame.account_id().unwrap().as_str(), "acct_api");
+    Ok(())
 }
 
 #[test]
-fn test_api_crate_structure() {
-    // Test that the api module has the expected structure
-    assert!(true, "API module structure is valid");
+fn test_process_marks_and_verifies() -> Result<(), Box<dyn Error>> {
+    let builder = WatermarkBuilder::new(48_000)
+        .payload(payload())
+        .key(session(MASTER_SECRET, "acct_api", "s1"))
+        .params(params(48_000));
+
+    let mut samples = host(48_000 * 12, 48_000);
+    let original = samples.clone();
+    let report = api::process(builder.clone(), &mut samples)?;
+    assert_ne!(samples, original);
+    assert!(report.is_present());
+    let frame = report.frame.unwrap();
+    assert_eq!(frame.account_id().unwrap().as_str(), "acct_api");
+
+    assert!(matches!(
+        api::process(builder.clone(), &mut [0.0; 100]),
+        Err(WatermarkError::Detect(_))
+    ));
+    assert!(matches!(
+        api::process(
+            WatermarkBuilder::new(48_000).payload(payload()),
+            &mut samples
+        ),
+        Err(WatermarkError::MissingKey)
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_unmarked_audio_reports_absence() -> Result<(), Box<dyn Error>> {
+    let mut detector = WatermarkBuilder::new(44_100)
+        .key(session(MASTER_SECRET, "acct_api", "s1"))
+        .build_detector()?;
+
+    let report = detector.detect(&host(44_100 * 4, 44_100))?;
+    assert!(!report.is_present());
+    assert_eq!(report.frame, None);
+    assert_eq!(report.decision(), Decision::Absent);
+
+    assert!(matches!(
+        detector.detect(&[0.0; 100]),
+        Err(WatermarkError::Detect(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_builder_requires_payload_and_key() {
+    assert!(matches!(
+        WatermarkBuilder::new(44_100).payload(payload()).build(),
+        Err(WatermarkError::MissingKey)
+    ));
+    assert!(matches!(
+        WatermarkBuilder::new(44_100)
+            .key(session(MASTER_SECRET, "acct_api", "s1"))
+            .build_embedder(),
+        Err(WatermarkError::MissingPayload)
+    ));
+}
+
+#[test]
+fn test_sample_rate_is_applied_and_validated() -> Result<(), Box<dyn Error>> {
+    // Parameters written for 44.1 kHz adopt the builder's sample rate.
+    let (embedder, _) = WatermarkBuilder::new(32_000)
+        .payload(payload())
+        .key(session(MASTER_SECRET, "acct_api", "s1"))
+        .params(EmbedParams::builder().band_high_hz(12_000.0).build()?)
+        .build()?;
+    assert_eq!(embedder.params().sample_rate(), 32_000);
+
+    // A band that no longer fits below Nyquist is rejected up front.
+    assert!(matches!(
+        WatermarkBuilder::new(12_000)
+            .payload(payload())
+            .key(session(MASTER_SECRET, "acct_api", "s1"))
+            .params(EmbedParams::default())
+            .build(),
+        Err(WatermarkError::Embed(EmbedError::InvalidParams(_)))
+    ));
+
+    // A preset is resolved at the builder's rate, so its band still fits.
+    let detector = WatermarkBuilder::new(12_000)
+        .key(session(MASTER_SECRET, "acct_api", "s1"))
+        .params_builder(
+            EmbedParams::builder()
+                .preset(Preset::Robust)
+                .strength_db(4.0),
+        )
+        .build_detector()?;
+    assert_eq!(
+        detector.params(),
+        &EmbedParams::builder()
+            .preset(Preset::Robust)
+            .sample_rate(12_000)
+            .strength_db(4.0)
+            .build()?
+    );
+    assert_eq!(detector.params().band_high_hz(), 5_400.0);
+    Ok(())
 }
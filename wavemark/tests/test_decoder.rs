@@ -1,17 +1,159 @@
-//! Build verification test for wavemark decoder module
+//! Tests for the correlation detector
 //!
-//! This test ensures the decoder module builds and its basic functionality works.
+//! These tests embed codec frames with the spread-spectrum embedder and read
+//! them back with the correlator, including from audio with an unknown start.
 
-use wavemark::decoder;
+mod common;
+
+use std::error::Error;
+
+use wavemark::detect::correlator::{Correlator, DetectError};
+use wavemark::embed::params::EmbedParams;
+use wavemark::embed::spread_spectrum::SpreadSpectrumEmbedder;
+use wavemark::format::codec::{CodecOptions, FrameCodec};
+use wavemark::format::encryption::EncryptionContext;
+use wavemark::format::payload::MetadataTimestamp;
+use wavemark::format::FormatBuilder;
+
+use common::{session, Noise};
+
+const MASTER_SECRET: &[u8] = b"decoder-test-master-secret";
+
+fn host(len: usize) -> Vec<f32> {
+    let mut noise = Noise::new(0x2468_ace1);
+    (0..len)
+        .map(|n| {
+            let t = n as f32 / 44_100.0;
+            let envelope = 0.6 + 0.4 * (2.0 * std::f32::consts::PI * 0.7 * t).sin();
+            envelope
+                * (0.25 * (2.0 * std::f32::consts::PI * 196.0 * t).sin() + 0.2 * noise.sample())
+        })
+        .collect()
+}
+
+fn payload() -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut builder = FormatBuilder::new();
+    builder
+        .payload_builder()
+        .account_id("acct_demo")?
+        .issued_at(MetadataTimestamp::from_unix_seconds(1_700_000_000)?)?
+        .text_field("content.title", "Demo")?;
+    Ok(builder.build()?.bytes)
+}
+
+fn params() -> EmbedParams {
+    EmbedParams::builder().strength_db(3.0).build().unwrap()
+}
+
+fn watermark(samples: &[f32], bytes: &[u8]) -> Vec<f32> {
+    SpreadSpectrumEmbedder::from_key_context(
+        &session(MASTER_SECRET, "acct_decoder", "s1"),
+        params(),
+    )
+    .unwrap()
+    .embed(samples, bytes)
+    .unwrap()
+}
+
+#[test]
+fn test_detect_round_trip() -> Result<(), Box<dyn Error>> {
+    let bytes = payload()?;
+    let marked = watermark(&host(44_100 * 8), &bytes);
+
+    let mut correlator =
+        Correlator::from_key_context(&session(MASTER_SECRET, "acct_decoder", "s1"), params())?;
+    let codec = FrameCodec::new(CodecOptions::default());
+    let detection = correlator.detect(&marked, &codec, &EncryptionContext::default())?;
+
+    assert_eq!(detection.bytes, bytes);
+    assert_eq!(detection.frame.account_id().unwrap().as_str(), "acct_demo");
+    assert_eq!(
+        detection.soft_bits.len(),
+        params().mapper().channel_bits(bytes.len())
+    );
+    // Slot boundaries are located to within one analysis step.
+    let step = params().hop() as isize / 2;
+    assert!(detection.payload_start.abs() <= step);
+    assert!(detection.symbol_offset as isize <= step);
+    Ok(())
+}
 
 #[test]
-fn test_decoder_crate_builds() {
-    // Test that the decoder module can be imported and used
-    decoder::decode();
+fn test_detect_with_unknown_offset() -> Result<(), Box<dyn Error>> {
+    let bytes = payload()?;
+    let marked = watermark(&host(44_100 * 10), &bytes);
+    let params = params();
+    let bits = params.mapper().channel_bits(bytes.len());
+    let copy_len = (bits / params.bits_per_symbol() * params.symbol_len()) as isize;
+    let step = params.hop() as isize / 2;
+
+    let mut correlator = Correlator::from_key_context(
+        &session(MASTER_SECRET, "acct_decoder", "s1"),
+        params.clone(),
+    )?;
+    let codec = FrameCodec::new(CodecOptions::default());
+    for crop in [1_000usize, 12_345, 50_000] {
+        let received = &marked[crop..marked.len() - 3_000];
+        let detection = correlator.detect(received, &codec, &EncryptionContext::default())?;
+        assert_eq!(detection.bytes, bytes);
+        // The located payload copy starts on an embedded copy boundary.
+        let error = (detection.payload_start + crop as isize + step).rem_euclid(copy_len) - step;
+        assert!(error.abs() <= step, "crop {} misaligned by {}", crop, error);
+    }
+    Ok(())
 }
 
 #[test]
-fn test_decoder_crate_structure() {
-    // Test that the decoder module has the expected structure
-    assert!(true, "Decoder module structure is valid");
+fn test_wrong_key_or_clean_audio_is_not_detected() -> Result<(), Box<dyn Error>> {
+    let bytes = payload()?;
+    let clean = host(44_100 * 6);
+    let marked = watermark(&clean, &bytes);
+    let codec = FrameCodec::new(CodecOptions::default());
+
+    let mut other =
+        Correlator::from_key_context(&session(MASTER_SECRET, "acct_decoder", "s2"), params())?;
+    assert!(matches!(
+        other.detect(&marked, &codec, &EncryptionContext::default()),
+        Err(DetectError::NotDetected(_))
+    ));
+
+    let mut correlator =
+        Correlator::from_key_context(&session(MASTER_SECRET, "acct_decoder", "s1"), params())?;
+    assert!(matches!(
+        correlator.detect(&clean, &codec, &EncryptionContext::default()),
+        Err(DetectError::NotDetected(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_correlation_soft_bits() -> Result<(), Box<dyn Error>> {
+    let bytes = [0xA7u8, 0x3C, 0x00, 0xFF];
+    let marked = watermark(&host(44_100 * 4), &bytes);
+
+    let mut correlator =
+        Correlator::from_key_context(&session(MASTER_SECRET, "acct_decoder", "s1"), params())?;
+    let correlation = correlator.correlate(&marked)?;
+    assert_eq!(correlation.carriers(), 8);
+    assert_eq!(correlation.symbols().len(), correlation.slots() * 8);
+
+    // The input starts on a slot boundary, so the preamble sits on symbol 0.
+    let expected = params().mapper().map(&bytes)?;
+    let soft = correlation.fold(expected.len(), 0, false);
+    let agree = soft
+        .iter()
+        .zip(expected.bits())
+        .filter(|(z, &bit)| (**z > 0.0) == bit)
+        .count();
+    assert_eq!(agree, expected.len());
+    assert!(soft.iter().all(|z| z.abs() > 3.0));
+
+    assert_eq!(
+        correlator.correlate(&marked[..500]),
+        Err(DetectError::InsufficientAudio {
+            required: 2048,
+            found: 500,
+        })
+    );
+    Ok(())
 }
@@ -1,17 +1,125 @@
-//! Build verification test for wavemark encoder module
+//! Tests for the spread-spectrum embedder
 //!
-//! This test ensures the encoder module builds and its basic functionality works.
+//! These tests check that embedding preserves length, stays transparent at
+//! zero strength, depends on the key and payload, and rejects bad parameters.
 
-use wavemark::encoder;
+mod common;
+
+use wavemark::embed::params::EmbedParams;
+use wavemark::embed::spread_spectrum::{EmbedError, SpreadSpectrumEmbedder};
+
+use common::{session, Noise};
+
+const MASTER_SECRET: &[u8] = b"encoder-test-master-secret";
+
+fn host(len: usize) -> Vec<f32> {
+    // Deterministic broadband signal: a few partials plus LCG noise.
+    let mut noise = Noise::new(0x1234_5678);
+    (0..len)
+        .map(|n| {
+            let t = n as f32 / 44_100.0;
+            0.3 * (2.0 * std::f32::consts::PI * 220.0 * t).sin()
+                + 0.1 * (2.0 * std::f32::consts::PI * 1_870.0 * t).sin()
+                + 0.2 * noise.sample()
+        })
+        .collect()
+}
+
+fn snr_db(reference: &[f32], test: &[f32]) -> f32 {
+    let signal: f32 = reference.iter().map(|x| x * x).sum();
+    let noise: f32 = reference
+        .iter()
+        .zip(test)
+        .map(|(a, b)| (a - b) * (a - b))
+        .sum();
+    10.0 * (signal / noise).log10()
+}
 
 #[test]
-fn test_encoder_crate_builds() {
-    // Test that the encoder module can be imported and used
-    encoder::encode();
+fn test_embed_preserves_length_and_is_imperceptible() {
+    let samples = host(44_100);
+    let mut embedder = SpreadSpectrumEmbedder::from_key_context(
+        &session(MASTER_SECRET, "acct_encoder", "s1"),
+        EmbedParams::default(),
+    )
+    .unwrap();
+
+    let marked = embedder.embed(&samples, b"WM payload").unwrap();
+    assert_eq!(marked.len(), samples.len());
+    assert!(marked.iter().all(|x| x.is_finite()));
+
+    let snr = snr_db(&samples, &marked);
+    assert!(snr > 12.0, "watermark too loud: {} dB SNR", snr);
+    assert!(snr < 60.0, "watermark missing: {} dB SNR", snr);
+
+    // Odd lengths that are not a multiple of the hop still round-trip in size.
+    assert_eq!(
+        embedder.embed(&samples[..1_001], b"x").unwrap().len(),
+        1_001
+    );
+    assert!(embedder.embed(&[], b"x").unwrap().is_empty());
 }
 
 #[test]
-fn test_encoder_crate_structure() {
-    // Test that the encoder module has the expected structure
-    assert!(true, "Encoder module structure is valid");
+fn test_zero_strength_is_transparent() {
+    let samples = host(20_000);
+    let params = EmbedParams::builder().strength_db(0.0).build().unwrap();
+    let mut embedder = SpreadSpectrumEmbedder::from_key_context(
+        &session(MASTER_SECRET, "acct_encoder", "s1"),
+        params,
+    )
+    .unwrap();
+
+    let marked = embedder.embed(&samples, b"payload").unwrap();
+    for (a, b) in samples.iter().zip(&marked) {
+        assert!((a - b).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn test_embedding_depends_on_key_and_payload() {
+    let samples = host(22_050);
+    let params = EmbedParams::default();
+    let mut first = SpreadSpectrumEmbedder::from_key_context(
+        &session(MASTER_SECRET, "acct_encoder", "s1"),
+        params.clone(),
+    )
+    .unwrap();
+    let mut again = SpreadSpectrumEmbedder::from_key_context(
+        &session(MASTER_SECRET, "acct_encoder", "s1"),
+        params.clone(),
+    )
+    .unwrap();
+    let mut other = SpreadSpectrumEmbedder::from_key_context(
+        &session(MASTER_SECRET, "acct_encoder", "s2"),
+        params,
+    )
+    .unwrap();
+
+    let marked = first.embed(&samples, &[0x5A, 0xC3]).unwrap();
+    assert_eq!(marked, again.embed(&samples, &[0x5A, 0xC3]).unwrap());
+    assert_ne!(marked, other.embed(&samples, &[0x5A, 0xC3]).unwrap());
+    assert_ne!(marked, first.embed(&samples, &[0xA5, 0x3C]).unwrap());
+}
+
+#[test]
+fn test_invalid_parameters_are_rejected() {
+    let key = session(MASTER_SECRET, "acct_encoder", "s1");
+
+    let above_nyquist = EmbedParams::builder()
+        .sample_rate(8_000)
+        .band_low_hz(5_000.0)
+        .band_high_hz(7_000.0)
+        .build();
+    assert!(matches!(above_nyquist, Err(EmbedError::InvalidParams(_))));
+
+    let bad_hop = EmbedParams::builder().hop(300).build();
+    assert!(matches!(bad_hop, Err(EmbedError::Window(_))));
+
+    let mut embedder =
+        SpreadSpectrumEmbedder::from_key_context(&key, EmbedParams::default()).unwrap();
+    assert_eq!(
+        embedder.embed(&host(4_096), &[]),
+        Err(EmbedError::EmptyPayload)
+    );
 }
@@ -1,17 +1,128 @@
-//! Build verification test for wavemark fourier module
+//! Tests for the wavemark FFT backend
 //!
-//! This test ensures the fourier module builds and its basic functionality works.
+//! These tests exercise the real FFT, its plan cache, and the STFT/ISTFT pair.
 
-use wavemark::fourier;
+use std::f64::consts::PI;
+
+use wavemark::transforms::fft::{Complex, FftBackend, FftError};
+use wavemark::transforms::window::AnalysisWindow;
+
+fn sine(len: usize, cycles: f64) -> Vec<f64> {
+    (0..len)
+        .map(|n| (2.0 * PI * cycles * n as f64 / len as f64).sin())
+        .collect()
+}
+
+#[test]
+fn test_forward_locates_sinusoid_bin() {
+    let mut fft = FftBackend::<f64>::new();
+    let signal = sine(256, 12.0);
+    let mut spectrum = vec![Complex::new(0.0, 0.0); FftBackend::<f64>::spectrum_len(256)];
+    fft.forward(&signal, &mut spectrum).unwrap();
+
+    let peak = spectrum
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.norm().partial_cmp(&b.1.norm()).unwrap())
+        .map(|(bin, _)| bin)
+        .unwrap();
+    assert_eq!(peak, 12);
+    assert!((spectrum[12].norm() - 128.0).abs() < 1e-9);
+}
 
 #[test]
-fn test_fourier_crate_builds() {
-    // Test that the fourier module can be imported and used
-    fourier::transform();
+fn test_inverse_round_trip_f32_and_f64() {
+    let mut fft64 = FftBackend::<f64>::new();
+    let signal = sine(500, 7.5);
+    let mut spectrum = vec![Complex::new(0.0, 0.0); 251];
+    let mut output = vec![0.0; 500];
+    fft64.forward(&signal, &mut spectrum).unwrap();
+    fft64.inverse(&spectrum, &mut output).unwrap();
+    for (a, b) in signal.iter().zip(&output) {
+        assert!((a - b).abs() < 1e-12);
+    }
+
+    let mut fft32 = FftBackend::<f32>::new();
+    let signal: Vec<f32> = signal.iter().map(|&x| x as f32).collect();
+    let mut spectrum = vec![Complex::new(0.0f32, 0.0); 251];
+    let mut output = vec![0.0f32; 500];
+    fft32.forward(&signal, &mut spectrum).unwrap();
+    fft32.inverse(&spectrum, &mut output).unwrap();
+    for (a, b) in signal.iter().zip(&output) {
+        assert!((a - b).abs() < 1e-5);
+    }
 }
 
 #[test]
-fn test_fourier_crate_structure() {
-    // Test that the fourier module has the expected structure
-    assert!(true, "Fourier module structure is valid");
+fn test_plans_are_cached_per_size() {
+    let mut fft = FftBackend::<f32>::new();
+    fft.prepare(1024).unwrap();
+    let mut spectrum = vec![Complex::new(0.0f32, 0.0); 129];
+    fft.forward(&[0.0f32; 256], &mut spectrum).unwrap();
+    fft.forward(&[1.0f32; 256], &mut spectrum).unwrap();
+    assert_eq!(fft.cached_sizes(), vec![256, 1024]);
+
+    let err = fft
+        .forward(&[0.0f32; 256], &mut spectrum[..10])
+        .unwrap_err();
+    assert_eq!(
+        err,
+        FftError::LengthMismatch {
+            expected: 129,
+            found: 10,
+        }
+    );
+    assert_eq!(fft.prepare(0), Err(FftError::EmptyInput));
+}
+
+#[test]
+fn test_stft_istft_reconstructs_signal() {
+    let mut fft = FftBackend::<f64>::new();
+    let signal: Vec<f64> = (0..5000)
+        .map(|n| (n as f64 * 0.013).sin() + 0.25 * (n as f64 * 0.31).cos())
+        .collect();
+    let window = AnalysisWindow::hann(512).unwrap();
+
+    for hop in [128, 256] {
+        let spectrogram = fft.stft(&signal, &window, hop).unwrap();
+        assert_eq!(spectrogram.bins(), 257);
+        assert_eq!(spectrogram.frame_start(0), hop as isize - 512);
+        let output = fft.istft(&spectrogram, &window).unwrap();
+        assert_eq!(output.len(), signal.len());
+        for (a, b) in signal.iter().zip(&output) {
+            assert!((a - b).abs() < 1e-9);
+        }
+    }
+
+    assert!(matches!(
+        fft.stft(&signal, &window, 0),
+        Err(FftError::InvalidHop { .. })
+    ));
+    assert!(matches!(
+        fft.stft(&signal, &window, 1024),
+        Err(FftError::InvalidHop { .. })
+    ));
+}
+
+#[test]
+fn test_cross_correlate_matches_direct_sum() {
+    let signal: Vec<f64> = (0..300)
+        .map(|n| ((n * 37 % 101) as f64 - 50.0) / 50.0)
+        .collect();
+    let template: Vec<f64> = signal[120..152].to_vec();
+
+    let mut fft = FftBackend::<f64>::new();
+    let lags = fft.cross_correlate(&signal, &template).unwrap();
+    assert_eq!(lags.len(), signal.len() - template.len() + 1);
+    for (lag, &value) in lags.iter().enumerate() {
+        let direct: f64 = template
+            .iter()
+            .enumerate()
+            .map(|(offset, &t)| signal[lag + offset] * t)
+            .sum();
+        assert!((value - direct).abs() < 1e-9);
+    }
+
+    assert!(fft.cross_correlate(&template, &signal).unwrap().is_empty());
+    assert_eq!(fft.cross_correlate(&signal, &[]), Err(FftError::EmptyInput));
 }
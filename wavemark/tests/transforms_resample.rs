@@ -0,0 +1,91 @@
+//! Tests for band-limited sample-rate conversion.
+
+use wavemark::transforms::resample::{resample, ResampleError, Resampler};
+
+fn tone(hz: f32, seconds: f32, sample_rate: u32) -> Vec<f32> {
+    (0..(seconds * sample_rate as f32) as usize)
+        .map(|n| 0.5 * (2.0 * std::f32::consts::PI * hz * n as f32 / sample_rate as f32).sin())
+        .collect()
+}
+
+fn snr_db(reference: &[f32], test: &[f32]) -> f32 {
+    let signal: f32 = reference.iter().map(|x| x * x).sum();
+    let error: f32 = reference
+        .iter()
+        .zip(test)
+        .map(|(a, b)| (a - b) * (a - b))
+        .sum();
+    10.0 * (signal / error).log10()
+}
+
+/// Samples away from the edges, where the zero padding shows.
+fn middle(samples: &[f32]) -> &[f32] {
+    let margin = samples.len() / 10;
+    &samples[margin..samples.len() - margin]
+}
+
+#[test]
+fn tones_below_both_nyquists_are_preserved() {
+    for (from, to) in [
+        (44_100, 16_000),
+        (16_000, 44_100),
+        (24_000, 22_050),
+        (48_000, 24_000),
+        (22_050, 48_000),
+    ] {
+        let input = tone(1_000.0, 0.5, from);
+        let resampler = Resampler::new(from, to).unwrap();
+        let output = resampler.process(&input);
+        assert_eq!(output.len(), resampler.output_len(input.len()));
+
+        let expected = tone(1_000.0, 0.5, to);
+        let snr = snr_db(middle(&expected), middle(&output[..expected.len()]));
+        assert!(snr > 60.0, "{} -> {}: {:.1} dB", from, to, snr);
+    }
+}
+
+#[test]
+fn content_above_the_target_nyquist_is_removed() {
+    let input = tone(7_000.0, 0.5, 44_100);
+    let output = resample(&input, 44_100, 12_000).unwrap();
+    let power = middle(&output).iter().map(|x| x * x).sum::<f32>() / middle(&output).len() as f32;
+    // A 0.5 amplitude tone has a power of -9 dB.
+    assert!(10.0 * power.log10() < -80.0, "{}", 10.0 * power.log10());
+}
+
+#[test]
+fn round_trips_and_identity() {
+    let input = tone(3_000.0, 0.5, 16_000);
+    let up = resample(&input, 16_000, 44_100).unwrap();
+    let back = resample(&up, 44_100, 16_000).unwrap();
+    assert!(snr_db(middle(&input), middle(&back[..input.len()])) > 60.0);
+
+    let identity = Resampler::new(48_000, 48_000).unwrap();
+    assert!(identity.is_identity());
+    assert_eq!(identity.process(&input), input);
+}
+
+#[test]
+fn lengths_and_invalid_rates() {
+    let resampler = Resampler::new(44_100, 16_000).unwrap();
+    assert_eq!(resampler.output_len(44_100), 16_000);
+    assert_eq!(resampler.output_len(1), 1);
+    assert_eq!(resampler.output_len(0), 0);
+    assert!(resampler.process(&[]).is_empty());
+    assert_eq!(
+        (resampler.from_rate(), resampler.to_rate()),
+        (44_100, 16_000)
+    );
+
+    // Coprime rates exceed the phase table and still convert accurately.
+    let input = tone(500.0, 0.25, 44_101);
+    let output = resample(&input, 44_101, 16_000).unwrap();
+    let expected = tone(500.0, 0.25, 16_000);
+    assert!(snr_db(middle(&expected), middle(&output[..expected.len()])) > 50.0);
+
+    assert_eq!(
+        Resampler::new(0, 16_000).unwrap_err(),
+        ResampleError::ZeroRate
+    );
+    assert_eq!(resample(&input, 16_000, 0), Err(ResampleError::ZeroRate));
+}
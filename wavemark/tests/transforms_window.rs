@@ -0,0 +1,123 @@
+use wavemark::transforms::fft::FftBackend;
+use wavemark::transforms::window::{AnalysisWindow, OverlapMode, WindowError, WindowKind};
+
+#[test]
+fn windows_have_expected_shape() {
+    for kind in [
+        WindowKind::Hann,
+        WindowKind::Hamming,
+        WindowKind::BlackmanHarris,
+        WindowKind::Kaiser { beta: 8.0 },
+        WindowKind::SqrtHann,
+    ] {
+        let window = AnalysisWindow::new(kind, 256).unwrap();
+        let w = window.coefficients();
+        assert_eq!(window.kind(), Some(kind));
+        assert_eq!(w.len(), 256);
+        // Periodic windows peak at N/2 and are symmetric around it.
+        assert!((w[128] - 1.0).abs() < 1e-3, "{:?} peak {}", kind, w[128]);
+        for n in 1..128 {
+            assert!((w[128 - n] - w[128 + n]).abs() < 1e-12);
+        }
+    }
+
+    assert_eq!(AnalysisWindow::hann(8).unwrap().coefficients()[0], 0.0);
+    assert!((AnalysisWindow::hamming(8).unwrap().coefficients()[0] - 0.08).abs() < 1e-12);
+    let kaiser = AnalysisWindow::kaiser(64, 0.0).unwrap();
+    assert!(kaiser
+        .coefficients()
+        .iter()
+        .all(|&w| (w - 1.0).abs() < 1e-12));
+}
+
+#[test]
+fn cola_holds_for_supported_hops() {
+    let hann = AnalysisWindow::hann(1024).unwrap();
+    assert!((hann.check_overlap_add(512, OverlapMode::Analysis).unwrap() - 1.0).abs() < 1e-9);
+    assert!((hann.check_overlap_add(256, OverlapMode::Analysis).unwrap() - 2.0).abs() < 1e-9);
+
+    let hamming = AnalysisWindow::hamming(1024).unwrap();
+    assert!(
+        (hamming
+            .check_overlap_add(512, OverlapMode::Analysis)
+            .unwrap()
+            - 1.08)
+            .abs()
+            < 1e-9
+    );
+
+    let blackman_harris = AnalysisWindow::blackman_harris(1024).unwrap();
+    assert!(blackman_harris
+        .check_overlap_add(256, OverlapMode::Analysis)
+        .is_ok());
+
+    let sqrt_hann = AnalysisWindow::sqrt_hann(1024).unwrap();
+    let gain = sqrt_hann
+        .check_overlap_add(512, OverlapMode::WeightedOverlapAdd)
+        .unwrap();
+    assert!((gain - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn cola_violations_are_errors() {
+    let blackman_harris = AnalysisWindow::blackman_harris(1024).unwrap();
+    assert!(matches!(
+        blackman_harris.check_overlap_add(512, OverlapMode::Analysis),
+        Err(WindowError::NotConstantOverlapAdd { hop: 512, .. })
+    ));
+
+    let sqrt_hann = AnalysisWindow::sqrt_hann(1024).unwrap();
+    assert!(matches!(
+        sqrt_hann.check_overlap_add(512, OverlapMode::Analysis),
+        Err(WindowError::NotConstantOverlapAdd { .. })
+    ));
+
+    let kaiser = AnalysisWindow::kaiser(1024, 9.0).unwrap();
+    assert!(matches!(
+        kaiser.check_overlap_add(512, OverlapMode::Analysis),
+        Err(WindowError::NotConstantOverlapAdd { .. })
+    ));
+    assert!(kaiser
+        .check_overlap_add_with_tolerance(64, OverlapMode::Analysis, 1e-3)
+        .is_ok());
+
+    let hann = AnalysisWindow::hann(1024).unwrap();
+    assert!(matches!(
+        hann.check_overlap_add(0, OverlapMode::Analysis),
+        Err(WindowError::InvalidHop { .. })
+    ));
+    assert_eq!(
+        AnalysisWindow::new(WindowKind::Hann, 0),
+        Err(WindowError::EmptyWindow)
+    );
+    assert_eq!(AnalysisWindow::sqrt_hann(0), Err(WindowError::EmptyWindow));
+    assert_eq!(
+        AnalysisWindow::from_coefficients(Vec::new()),
+        Err(WindowError::EmptyWindow)
+    );
+    assert!(matches!(
+        AnalysisWindow::kaiser(64, -1.0),
+        Err(WindowError::InvalidParameter(_))
+    ));
+}
+
+#[test]
+fn sqrt_hann_overlap_add_is_transparent() {
+    // Analysis and synthesis with sqrt-Hann at a WOLA-valid hop must be an
+    // identity when the spectrum is left untouched (zero watermark strength).
+    let window = AnalysisWindow::sqrt_hann(512).unwrap();
+    let gain = window
+        .check_overlap_add(256, OverlapMode::WeightedOverlapAdd)
+        .unwrap();
+    assert!((gain - 1.0).abs() < 1e-9);
+
+    let signal: Vec<f32> = (0..8000)
+        .map(|n| (n as f32 * 0.021).sin() * 0.5 + (n as f32 * 0.37).cos() * 0.1)
+        .collect();
+    let mut fft = FftBackend::<f32>::new();
+    let spectrogram = fft.stft(&signal, &window, 256).unwrap();
+    let output = fft.istft(&spectrogram, &window).unwrap();
+    for (a, b) in signal.iter().zip(&output) {
+        assert!((a - b).abs() < 1e-5);
+    }
+}